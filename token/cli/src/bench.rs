@@ -1,19 +1,23 @@
 /// The `bench` subcommand
 use {
-    crate::{config::Config, owner_address_arg, CommandResult, Error},
-    clap::{value_t_or_exit, App, AppSettings, Arg, ArgMatches, SubCommand},
-    solana_clap_utils::{
-        input_parsers::pubkey_of_signer,
-        input_validators::{is_amount, is_parsable, is_valid_pubkey},
+    crate::{
+        config::Config,
+        env_args::{amount_of, is_amount, is_valid_pubkey, pubkey_of_signer},
+        native_mint,
+        output::{CliLatency, CliRpcBench},
+        owner_address_arg, CommandResult, Error,
     },
+    clap::{value_t_or_exit, App, AppSettings, Arg, ArgMatches, SubCommand},
+    solana_clap_utils::input_validators::is_parsable,
     solana_client::{
         nonblocking::rpc_client::RpcClient, rpc_client::RpcClient as BlockingRpcClient,
-        tpu_client::TpuClient, tpu_client::TpuClientConfig,
+        rpc_config::RpcSimulateTransactionConfig, tpu_client::TpuClient,
+        tpu_client::TpuClientConfig,
     },
     solana_remote_wallet::remote_wallet::RemoteWalletManager,
     solana_sdk::{
-        message::Message, native_token::Sol, program_pack::Pack, pubkey::Pubkey, signature::Signer,
-        system_instruction,
+        instruction::Instruction, message::Message, native_token::Sol, program_pack::Pack,
+        pubkey::Pubkey, signature::Signer, system_instruction, transaction::Transaction,
     },
     spl_associated_token_account::*,
     spl_token_2022::{
@@ -21,7 +25,10 @@ use {
         instruction,
         state::{Account, Mint},
     },
-    std::{sync::Arc, time::Instant},
+    std::{
+        sync::Arc,
+        time::{Duration, Instant},
+    },
 };
 
 pub(crate) trait BenchSubCommand {
@@ -160,6 +167,27 @@ impl BenchSubCommand for App<'_, '_> {
                                 .help("The recipient token account address [default: associated token account for --owner]")
                         )
                         .arg(owner_address_arg()),
+                )
+                .subcommand(
+                    SubCommand::with_name("rpc")
+                        .about("Measure the latency of the RPC node, to compare endpoints")
+                        .arg(
+                            Arg::with_name("iterations")
+                                .long("iterations")
+                                .validator(is_parsable::<usize>)
+                                .value_name("N")
+                                .takes_value(true)
+                                .default_value("10")
+                                .help("The number of times to send each request"),
+                        )
+                        .arg(
+                            Arg::with_name("token")
+                                .long("token")
+                                .validator(is_valid_pubkey)
+                                .value_name("TOKEN_ADDRESS")
+                                .takes_value(true)
+                                .help("The mint to fetch [default: the native mint]"),
+                        ),
                 ),
         )
     }
@@ -202,7 +230,7 @@ pub(crate) async fn bench_process_command(
                 .unwrap()
                 .unwrap();
             let n = value_t_or_exit!(arg_matches, "n", usize);
-            let ui_amount = value_t_or_exit!(arg_matches, "amount", f64);
+            let ui_amount = amount_of(arg_matches, "amount").unwrap();
             let (owner_signer, owner) =
                 config.signer_or_default(arg_matches, "owner", wallet_manager);
             signers.push(owner_signer);
@@ -217,7 +245,7 @@ pub(crate) async fn bench_process_command(
                 .unwrap()
                 .unwrap();
             let n = value_t_or_exit!(arg_matches, "n", usize);
-            let ui_amount = value_t_or_exit!(arg_matches, "amount", f64);
+            let ui_amount = amount_of(arg_matches, "amount").unwrap();
             let (owner_signer, owner) =
                 config.signer_or_default(arg_matches, "owner", wallet_manager);
             signers.push(owner_signer);
@@ -227,12 +255,93 @@ pub(crate) async fn bench_process_command(
             )
             .await?;
         }
+        ("rpc", Some(arg_matches)) => {
+            let iterations = value_t_or_exit!(arg_matches, "iterations", usize);
+            let token = pubkey_of_signer(arg_matches, "token", wallet_manager).unwrap();
+            return command_rpc(config, iterations, token).await;
+        }
         _ => unreachable!(),
     }
 
     Ok("".to_string())
 }
 
+fn latency(mut samples: Vec<Duration>) -> CliLatency {
+    let samples_ms = samples
+        .iter()
+        .map(|sample| sample.as_secs_f64() * 1000.0)
+        .collect::<Vec<_>>();
+    samples.sort();
+    // Nearest rank
+    let percentile = |p: f64| {
+        let rank = (samples.len() as f64 * p / 100.0).ceil() as usize;
+        samples[rank.max(1) - 1].as_secs_f64() * 1000.0
+    };
+    CliLatency {
+        p50_ms: percentile(50.0),
+        p95_ms: percentile(95.0),
+        samples_ms,
+    }
+}
+
+// Each request is timed on its own, one after the other, so the samples are round trips rather
+// than what the node can serve concurrently
+async fn command_rpc(
+    config: &Config<'_>,
+    iterations: usize,
+    token: Option<Pubkey>,
+) -> CommandResult {
+    if iterations == 0 {
+        return Err("Error: At least one iteration is needed".into());
+    }
+    let rpc_client = &config.rpc_client;
+    let token = match token {
+        Some(token) => token,
+        None => native_mint(&config.program_id)?,
+    };
+
+    let mut get_account = vec![];
+    let mut get_latest_blockhash = vec![];
+    let mut simulate_transaction = vec![];
+    for _ in 0..iterations {
+        let start = Instant::now();
+        rpc_client
+            .get_account_with_commitment(&token, config.commitment)
+            .await?;
+        get_account.push(start.elapsed());
+
+        let start = Instant::now();
+        let blockhash = rpc_client.get_latest_blockhash().await?;
+        get_latest_blockhash.push(start.elapsed());
+
+        // Unsigned, and whether it would succeed doesn't matter
+        let mut transaction = Transaction::new_unsigned(Message::new(
+            &[spl_memo::build_memo(b"bench", &[])],
+            Some(&config.fee_payer),
+        ));
+        transaction.message.recent_blockhash = blockhash;
+        let start = Instant::now();
+        rpc_client
+            .simulate_transaction_with_config(
+                &transaction,
+                RpcSimulateTransactionConfig {
+                    sig_verify: false,
+                    ..RpcSimulateTransactionConfig::default()
+                },
+            )
+            .await?;
+        simulate_transaction.push(start.elapsed());
+    }
+
+    Ok(config.output_format.formatted_string(&CliRpcBench {
+        url: rpc_client.url(),
+        iterations,
+        get_account: latency(get_account),
+        get_latest_blockhash: latency(get_latest_blockhash),
+        simulate_transaction: latency(simulate_transaction),
+    }))
+}
+
 fn get_token_address_with_seed(
     program_id: &Pubkey,
     token: &Pubkey,
@@ -299,7 +408,8 @@ async fn command_create_accounts(
         for (account, (address, seed)) in accounts_chunk.iter().zip(address_chunk) {
             if account.is_none() {
                 lamports_required += minimum_balance_for_rent_exemption;
-                messages.push(Message::new(
+                messages.push(new_message(
+                    config,
                     &[
                         system_instruction::create_account_with_seed(
                             &config.fee_payer,
@@ -312,7 +422,6 @@ async fn command_create_accounts(
                         ),
                         instruction::initialize_account(&program_id, address, token, owner)?,
                     ],
-                    Some(&config.fee_payer),
                 ));
             }
         }
@@ -350,7 +459,8 @@ async fn command_close_accounts(
                                 address,
                             );
                         } else {
-                            messages.push(Message::new(
+                            messages.push(new_message(
+                                config,
                                 &[instruction::close_account(
                                     &program_id,
                                     address,
@@ -358,7 +468,6 @@ async fn command_close_accounts(
                                     owner,
                                     &[],
                                 )?],
-                                Some(&config.fee_payer),
                             ));
                         }
                     }
@@ -404,7 +513,8 @@ async fn command_deposit_into_or_withdraw_from(
 
         for (account, (address, _seed)) in accounts_chunk.iter().zip(address_chunk) {
             if account.is_some() {
-                messages.push(Message::new(
+                messages.push(new_message(
+                    config,
                     &[instruction::transfer_checked(
                         &program_id,
                         if deposit_into { &from_or_to } else { address },
@@ -415,7 +525,6 @@ async fn command_deposit_into_or_withdraw_from(
                         amount,
                         mint_info.decimals,
                     )?],
-                    Some(&config.fee_payer),
                 ));
             } else {
                 eprintln!("Token account does not exist: {}", address)
@@ -426,6 +535,15 @@ async fn command_deposit_into_or_withdraw_from(
     send_messages(config, &messages, 0, signers).await
 }
 
+fn new_message(config: &Config<'_>, instructions: &[Instruction]) -> Message {
+    let instructions = config
+        .compute_budget_instructions()
+        .into_iter()
+        .chain(instructions.iter().cloned())
+        .collect::<Vec<_>>();
+    Message::new(&instructions, Some(&config.fee_payer))
+}
+
 async fn send_messages(
     config: &Config<'_>,
     messages: &[Message],
@@ -439,7 +557,7 @@ async fn send_messages(
 
     let (_blockhash, fee_calculator, _last_valid_block_height) = config
         .rpc_client
-        .get_recent_blockhash_with_commitment(config.rpc_client.commitment())
+        .get_recent_blockhash_with_commitment(config.commitment)
         .await?
         .value;
 
@@ -1,19 +1,32 @@
-use crate::Error;
+use crate::{env_args::pubkey_of_signer, error::TokenCliError, Error};
 use clap::ArgMatches;
-use solana_clap_utils::{
-    input_parsers::pubkey_of_signer,
-    keypair::{pubkey_from_path, signer_from_path_with_config, SignerFromPathConfig},
+use solana_clap_utils::keypair::{
+    pubkey_from_path, signer_from_path_with_config, SignerFromPathConfig,
 };
 use solana_cli_output::OutputFormat;
-use solana_client::nonblocking::rpc_client::RpcClient;
-use solana_remote_wallet::remote_wallet::RemoteWalletManager;
-use solana_sdk::{pubkey::Pubkey, signature::Signer};
+use solana_client::{
+    nonblocking::rpc_client::RpcClient, rpc_request::MAX_MULTIPLE_ACCOUNTS,
+    rpc_response::RpcKeyedAccount,
+};
+use solana_remote_wallet::remote_wallet::{RemoteWalletError, RemoteWalletManager};
+use solana_sdk::{
+    account::Account as SolanaAccount, commitment_config::CommitmentConfig,
+    compute_budget::ComputeBudgetInstruction, hash::Hash, instruction::Instruction,
+    program_option::COption, program_pack::Pack, pubkey::Pubkey, signature::Signer,
+    signer::SignerError, system_program,
+};
 use spl_associated_token_account::*;
 use spl_token_2022::{
     extension::StateWithExtensionsOwned,
-    state::{Account, Mint},
+    state::{Account, Mint, Multisig},
+};
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    process::exit,
+    str::FromStr,
+    sync::{Arc, Mutex},
 };
-use std::{process::exit, sync::Arc};
 
 #[cfg(test)]
 use solana_sdk::signer::keypair::Keypair;
@@ -26,27 +39,83 @@ pub(crate) enum KeypairOrPath {
     Path(String),
 }
 
+#[derive(Clone, Debug)]
 pub(crate) struct MintInfo {
     pub program_id: Pubkey,
     pub address: Pubkey,
     pub decimals: u8,
 }
 
+#[derive(Clone, Debug)]
+pub(crate) struct MultisigInfo {
+    pub address: Pubkey,
+    pub m: u8,
+    pub signers: Vec<Pubkey>,
+}
+
 pub(crate) struct Config<'a> {
     pub(crate) rpc_client: Arc<RpcClient>,
+    // The RPC client is built with this too, so that it also applies to sending and confirming
+    pub(crate) commitment: CommitmentConfig,
     pub(crate) websocket_url: String,
     pub(crate) output_format: OutputFormat,
+    // One JSON object per line for list-type commands, compact JSON for everything else
+    pub(crate) json_lines: bool,
     pub(crate) fee_payer: Pubkey,
     pub(crate) default_keypair: KeypairOrPath,
     pub(crate) nonce_account: Option<Pubkey>,
     pub(crate) nonce_authority: Option<Pubkey>,
+    // Given with `--blockhash`, so that offline signing doesn't need to fetch one
+    pub(crate) blockhash: Option<Hash>,
     pub(crate) sign_only: bool,
+    pub(crate) simulate: bool,
+    pub(crate) fee_payer_balance_check: bool,
+    pub(crate) rpc_max_retries: u32,
+    pub(crate) strict: bool,
+    pub(crate) quiet: bool,
+    // Return as soon as transactions are sent, without waiting for them to be confirmed
+    pub(crate) no_wait: bool,
+    // Attached to every transaction the command sends
+    pub(crate) memo: Option<String>,
     pub(crate) dump_transaction_message: bool,
+    pub(crate) output_file: Option<String>,
+    pub(crate) input_file: Option<String>,
+    pub(crate) compute_unit_limit: Option<u32>,
+    pub(crate) compute_unit_price: Option<u64>,
+    // Set the compute unit limit of each transaction from a simulation of it, plus this margin in
+    // percent
+    pub(crate) compute_unit_margin: Option<u32>,
     pub(crate) multisigner_pubkeys: Vec<&'a Pubkey>,
     pub(crate) program_id: Pubkey,
+    pub(crate) mint_info_cache: Mutex<HashMap<Pubkey, MintInfo>>,
+    // Accounts read from `--account-source`, used in place of the RPC node's copy of them
+    pub(crate) account_source: HashMap<Pubkey, SolanaAccount>,
+    // How many times to try a hardware wallet in all before giving up, see `with_sign_attempts`
+    pub(crate) max_sign_attempts: u32,
+    // Nothing at all may be fetched, see `OfflineSender`. Implies `sign_only`.
+    pub(crate) offline: bool,
+    // The fee payer is a relayer that countersigns what the CLI leaves partially signed, see
+    // `handle_tx_with_progress`. Implies `sign_only`.
+    pub(crate) relayer_sign_only: bool,
 }
 
 impl<'a> Config<'a> {
+    // Instructions to prepend to every transaction for the requested compute budget
+    pub(crate) fn compute_budget_instructions(&self) -> Vec<Instruction> {
+        let mut instructions = vec![];
+        if let Some(compute_unit_limit) = self.compute_unit_limit {
+            instructions.push(ComputeBudgetInstruction::set_compute_unit_limit(
+                compute_unit_limit,
+            ));
+        }
+        if let Some(compute_unit_price) = self.compute_unit_price {
+            instructions.push(ComputeBudgetInstruction::set_compute_unit_price(
+                compute_unit_price,
+            ));
+        }
+        instructions
+    }
+
     // Check if an explicit token account address was provided, otherwise
     // return the associated token address for the default address.
     pub(crate) async fn associated_token_address_or_override(
@@ -80,7 +149,11 @@ impl<'a> Config<'a> {
         }
 
         let token = token.unwrap();
-        let program_id = self.get_mint_info(&token, None).await.unwrap().program_id;
+        let program_id = self
+            .get_mint_info_cached(&token, None)
+            .await
+            .unwrap()
+            .program_id;
         self.associated_token_address_for_token_and_program(
             arg_matches,
             wallet_manager,
@@ -157,15 +230,114 @@ impl<'a> Config<'a> {
             self.default_signer(arg_matches, wallet_manager, &config)
         };
 
-        let authority = load_authority().unwrap_or_else(|e| {
-            eprintln!("error: {}", e);
-            exit(1);
-        });
+        let authority = self
+            .with_sign_attempts(&mut load_authority, |err| {
+                is_retriable_load_error(err.as_ref())
+            })
+            .unwrap_or_else(|e| {
+                eprintln!("error: {}", e);
+                exit(1);
+            });
 
         let authority_address = authority.pubkey();
         (authority, authority_address)
     }
 
+    // Like `pubkey_or_default`, but an owner manifest yields one address per listed signer
+    pub(crate) fn pubkeys_or_default(
+        &self,
+        arg_matches: &ArgMatches,
+        address_name: &str,
+        wallet_manager: &mut Option<Arc<RemoteWalletManager>>,
+    ) -> Vec<Pubkey> {
+        match arg_matches
+            .value_of(address_name)
+            .and_then(read_signer_manifest)
+        {
+            Some(paths) => paths
+                .iter()
+                .map(|path| {
+                    pubkey_from_path(arg_matches, path, address_name, wallet_manager)
+                        .unwrap_or_else(|e| {
+                            eprintln!("error: {}", e);
+                            exit(1);
+                        })
+                })
+                .collect(),
+            None => vec![self.pubkey_or_default(arg_matches, address_name, wallet_manager)],
+        }
+    }
+
+    // Like `signer_or_default`, but an owner manifest yields every listed signer
+    pub(crate) fn signers_or_default(
+        &self,
+        arg_matches: &ArgMatches,
+        authority_name: &str,
+        wallet_manager: &mut Option<Arc<RemoteWalletManager>>,
+    ) -> Vec<(Box<dyn Signer>, Pubkey)> {
+        let paths = match arg_matches
+            .value_of(authority_name)
+            .and_then(read_signer_manifest)
+        {
+            Some(paths) => paths,
+            None => {
+                return vec![self.signer_or_default(arg_matches, authority_name, wallet_manager)]
+            }
+        };
+        let config = SignerFromPathConfig {
+            allow_null_signer: !self.multisigner_pubkeys.is_empty(),
+        };
+        paths
+            .iter()
+            .map(|path| {
+                let signer = self
+                    .with_sign_attempts(
+                        || {
+                            signer_from_path_with_config(
+                                arg_matches,
+                                path,
+                                authority_name,
+                                wallet_manager,
+                                &config,
+                            )
+                        },
+                        |err| is_retriable_load_error(err.as_ref()),
+                    )
+                    .unwrap_or_else(|e| {
+                        eprintln!("error: {}", e);
+                        exit(1);
+                    });
+                let address = signer.pubkey();
+                (signer, address)
+            })
+            .collect()
+    }
+
+    // Run `f` again, up to `max_sign_attempts` times in all, while it fails the way a hardware
+    // wallet does when it's locked, unplugged or its prompt was rejected. The user gets to fix the
+    // device in between.
+    pub(crate) fn with_sign_attempts<T, E: Display>(
+        &self,
+        mut f: impl FnMut() -> Result<T, E>,
+        is_retriable: impl Fn(&E) -> bool,
+    ) -> Result<T, E> {
+        let mut attempt = 1;
+        loop {
+            match f() {
+                Err(err) if attempt < self.max_sign_attempts && is_retriable(&err) => {
+                    eprintln!(
+                        "Wallet failed: {} (attempt {} of {}). \
+                         Unlock or reconnect the device, then press Enter to try again",
+                        err, attempt, self.max_sign_attempts
+                    );
+                    let _ = std::io::stdin().read_line(&mut String::new());
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
     fn default_address(
         &self,
         matches: &ArgMatches,
@@ -216,25 +388,34 @@ impl<'a> Config<'a> {
         &self,
         mint: &Pubkey,
         mint_decimals: Option<u8>,
-    ) -> Result<MintInfo, Error> {
-        if self.sign_only {
+    ) -> Result<MintInfo, TokenCliError> {
+        // A relayer transaction is only signed like a sign-only one, the network is still there
+        // to fetch the decimals from
+        if self.sign_only && !(self.relayer_sign_only && mint_decimals.is_none()) {
+            // Without the network, nothing would catch amounts scaled by the wrong decimals
+            if self.offline && mint_decimals.is_none() {
+                return Err(TokenCliError::MintDecimalsRequired(*mint));
+            }
             Ok(MintInfo {
                 program_id: self.program_id,
                 address: *mint,
                 decimals: mint_decimals.unwrap_or_default(),
             })
         } else {
-            let account = self.rpc_client.get_account(mint).await?;
+            let account = self
+                .get_account_with_source(mint)
+                .await?
+                .ok_or(TokenCliError::MintNotFound(*mint))?;
             self.check_owner(mint, &account.owner)?;
             let mint_account = StateWithExtensionsOwned::<Mint>::unpack(account.data)
-                .map_err(|_| format!("Could not find mint account {}", mint))?;
+                .map_err(|_| TokenCliError::InvalidMint(*mint))?;
             if let Some(decimals) = mint_decimals {
                 if decimals != mint_account.base.decimals {
-                    return Err(format!(
-                        "Mint {:?} has decimals {}, not configured decimals {}",
-                        mint, mint_account.base.decimals, decimals
-                    )
-                    .into());
+                    return Err(TokenCliError::DecimalsMismatch {
+                        mint: *mint,
+                        decimals: mint_account.base.decimals,
+                        expected: decimals,
+                    });
                 }
             }
             Ok(MintInfo {
@@ -245,41 +426,259 @@ impl<'a> Config<'a> {
         }
     }
 
-    pub(crate) fn check_owner(&self, account: &Pubkey, owner: &Pubkey) -> Result<(), Error> {
+    // `None` unless the address holds an initialized multisig of either token program
+    pub(crate) async fn get_multisig_info(
+        &self,
+        multisig: &Pubkey,
+    ) -> Result<Option<MultisigInfo>, Error> {
+        let account = self
+            .rpc_client
+            .get_account_with_commitment(multisig, self.commitment)
+            .await?
+            .value;
+        let account = match account {
+            Some(account)
+                if (account.owner == spl_token::id() || account.owner == spl_token_2022::id())
+                    && account.data.len() == Multisig::LEN =>
+            {
+                account
+            }
+            _ => return Ok(None),
+        };
+        Ok(Multisig::unpack(&account.data)
+            .ok()
+            .map(|multisig_account| MultisigInfo {
+                address: *multisig,
+                m: multisig_account.m,
+                signers: multisig_account.signers[..multisig_account.n as usize].to_vec(),
+            }))
+    }
+
+    // Same as `get_mint_info`, but reuse the result of an earlier lookup of the same mint in this
+    // invocation. Sign-only mode fabricates the mint info, so it bypasses the cache.
+    pub(crate) async fn get_mint_info_cached(
+        &self,
+        mint: &Pubkey,
+        mint_decimals: Option<u8>,
+    ) -> Result<MintInfo, Error> {
+        if self.sign_only {
+            return Ok(self.get_mint_info(mint, mint_decimals).await?);
+        }
+
+        let cached_mint_info = self.mint_info_cache.lock().unwrap().get(mint).cloned();
+        let mint_info = if let Some(mint_info) = cached_mint_info {
+            mint_info
+        } else {
+            let mint_info = self.get_mint_info(mint, None).await?;
+            self.mint_info_cache
+                .lock()
+                .unwrap()
+                .insert(*mint, mint_info.clone());
+            mint_info
+        };
+        if let Some(decimals) = mint_decimals {
+            if decimals != mint_info.decimals {
+                return Err(TokenCliError::DecimalsMismatch {
+                    mint: *mint,
+                    decimals: mint_info.decimals,
+                    expected: decimals,
+                }
+                .into());
+            }
+        }
+        Ok(mint_info)
+    }
+
+    // The account from `--account-source` if it holds one, otherwise from the RPC node
+    async fn get_account_with_source(
+        &self,
+        address: &Pubkey,
+    ) -> Result<Option<SolanaAccount>, TokenCliError> {
+        if let Some(account) = self.account_source.get(address) {
+            return Ok(Some(account.clone()));
+        }
+        Ok(self
+            .rpc_client
+            .get_account_with_commitment(address, self.commitment)
+            .await?
+            .value)
+    }
+
+    pub(crate) fn check_owner(
+        &self,
+        account: &Pubkey,
+        owner: &Pubkey,
+    ) -> Result<(), TokenCliError> {
         if self.program_id != *owner {
-            Err(format!(
-                "Account {:?} is owned by {}, not configured program id {}",
-                account, owner, self.program_id
-            )
-            .into())
+            Err(TokenCliError::WrongProgramOwner {
+                account: *account,
+                owner: *owner,
+                program_id: self.program_id,
+            })
         } else {
             Ok(())
         }
     }
 
+    // Fetch many accounts with one `getMultipleAccounts` request per `MAX_MULTIPLE_ACCOUNTS`
+    // addresses. Addresses that do not exist are returned as `None`.
+    pub(crate) async fn get_multiple_accounts(
+        &self,
+        addresses: &[Pubkey],
+    ) -> Result<Vec<Option<solana_sdk::account::Account>>, Error> {
+        let mut accounts = Vec::with_capacity(addresses.len());
+        for chunk in addresses.chunks(MAX_MULTIPLE_ACCOUNTS) {
+            accounts.extend(self.rpc_client.get_multiple_accounts(chunk).await?);
+        }
+        Ok(accounts)
+    }
+
+    // Same as `get_multiple_accounts`, but parse each account as a token account owned by the
+    // configured program. Addresses that have not been allocated yet are returned as `None`.
+    pub(crate) async fn get_multiple_accounts_checked(
+        &self,
+        addresses: &[Pubkey],
+    ) -> Result<Vec<Option<StateWithExtensionsOwned<Account>>>, Error> {
+        let accounts = self.get_multiple_accounts(addresses).await?;
+        let mut token_accounts = Vec::with_capacity(accounts.len());
+        for (address, account) in addresses.iter().zip(accounts) {
+            let token_account = match account {
+                Some(account) if account.owner != system_program::id() => {
+                    self.check_owner(address, &account.owner)?;
+                    let token_account =
+                        StateWithExtensionsOwned::<Account>::unpack(account.data)
+                            .map_err(|_| format!("Could not find token account {}", address))?;
+                    Some(token_account)
+                }
+                _ => None,
+            };
+            token_accounts.push(token_account);
+        }
+        Ok(token_accounts)
+    }
+
     pub(crate) async fn check_account(
         &self,
         token_account: &Pubkey,
         mint_address: Option<Pubkey>,
-    ) -> Result<Pubkey, Error> {
+    ) -> Result<Pubkey, TokenCliError> {
+        self.check_account_with_owner(token_account, mint_address, None)
+            .await
+    }
+
+    // Same as `check_account`, but also make sure `expected_owner` may move the account's tokens,
+    // as its owner or its delegate
+    pub(crate) async fn check_account_with_owner(
+        &self,
+        token_account: &Pubkey,
+        mint_address: Option<Pubkey>,
+        expected_owner: Option<Pubkey>,
+    ) -> Result<Pubkey, TokenCliError> {
         if !self.sign_only {
-            let account = self.rpc_client.get_account(token_account).await?;
-            let source_account = StateWithExtensionsOwned::<Account>::unpack(account.data)
-                .map_err(|_| format!("Could not find token account {}", token_account))?;
-            let source_mint = source_account.base.mint;
-            if let Some(mint) = mint_address {
-                if source_mint != mint {
-                    return Err(format!(
-                        "Source {:?} does not contain {:?} tokens",
-                        token_account, mint
-                    )
-                    .into());
+            let (source_account, _) = self.check_account_full(token_account, mint_address).await?;
+            if let Some(expected_owner) = expected_owner {
+                if source_account.base.owner != expected_owner
+                    && source_account.base.delegate != COption::Some(expected_owner)
+                {
+                    return Err(TokenCliError::WrongAccountOwner {
+                        account: *token_account,
+                        owner: source_account.base.owner,
+                        expected: expected_owner,
+                    });
                 }
             }
-            self.check_owner(token_account, &account.owner)?;
-            Ok(source_mint)
+            Ok(source_account.base.mint)
         } else {
             Ok(mint_address.unwrap_or_default())
         }
     }
+
+    // Same checks as `check_account`, but return the whole parsed account along with the program
+    // that owns it, for callers that need more than the mint. Unlike `check_account`, this always
+    // reads the account, so it can't be used in sign-only mode.
+    pub(crate) async fn check_account_full(
+        &self,
+        token_account: &Pubkey,
+        mint_address: Option<Pubkey>,
+    ) -> Result<(StateWithExtensionsOwned<Account>, Pubkey), TokenCliError> {
+        let account = self
+            .get_account_with_source(token_account)
+            .await?
+            .ok_or(TokenCliError::TokenAccountNotFound(*token_account))?;
+        let source_account = StateWithExtensionsOwned::<Account>::unpack(account.data)
+            .map_err(|_| TokenCliError::TokenAccountNotFound(*token_account))?;
+        if let Some(mint) = mint_address {
+            if source_account.base.mint != mint {
+                return Err(TokenCliError::MintMismatch {
+                    account: *token_account,
+                    mint,
+                });
+            }
+        }
+        self.check_owner(token_account, &account.owner)?;
+        Ok((source_account, account.owner))
+    }
+}
+
+// Errors a user can fix at the device, unlike, say, a wrong derivation path
+fn is_retriable_load_error(err: &(dyn std::error::Error + 'static)) -> bool {
+    matches!(
+        err.downcast_ref::<RemoteWalletError>(),
+        Some(RemoteWalletError::Hid(_))
+            | Some(RemoteWalletError::NoDeviceFound)
+            | Some(RemoteWalletError::UserCancel)
+    )
+}
+
+pub(crate) fn is_retriable_sign_error(err: &SignerError) -> bool {
+    matches!(
+        err,
+        SignerError::Connection(_) | SignerError::NoDeviceFound | SignerError::UserCancel(_)
+    )
+}
+
+// Accounts as dumped by `solana account --output json`, either one such object or an array of
+// them
+pub(crate) fn read_account_source(path: &str) -> Result<HashMap<Pubkey, SolanaAccount>, Error> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|err| format!("Unable to read account source {}: {}", path, err))?;
+    let value: serde_json::Value = serde_json::from_str(&contents)
+        .map_err(|err| format!("Unable to parse account source {}: {}", path, err))?;
+    let keyed_accounts: Vec<RpcKeyedAccount> = if value.is_array() {
+        serde_json::from_value(value)
+    } else {
+        serde_json::from_value(value).map(|keyed_account| vec![keyed_account])
+    }
+    .map_err(|err| format!("Unable to parse account source {}: {}", path, err))?;
+
+    let mut accounts = HashMap::new();
+    for keyed_account in keyed_accounts {
+        let address = Pubkey::from_str(&keyed_account.pubkey)?;
+        let account = keyed_account
+            .account
+            .decode::<SolanaAccount>()
+            .ok_or_else(|| format!("Could not decode account {} in {}", address, path))?;
+        accounts.insert(address, account);
+    }
+    Ok(accounts)
+}
+
+// An owner may also be given as a manifest: a text file listing one signer per line, ignoring
+// blank lines and `#` comments. Keypair files are JSON arrays, so they never read as a manifest.
+fn read_signer_manifest(path: &str) -> Option<Vec<String>> {
+    let contents = std::fs::read_to_string(path).ok()?;
+    if contents.trim_start().starts_with('[') {
+        return None;
+    }
+    let paths = contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_string)
+        .collect::<Vec<_>>();
+    if paths.is_empty() {
+        eprintln!("error: owner manifest {} lists no signers", path);
+        exit(1);
+    }
+    Some(paths)
 }
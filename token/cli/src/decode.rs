@@ -0,0 +1,227 @@
+use crate::output::{
+    CliDecodedInstruction, CliDecodedTransaction, CliInstructionAccount, CliLabeledAccount,
+    CliTransactionSigner,
+};
+use solana_sdk::{
+    borsh::try_from_slice_unchecked, compute_budget::ComputeBudgetInstruction, message::Message,
+    pubkey::Pubkey, signature::Signature, system_instruction::SystemInstruction, system_program,
+    sysvar, transaction::Transaction,
+};
+use spl_associated_token_account::{
+    get_associated_token_address_with_program_id, instruction::AssociatedTokenAccountInstruction,
+};
+use spl_token_2022::instruction::TokenInstruction;
+use std::collections::HashMap;
+
+pub(crate) fn program_name(program_id: &Pubkey) -> Option<&'static str> {
+    if *program_id == spl_token::id() {
+        Some("Token program")
+    } else if *program_id == spl_token_2022::id() {
+        Some("Token-2022 program")
+    } else if *program_id == spl_associated_token_account::id() {
+        Some("Associated token account program")
+    } else if *program_id == system_program::id() {
+        Some("System program")
+    } else if *program_id == solana_sdk::compute_budget::id() {
+        Some("Compute budget program")
+    } else if *program_id == spl_memo::id() {
+        Some("Memo program")
+    } else {
+        None
+    }
+}
+
+// The instruction data of the programs the CLI itself uses, in their `Debug` form. Token-2022
+// extension instructions only show the extension, not the data that follows it.
+pub(crate) fn decode_instruction(program_id: &Pubkey, data: &[u8]) -> Option<String> {
+    if *program_id == spl_token::id() || *program_id == spl_token_2022::id() {
+        TokenInstruction::unpack(data)
+            .ok()
+            .map(|instruction| format!("{:?}", instruction))
+    } else if *program_id == spl_associated_token_account::id() {
+        // The original instruction to create an account has no data
+        if data.is_empty() {
+            Some(format!("{:?}", AssociatedTokenAccountInstruction::Create))
+        } else {
+            try_from_slice_unchecked::<AssociatedTokenAccountInstruction>(data)
+                .ok()
+                .map(|instruction| format!("{:?}", instruction))
+        }
+    } else if *program_id == system_program::id() {
+        bincode::deserialize::<SystemInstruction>(data)
+            .ok()
+            .map(|instruction| format!("{:?}", instruction))
+    } else if *program_id == solana_sdk::compute_budget::id() {
+        try_from_slice_unchecked::<ComputeBudgetInstruction>(data)
+            .ok()
+            .map(|instruction| format!("{:?}", instruction))
+    } else if *program_id == spl_memo::id() {
+        std::str::from_utf8(data)
+            .ok()
+            .map(|memo| format!("Memo {:?}", memo))
+    } else {
+        None
+    }
+}
+
+// The position of the mint among the accounts of the token instructions that take one
+fn mint_index(instruction: &TokenInstruction) -> Option<usize> {
+    match instruction {
+        TokenInstruction::InitializeMint { .. }
+        | TokenInstruction::InitializeMint2 { .. }
+        | TokenInstruction::MintTo { .. }
+        | TokenInstruction::MintToChecked { .. }
+        | TokenInstruction::InitializeMintCloseAuthority { .. }
+        | TokenInstruction::InitializeNonTransferableMint => Some(0),
+        TokenInstruction::InitializeAccount
+        | TokenInstruction::InitializeAccount2 { .. }
+        | TokenInstruction::InitializeAccount3 { .. }
+        | TokenInstruction::TransferChecked { .. }
+        | TokenInstruction::ApproveChecked { .. }
+        | TokenInstruction::Burn { .. }
+        | TokenInstruction::BurnChecked { .. }
+        | TokenInstruction::FreezeAccount
+        | TokenInstruction::ThawAccount => Some(1),
+        _ => None,
+    }
+}
+
+// Names for the accounts of a message that can be told from the message alone: the fee payer,
+// well-known programs and sysvars, nonce accounts, mints, and the associated token accounts of
+// those mints for any other account of the message. Anything else stays unlabeled.
+pub(crate) fn account_labels(message: &Message) -> HashMap<Pubkey, String> {
+    let keys = &message.account_keys;
+    let mut labels = HashMap::new();
+    for key in keys {
+        if let Some(name) = program_name(key) {
+            labels.insert(*key, name.to_string());
+        } else if *key == sysvar::rent::id() {
+            labels.insert(*key, "Rent sysvar".to_string());
+        } else if *key == sysvar::recent_blockhashes::id() {
+            labels.insert(*key, "Recent blockhashes sysvar".to_string());
+        }
+    }
+
+    let mut mints = vec![];
+    for instruction in &message.instructions {
+        let program_id = keys[instruction.program_id_index as usize];
+        let account = |position: usize| {
+            instruction
+                .accounts
+                .get(position)
+                .map(|index| keys[*index as usize])
+        };
+        if program_id == spl_token::id() || program_id == spl_token_2022::id() {
+            let mint = TokenInstruction::unpack(&instruction.data)
+                .ok()
+                .as_ref()
+                .and_then(mint_index)
+                .and_then(account);
+            if let Some(mint) = mint {
+                labels.insert(mint, "mint".to_string());
+                mints.push((mint, program_id));
+            }
+        } else if program_id == spl_associated_token_account::id() {
+            if let (Some(wallet), Some(mint), Some(token_program)) =
+                (account(2), account(3), account(5))
+            {
+                labels.insert(wallet, "wallet".to_string());
+                labels.insert(mint, "mint".to_string());
+                mints.push((mint, token_program));
+            }
+        } else if program_id == system_program::id() {
+            if let Ok(SystemInstruction::AdvanceNonceAccount) =
+                bincode::deserialize::<SystemInstruction>(&instruction.data)
+            {
+                if let (Some(nonce_account), Some(nonce_authority)) = (account(0), account(2)) {
+                    labels.insert(nonce_account, "nonce account".to_string());
+                    labels.insert(nonce_authority, "nonce authority".to_string());
+                }
+            }
+        }
+    }
+
+    mints.sort();
+    mints.dedup();
+    for (mint, token_program) in mints {
+        for owner in keys {
+            let address =
+                get_associated_token_address_with_program_id(owner, &mint, &token_program);
+            if keys.contains(&address) {
+                labels
+                    .entry(address)
+                    .or_insert_with(|| format!("associated token account of {}", owner));
+            }
+        }
+    }
+
+    // Set last, as the fee payer may also be any of the above
+    if let Some(fee_payer) = keys.first() {
+        labels.insert(*fee_payer, "fee payer".to_string());
+    }
+    labels
+}
+
+pub(crate) fn label_accounts(message: &Message) -> Vec<CliLabeledAccount> {
+    let labels = account_labels(message);
+    message
+        .account_keys
+        .iter()
+        .map(|key| CliLabeledAccount {
+            address: key.to_string(),
+            label: labels.get(key).cloned(),
+        })
+        .collect()
+}
+
+pub(crate) fn decode_transaction(transaction: &Transaction) -> CliDecodedTransaction {
+    let message = &transaction.message;
+    let labels = account_labels(message);
+    let signers = message
+        .account_keys
+        .iter()
+        .take(message.header.num_required_signatures as usize)
+        .zip(&transaction.signatures)
+        .map(|(address, signature)| CliTransactionSigner {
+            address: address.to_string(),
+            signature: if *signature == Signature::default() {
+                None
+            } else {
+                Some(signature.to_string())
+            },
+        })
+        .collect();
+
+    let instructions = message
+        .instructions
+        .iter()
+        .map(|instruction| {
+            let program_id = message.account_keys[instruction.program_id_index as usize];
+            CliDecodedInstruction {
+                program_id: program_id.to_string(),
+                program: program_name(&program_id).map(|name| name.to_string()),
+                accounts: instruction
+                    .accounts
+                    .iter()
+                    .map(|index| {
+                        let index = *index as usize;
+                        CliInstructionAccount {
+                            address: message.account_keys[index].to_string(),
+                            label: labels.get(&message.account_keys[index]).cloned(),
+                            signer: message.is_signer(index),
+                            writable: message.is_writable(index),
+                        }
+                    })
+                    .collect(),
+                instruction: decode_instruction(&program_id, &instruction.data),
+                data: base64::encode(&instruction.data),
+            }
+        })
+        .collect();
+
+    CliDecodedTransaction {
+        blockhash: message.recent_blockhash.to_string(),
+        signers,
+        instructions,
+    }
+}
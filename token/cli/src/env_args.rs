@@ -0,0 +1,100 @@
+use clap::ArgMatches;
+use solana_clap_utils::{input_parsers, input_validators, keypair::pubkey_from_path};
+use solana_remote_wallet::remote_wallet::RemoteWalletManager;
+use solana_sdk::pubkey::Pubkey;
+use std::{env, fmt::Display, str::FromStr, sync::Arc};
+
+// Pubkey and amount arguments may be given as `env:VARNAME`, so that they can be kept out of
+// process listings. Only those arguments are resolved, through the drop-in replacements below for
+// the `solana_clap_utils` validators and parsers they go through; anything else, like a memo or a
+// file path, is taken as written.
+
+/// The value of the environment variable an `env:VARNAME` value refers to, or `None` for any
+/// other value
+pub(crate) fn resolve_env_value(value: &str) -> Result<Option<String>, String> {
+    match value.strip_prefix("env:") {
+        Some(name) => env::var(name).map(Some).map_err(|_| {
+            format!(
+                "environment variable {} referenced by `{}` is not set",
+                name, value
+            )
+        }),
+        None => Ok(None),
+    }
+}
+
+/// An argument's value, with `env:VARNAME` resolved. The validator already rejected a variable
+/// that isn't set, so one that has been unset since is left as written.
+pub(crate) fn resolved_value_of(matches: &ArgMatches<'_>, name: &str) -> Option<String> {
+    matches.value_of(name).map(|value| {
+        resolve_env_value(value)
+            .ok()
+            .flatten()
+            .unwrap_or_else(|| value.to_string())
+    })
+}
+
+pub(crate) fn is_valid_pubkey<T>(string: T) -> Result<(), String>
+where
+    T: AsRef<str> + Display,
+{
+    match resolve_env_value(string.as_ref())? {
+        Some(value) => input_validators::is_valid_pubkey(value),
+        None => input_validators::is_valid_pubkey(string),
+    }
+}
+
+pub(crate) fn is_amount<T>(amount: T) -> Result<(), String>
+where
+    T: AsRef<str> + Display,
+{
+    match resolve_env_value(amount.as_ref())? {
+        Some(value) => input_validators::is_amount(value),
+        None => input_validators::is_amount(amount),
+    }
+}
+
+pub(crate) fn amount_of(matches: &ArgMatches<'_>, name: &str) -> Option<f64> {
+    resolved_value_of(matches, name).and_then(|value| value.parse().ok())
+}
+
+pub(crate) fn pubkey_of(matches: &ArgMatches<'_>, name: &str) -> Option<Pubkey> {
+    match matches.value_of(name).map(resolve_env_value) {
+        Some(Ok(Some(value))) => Pubkey::from_str(&value).ok(),
+        _ => input_parsers::pubkey_of(matches, name),
+    }
+}
+
+pub(crate) fn pubkey_of_signer(
+    matches: &ArgMatches<'_>,
+    name: &str,
+    wallet_manager: &mut Option<Arc<RemoteWalletManager>>,
+) -> Result<Option<Pubkey>, Box<dyn std::error::Error>> {
+    match resolved_value_of(matches, name) {
+        Some(location) => Ok(Some(pubkey_from_path(
+            matches,
+            &location,
+            name,
+            wallet_manager,
+        )?)),
+        None => Ok(None),
+    }
+}
+
+pub(crate) fn pubkeys_of_multiple_signers(
+    matches: &ArgMatches<'_>,
+    name: &str,
+    wallet_manager: &mut Option<Arc<RemoteWalletManager>>,
+) -> Result<Option<Vec<Pubkey>>, Box<dyn std::error::Error>> {
+    match matches.values_of(name) {
+        Some(values) => {
+            let mut pubkeys = vec![];
+            for value in values {
+                let location = resolve_env_value(value)?.unwrap_or_else(|| value.to_string());
+                pubkeys.push(pubkey_from_path(matches, &location, name, wallet_manager)?);
+            }
+            Ok(Some(pubkeys))
+        }
+        None => Ok(None),
+    }
+}
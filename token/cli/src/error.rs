@@ -0,0 +1,122 @@
+use solana_client::client_error::ClientError;
+use solana_sdk::{native_token::lamports_to_sol, pubkey::Pubkey};
+use std::fmt;
+
+/// Failures that callers may want to tell apart. Everything else is reported as a plain message
+/// through the boxed `Error`, which these convert into.
+#[derive(Debug)]
+pub(crate) enum TokenCliError {
+    Rpc(ClientError),
+    MintNotFound(Pubkey),
+    InvalidMint(Pubkey),
+    MintDecimalsRequired(Pubkey),
+    TokenAccountNotFound(Pubkey),
+    WrongProgramOwner {
+        account: Pubkey,
+        owner: Pubkey,
+        program_id: Pubkey,
+    },
+    DecimalsMismatch {
+        mint: Pubkey,
+        decimals: u8,
+        expected: u8,
+    },
+    MintMismatch {
+        account: Pubkey,
+        mint: Pubkey,
+    },
+    WrongAccountOwner {
+        account: Pubkey,
+        owner: Pubkey,
+        expected: Pubkey,
+    },
+    InsufficientFunds {
+        account: Pubkey,
+        fee_payer: bool,
+        required: u64,
+        available: u64,
+    },
+    // A command that got only part of its work done. The formatted output of that part is
+    // reported together with the error, instead of printed on its own.
+    Incomplete {
+        output: String,
+        message: String,
+    },
+}
+
+impl fmt::Display for TokenCliError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Rpc(err) => write!(f, "{}", err),
+            Self::MintNotFound(mint) => write!(f, "Mint account {} not found", mint),
+            Self::InvalidMint(mint) => write!(f, "Account {} is not a valid mint", mint),
+            Self::MintDecimalsRequired(mint) => write!(
+                f,
+                "The decimals of mint {} can't be fetched offline, pass them with --mint-decimals",
+                mint
+            ),
+            Self::TokenAccountNotFound(account) => {
+                write!(f, "Could not find token account {}", account)
+            }
+            Self::WrongProgramOwner {
+                account,
+                owner,
+                program_id,
+            } => write!(
+                f,
+                "Account {:?} is owned by {}, not configured program id {}",
+                account, owner, program_id
+            ),
+            Self::DecimalsMismatch {
+                mint,
+                decimals,
+                expected,
+            } => write!(
+                f,
+                "Mint {:?} has decimals {}, not configured decimals {}",
+                mint, decimals, expected
+            ),
+            Self::MintMismatch { account, mint } => {
+                write!(f, "Source {:?} does not contain {:?} tokens", account, mint)
+            }
+            Self::WrongAccountOwner {
+                account,
+                owner,
+                expected,
+            } => write!(
+                f,
+                "Token account {} is owned by {}, not {}",
+                account, owner, expected
+            ),
+            Self::InsufficientFunds {
+                account,
+                fee_payer,
+                required,
+                available,
+            } => write!(
+                f,
+                "{} {}, has insufficient balance: {} required, {} available",
+                if *fee_payer { "Fee payer," } else { "Wallet" },
+                account,
+                lamports_to_sol(*required),
+                lamports_to_sol(*available)
+            ),
+            Self::Incomplete { message, .. } => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for TokenCliError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Rpc(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<ClientError> for TokenCliError {
+    fn from(err: ClientError) -> Self {
+        Self::Rpc(err)
+    }
+}
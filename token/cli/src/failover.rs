@@ -0,0 +1,83 @@
+use async_trait::async_trait;
+use solana_client::{
+    client_error::{ClientError, ClientErrorKind, Result as ClientResult},
+    http_sender::HttpSender,
+    rpc_request::RpcRequest,
+    rpc_sender::{RpcSender, RpcTransportStats},
+};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Sends each request to one of several RPC endpoints, moving on to the next one when an
+/// endpoint can't be reached. Whichever endpoint last answered is tried first for the following
+/// requests, so a dead endpoint only costs one failed attempt for the whole invocation.
+pub(crate) struct FailoverSender {
+    senders: Vec<HttpSender>,
+    current: AtomicUsize,
+}
+
+impl FailoverSender {
+    pub(crate) fn new(urls: &[String]) -> Self {
+        assert!(!urls.is_empty());
+        Self {
+            senders: urls.iter().map(HttpSender::new).collect(),
+            current: AtomicUsize::new(0),
+        }
+    }
+}
+
+// Only failures that say nothing about the request itself are worth retrying elsewhere; anything
+// the node actually answered, like a failed simulation, would fail the same way on every endpoint
+fn is_endpoint_error(err: &ClientError) -> bool {
+    match err.kind() {
+        ClientErrorKind::Io(_) => true,
+        ClientErrorKind::Reqwest(err) => {
+            err.is_connect()
+                || err.is_timeout()
+                || err
+                    .status()
+                    .map(|status| status.is_server_error())
+                    .unwrap_or(false)
+        }
+        _ => false,
+    }
+}
+
+#[async_trait]
+impl RpcSender for FailoverSender {
+    async fn send(
+        &self,
+        request: RpcRequest,
+        params: serde_json::Value,
+    ) -> ClientResult<serde_json::Value> {
+        let first = self.current.load(Ordering::Relaxed);
+        let mut last_err = None;
+        for offset in 0..self.senders.len() {
+            let index = (first + offset) % self.senders.len();
+            match self.senders[index].send(request, params.clone()).await {
+                Err(err) if is_endpoint_error(&err) => {
+                    if offset + 1 < self.senders.len() {
+                        eprintln!(
+                            "RPC endpoint {} failed ({}), trying the next one",
+                            self.senders[index].url(),
+                            err
+                        );
+                    }
+                    last_err = Some(err);
+                }
+                result => {
+                    self.current.store(index, Ordering::Relaxed);
+                    return result;
+                }
+            }
+        }
+        Err(last_err.unwrap())
+    }
+
+    fn get_transport_stats(&self) -> RpcTransportStats {
+        self.senders[self.current.load(Ordering::Relaxed)].get_transport_stats()
+    }
+
+    fn url(&self) -> String {
+        self.senders[self.current.load(Ordering::Relaxed)].url()
+    }
+}
@@ -1,59 +1,117 @@
 #![allow(deprecated)] // TODO: Remove when SPL upgrades to Solana 1.8
 use clap::{
     crate_description, crate_name, crate_version, value_t, value_t_or_exit, App, AppSettings, Arg,
-    ArgMatches, SubCommand,
+    ArgGroup, ArgMatches, SubCommand,
 };
 use serde::Serialize;
 use solana_account_decoder::{
-    parse_token::{TokenAccountType, UiAccountState},
-    UiAccountData,
+    parse_token::{token_amount_to_ui_amount, TokenAccountType, UiAccountState},
+    UiAccountData, UiAccountEncoding, UiDataSliceConfig,
 };
 use solana_clap_utils::{
     fee_payer::fee_payer_arg,
-    input_parsers::{pubkey_of, pubkey_of_signer, pubkeys_of_multiple_signers, value_of},
+    input_parsers::{pubkeys_sigs_of, value_of},
     input_validators::{
-        is_amount, is_amount_or_all, is_parsable, is_url_or_moniker, is_valid_pubkey,
+        is_amount_or_all, is_derived_address_seed, is_parsable, is_pubkey_sig, is_url_or_moniker,
         is_valid_signer, normalize_to_url_if_moniker,
     },
-    keypair::{signer_from_path, CliSignerInfo},
+    keypair::{
+        signer_from_path, signer_from_path_with_config, CliSignerInfo, SignerFromPathConfig,
+    },
     memo::memo_arg,
     nonce::*,
     offline::{self, *},
     ArgConstant,
 };
 use solana_cli_output::{
-    return_signers_data, CliSignOnlyData, CliSignature, OutputFormat, QuietDisplay,
-    ReturnSignersConfig, VerboseDisplay,
+    return_signers_data, CliSignature, OutputFormat, QuietDisplay, ReturnSignersConfig,
+    VerboseDisplay,
+};
+use solana_client::{
+    client_error::{ClientError, ClientErrorKind},
+    nonblocking::rpc_client::RpcClient,
+    rpc_client::RpcClientConfig,
+    rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig, RpcSimulateTransactionConfig},
+    rpc_filter::{Memcmp, MemcmpEncodedBytes, RpcFilterType},
+    rpc_request::{
+        RpcError, RpcRequest, RpcResponseErrorData, TokenAccountsFilter,
+        MAX_GET_SIGNATURE_STATUSES_QUERY_ITEMS,
+    },
+    rpc_response::{Response, RpcKeyedAccount},
 };
-use solana_client::{nonblocking::rpc_client::RpcClient, rpc_request::TokenAccountsFilter};
 use solana_remote_wallet::remote_wallet::RemoteWalletManager;
 use solana_sdk::{
     commitment_config::CommitmentConfig,
+    compute_budget::ComputeBudgetInstruction,
+    hash::Hash,
     instruction::Instruction,
     message::Message,
     native_token::*,
+    packet::PACKET_DATA_SIZE,
+    program_error::ProgramError,
     program_option::COption,
     program_pack::Pack,
     pubkey::Pubkey,
-    signature::{Keypair, Signer},
+    rent::Rent,
+    sanitize::Sanitize,
+    signature::{Keypair, Signature, Signer},
+    signer::SignerError,
     system_instruction, system_program,
-    transaction::Transaction,
+    sysvar::{self, clock::Clock},
+    transaction::{Transaction, TransactionError},
 };
 use spl_associated_token_account::{
-    get_associated_token_address_with_program_id, instruction::create_associated_token_account,
+    get_associated_token_address_with_program_id,
+    instruction::{
+        create_associated_token_account, create_associated_token_account_idempotent, recover_nested,
+    },
 };
 use spl_token_2022::{
-    extension::StateWithExtensionsOwned,
+    extension::{
+        confidential_transfer::{
+            self, ConfidentialTransferAccount, ConfidentialTransferMint, EncryptionPubkey,
+        },
+        default_account_state::{
+            instruction::{initialize_default_account_state, update_default_account_state},
+            DefaultAccountState,
+        },
+        interest_bearing_mint::{self, InterestBearingConfig},
+        memo_transfer::{
+            instruction::{disable_required_transfer_memos, enable_required_transfer_memos},
+            MemoTransfer,
+        },
+        mint_close_authority::MintCloseAuthority,
+        transfer_fee::{
+            instruction::{
+                harvest_withheld_tokens_to_mint, set_transfer_fee, transfer_checked_with_fee,
+                withdraw_withheld_tokens_from_mint, TransferFeeInstruction,
+            },
+            TransferFee, TransferFeeAmount, TransferFeeConfig, MAX_FEE_BASIS_POINTS,
+        },
+        AccountType, ExtensionType, StateWithExtensionsOwned,
+    },
     instruction::*,
-    state::{Account, Mint, Multisig},
+    solana_zk_token_sdk::encryption::{
+        auth_encryption::{AeCiphertext, AeKey},
+        elgamal::{ElGamalKeypair, ElGamalPubkey},
+    },
+    state::{Account, AccountState, Mint, Multisig},
 };
 use std::{
-    collections::HashMap, fmt::Display, process::exit, str::FromStr, string::ToString, sync::Arc,
+    collections::{HashMap, HashSet},
+    convert::{TryFrom, TryInto},
+    fmt::Display,
+    future::Future,
+    process::exit,
+    str::FromStr,
+    string::ToString,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
 };
 use strum_macros::{EnumString, IntoStaticStr, ToString};
 
 mod config;
-use config::{Config, KeypairOrPath, MintInfo};
+use config::{is_retriable_sign_error, read_account_source, Config, KeypairOrPath, MintInfo};
 
 mod output;
 use output::*;
@@ -64,6 +122,27 @@ use sort::{is_supported_program, sort_and_parse_token_accounts};
 mod bench;
 use bench::*;
 
+mod error;
+use error::TokenCliError;
+
+mod failover;
+use failover::FailoverSender;
+
+mod offline_sender;
+use offline_sender::OfflineSender;
+
+mod decode;
+use decode::{decode_transaction, label_accounts};
+
+mod progress;
+use progress::Progress;
+
+mod env_args;
+use env_args::{
+    amount_of, is_amount, is_valid_pubkey, pubkey_of, pubkey_of_signer,
+    pubkeys_of_multiple_signers, resolve_env_value, resolved_value_of,
+};
+
 pub const OWNER_ADDRESS_ARG: ArgConstant<'static> = ArgConstant {
     name: "owner",
     long: "owner",
@@ -105,27 +184,54 @@ pub const MULTISIG_SIGNER_ARG: ArgConstant<'static> = ArgConstant {
 pub enum CommandName {
     CreateToken,
     Close,
+    CloseMint,
     Bench,
     CreateAccount,
+    RecreateAta,
+    RecoverNested,
     CreateMultisig,
+    CombineSignatures,
     Authorize,
     Transfer,
+    BatchTransfer,
     Burn,
     Mint,
     Freeze,
     Thaw,
+    FreezeAll,
+    ThawAll,
+    SetTransferFee,
+    HarvestWithheldTokensToMint,
+    WithdrawWithheldTokens,
+    UpdateDefaultAccountState,
+    UpdateInterestRate,
+    EnableRequiredTransferMemos,
+    DisableRequiredTransferMemos,
+    ConfigureConfidentialTransferAccount,
+    DepositConfidential,
+    ApplyPendingBalance,
     Wrap,
     Unwrap,
     Approve,
+    ApproveMany,
     Revoke,
+    RevokeAll,
     Balance,
     Supply,
     Accounts,
     Address,
     AccountInfo,
     MultisigInfo,
+    Display,
+    AmountToUiAmount,
+    UiAmountToAmount,
     Gc,
+    CloseEmptyAccounts,
+    ExportAccounts,
+    FindMints,
     SyncNative,
+    Confirm,
+    DecodeTransaction,
 }
 
 pub fn owner_address_arg<'a, 'b>() -> Arg<'a, 'b> {
@@ -137,6 +243,17 @@ pub fn owner_address_arg<'a, 'b>() -> Arg<'a, 'b> {
         .help(OWNER_ADDRESS_ARG.help)
 }
 
+pub fn show_balances_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("show_balances")
+        .long("show-balances")
+        .takes_value(false)
+        .conflicts_with("no_wait")
+        .help(
+            "Print the balances of the token accounts involved before and after the \
+             transaction. Skipped in sign-only mode",
+        )
+}
+
 pub fn owner_keypair_arg_with_value_name<'a, 'b>(value_name: &'static str) -> Arg<'a, 'b> {
     Arg::with_name(OWNER_KEYPAIR_ARG.name)
         .long(OWNER_KEYPAIR_ARG.long)
@@ -221,6 +338,129 @@ fn is_multisig_minimum_signers(string: String) -> Result<(), String> {
     }
 }
 
+// Expands a `k`, `m` or `b` suffix on a token amount by shifting the decimal point of the string
+// representation, so that e.g. `1.5k` becomes `1500` without losing precision to floating point
+// multiplication. Returns `None` if the amount has no suffix, or if what precedes it isn't a
+// number with at most one decimal point.
+fn expand_amount_suffix(amount: &str) -> Option<String> {
+    let shift = match amount.chars().last()? {
+        'k' | 'K' => 3,
+        'm' | 'M' => 6,
+        'b' | 'B' => 9,
+        _ => return None,
+    };
+    let digits = &amount[..amount.len() - 1];
+    if !digits.chars().any(|c| c.is_ascii_digit())
+        || !digits.chars().all(|c| c.is_ascii_digit() || c == '.')
+        || digits.matches('.').count() > 1
+    {
+        return None;
+    }
+    let (integer, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+    let fraction = format!("{:0<1$}", fraction, shift);
+    let (shifted, fraction) = fraction.split_at(shift);
+    let integer = format!("{}{}", integer, shifted);
+    let integer = match integer.trim_start_matches('0') {
+        "" => "0",
+        trimmed => trimmed,
+    };
+    if fraction.is_empty() {
+        Some(integer.to_string())
+    } else {
+        Some(format!("{}.{}", integer, fraction))
+    }
+}
+
+// Parses an amount given as a percentage of the current balance, like `25%`, into hundredths of
+// a percent. It's read as decimal digits rather than a float, which can't hold most fractions
+// exactly, and any digits past the hundredths are dropped, rounding down.
+fn parse_balance_percentage(amount: &str) -> Option<u64> {
+    let number = amount.strip_suffix('%')?;
+    let (integer, fraction) = number.split_once('.').unwrap_or((number, ""));
+    if (integer.is_empty() && fraction.is_empty())
+        || !integer
+            .chars()
+            .chain(fraction.chars())
+            .all(|c| c.is_ascii_digit())
+    {
+        return None;
+    }
+    let integer = if integer.is_empty() {
+        0
+    } else {
+        integer.parse::<u64>().ok()?
+    };
+    let fraction = format!("{:0<2}", &fraction[..fraction.len().min(2)])
+        .parse::<u64>()
+        .ok()?;
+    let hundredths = integer.checked_mul(100)?.checked_add(fraction)?;
+    if hundredths > 0 && hundredths <= 10_000 {
+        Some(hundredths)
+    } else {
+        None
+    }
+}
+
+fn is_amount_with_suffix_or_all(string: String) -> Result<(), String> {
+    let string = resolve_env_value(&string)?.unwrap_or(string);
+    if string.ends_with('%') {
+        return parse_balance_percentage(&string)
+            .map(|_| ())
+            .ok_or_else(|| format!("Unable to parse input percentage, provided: {}", string));
+    }
+    match expand_amount_suffix(&string) {
+        Some(expanded) => is_amount(expanded),
+        None => is_amount_or_all(string),
+    }
+}
+
+// Several endpoints may be given as a comma-separated list, see `FailoverSender`
+fn is_url_or_moniker_list(string: String) -> Result<(), String> {
+    string
+        .split(',')
+        .try_for_each(|url| is_url_or_moniker(url.trim()))
+}
+
+fn is_transfer_fee_basis_points(string: String) -> Result<(), String> {
+    let v = u16::from_str(&string).map_err(|e| e.to_string())?;
+    if v > MAX_FEE_BASIS_POINTS {
+        Err(format!("must be at most {}", MAX_FEE_BASIS_POINTS))
+    } else {
+        Ok(())
+    }
+}
+
+// Names accepted for the token programs wherever their program id is expected
+const PROGRAM_ID_KEYWORDS: &[&str] = &["token", "token-2022", "tk22"];
+
+fn program_id_from_keyword(keyword: &str) -> Option<Pubkey> {
+    match keyword {
+        "token" => Some(spl_token::id()),
+        "token-2022" | "tk22" => Some(spl_token_2022::id()),
+        _ => None,
+    }
+}
+
+fn is_program_id(string: String) -> Result<(), String> {
+    if program_id_from_keyword(&string).is_some() {
+        return Ok(());
+    }
+    is_valid_pubkey(&string).map_err(|_| {
+        format!(
+            "Unrecognized program id {}, expected an address or one of: {}",
+            string,
+            PROGRAM_ID_KEYWORDS.join(", ")
+        )
+    })
+}
+
+fn program_id_of(matches: &ArgMatches<'_>, name: &str) -> Option<Pubkey> {
+    matches
+        .value_of(name)
+        .and_then(program_id_from_keyword)
+        .or_else(|| pubkey_of(matches, name))
+}
+
 pub(crate) type Error = Box<dyn std::error::Error + Send + Sync>;
 
 type BulkSigners = Vec<Box<dyn Signer>>;
@@ -252,14 +492,25 @@ pub(crate) async fn check_fee_payer_balance(
     config: &Config<'_>,
     required_balance: u64,
 ) -> Result<(), Error> {
-    let balance = config.rpc_client.get_balance(&config.fee_payer).await?;
+    check_balance_of_fee_payer(config, &config.fee_payer, required_balance).await
+}
+
+async fn check_balance_of_fee_payer(
+    config: &Config<'_>,
+    fee_payer: &Pubkey,
+    required_balance: u64,
+) -> Result<(), Error> {
+    if config.sign_only || !config.fee_payer_balance_check {
+        return Ok(());
+    }
+    let balance = config.rpc_client.get_balance(fee_payer).await?;
     if balance < required_balance {
-        Err(format!(
-            "Fee payer, {}, has insufficient balance: {} required, {} available",
-            config.fee_payer,
-            lamports_to_sol(required_balance),
-            lamports_to_sol(balance)
-        )
+        Err(TokenCliError::InsufficientFunds {
+            account: *fee_payer,
+            fee_payer: true,
+            required: required_balance,
+            available: balance,
+        }
         .into())
     } else {
         Ok(())
@@ -273,12 +524,12 @@ async fn check_wallet_balance(
 ) -> Result<(), Error> {
     let balance = config.rpc_client.get_balance(wallet).await?;
     if balance < required_balance {
-        Err(format!(
-            "Wallet {}, has insufficient balance: {} required, {} available",
-            wallet,
-            lamports_to_sol(required_balance),
-            lamports_to_sol(balance)
-        )
+        Err(TokenCliError::InsufficientFunds {
+            account: *wallet,
+            fee_payer: false,
+            required: required_balance,
+            available: balance,
+        }
         .into())
     } else {
         Ok(())
@@ -305,6 +556,109 @@ pub fn signers_of(
     }
 }
 
+// How the recipient of a transfer was said to be interpreted, instead of guessing from the account
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum RecipientKind {
+    TokenAccount,
+    Owner,
+}
+
+// Token-2022 extensions that `create-token` can initialize on a new mint
+enum MintExtension {
+    NonTransferable,
+    DefaultAccountState(AccountState),
+    InterestBearing { rate_authority: Pubkey, rate: i16 },
+}
+
+impl MintExtension {
+    fn extension_type(&self) -> ExtensionType {
+        match self {
+            Self::NonTransferable => ExtensionType::NonTransferable,
+            Self::DefaultAccountState(_) => ExtensionType::DefaultAccountState,
+            Self::InterestBearing { .. } => ExtensionType::InterestBearingConfig,
+        }
+    }
+
+    fn instruction(&self, program_id: &Pubkey, mint: &Pubkey) -> Result<Instruction, Error> {
+        Ok(match self {
+            Self::NonTransferable => initialize_non_transferable_mint(program_id, mint)?,
+            Self::DefaultAccountState(state) => {
+                initialize_default_account_state(program_id, mint, state)?
+            }
+            Self::InterestBearing {
+                rate_authority,
+                rate,
+            } => interest_bearing_mint::instruction::initialize(
+                program_id,
+                mint,
+                Some(*rate_authority),
+                *rate,
+            )?,
+        })
+    }
+}
+
+fn parse_account_state(state: &str) -> AccountState {
+    match state {
+        "initialized" => AccountState::Initialized,
+        "frozen" => AccountState::Frozen,
+        _ => unreachable!(),
+    }
+}
+
+// Size and rent-exempt balance of a new mint with the given extensions
+async fn mint_space_and_rent(
+    config: &Config<'_>,
+    extensions: &[MintExtension],
+) -> Result<(usize, u64), Error> {
+    if !extensions.is_empty() && config.program_id != spl_token_2022::id() {
+        return Err(format!(
+            "Error: Mint extensions are only supported by Token-2022, \
+             use `--program-id {}`",
+            spl_token_2022::id()
+        )
+        .into());
+    }
+    let space = if extensions.is_empty() {
+        Mint::LEN
+    } else {
+        ExtensionType::get_account_len::<Mint>(
+            &extensions
+                .iter()
+                .map(MintExtension::extension_type)
+                .collect::<Vec<_>>(),
+        )
+    };
+
+    // Sign-only mode can't query the cluster, so it assumes the default rent parameters in order
+    // to produce a transaction that will actually fund the mint
+    let minimum_balance_for_rent_exemption = if !config.sign_only {
+        config
+            .rpc_client
+            .get_minimum_balance_for_rent_exemption(space)
+            .await?
+    } else {
+        Rent::default().minimum_balance(space)
+    };
+    Ok((space, minimum_balance_for_rent_exemption))
+}
+
+async fn command_create_token_dry_run(
+    config: &Config<'_>,
+    extensions: Vec<MintExtension>,
+) -> CommandResult {
+    let (space, minimum_balance_for_rent_exemption) =
+        mint_space_and_rent(config, &extensions).await?;
+    Ok(config.output_format.formatted_string(&CliMintSize {
+        extensions: extensions
+            .iter()
+            .map(|extension| format!("{:?}", extension.extension_type()))
+            .collect(),
+        space,
+        rent_exempt_lamports: minimum_balance_for_rent_exemption,
+    }))
+}
+
 #[allow(clippy::too_many_arguments)]
 async fn command_create_token(
     config: &Config<'_>,
@@ -312,40 +666,53 @@ async fn command_create_token(
     token: Pubkey,
     authority: Pubkey,
     enable_freeze: bool,
-    memo: Option<String>,
+    extensions: Vec<MintExtension>,
     bulk_signers: Vec<Box<dyn Signer>>,
 ) -> CommandResult {
     println_display(config, format!("Creating token {}", token));
 
-    let minimum_balance_for_rent_exemption = if !config.sign_only {
-        config
+    if !config.sign_only
+        && config
             .rpc_client
-            .get_minimum_balance_for_rent_exemption(Mint::LEN)
+            .get_account_with_commitment(&token, config.commitment)
             .await?
-    } else {
-        0
-    };
+            .value
+            .is_some()
+    {
+        return Err(format!("Error: Account already exists: {}", token).into());
+    }
+
+    let (space, minimum_balance_for_rent_exemption) =
+        mint_space_and_rent(config, &extensions).await?;
+    if config.sign_only {
+        println_display(
+            config,
+            format!(
+                "  Rent: {} SOL, assuming the default rent parameters",
+                lamports_to_sol(minimum_balance_for_rent_exemption)
+            ),
+        );
+    }
     let freeze_authority_pubkey = if enable_freeze { Some(authority) } else { None };
 
-    let mut instructions = vec![
-        system_instruction::create_account(
-            &config.fee_payer,
-            &token,
-            minimum_balance_for_rent_exemption,
-            Mint::LEN as u64,
-            &config.program_id,
-        ),
-        initialize_mint(
-            &config.program_id,
-            &token,
-            &authority,
-            freeze_authority_pubkey.as_ref(),
-            decimals,
-        )?,
-    ];
-    if let Some(text) = memo {
-        instructions.push(spl_memo::build_memo(text.as_bytes(), &[&config.fee_payer]));
+    let mut instructions = vec![system_instruction::create_account(
+        &config.fee_payer,
+        &token,
+        minimum_balance_for_rent_exemption,
+        space as u64,
+        &config.program_id,
+    )];
+    // Extensions must be initialized before the mint itself
+    for extension in &extensions {
+        instructions.push(extension.instruction(&config.program_id, &token)?);
     }
+    instructions.push(initialize_mint(
+        &config.program_id,
+        &token,
+        &authority,
+        freeze_authority_pubkey.as_ref(),
+        decimals,
+    )?);
 
     let tx_return = handle_tx(
         &CliSignerInfo {
@@ -371,65 +738,180 @@ async fn command_create_token(
         TransactionReturnData::CliSignOnlyData(cli_sign_only_data) => {
             format_output(cli_sign_only_data, &CommandName::CreateToken, config)
         }
+        TransactionReturnData::CliSimulation(simulation) => {
+            format_output(simulation, &CommandName::CreateToken, config)
+        }
     })
 }
 
+// The rent of a new associated token account of `token`. A Token-2022 account is sized for the
+// extensions its mint requires of every account, plus the immutable owner the associated token
+// program always adds.
+async fn associated_token_account_rent(
+    config: &Config<'_>,
+    token: &Pubkey,
+    program_id: &Pubkey,
+) -> Result<u64, Error> {
+    let space = if *program_id == spl_token_2022::id() {
+        let mint_account = config.rpc_client.get_account(token).await?;
+        let mint = StateWithExtensionsOwned::<Mint>::unpack(mint_account.data)?;
+        let mut extension_types =
+            ExtensionType::get_required_init_account_extensions(&mint.get_extension_types()?);
+        extension_types.push(ExtensionType::ImmutableOwner);
+        ExtensionType::get_account_len::<Account>(&extension_types)
+    } else {
+        Account::LEN
+    };
+    Ok(config
+        .rpc_client
+        .get_minimum_balance_for_rent_exemption(space)
+        .await?)
+}
+
 async fn command_create_account(
     config: &Config<'_>,
     token: Pubkey,
     owner: Pubkey,
     maybe_account: Option<Pubkey>,
+    idempotent: bool,
+    immutable_owner: bool,
+    seed: Option<&str>,
     bulk_signers: Vec<Box<dyn Signer>>,
 ) -> CommandResult {
-    let minimum_balance_for_rent_exemption = if !config.sign_only {
-        config
-            .rpc_client
-            .get_minimum_balance_for_rent_exemption(Account::LEN)
-            .await?
-    } else {
-        0
-    };
-
     let mint_info = config.get_mint_info(&token, None).await?;
-    let (account, system_account_ok, instructions) = if let Some(account) = maybe_account {
-        println_display(config, format!("Creating account {}", account));
-        (
-            account,
-            false,
-            vec![
-                system_instruction::create_account(
+    // A seed derives the address from the owner instead of an account keypair
+    let maybe_account = match seed {
+        Some(seed) => Some(Pubkey::create_with_seed(
+            &owner,
+            seed,
+            &mint_info.program_id,
+        )?),
+        None => maybe_account,
+    };
+    if immutable_owner && mint_info.program_id != spl_token_2022::id() {
+        return Err(format!(
+            "Error: An immutable owner is only supported by the Token-2022 program, not {}",
+            mint_info.program_id
+        )
+        .into());
+    }
+    let (account, system_account_ok, instructions, minimum_balance_for_rent_exemption) =
+        if let Some(account) = maybe_account {
+            // Some Token-2022 mint extensions need a matching extension on every account, like
+            // the withheld amount for transfer fees, which the associated token program accounts
+            // for by itself. Offline there is no mint to size the account by.
+            if config.sign_only && mint_info.program_id == spl_token_2022::id() {
+                return Err(
+                    "Error: A Token-2022 account is sized for the extensions its mint \
+                     requires, which can't be looked up with `--sign-only`. Leave out \
+                     the account keypair and `--seed` to create the associated token \
+                     account instead"
+                        .into(),
+                );
+            }
+            println_display(config, format!("Creating account {}", account));
+            let mut extension_types = if mint_info.program_id == spl_token_2022::id() {
+                let mint_account = config.rpc_client.get_account(&token).await?;
+                let mint = StateWithExtensionsOwned::<Mint>::unpack(mint_account.data)?;
+                ExtensionType::get_required_init_account_extensions(&mint.get_extension_types()?)
+            } else {
+                vec![]
+            };
+            if immutable_owner {
+                extension_types.push(ExtensionType::ImmutableOwner);
+            }
+            let space = ExtensionType::get_account_len::<Account>(&extension_types);
+            let minimum_balance_for_rent_exemption = if !config.sign_only {
+                config
+                    .rpc_client
+                    .get_minimum_balance_for_rent_exemption(space)
+                    .await?
+            } else {
+                Rent::default().minimum_balance(space)
+            };
+            let mut instructions = vec![match seed {
+                Some(seed) => system_instruction::create_account_with_seed(
                     &config.fee_payer,
                     &account,
+                    &owner,
+                    seed,
                     minimum_balance_for_rent_exemption,
-                    Account::LEN as u64,
+                    space as u64,
                     &mint_info.program_id,
                 ),
-                initialize_account(&mint_info.program_id, &account, &token, &owner)?,
-            ],
-        )
-    } else {
-        let account =
-            get_associated_token_address_with_program_id(&owner, &token, &mint_info.program_id);
-        println_display(config, format!("Creating account {}", account));
-        (
-            account,
-            true,
-            vec![create_associated_token_account(
-                &config.fee_payer,
-                &owner,
-                &token,
+                None => system_instruction::create_account(
+                    &config.fee_payer,
+                    &account,
+                    minimum_balance_for_rent_exemption,
+                    space as u64,
+                    &mint_info.program_id,
+                ),
+            }];
+            // Extensions of the account itself must be initialized before the account
+            if immutable_owner {
+                instructions.push(initialize_immutable_owner(&mint_info.program_id, &account)?);
+            }
+            instructions.push(initialize_account3(
                 &mint_info.program_id,
-            )],
-        )
-    };
+                &account,
+                &token,
+                &owner,
+            )?);
+            (
+                account,
+                false,
+                instructions,
+                minimum_balance_for_rent_exemption,
+            )
+        } else {
+            let account =
+                get_associated_token_address_with_program_id(&owner, &token, &mint_info.program_id);
+            println_display(config, format!("Creating account {}", account));
+            let instruction = if idempotent {
+                create_associated_token_account_idempotent(
+                    &config.fee_payer,
+                    &owner,
+                    &token,
+                    &mint_info.program_id,
+                )
+            } else {
+                create_associated_token_account(
+                    &config.fee_payer,
+                    &owner,
+                    &token,
+                    &mint_info.program_id,
+                )
+            };
+            let minimum_balance_for_rent_exemption = if !config.sign_only {
+                associated_token_account_rent(config, &token, &mint_info.program_id).await?
+            } else {
+                0
+            };
+            (
+                account,
+                true,
+                vec![instruction],
+                minimum_balance_for_rent_exemption,
+            )
+        };
 
     if !config.sign_only {
         if let Some(account_data) = config
             .rpc_client
-            .get_account_with_commitment(&account, config.rpc_client.commitment())
+            .get_account_with_commitment(&account, config.commitment)
             .await?
             .value
         {
+            // Only the associated token program can have made a token account at this address,
+            // so it's for the right owner and mint
+            if idempotent && account_data.owner == mint_info.program_id {
+                let output: CliCreatedAccount<CliSignature> = CliCreatedAccount {
+                    address: account.to_string(),
+                    existed: true,
+                    transaction_data: None,
+                };
+                return Ok(config.output_format.formatted_string(&output));
+            }
             if !(account_data.owner == system_program::id() && system_account_ok) {
                 return Err(format!("Error: Account already exists: {}", account).into());
             }
@@ -447,40 +929,358 @@ async fn command_create_account(
     )
     .await?;
 
+    // The address of an associated or seed-derived account is only known to the user from the
+    // output
+    if !idempotent && seed.is_none() {
+        return Ok(match tx_return {
+            TransactionReturnData::CliSignature(signature) => {
+                config.output_format.formatted_string(&signature)
+            }
+            TransactionReturnData::CliSignOnlyData(sign_only_data) => {
+                config.output_format.formatted_string(&sign_only_data)
+            }
+            TransactionReturnData::CliSimulation(simulation) => {
+                config.output_format.formatted_string(&simulation)
+            }
+        });
+    }
+    let address = account.to_string();
     Ok(match tx_return {
         TransactionReturnData::CliSignature(signature) => {
-            config.output_format.formatted_string(&signature)
+            config.output_format.formatted_string(&CliCreatedAccount {
+                address,
+                existed: false,
+                transaction_data: Some(signature),
+            })
         }
         TransactionReturnData::CliSignOnlyData(sign_only_data) => {
-            config.output_format.formatted_string(&sign_only_data)
+            config.output_format.formatted_string(&CliCreatedAccount {
+                address,
+                existed: false,
+                transaction_data: Some(sign_only_data),
+            })
+        }
+        TransactionReturnData::CliSimulation(simulation) => {
+            config.output_format.formatted_string(&CliCreatedAccount {
+                address,
+                existed: false,
+                transaction_data: Some(simulation),
+            })
         }
     })
 }
 
-async fn command_create_multisig(
+// Make sure the owner has an associated token account under the program that owns the mint, and
+// move the balance of the owner's other accounts of the token into it
+async fn command_recreate_ata(
     config: &Config<'_>,
-    multisig: Pubkey,
-    minimum_signers: u8,
-    multisig_members: Vec<Pubkey>,
+    token: Pubkey,
+    owner: Pubkey,
+    sources: Option<Vec<Pubkey>>,
+    confirm: bool,
     bulk_signers: BulkSigners,
 ) -> CommandResult {
-    println_display(
-        config,
-        format!(
-            "Creating {}/{} multisig {}",
-            minimum_signers,
-            multisig_members.len(),
-            multisig
-        ),
-    );
+    if config.sign_only {
+        return Err(
+            "Error: Finding the accounts to move is not possible with `--sign-only`, \
+             use `create-account` and `transfer` instead"
+                .into(),
+        );
+    }
+    let mint_info = config.get_mint_info(&token, None).await?;
+    let associated_token_address =
+        get_associated_token_address_with_program_id(&owner, &token, &mint_info.program_id);
 
-    let minimum_balance_for_rent_exemption = if !config.sign_only {
-        config
-            .rpc_client
-            .get_minimum_balance_for_rent_exemption(Multisig::LEN)
-            .await?
-    } else {
-        0
+    let mut instructions = vec![];
+    let mut minimum_balance_for_rent_exemption = 0;
+    let associated_account = config
+        .rpc_client
+        .get_account_with_commitment(&associated_token_address, config.commitment)
+        .await?
+        .value;
+    match associated_account {
+        Some(account) if account.owner != system_program::id() => {
+            config
+                .check_account(&associated_token_address, Some(token))
+                .await?;
+        }
+        _ => {
+            minimum_balance_for_rent_exemption =
+                associated_token_account_rent(config, &token, &mint_info.program_id).await?;
+            instructions.push(create_associated_token_account(
+                &config.fee_payer,
+                &owner,
+                &token,
+                &mint_info.program_id,
+            ));
+        }
+    }
+
+    let explicit_sources = sources.is_some();
+    let addresses = match sources {
+        Some(sources) => sources,
+        None => config
+            .rpc_client
+            .get_token_accounts_by_owner(&owner, TokenAccountsFilter::Mint(token))
+            .await?
+            .into_iter()
+            .map(|keyed_account| {
+                keyed_account
+                    .pubkey
+                    .parse::<Pubkey>()
+                    .unwrap_or_else(|err| panic!("Invalid token account: {}", err))
+            })
+            .filter(|address| *address != associated_token_address)
+            .collect::<Vec<_>>(),
+    };
+    let accounts = config.get_multiple_accounts_checked(&addresses).await?;
+    let mut drained = vec![];
+    for (address, account) in addresses.iter().zip(accounts) {
+        let account = match account {
+            Some(account) => account,
+            None if explicit_sources => {
+                return Err(TokenCliError::TokenAccountNotFound(*address).into())
+            }
+            None => continue,
+        };
+        if explicit_sources {
+            if *address == associated_token_address {
+                return Err(
+                    format!("Error: {} is the associated token account itself", address).into(),
+                );
+            }
+            if account.base.mint != token {
+                return Err(format!("Error: {} does not hold {} tokens", address, token).into());
+            }
+            if account.base.owner != owner {
+                return Err(TokenCliError::WrongAccountOwner {
+                    account: *address,
+                    owner: account.base.owner,
+                    expected: owner,
+                }
+                .into());
+            }
+        }
+        if account.base.amount == 0 {
+            continue;
+        }
+        if account.base.is_frozen() {
+            eprintln!("Warning: {} is frozen, its balance can't be moved", address);
+            continue;
+        }
+        drained.push((*address, account.base.amount));
+    }
+
+    // Without named sources, every other account of the token is drained, so they are listed
+    // first and only moved once confirmed
+    if !explicit_sources && !confirm && !drained.is_empty() {
+        return Err(format!(
+            "Error: This will move the balances of {} into {}:\n{}\n\
+             Rerun with `--confirm` to proceed, or name the accounts to move with `--from`",
+            drained.len(),
+            associated_token_address,
+            drained
+                .iter()
+                .map(|(address, amount)| format!(
+                    "  {} ({} tokens)",
+                    address,
+                    spl_token::amount_to_ui_amount(*amount, mint_info.decimals)
+                ))
+                .collect::<Vec<_>>()
+                .join("\n")
+        )
+        .into());
+    }
+
+    let created = !instructions.is_empty();
+    let mut moved = vec![];
+    for (address, amount) in drained {
+        instructions.push(transfer_checked(
+            &mint_info.program_id,
+            &address,
+            &token,
+            &associated_token_address,
+            &owner,
+            &config.multisigner_pubkeys,
+            amount,
+            mint_info.decimals,
+        )?);
+        moved.push(CliMovedBalance {
+            address: address.to_string(),
+            amount,
+            ui_amount: spl_token_2022::amount_to_ui_amount_string_trimmed(
+                amount,
+                mint_info.decimals,
+            ),
+        });
+    }
+
+    let mut transactions = batch_instructions(config, instructions)
+        .into_iter()
+        .map(|tx_instructions| (config.fee_payer, tx_instructions, 0))
+        .collect::<Vec<_>>();
+    let progress = Progress::transactions(config, transactions.len());
+    let signer_info = CliSignerInfo {
+        signers: bulk_signers,
+    };
+    // The transaction creating the account is confirmed on its own first, so that none of the
+    // transfers into it can land before it exists
+    let mut results = vec![];
+    if created {
+        let rest = transactions.split_off(1);
+        transactions[0].2 = minimum_balance_for_rent_exemption;
+        results = handle_txs_with_fee_payers(&signer_info, config, transactions, &progress).await;
+        transactions = rest;
+    }
+    if results.iter().any(|result| result.is_err()) {
+        results.extend(transactions.iter().map(|_| Err("Not sent".into())));
+    } else if !transactions.is_empty() {
+        results.extend(
+            handle_txs_with_fee_payers(&signer_info, config, transactions, &progress).await,
+        );
+    }
+    let (signatures, simulations, failures) = split_batch_results(results);
+
+    finish_batch(
+        config.output_format.formatted_string(&CliRecreateAta {
+            address: associated_token_address.to_string(),
+            program_id: mint_info.program_id.to_string(),
+            created,
+            moved,
+            signatures,
+            simulations,
+        }),
+        failures,
+    )
+}
+
+// Move the tokens of an associated token account that was mistakenly created for another
+// associated token account of the owner back to the owner's own associated token account of the
+// token. The nested account is closed, and its rent goes to the owner.
+async fn command_recover_nested(
+    config: &Config<'_>,
+    owner: Pubkey,
+    owner_token: Pubkey,
+    nested_token: Pubkey,
+    bulk_signers: BulkSigners,
+) -> CommandResult {
+    let owner_mint_info = config.get_mint_info(&owner_token, None).await?;
+    let nested_mint_info = config.get_mint_info(&nested_token, None).await?;
+    let program_id = owner_mint_info.program_id;
+    if nested_mint_info.program_id != program_id {
+        return Err(format!(
+            "Error: Tokens {} and {} belong to different programs, so neither can have an \
+             associated token account of the other",
+            owner_token, nested_token
+        )
+        .into());
+    }
+
+    let owner_associated_token_address =
+        get_associated_token_address_with_program_id(&owner, &owner_token, &program_id);
+    let nested_token_address = get_associated_token_address_with_program_id(
+        &owner_associated_token_address,
+        &nested_token,
+        &program_id,
+    );
+    let destination_address =
+        get_associated_token_address_with_program_id(&owner, &nested_token, &program_id);
+
+    let mut minimum_balance_for_rent_exemption = 0;
+    if !config.sign_only {
+        config
+            .check_account_with_owner(
+                &owner_associated_token_address,
+                Some(owner_token),
+                Some(owner),
+            )
+            .await?;
+        let (nested_account, _) = config
+            .check_account_full(&nested_token_address, Some(nested_token))
+            .await?;
+        println_display(
+            config,
+            format!(
+                "Recovering {} tokens from nested account {}\n  Destination: {}",
+                spl_token::amount_to_ui_amount(
+                    nested_account.base.amount,
+                    nested_mint_info.decimals
+                ),
+                nested_token_address,
+                destination_address
+            ),
+        );
+
+        let destination_account = config
+            .rpc_client
+            .get_account_with_commitment(&destination_address, config.commitment)
+            .await?
+            .value;
+        if destination_account.is_none() {
+            println_display(config, "  Creating the destination".to_string());
+            minimum_balance_for_rent_exemption =
+                associated_token_account_rent(config, &nested_token, &program_id).await?;
+        }
+    }
+
+    // The destination must exist for the tokens to be moved into it
+    let instructions = vec![
+        create_associated_token_account_idempotent(
+            &config.fee_payer,
+            &owner,
+            &nested_token,
+            &program_id,
+        ),
+        recover_nested(&owner, &owner_token, &nested_token, &program_id),
+    ];
+
+    let tx_return = handle_tx(
+        &CliSignerInfo {
+            signers: bulk_signers,
+        },
+        config,
+        false,
+        minimum_balance_for_rent_exemption,
+        instructions,
+    )
+    .await?;
+    Ok(match tx_return {
+        TransactionReturnData::CliSignature(signature) => {
+            config.output_format.formatted_string(&signature)
+        }
+        TransactionReturnData::CliSignOnlyData(sign_only_data) => {
+            config.output_format.formatted_string(&sign_only_data)
+        }
+        TransactionReturnData::CliSimulation(simulation) => {
+            config.output_format.formatted_string(&simulation)
+        }
+    })
+}
+
+async fn command_create_multisig(
+    config: &Config<'_>,
+    multisig: Pubkey,
+    minimum_signers: u8,
+    multisig_members: Vec<Pubkey>,
+    bulk_signers: BulkSigners,
+) -> CommandResult {
+    println_display(
+        config,
+        format!(
+            "Creating {}/{} multisig {}",
+            minimum_signers,
+            multisig_members.len(),
+            multisig
+        ),
+    );
+
+    let minimum_balance_for_rent_exemption = if !config.sign_only {
+        config
+            .rpc_client
+            .get_minimum_balance_for_rent_exemption(Multisig::LEN)
+            .await?
+    } else {
+        0
     };
 
     let instructions = vec![
@@ -509,27 +1309,80 @@ async fn command_create_multisig(
         instructions,
     )
     .await?;
+    let multisig = CliMultisig {
+        address: multisig.to_string(),
+        m: minimum_signers,
+        n: multisig_members.len() as u8,
+        signers: multisig_members
+            .iter()
+            .map(|member| member.to_string())
+            .collect(),
+    };
     Ok(match tx_return {
         TransactionReturnData::CliSignature(signature) => {
-            config.output_format.formatted_string(&signature)
+            config.output_format.formatted_string(&CliCreatedMultisig {
+                multisig,
+                transaction_data: signature,
+            })
         }
         TransactionReturnData::CliSignOnlyData(sign_only_data) => {
-            config.output_format.formatted_string(&sign_only_data)
+            config.output_format.formatted_string(&CliCreatedMultisig {
+                multisig,
+                transaction_data: sign_only_data,
+            })
+        }
+        TransactionReturnData::CliSimulation(simulation) => {
+            config.output_format.formatted_string(&CliCreatedMultisig {
+                multisig,
+                transaction_data: simulation,
+            })
         }
     })
 }
 
+// Multisig members may also be listed in a file, one pubkey per line, with `#` starting a comment
+fn read_multisig_members_file(path: &str) -> Result<Vec<Pubkey>, Error> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|err| format!("Unable to read signers file {}: {}", path, err))?;
+    let mut members = vec![];
+    for (line_number, line) in contents.lines().enumerate() {
+        let line = line.split('#').next().unwrap().trim();
+        if line.is_empty() {
+            continue;
+        }
+        let member = Pubkey::from_str(line).map_err(|_| {
+            format!(
+                "Signers file {} line {}: `{}` is not a valid pubkey",
+                path,
+                line_number + 1,
+                line
+            )
+        })?;
+        members.push(member);
+    }
+    Ok(members)
+}
+
+// Extensions that go with an authority can only be added when the mint is created
+fn missing_extension(mint: Pubkey, extension: &str) -> String {
+    format!(
+        "Error: Mint {} does not have the {} extension, \
+         which can only be added when the mint is created",
+        mint, extension
+    )
+}
+
 #[allow(clippy::too_many_arguments)]
 async fn command_authorize(
     config: &Config<'_>,
     account: Pubkey,
-    authority_type: AuthorityType,
+    mut authority_type: AuthorityType,
     authority: Pubkey,
     new_authority: Option<Pubkey>,
     force_authorize: bool,
     bulk_signers: BulkSigners,
 ) -> CommandResult {
-    let auth_str = match authority_type {
+    let mut auth_str = match authority_type {
         AuthorityType::MintTokens => "mint authority",
         AuthorityType::FreezeAccount => "freeze authority",
         AuthorityType::AccountOwner => "owner",
@@ -539,6 +1392,9 @@ async fn command_authorize(
         AuthorityType::WithheldWithdraw => "withdraw withheld authority",
         AuthorityType::InterestRate => "interest rate authority",
     };
+    if authority_type == AuthorityType::AccountOwner && new_authority.is_none() {
+        return Err("Error: The owner of a token account can't be disabled".into());
+    }
     let (previous_authority, program_id) = if !config.sign_only {
         let target_account = config.rpc_client.get_account(&account).await?;
         config.check_owner(&account, &target_account.owner)?;
@@ -546,6 +1402,11 @@ async fn command_authorize(
         let previous_authority = if let Ok(mint) =
             StateWithExtensionsOwned::<Mint>::unpack(target_account.data.clone())
         {
+            // `close` on a mint can only mean its close authority
+            if authority_type == AuthorityType::CloseAccount {
+                authority_type = AuthorityType::CloseMint;
+                auth_str = "close mint authority";
+            }
             match authority_type {
                 AuthorityType::AccountOwner | AuthorityType::CloseAccount => Err(format!(
                     "Authority type `{}` not supported for SPL Token mints",
@@ -553,10 +1414,34 @@ async fn command_authorize(
                 )),
                 AuthorityType::MintTokens => Ok(mint.base.mint_authority),
                 AuthorityType::FreezeAccount => Ok(mint.base.freeze_authority),
-                AuthorityType::CloseMint => unimplemented!(),
-                AuthorityType::TransferFeeConfig => unimplemented!(),
-                AuthorityType::WithheldWithdraw => unimplemented!(),
-                AuthorityType::InterestRate => unimplemented!(),
+                AuthorityType::CloseMint => mint
+                    .get_extension::<MintCloseAuthority>()
+                    .map(|extension| {
+                        COption::from(Option::<Pubkey>::from(extension.close_authority))
+                    })
+                    .map_err(|_| missing_extension(account, "close authority")),
+                AuthorityType::TransferFeeConfig => mint
+                    .get_extension::<TransferFeeConfig>()
+                    .map(|extension| {
+                        COption::from(Option::<Pubkey>::from(
+                            extension.transfer_fee_config_authority,
+                        ))
+                    })
+                    .map_err(|_| missing_extension(account, "transfer fee")),
+                AuthorityType::WithheldWithdraw => mint
+                    .get_extension::<TransferFeeConfig>()
+                    .map(|extension| {
+                        COption::from(Option::<Pubkey>::from(
+                            extension.withdraw_withheld_authority,
+                        ))
+                    })
+                    .map_err(|_| missing_extension(account, "transfer fee")),
+                AuthorityType::InterestRate => mint
+                    .get_extension::<InterestBearingConfig>()
+                    .map(|extension| {
+                        COption::from(Option::<Pubkey>::from(extension.rate_authority))
+                    })
+                    .map_err(|_| missing_extension(account, "interest-bearing")),
             }
         } else if let Ok(token_account) =
             StateWithExtensionsOwned::<Account>::unpack(target_account.data)
@@ -593,6 +1478,13 @@ async fn command_authorize(
                 )),
                 AuthorityType::AccountOwner => {
                     check_associated_token_account()?;
+                    if token_account.base.owner != authority {
+                        return Err(format!(
+                            "Error: {} is not the owner of account {}, {} is",
+                            authority, account, token_account.base.owner
+                        )
+                        .into());
+                    }
                     Ok(COption::Some(token_account.base.owner))
                 }
                 AuthorityType::CloseAccount => {
@@ -627,6 +1519,14 @@ async fn command_authorize(
                 .unwrap_or_else(|| "disabled".to_string())
         ),
     );
+    if authority_type == AuthorityType::AccountOwner && Some(authority) != new_authority {
+        eprintln!(
+            "Warning: Only {} will be able to move the tokens of {} or give it back, \
+             make sure you control it",
+            new_authority.unwrap(),
+            account
+        );
+    }
 
     let instructions = vec![set_authority(
         &program_id,
@@ -653,6 +1553,93 @@ async fn command_authorize(
         TransactionReturnData::CliSignOnlyData(sign_only_data) => {
             config.output_format.formatted_string(&sign_only_data)
         }
+        TransactionReturnData::CliSimulation(simulation) => {
+            config.output_format.formatted_string(&simulation)
+        }
+    })
+}
+
+// The authority of a confidential transfer mint is part of the extension's configuration rather
+// than a `set_authority` authority type, so it's replaced along with the rest of the
+// configuration, which the new authority has to sign for
+async fn command_authorize_confidential_transfer_mint(
+    config: &Config<'_>,
+    token: Pubkey,
+    authority: Pubkey,
+    new_authority: Option<Pubkey>,
+    bulk_signers: BulkSigners,
+) -> CommandResult {
+    if !config.multisigner_pubkeys.is_empty() {
+        return Err("Error: The confidential transfer mint authority can't be a multisig".into());
+    }
+    if config.sign_only {
+        return Err(
+            "Error: Updating the confidential transfer mint authority needs the rest of the \
+             current configuration of the mint, which isn't available in sign-only mode"
+                .into(),
+        );
+    }
+    let mint_account = config.rpc_client.get_account(&token).await?;
+    config.check_owner(&token, &mint_account.owner)?;
+    let program_id = mint_account.owner;
+    let mint = StateWithExtensionsOwned::<Mint>::unpack(mint_account.data)
+        .map_err(|_| format!("Error: {} is not a mint", token))?;
+    let confidential_mint = *mint
+        .get_extension::<ConfidentialTransferMint>()
+        .map_err(|_| missing_extension(token, "confidential transfer"))?;
+    // An all-zero authority means the configuration can no longer be changed
+    let previous_authority =
+        Some(confidential_mint.authority).filter(|current| *current != Pubkey::default());
+    if previous_authority != Some(authority) {
+        return Err(format!(
+            "Error: {} is not the confidential transfer mint authority of {}",
+            authority, token
+        )
+        .into());
+    }
+
+    println_display(
+        config,
+        format!(
+            "Updating {}\n  Current confidential transfer mint authority: {}\n  \
+             New confidential transfer mint authority: {}",
+            token,
+            authority,
+            new_authority
+                .map(|pubkey| pubkey.to_string())
+                .unwrap_or_else(|| "disabled".to_string())
+        ),
+    );
+
+    let instructions = vec![confidential_transfer::instruction::update_mint(
+        &program_id,
+        &token,
+        &ConfidentialTransferMint {
+            authority: new_authority.unwrap_or_default(),
+            ..confidential_mint
+        },
+        &authority,
+    )?];
+    let tx_return = handle_tx(
+        &CliSignerInfo {
+            signers: bulk_signers,
+        },
+        config,
+        false,
+        0,
+        instructions,
+    )
+    .await?;
+    Ok(match tx_return {
+        TransactionReturnData::CliSignature(signature) => {
+            config.output_format.formatted_string(&signature)
+        }
+        TransactionReturnData::CliSignOnlyData(sign_only_data) => {
+            config.output_format.formatted_string(&sign_only_data)
+        }
+        TransactionReturnData::CliSimulation(simulation) => {
+            config.output_format.formatted_string(&simulation)
+        }
     })
 }
 
@@ -669,23 +1656,72 @@ async fn validate_mint(config: &Config<'_>, token: Pubkey) -> Result<Pubkey, Err
     Ok(mint.owner)
 }
 
+// The token balances of `accounts`, `None` for those that don't exist yet
+async fn token_balances(
+    config: &Config<'_>,
+    accounts: &[Pubkey],
+) -> Result<Vec<Option<u64>>, Error> {
+    let mut balances = vec![];
+    for account in accounts {
+        balances.push(match config.check_account_full(account, None).await {
+            Ok((token_account, _)) => Some(token_account.base.amount),
+            Err(TokenCliError::TokenAccountNotFound(_)) => None,
+            Err(err) => return Err(err.into()),
+        });
+    }
+    Ok(balances)
+}
+
+// Compare `balances_before` with the balances after the transaction, counting a missing account
+// as empty
+async fn balance_changes(
+    config: &Config<'_>,
+    accounts: &[Pubkey],
+    balances_before: Vec<Option<u64>>,
+    decimals: u8,
+) -> Result<Vec<CliBalanceChange>, Error> {
+    let balances_after = token_balances(config, accounts).await?;
+    let ui_amount =
+        |amount: u64| spl_token_2022::amount_to_ui_amount_string_trimmed(amount, decimals);
+    Ok(accounts
+        .iter()
+        .zip(balances_before)
+        .zip(balances_after)
+        .map(|((address, before), after)| {
+            let (before_amount, after_amount) = (before.unwrap_or(0), after.unwrap_or(0));
+            let change = if after_amount >= before_amount {
+                format!("+{}", ui_amount(after_amount - before_amount))
+            } else {
+                format!("-{}", ui_amount(before_amount - after_amount))
+            };
+            CliBalanceChange {
+                address: address.to_string(),
+                before: before.map(ui_amount),
+                after: after.map(ui_amount),
+                change,
+            }
+        })
+        .collect())
+}
+
 #[allow(clippy::too_many_arguments)]
 async fn command_transfer(
     config: &Config<'_>,
     token: Pubkey,
     ui_amount: Option<f64>,
+    balance_percentage: Option<u64>,
     recipient: Pubkey,
     sender: Option<Pubkey>,
     sender_owner: Pubkey,
     allow_unfunded_recipient: bool,
     fund_recipient: bool,
     mint_decimals: Option<u8>,
-    recipient_is_ata_owner: bool,
+    recipient_kind: Option<RecipientKind>,
     use_unchecked_instruction: bool,
-    memo: Option<String>,
+    expected_fee: Option<f64>,
     bulk_signers: BulkSigners,
-    no_wait: bool,
     allow_non_system_account_recipient: bool,
+    show_balances: bool,
 ) -> CommandResult {
     let mint_info = config.get_mint_info(&token, mint_decimals).await?;
     let sender = if let Some(sender) = sender {
@@ -693,9 +1729,13 @@ async fn command_transfer(
     } else {
         get_associated_token_address_with_program_id(&sender_owner, &token, &mint_info.program_id)
     };
-    config.check_account(&sender, Some(token)).await?;
+    config
+        .check_account_with_owner(&sender, Some(token), Some(sender_owner))
+        .await?;
     let maybe_transfer_balance =
         ui_amount.map(|ui_amount| spl_token::ui_amount_to_amount(ui_amount, mint_info.decimals));
+    let expected_fee =
+        expected_fee.map(|fee| spl_token::ui_amount_to_amount(fee, mint_info.decimals));
     let transfer_balance = if !config.sign_only {
         let sender_token_amount = config
             .rpc_client
@@ -713,7 +1753,11 @@ async fn command_transfer(
                 sender, err
             )
         })?;
-        let transfer_balance = maybe_transfer_balance.unwrap_or(sender_balance);
+        let transfer_balance = match balance_percentage {
+            // Round down so the transfer never exceeds the requested share
+            Some(hundredths) => (sender_balance as u128 * hundredths as u128 / 10_000) as u64,
+            None => maybe_transfer_balance.unwrap_or(sender_balance),
+        };
         println_display(
             config,
             format!(
@@ -734,6 +1778,41 @@ async fn command_transfer(
             )
             .into());
         }
+
+        let mint_account = config.rpc_client.get_account(&token).await?;
+        let mint = StateWithExtensionsOwned::<Mint>::unpack(mint_account.data)?;
+        if let Ok(transfer_fee_config) = mint.get_extension::<TransferFeeConfig>() {
+            let epoch = config.rpc_client.get_epoch_info().await?.epoch;
+            let fee = transfer_fee_config
+                .calculate_epoch_fee(epoch, transfer_balance)
+                .ok_or("Error: Could not calculate the transfer fee")?;
+            println_display(
+                config,
+                format!(
+                    "  Fee: {}, recipient receives: {}",
+                    spl_token_2022::amount_to_ui_amount_string_trimmed(fee, mint_info.decimals),
+                    spl_token_2022::amount_to_ui_amount_string_trimmed(
+                        transfer_balance - fee,
+                        mint_info.decimals
+                    ),
+                ),
+            );
+            if let Some(expected_fee) = expected_fee {
+                if expected_fee != fee {
+                    return Err(format!(
+                        "Error: The transfer fee is {}, not the expected {}",
+                        spl_token_2022::amount_to_ui_amount_string_trimmed(fee, mint_info.decimals),
+                        spl_token_2022::amount_to_ui_amount_string_trimmed(
+                            expected_fee,
+                            mint_info.decimals
+                        ),
+                    )
+                    .into());
+                }
+            }
+        } else if expected_fee.is_some() {
+            return Err(format!("Error: Mint {} does not have transfer fees", token).into());
+        }
         transfer_balance
     } else {
         maybe_transfer_balance.unwrap()
@@ -747,21 +1826,32 @@ async fn command_transfer(
     let recipient_is_token_account = if !config.sign_only {
         let recipient_account_info = config
             .rpc_client
-            .get_account_with_commitment(&recipient, config.rpc_client.commitment())
+            .get_account_with_commitment(&recipient, config.commitment)
             .await?
             .value
             .map(|account| {
                 (
-                    account.owner == mint_info.program_id && account.data.len() == Account::LEN,
+                    account.owner == mint_info.program_id
+                        && StateWithExtensionsOwned::<Account>::unpack(account.data).is_ok(),
                     account.owner == system_program::id(),
                 )
             });
+        let recipient_is_token_account = recipient_account_info
+            .map(|(recipient_is_token_account, _)| recipient_is_token_account)
+            .unwrap_or(false);
+        if recipient_kind == Some(RecipientKind::TokenAccount) && !recipient_is_token_account {
+            return Err(
+                format!("Error: The recipient {} is not a token account", recipient).into(),
+            );
+        }
         if let Some((recipient_is_token_account, recipient_is_system_account)) =
             recipient_account_info
         {
+            // An owner given explicitly may well be a program address
             if !recipient_is_token_account
                 && !recipient_is_system_account
                 && !allow_non_system_account_recipient
+                && recipient_kind.is_none()
             {
                 return Err("Error: The recipient address is not owned by the System Program. \
                                      Add `--allow-non-system-account-recipient` to complete the transfer. \
@@ -773,11 +1863,26 @@ async fn command_transfer(
                                    "
             .into());
         }
-        recipient_account_info
-            .map(|(recipient_is_token_account, _)| recipient_is_token_account)
-            .unwrap_or(false)
+        match recipient_kind {
+            Some(recipient_kind) => recipient_kind == RecipientKind::TokenAccount,
+            None => {
+                println_display(
+                    config,
+                    if recipient_is_token_account {
+                        "  Recipient is a token account \
+                         (add `--recipient-is-owner` to send to its associated token account)"
+                            .to_string()
+                    } else {
+                        "  Recipient is the owner of the receiving account \
+                         (add `--recipient-is-token-account` if it is the token account itself)"
+                            .to_string()
+                    },
+                );
+                recipient_is_token_account
+            }
+        }
     } else {
-        !recipient_is_ata_owner
+        recipient_kind != Some(RecipientKind::Owner)
     };
 
     if !recipient_is_token_account {
@@ -797,10 +1902,7 @@ async fn command_transfer(
         let needs_funding = if !config.sign_only {
             if let Some(recipient_token_account_data) = config
                 .rpc_client
-                .get_account_with_commitment(
-                    &recipient_token_account,
-                    config.rpc_client.commitment(),
-                )
+                .get_account_with_commitment(&recipient_token_account, config.commitment)
                 .await?
                 .value
             {
@@ -852,6 +1954,29 @@ async fn command_transfer(
         }
     }
 
+    if !config.sign_only && config.memo.is_none() {
+        let requires_memo = config
+            .rpc_client
+            .get_account_with_commitment(&recipient_token_account, config.commitment)
+            .await?
+            .value
+            .and_then(|account| StateWithExtensionsOwned::<Account>::unpack(account.data).ok())
+            .and_then(|token_account| {
+                token_account
+                    .get_extension::<MemoTransfer>()
+                    .map(|memo_transfer| bool::from(memo_transfer.require_incoming_transfer_memos))
+                    .ok()
+            })
+            .unwrap_or(false);
+        if requires_memo {
+            eprintln!(
+                "Warning: {} requires a memo on incoming transfers, \
+                 the transfer will fail without `--with-memo`",
+                recipient_token_account
+            );
+        }
+    }
+
     if use_unchecked_instruction {
         instructions.push(transfer(
             &mint_info.program_id,
@@ -861,7 +1986,20 @@ async fn command_transfer(
             &config.multisigner_pubkeys,
             transfer_balance,
         )?);
-    } else {
+    } else if let Some(expected_fee) = expected_fee {
+        // The program rejects the transfer if the fee changed after it was checked here
+        instructions.push(transfer_checked_with_fee(
+            &mint_info.program_id,
+            &sender,
+            &mint_info.address,
+            &recipient_token_account,
+            &sender_owner,
+            &config.multisigner_pubkeys,
+            transfer_balance,
+            mint_info.decimals,
+            expected_fee,
+        )?);
+    } else {
         instructions.push(transfer_checked(
             &mint_info.program_id,
             &sender,
@@ -873,29 +2011,497 @@ async fn command_transfer(
             mint_info.decimals,
         )?);
     }
-    if let Some(text) = memo {
-        instructions.push(spl_memo::build_memo(text.as_bytes(), &[&config.fee_payer]));
-    }
+
+    let accounts = [sender, recipient_token_account];
+    let balances_before = if show_balances && !config.sign_only {
+        Some(token_balances(config, &accounts).await?)
+    } else {
+        None
+    };
     let tx_return = handle_tx(
         &CliSignerInfo {
             signers: bulk_signers,
         },
         config,
-        no_wait,
+        false,
         minimum_balance_for_rent_exemption,
         instructions,
     )
     .await?;
     Ok(match tx_return {
-        TransactionReturnData::CliSignature(signature) => {
-            config.output_format.formatted_string(&signature)
-        }
+        TransactionReturnData::CliSignature(signature) => match balances_before {
+            Some(balances_before) => config.output_format.formatted_string(&CliBalanceChanges {
+                balances: balance_changes(config, &accounts, balances_before, mint_info.decimals)
+                    .await?,
+                transaction_data: signature,
+            }),
+            None => config.output_format.formatted_string(&signature),
+        },
         TransactionReturnData::CliSignOnlyData(sign_only_data) => {
             config.output_format.formatted_string(&sign_only_data)
         }
+        TransactionReturnData::CliSimulation(simulation) => {
+            config.output_format.formatted_string(&simulation)
+        }
     })
 }
 
+struct BatchTransferRow {
+    line: usize,
+    recipient: Pubkey,
+    ui_amount: f64,
+    decimals: Option<u8>,
+    // Pays for the transaction carrying this row instead of the configured fee payer
+    fee_payer: Option<Pubkey>,
+}
+
+// `resolve_fee_payer` turns the keypair source in the fee payer column into the fee payer's
+// address, keeping its signer around for signing
+fn parse_batch_transfer_file<F>(
+    path: &str,
+    mut resolve_fee_payer: F,
+) -> Result<Vec<BatchTransferRow>, Error>
+where
+    F: FnMut(&str) -> Result<Pubkey, Error>,
+{
+    let contents = std::fs::read_to_string(path)
+        .map_err(|err| format!("Unable to read batch transfer file {}: {}", path, err))?;
+
+    let mut rows = vec![];
+    for (i, line) in contents.lines().enumerate() {
+        let line_number = i + 1;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let fields: Vec<&str> = line.split(',').map(|field| field.trim()).collect();
+        if rows.is_empty() && fields[0].eq_ignore_ascii_case("recipient") {
+            // header row
+            continue;
+        }
+        if fields.len() < 2 || fields.len() > 4 {
+            return Err(format!(
+                "Line {}: expected `recipient,amount[,decimals[,fee_payer]]`, found `{}`",
+                line_number, line
+            )
+            .into());
+        }
+        let recipient = Pubkey::from_str(fields[0]).map_err(|err| {
+            format!(
+                "Line {}: invalid recipient address `{}`: {}",
+                line_number, fields[0], err
+            )
+        })?;
+        let ui_amount = fields[1].parse::<f64>().map_err(|err| {
+            format!(
+                "Line {}: invalid amount `{}`: {}",
+                line_number, fields[1], err
+            )
+        })?;
+        // Either optional column may be left empty to fill in the next one
+        let decimals = fields
+            .get(2)
+            .filter(|decimals| !decimals.is_empty())
+            .map(|decimals| decimals.parse::<u8>())
+            .transpose()
+            .map_err(|err| format!("Line {}: invalid decimals: {}", line_number, err))?;
+        let fee_payer = fields
+            .get(3)
+            .filter(|fee_payer| !fee_payer.is_empty())
+            .map(|fee_payer| {
+                resolve_fee_payer(fee_payer).map_err(|err| {
+                    format!(
+                        "Line {}: invalid fee payer `{}`: {}",
+                        line_number, fee_payer, err
+                    )
+                })
+            })
+            .transpose()?;
+        rows.push(BatchTransferRow {
+            line: line_number,
+            recipient,
+            ui_amount,
+            decimals,
+            fee_payer,
+        });
+    }
+
+    if rows.is_empty() {
+        Err(format!("No transfers found in {}", path).into())
+    } else {
+        Ok(rows)
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn command_batch_transfer(
+    config: &Config<'_>,
+    token: Pubkey,
+    rows: Vec<BatchTransferRow>,
+    sender: Option<Pubkey>,
+    sender_owner: Pubkey,
+    allow_unfunded_recipient: bool,
+    fund_recipient: bool,
+    mint_decimals: Option<u8>,
+    recipient_is_ata_owner: bool,
+    bulk_signers: BulkSigners,
+) -> CommandResult {
+    let mint_decimals = mint_decimals.or_else(|| rows.iter().find_map(|row| row.decimals));
+    if config.sign_only && mint_decimals.is_none() {
+        return Err(
+            "Error: `--mint-decimals` or a decimals column is required with `--sign-only`".into(),
+        );
+    }
+    let mint_info = config.get_mint_info(&token, mint_decimals).await?;
+    for row in &rows {
+        if let Some(decimals) = row.decimals {
+            if decimals != mint_info.decimals {
+                return Err(format!(
+                    "Line {}: decimals {} do not match mint decimals {}",
+                    row.line, decimals, mint_info.decimals
+                )
+                .into());
+            }
+        }
+    }
+
+    let sender = if let Some(sender) = sender {
+        sender
+    } else {
+        get_associated_token_address_with_program_id(&sender_owner, &token, &mint_info.program_id)
+    };
+    config.check_account(&sender, Some(token)).await?;
+
+    let total_amount = rows
+        .iter()
+        .map(|row| spl_token::ui_amount_to_amount(row.ui_amount, mint_info.decimals))
+        .sum::<u64>();
+    println_display(
+        config,
+        format!(
+            "Batch transfer {} tokens\n  Sender: {}\n  Recipients: {}",
+            spl_token::amount_to_ui_amount(total_amount, mint_info.decimals),
+            sender,
+            rows.len()
+        ),
+    );
+
+    let minimum_balance_for_rent_exemption = if !config.sign_only {
+        let sender_token_amount = config
+            .rpc_client
+            .get_token_account_balance(&sender)
+            .await
+            .map_err(|err| {
+                format!(
+                    "Error: Failed to get token balance of sender address {}: {}",
+                    sender, err
+                )
+            })?;
+        let sender_balance = sender_token_amount.amount.parse::<u64>().map_err(|err| {
+            format!(
+                "Token account {} balance could not be parsed: {}",
+                sender, err
+            )
+        })?;
+        if total_amount > sender_balance {
+            return Err(format!(
+                "Error: Sender has insufficient funds, current balance is {}",
+                spl_token_2022::amount_to_ui_amount_string_trimmed(
+                    sender_balance,
+                    mint_info.decimals
+                )
+            )
+            .into());
+        }
+
+        associated_token_account_rent(config, &token, &mint_info.program_id).await?
+    } else {
+        0
+    };
+
+    // Resolve every recipient up front so that nothing is sent if any row is invalid
+    let recipients = rows.iter().map(|row| row.recipient).collect::<Vec<_>>();
+    let recipient_accounts = if !config.sign_only {
+        config.get_multiple_accounts(&recipients).await?
+    } else {
+        vec![None; recipients.len()]
+    };
+    let mut recipient_token_accounts = vec![];
+    for (row, recipient_account) in rows.iter().zip(recipient_accounts) {
+        let recipient_is_token_account = if !config.sign_only {
+            match recipient_account {
+                Some(account) if account.owner == mint_info.program_id => {
+                    let recipient_account = StateWithExtensionsOwned::<Account>::unpack(
+                        account.data,
+                    )
+                    .map_err(|_| {
+                        format!(
+                            "Line {}: recipient {} is not a token account",
+                            row.line, row.recipient
+                        )
+                    })?;
+                    if recipient_account.base.mint != token {
+                        return Err(format!(
+                            "Line {}: recipient {} does not hold {} tokens",
+                            row.line, row.recipient, token
+                        )
+                        .into());
+                    }
+                    true
+                }
+                Some(account) if account.owner == system_program::id() => false,
+                Some(_) => {
+                    return Err(format!(
+                        "Line {}: recipient {} is not owned by the System Program",
+                        row.line, row.recipient
+                    )
+                    .into());
+                }
+                None if allow_unfunded_recipient => false,
+                None => {
+                    return Err(format!(
+                        "Line {}: recipient {} is not funded. \
+                         Add `--allow-unfunded-recipient` to complete the transfer.",
+                        row.line, row.recipient
+                    )
+                    .into());
+                }
+            }
+        } else {
+            !recipient_is_ata_owner
+        };
+
+        recipient_token_accounts.push(if recipient_is_token_account {
+            (row.recipient, false)
+        } else {
+            let recipient_token_account = get_associated_token_address_with_program_id(
+                &row.recipient,
+                &mint_info.address,
+                &mint_info.program_id,
+            );
+            (recipient_token_account, true)
+        });
+    }
+
+    let associated_token_accounts = recipient_token_accounts
+        .iter()
+        .filter(|(_, is_associated)| *is_associated)
+        .map(|(address, _)| *address)
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect::<Vec<_>>();
+    let existing_associated_token_accounts = if !config.sign_only {
+        config
+            .get_multiple_accounts_checked(&associated_token_accounts)
+            .await?
+            .into_iter()
+            .zip(associated_token_accounts)
+            .filter_map(|(account, address)| account.map(|_| address))
+            .collect::<HashSet<_>>()
+    } else {
+        HashSet::new()
+    };
+
+    let mut funded_accounts = HashSet::new();
+    let mut row_instructions = vec![];
+    for (row, (recipient_token_account, is_associated)) in rows.iter().zip(recipient_token_accounts)
+    {
+        let amount = spl_token::ui_amount_to_amount(row.ui_amount, mint_info.decimals);
+        let fee_payer = row.fee_payer.unwrap_or(config.fee_payer);
+        let mut instructions = vec![];
+        let mut lamports = 0;
+
+        if is_associated && funded_accounts.insert(recipient_token_account) {
+            let needs_funding = if !config.sign_only {
+                !existing_associated_token_accounts.contains(&recipient_token_account)
+            } else {
+                fund_recipient
+            };
+
+            if needs_funding {
+                if !fund_recipient {
+                    return Err(format!(
+                        "Line {}: recipient's associated token account does not exist. \
+                         Add `--fund-recipient` to fund their account",
+                        row.line
+                    )
+                    .into());
+                }
+                instructions.push(create_associated_token_account(
+                    &fee_payer,
+                    &row.recipient,
+                    &mint_info.address,
+                    &mint_info.program_id,
+                ));
+                lamports += minimum_balance_for_rent_exemption;
+            }
+        }
+
+        instructions.push(transfer_checked(
+            &mint_info.program_id,
+            &sender,
+            &mint_info.address,
+            &recipient_token_account,
+            &sender_owner,
+            &config.multisigner_pubkeys,
+            amount,
+            mint_info.decimals,
+        )?);
+        row_instructions.push((row.line, fee_payer, instructions, lamports));
+    }
+
+    // Each row funds at most one new account, so the rows carrying lamports count them. Offline
+    // there is no telling which accounts exist, nor what rent costs.
+    let recipient_rent = if fund_recipient && !config.sign_only {
+        let lamports = row_instructions
+            .iter()
+            .map(|(_, _, _, lamports)| lamports)
+            .sum::<u64>();
+        let accounts = row_instructions
+            .iter()
+            .filter(|(_, _, _, lamports)| *lamports > 0)
+            .count();
+        println_display(
+            config,
+            format!(
+                "  New recipient accounts: {}\n  Rent for new accounts: {} SOL",
+                accounts,
+                lamports_to_sol(lamports)
+            ),
+        );
+        Some(CliRecipientRent {
+            accounts,
+            lamports,
+            sol: lamports_to_sol(lamports),
+        })
+    } else {
+        None
+    };
+
+    // Pack as many rows as possible into each transaction, never splitting a row. Rows only share
+    // a transaction with rows of the same fee payer, so each fee payer fills its own transactions.
+    let mut transactions: Vec<(Vec<usize>, Pubkey, Vec<Instruction>, u64)> = vec![];
+    let mut open_transactions: HashMap<Pubkey, usize> = HashMap::new();
+    for (line, fee_payer, instructions, lamports) in row_instructions {
+        if let Some(index) = open_transactions.get(&fee_payer) {
+            let (lines, _, tx_instructions, tx_lamports) = &mut transactions[*index];
+            let mut candidate = tx_instructions.clone();
+            candidate.extend(instructions.iter().cloned());
+            if transaction_fits_with_fee_payer(config, &fee_payer, &candidate) {
+                *tx_instructions = candidate;
+                lines.push(line);
+                *tx_lamports += lamports;
+                continue;
+            }
+        }
+        if !transaction_fits_with_fee_payer(config, &fee_payer, &instructions) {
+            return Err(format!("Line {}: transfer does not fit in a transaction", line).into());
+        }
+        open_transactions.insert(fee_payer, transactions.len());
+        transactions.push((vec![line], fee_payer, instructions, lamports));
+    }
+
+    if (config.output_file.is_some() || config.input_file.is_some()) && transactions.len() > 1 {
+        return Err(format!(
+            "Error: batch requires {} transactions, but a transaction file can only hold one",
+            transactions.len()
+        )
+        .into());
+    }
+    if config.nonce_account.is_some() && transactions.len() > 1 {
+        return Err(format!(
+            "Error: batch requires {} transactions, but a durable nonce can only be used \
+             for a single transaction",
+            transactions.len()
+        )
+        .into());
+    }
+
+    // Make sure each fee payer can cover its part of the batch before sending the first
+    // transaction, rather than running dry partway through
+    if !config.sign_only && config.fee_payer_balance_check {
+        let recent_blockhash = config.rpc_client.get_latest_blockhash().await?;
+        let mut required_balances: Vec<(Pubkey, u64)> = vec![];
+        for (_, fee_payer, instructions, lamports) in &transactions {
+            let message = new_message_with_fee_payer(
+                config,
+                fee_payer,
+                instructions.clone(),
+                &recent_blockhash,
+            );
+            let required = lamports + config.rpc_client.get_fee_for_message(&message).await?;
+            match required_balances
+                .iter_mut()
+                .find(|(payer, _)| payer == fee_payer)
+            {
+                Some((_, required_balance)) => *required_balance += required,
+                None => required_balances.push((*fee_payer, required)),
+            }
+        }
+        for (fee_payer, required_balance) in required_balances {
+            check_balance_of_fee_payer(config, &fee_payer, required_balance).await?;
+        }
+    }
+
+    let signer_info = CliSignerInfo {
+        signers: bulk_signers,
+    };
+    let (transaction_rows, transactions): (Vec<_>, Vec<_>) = transactions
+        .into_iter()
+        .map(|(rows, fee_payer, instructions, lamports)| {
+            (rows, (fee_payer, instructions, lamports))
+        })
+        .unzip();
+    let progress = Progress::transactions(config, transactions.len());
+    let results = handle_txs_with_fee_payers(&signer_info, config, transactions, &progress).await;
+    let mut cli_transactions = vec![];
+    let mut failures = 0;
+    for (rows, tx_return) in transaction_rows.into_iter().zip(results) {
+        let mut cli_transaction = CliBatchTransferTransaction {
+            rows,
+            signature: None,
+            sign_only_data: None,
+            simulation: None,
+            error: None,
+        };
+        match tx_return {
+            Ok(TransactionReturnData::CliSignature(signature)) => {
+                cli_transaction.signature = Some(signature.signature);
+            }
+            Ok(TransactionReturnData::CliSignOnlyData(sign_only_data)) => {
+                cli_transaction.sign_only_data = Some(sign_only_data);
+            }
+            Ok(TransactionReturnData::CliSimulation(simulation)) => {
+                cli_transaction.simulation = Some(simulation);
+            }
+            Err(err) => {
+                cli_transaction.error = Some(err.to_string());
+                failures += 1;
+            }
+        }
+        cli_transactions.push(cli_transaction);
+    }
+
+    let cli_batch_transfer = CliBatchTransfer {
+        mint: token.to_string(),
+        sender: sender.to_string(),
+        recipient_rent,
+        transactions: cli_transactions,
+    };
+    if failures > 0 {
+        return Err(TokenCliError::Incomplete {
+            output: config.output_format.formatted_string(&cli_batch_transfer),
+            message: format!(
+                "Error: batch transfer incomplete: {} of {} transactions failed",
+                failures,
+                cli_batch_transfer.transactions.len()
+            ),
+        }
+        .into());
+    }
+    Ok(config.output_format.formatted_string(&cli_batch_transfer))
+}
+
 #[allow(clippy::too_many_arguments)]
 async fn command_burn(
     config: &Config<'_>,
@@ -905,7 +2511,7 @@ async fn command_burn(
     mint_address: Option<Pubkey>,
     mint_decimals: Option<u8>,
     use_unchecked_instruction: bool,
-    memo: Option<String>,
+    show_balances: bool,
     bulk_signers: BulkSigners,
 ) -> CommandResult {
     println_display(
@@ -937,9 +2543,12 @@ async fn command_burn(
             mint_info.decimals,
         )?]
     };
-    if let Some(text) = memo {
-        instructions.push(spl_memo::build_memo(text.as_bytes(), &[&config.fee_payer]));
-    }
+    let accounts = [source];
+    let balances_before = if show_balances && !config.sign_only {
+        Some(token_balances(config, &accounts).await?)
+    } else {
+        None
+    };
     let tx_return = handle_tx(
         &CliSignerInfo {
             signers: bulk_signers,
@@ -951,12 +2560,20 @@ async fn command_burn(
     )
     .await?;
     Ok(match tx_return {
-        TransactionReturnData::CliSignature(signature) => {
-            config.output_format.formatted_string(&signature)
-        }
+        TransactionReturnData::CliSignature(signature) => match balances_before {
+            Some(balances_before) => config.output_format.formatted_string(&CliBalanceChanges {
+                balances: balance_changes(config, &accounts, balances_before, mint_info.decimals)
+                    .await?,
+                transaction_data: signature,
+            }),
+            None => config.output_format.formatted_string(&signature),
+        },
         TransactionReturnData::CliSignOnlyData(sign_only_data) => {
             config.output_format.formatted_string(&sign_only_data)
         }
+        TransactionReturnData::CliSimulation(simulation) => {
+            config.output_format.formatted_string(&simulation)
+        }
     })
 }
 
@@ -966,9 +2583,12 @@ async fn command_mint(
     token: Pubkey,
     ui_amount: f64,
     recipient: Pubkey,
+    recipient_owner: Option<Pubkey>,
+    fund_recipient: bool,
     mint_info: MintInfo,
     mint_authority: Pubkey,
     use_unchecked_instruction: bool,
+    show_balances: bool,
     bulk_signers: BulkSigners,
 ) -> CommandResult {
     println_display(
@@ -979,18 +2599,77 @@ async fn command_mint(
         ),
     );
 
+    if !config.sign_only {
+        let mint_account = config.rpc_client.get_account(&token).await?;
+        let mint = StateWithExtensionsOwned::<Mint>::unpack(mint_account.data)?;
+        if mint.base.mint_authority != COption::Some(mint_authority) {
+            return Err(format!(
+                "Error: {} is not the mint authority of {}",
+                mint_authority, token
+            )
+            .into());
+        }
+    }
+
+    // When minting to an owner, `recipient` is the owner's associated token account, which may
+    // have to be created first
+    let mut instructions = vec![];
+    let mut minimum_balance_for_rent_exemption = 0;
+    let recipient_exists = if config.sign_only {
+        recipient_owner.is_none() || !fund_recipient
+    } else {
+        recipient_owner.is_none()
+            || config
+                .rpc_client
+                .get_account_with_commitment(&recipient, config.commitment)
+                .await?
+                .value
+                .is_some()
+    };
+    if recipient_exists {
+        config.check_account(&recipient, Some(token)).await?;
+    } else if let Some(recipient_owner) = recipient_owner {
+        if !fund_recipient {
+            return Err(format!(
+                "Error: The associated token account {} of {} does not exist. \
+                 Add `--fund-recipient` to fund it",
+                recipient, recipient_owner
+            )
+            .into());
+        }
+        if !config.sign_only {
+            minimum_balance_for_rent_exemption =
+                associated_token_account_rent(config, &token, &mint_info.program_id).await?;
+            println_display(
+                config,
+                format!(
+                    "  Funding recipient: {} ({} SOL)",
+                    recipient,
+                    lamports_to_sol(minimum_balance_for_rent_exemption)
+                ),
+            );
+        }
+        // Idempotent, as in sign-only mode the account may well exist already
+        instructions.push(create_associated_token_account_idempotent(
+            &config.fee_payer,
+            &recipient_owner,
+            &token,
+            &mint_info.program_id,
+        ));
+    }
+
     let amount = spl_token::ui_amount_to_amount(ui_amount, mint_info.decimals);
-    let instructions = if use_unchecked_instruction {
-        vec![mint_to(
+    if use_unchecked_instruction {
+        instructions.push(mint_to(
             &mint_info.program_id,
             &token,
             &recipient,
             &mint_authority,
             &config.multisigner_pubkeys,
             amount,
-        )?]
+        )?);
     } else {
-        vec![mint_to_checked(
+        instructions.push(mint_to_checked(
             &mint_info.program_id,
             &token,
             &recipient,
@@ -998,7 +2677,13 @@ async fn command_mint(
             &config.multisigner_pubkeys,
             amount,
             mint_info.decimals,
-        )?]
+        )?);
+    }
+    let accounts = [recipient];
+    let balances_before = if show_balances && !config.sign_only {
+        Some(token_balances(config, &accounts).await?)
+    } else {
+        None
     };
     let tx_return = handle_tx(
         &CliSignerInfo {
@@ -1006,17 +2691,25 @@ async fn command_mint(
         },
         config,
         false,
-        0,
+        minimum_balance_for_rent_exemption,
         instructions,
     )
     .await?;
     Ok(match tx_return {
-        TransactionReturnData::CliSignature(signature) => {
-            config.output_format.formatted_string(&signature)
-        }
+        TransactionReturnData::CliSignature(signature) => match balances_before {
+            Some(balances_before) => config.output_format.formatted_string(&CliBalanceChanges {
+                balances: balance_changes(config, &accounts, balances_before, mint_info.decimals)
+                    .await?,
+                transaction_data: signature,
+            }),
+            None => config.output_format.formatted_string(&signature),
+        },
         TransactionReturnData::CliSignOnlyData(sign_only_data) => {
             config.output_format.formatted_string(&sign_only_data)
         }
+        TransactionReturnData::CliSimulation(simulation) => {
+            config.output_format.formatted_string(&simulation)
+        }
     })
 }
 
@@ -1062,9 +2755,269 @@ async fn command_freeze(
         TransactionReturnData::CliSignOnlyData(sign_only_data) => {
             config.output_format.formatted_string(&sign_only_data)
         }
+        TransactionReturnData::CliSimulation(simulation) => {
+            config.output_format.formatted_string(&simulation)
+        }
     })
 }
 
+async fn command_freeze_all(
+    config: &Config<'_>,
+    mint_address: Pubkey,
+    freeze_authority: Pubkey,
+    confirm: bool,
+    signer_info: &CliSignerInfo,
+) -> CommandResult {
+    freeze_or_thaw_all(
+        config,
+        mint_address,
+        freeze_authority,
+        AccountState::Frozen,
+        confirm,
+        signer_info,
+    )
+    .await
+}
+
+async fn command_thaw_all(
+    config: &Config<'_>,
+    mint_address: Pubkey,
+    freeze_authority: Pubkey,
+    confirm: bool,
+    signer_info: &CliSignerInfo,
+) -> CommandResult {
+    freeze_or_thaw_all(
+        config,
+        mint_address,
+        freeze_authority,
+        AccountState::Initialized,
+        confirm,
+        signer_info,
+    )
+    .await
+}
+
+// Find every token account of a mint with `getProgramAccounts`, skipping anything at the mint
+// offset that doesn't parse as a token account
+async fn get_all_token_accounts_of_mint(
+    config: &Config<'_>,
+    mint_info: &MintInfo,
+) -> Result<Vec<(Pubkey, StateWithExtensionsOwned<Account>)>, Error> {
+    let progress = Progress::spinner(
+        config,
+        format!(
+            "Fetching all token accounts of mint {}, this may take a while",
+            mint_info.address
+        ),
+    );
+    let mut filters = vec![RpcFilterType::Memcmp(Memcmp {
+        offset: 0,
+        bytes: MemcmpEncodedBytes::Base58(mint_info.address.to_string()),
+        encoding: None,
+    })];
+    if mint_info.program_id == spl_token::id() {
+        filters.push(RpcFilterType::DataSize(Account::LEN as u64));
+    }
+    let accounts = config
+        .rpc_client
+        .get_program_accounts_with_config(
+            &mint_info.program_id,
+            RpcProgramAccountsConfig {
+                filters: Some(filters),
+                account_config: RpcAccountInfoConfig {
+                    encoding: Some(UiAccountEncoding::Base64),
+                    commitment: Some(config.commitment),
+                    ..RpcAccountInfoConfig::default()
+                },
+                with_context: None,
+            },
+        )
+        .await?;
+    let accounts = accounts
+        .into_iter()
+        .filter_map(|(address, account)| {
+            StateWithExtensionsOwned::<Account>::unpack(account.data)
+                .ok()
+                .map(|token_account| (address, token_account))
+        })
+        .collect::<Vec<_>>();
+    progress.finish_with_message(format!("{} token accounts scanned", accounts.len()));
+    Ok(accounts)
+}
+
+// Where the fields that scans look at sit in the base token account layout, shared by both
+// programs. The delegate is a 4-byte `COption` tag followed by the pubkey.
+const ACCOUNT_DELEGATE_OFFSET: usize = 72;
+const ACCOUNT_STATE_OFFSET: usize = 108;
+
+// Filters that each let through one kind of mint or token account. Token-2022 mints and accounts
+// with extensions are longer than their base state and store their type right after the length
+// of a base token account, which mints are padded up to, so they take a filter of their own.
+fn account_type_filters(program_id: &Pubkey, account_type: AccountType) -> Vec<RpcFilterType> {
+    let base_len = match account_type {
+        AccountType::Mint => Mint::LEN,
+        _ => Account::LEN,
+    };
+    let mut filters = vec![RpcFilterType::DataSize(base_len as u64)];
+    if *program_id == spl_token_2022::id() {
+        filters.push(RpcFilterType::Memcmp(Memcmp {
+            offset: Account::LEN,
+            bytes: MemcmpEncodedBytes::Base58(bs58::encode([account_type as u8]).into_string()),
+            encoding: None,
+        }));
+    }
+    filters
+}
+
+// Scan a token program for the mints or token accounts matching `filters`, fetching only
+// `length` bytes of each from `offset`. That is much less to download than whole accounts, but
+// leaves out the extensions, for which `get_all_token_accounts_of_mint` is still needed.
+async fn scan_program_accounts(
+    config: &Config<'_>,
+    program_id: &Pubkey,
+    account_type: AccountType,
+    filters: Vec<RpcFilterType>,
+    offset: usize,
+    length: usize,
+) -> Result<Vec<(Pubkey, Vec<u8>)>, Error> {
+    let mut found = vec![];
+    for type_filter in account_type_filters(program_id, account_type) {
+        let mut filters = filters.clone();
+        filters.push(type_filter);
+        let accounts = config
+            .rpc_client
+            .get_program_accounts_with_config(
+                program_id,
+                RpcProgramAccountsConfig {
+                    filters: Some(filters),
+                    account_config: RpcAccountInfoConfig {
+                        encoding: Some(UiAccountEncoding::Base64),
+                        data_slice: Some(UiDataSliceConfig { offset, length }),
+                        commitment: Some(config.commitment),
+                        ..RpcAccountInfoConfig::default()
+                    },
+                    with_context: None,
+                },
+            )
+            .await?;
+        found.extend(
+            accounts
+                .into_iter()
+                .map(|(address, account)| (address, account.data)),
+        );
+    }
+    Ok(found)
+}
+
+async fn freeze_or_thaw_all(
+    config: &Config<'_>,
+    mint_address: Pubkey,
+    freeze_authority: Pubkey,
+    target_state: AccountState,
+    confirm: bool,
+    signer_info: &CliSignerInfo,
+) -> CommandResult {
+    let freeze = target_state == AccountState::Frozen;
+    let (verb, done) = if freeze {
+        ("freeze", "frozen")
+    } else {
+        ("thaw", "thawed")
+    };
+    if config.sign_only {
+        return Err(format!(
+            "Error: Finding the accounts to {} is not possible with `--sign-only`",
+            verb
+        )
+        .into());
+    }
+    if !confirm {
+        return Err(format!(
+            "Error: This will {} every account of mint {}, and finding them queries the whole \
+             token program, which public RPC nodes may reject. Rerun with `--confirm` to proceed",
+            verb, mint_address
+        )
+        .into());
+    }
+
+    let mint_info = config.get_mint_info(&mint_address, None).await?;
+    let mint_account = config.rpc_client.get_account(&mint_address).await?;
+    let mint = StateWithExtensionsOwned::<Mint>::unpack(mint_account.data)?;
+    if mint.base.freeze_authority != COption::Some(freeze_authority) {
+        return Err(format!(
+            "Error: {} is not the freeze authority of mint {}",
+            freeze_authority, mint_address
+        )
+        .into());
+    }
+
+    // Only the state of each account is needed
+    let progress = Progress::spinner(
+        config,
+        format!(
+            "Fetching all token accounts of mint {}, this may take a while",
+            mint_address
+        ),
+    );
+    let accounts = scan_program_accounts(
+        config,
+        &mint_info.program_id,
+        AccountType::Account,
+        vec![RpcFilterType::Memcmp(Memcmp {
+            offset: 0,
+            bytes: MemcmpEncodedBytes::Base58(mint_address.to_string()),
+            encoding: None,
+        })],
+        ACCOUNT_STATE_OFFSET,
+        1,
+    )
+    .await?;
+    progress.finish_with_message(format!("{} token accounts scanned", accounts.len()));
+
+    let mut changed = vec![];
+    let mut unchanged = 0;
+    let mut instructions = vec![];
+    for (address, data) in accounts {
+        // Uninitialized accounts can be neither frozen nor thawed
+        let state = match data.first().map(|state| AccountState::try_from(*state)) {
+            Some(Ok(AccountState::Uninitialized)) | Some(Err(_)) | None => continue,
+            Some(Ok(state)) => state,
+        };
+        if state == target_state {
+            unchanged += 1;
+            continue;
+        }
+        let instruction = if freeze { freeze_account } else { thaw_account };
+        instructions.push(instruction(
+            &mint_info.program_id,
+            &address,
+            &mint_address,
+            &freeze_authority,
+            &config.multisigner_pubkeys,
+        )?);
+        changed.push(address.to_string());
+    }
+
+    let transactions = batch_instructions(config, instructions)
+        .into_iter()
+        .map(|tx_instructions| (tx_instructions, 0))
+        .collect::<Vec<_>>();
+    let progress = Progress::transactions(config, transactions.len());
+    let results = handle_txs(signer_info, config, transactions, &progress).await;
+    let (signatures, simulations, failures) = split_batch_results(results);
+
+    finish_batch(
+        config.output_format.formatted_string(&CliFreezeAll {
+            mint: mint_address.to_string(),
+            state: done.to_string(),
+            accounts: changed,
+            unchanged,
+            signatures,
+            simulations,
+        }),
+        failures,
+    )
+}
+
 async fn command_thaw(
     config: &Config<'_>,
     account: Pubkey,
@@ -1107,83 +3060,46 @@ async fn command_thaw(
         TransactionReturnData::CliSignOnlyData(sign_only_data) => {
             config.output_format.formatted_string(&sign_only_data)
         }
+        TransactionReturnData::CliSimulation(simulation) => {
+            config.output_format.formatted_string(&simulation)
+        }
     })
 }
 
-fn native_mint(program_id: &Pubkey) -> Result<Pubkey, Error> {
-    if program_id == &spl_token_2022::id() {
-        Ok(spl_token_2022::native_mint::id())
-    } else if program_id == &spl_token::id() {
-        Ok(spl_token::native_mint::id())
-    } else {
-        Err(format!("Error: unknown token program id {}", program_id).into())
-    }
-}
-
-async fn command_wrap(
+async fn command_update_default_account_state(
     config: &Config<'_>,
-    sol: f64,
-    wallet_address: Pubkey,
-    wrapped_sol_account: Option<Pubkey>,
+    token: Pubkey,
+    state: AccountState,
+    freeze_authority: Pubkey,
     bulk_signers: BulkSigners,
 ) -> CommandResult {
-    let lamports = sol_to_lamports(sol);
-
-    let native_mint = native_mint(&config.program_id)?;
-    let instructions = if let Some(wrapped_sol_account) = wrapped_sol_account {
-        println_display(
-            config,
-            format!("Wrapping {} SOL into {}", sol, wrapped_sol_account),
-        );
-        vec![
-            system_instruction::create_account(
-                &wallet_address,
-                &wrapped_sol_account,
-                lamports,
-                Account::LEN as u64,
-                &config.program_id,
-            ),
-            initialize_account(
-                &config.program_id,
-                &wrapped_sol_account,
-                &native_mint,
-                &wallet_address,
-            )?,
-        ]
-    } else {
-        let account = get_associated_token_address_with_program_id(
-            &wallet_address,
-            &native_mint,
-            &config.program_id,
-        );
-
-        if !config.sign_only {
-            if let Some(account_data) = config
-                .rpc_client
-                .get_account_with_commitment(&account, config.rpc_client.commitment())
-                .await?
-                .value
-            {
-                if account_data.owner != system_program::id() {
-                    return Err(format!("Error: Account already exists: {}", account).into());
-                }
-            }
-        }
-
-        println_display(config, format!("Wrapping {} SOL into {}", sol, account));
-        vec![
-            system_instruction::transfer(&wallet_address, &account, lamports),
-            create_associated_token_account(
-                &config.fee_payer,
-                &wallet_address,
-                &native_mint,
-                &config.program_id,
-            ),
-        ]
-    };
+    let mint_info = config.get_mint_info(&token, None).await?;
     if !config.sign_only {
-        check_wallet_balance(config, &wallet_address, lamports).await?;
+        let mint_account = config.rpc_client.get_account(&token).await?;
+        let mint = StateWithExtensionsOwned::<Mint>::unpack(mint_account.data)?;
+        mint.get_extension::<DefaultAccountState>().map_err(|_| {
+            format!(
+                "Error: Mint {} does not have the default account state extension",
+                token
+            )
+        })?;
     }
+
+    println_display(
+        config,
+        format!(
+            "Updating default account state for {} to {:?}",
+            token, state
+        ),
+    );
+
+    let instructions = vec![update_default_account_state(
+        &mint_info.program_id,
+        &mint_info.address,
+        &freeze_authority,
+        &config.multisigner_pubkeys,
+        &state,
+    )?];
     let tx_return = handle_tx(
         &CliSignerInfo {
             signers: bulk_signers,
@@ -1201,47 +3117,50 @@ async fn command_wrap(
         TransactionReturnData::CliSignOnlyData(sign_only_data) => {
             config.output_format.formatted_string(&sign_only_data)
         }
+        TransactionReturnData::CliSimulation(simulation) => {
+            config.output_format.formatted_string(&simulation)
+        }
     })
 }
 
-async fn command_unwrap(
+async fn command_update_interest_rate(
     config: &Config<'_>,
-    wallet_address: Pubkey,
-    address: Option<Pubkey>,
+    token: Pubkey,
+    rate: i16,
+    rate_authority: Pubkey,
     bulk_signers: BulkSigners,
 ) -> CommandResult {
-    let use_associated_account = address.is_none();
-    let native_mint = native_mint(&config.program_id)?;
-    let address = address.unwrap_or_else(|| {
-        get_associated_token_address_with_program_id(
-            &wallet_address,
-            &native_mint,
-            &config.program_id,
-        )
-    });
-    println_display(config, format!("Unwrapping {}", address));
+    let mint_info = config.get_mint_info(&token, None).await?;
     if !config.sign_only {
-        let lamports = config.rpc_client.get_balance(&address).await?;
-        if lamports == 0 {
-            if use_associated_account {
-                return Err("No wrapped SOL in associated account; did you mean to specify an auxiliary address?".to_string().into());
-            } else {
-                return Err(format!("No wrapped SOL in {}", address).into());
-            }
+        let mint_account = config.rpc_client.get_account(&token).await?;
+        let mint = StateWithExtensionsOwned::<Mint>::unpack(mint_account.data)?;
+        let extension = mint.get_extension::<InterestBearingConfig>().map_err(|_| {
+            format!(
+                "Error: Mint {} does not have the interest-bearing extension",
+                token
+            )
+        })?;
+        let expected_authority = Option::<Pubkey>::from(extension.rate_authority);
+        if expected_authority != Some(rate_authority) {
+            return Err(format!(
+                "Error: {} is not the rate authority of {}",
+                rate_authority, token
+            )
+            .into());
         }
-        println_display(
-            config,
-            format!("  Amount: {} SOL", lamports_to_sol(lamports)),
-        );
     }
-    println_display(config, format!("  Recipient: {}", &wallet_address));
 
-    let instructions = vec![close_account(
-        &config.program_id,
-        &address,
-        &wallet_address,
-        &wallet_address,
+    println_display(
+        config,
+        format!("Updating interest rate for {} to {}bps", token, rate),
+    );
+
+    let instructions = vec![interest_bearing_mint::instruction::update_rate(
+        &mint_info.program_id,
+        &mint_info.address,
+        &rate_authority,
         &config.multisigner_pubkeys,
+        rate,
     )?];
     let tx_return = handle_tx(
         &CliSignerInfo {
@@ -1260,54 +3179,62 @@ async fn command_unwrap(
         TransactionReturnData::CliSignOnlyData(sign_only_data) => {
             config.output_format.formatted_string(&sign_only_data)
         }
+        TransactionReturnData::CliSimulation(simulation) => {
+            config.output_format.formatted_string(&simulation)
+        }
     })
 }
 
-#[allow(clippy::too_many_arguments)]
-async fn command_approve(
+async fn command_set_transfer_fee(
     config: &Config<'_>,
-    account: Pubkey,
-    owner: Pubkey,
-    ui_amount: f64,
-    delegate: Pubkey,
-    mint_address: Option<Pubkey>,
+    token: Pubkey,
+    transfer_fee_basis_points: u16,
+    maximum_fee: f64,
+    transfer_fee_authority: Pubkey,
     mint_decimals: Option<u8>,
-    use_unchecked_instruction: bool,
     bulk_signers: BulkSigners,
 ) -> CommandResult {
+    if transfer_fee_basis_points > MAX_FEE_BASIS_POINTS {
+        return Err(format!(
+            "Error: Transfer fee of {} basis points exceeds the maximum of {}",
+            transfer_fee_basis_points, MAX_FEE_BASIS_POINTS
+        )
+        .into());
+    }
+    let mint_info = config.get_mint_info(&token, mint_decimals).await?;
+    let maximum_fee_amount = spl_token::ui_amount_to_amount(maximum_fee, mint_info.decimals);
+
     println_display(
         config,
         format!(
-            "Approve {} tokens\n  Account: {}\n  Delegate: {}",
-            ui_amount, account, delegate
+            "Setting transfer fee for {} to {} basis points, with a maximum fee of {}",
+            token, transfer_fee_basis_points, maximum_fee
         ),
     );
+    if !config.sign_only {
+        let mint_account = config.rpc_client.get_account(&token).await?;
+        let mint = StateWithExtensionsOwned::<Mint>::unpack(mint_account.data)?;
+        mint.get_extension::<TransferFeeConfig>()
+            .map_err(|_| format!("Error: Mint {} has no transfer fee configured", token))?;
+        // The program schedules the new fee two epochs ahead of the current one
+        let epoch = config.rpc_client.get_epoch_info().await?.epoch;
+        println_display(
+            config,
+            format!(
+                "  New fee takes effect in epoch {}",
+                epoch.saturating_add(2)
+            ),
+        );
+    }
 
-    let mint_address = config.check_account(&account, mint_address).await?;
-    let mint_info = config.get_mint_info(&mint_address, mint_decimals).await?;
-    let amount = spl_token::ui_amount_to_amount(ui_amount, mint_info.decimals);
-
-    let instructions = if use_unchecked_instruction {
-        vec![approve(
-            &mint_info.program_id,
-            &account,
-            &delegate,
-            &owner,
-            &config.multisigner_pubkeys,
-            amount,
-        )?]
-    } else {
-        vec![approve_checked(
-            &mint_info.program_id,
-            &account,
-            &mint_info.address,
-            &delegate,
-            &owner,
-            &config.multisigner_pubkeys,
-            amount,
-            mint_info.decimals,
-        )?]
-    };
+    let instructions = vec![set_transfer_fee(
+        &mint_info.program_id,
+        &mint_info.address,
+        &transfer_fee_authority,
+        &config.multisigner_pubkeys,
+        transfer_fee_basis_points,
+        maximum_fee_amount,
+    )?];
     let tx_return = handle_tx(
         &CliSignerInfo {
             signers: bulk_signers,
@@ -1325,49 +3252,160 @@ async fn command_approve(
         TransactionReturnData::CliSignOnlyData(sign_only_data) => {
             config.output_format.formatted_string(&sign_only_data)
         }
+        TransactionReturnData::CliSimulation(simulation) => {
+            config.output_format.formatted_string(&simulation)
+        }
     })
 }
 
-async fn command_revoke(
+async fn get_transfer_fee_mint(
     config: &Config<'_>,
-    account: Pubkey,
-    owner: Pubkey,
-    delegate: Option<Pubkey>,
-    bulk_signers: BulkSigners,
-) -> CommandResult {
-    let (delegate, program_id) = if !config.sign_only {
-        let source_account = config.rpc_client.get_account(&account).await?;
-        let source_state = StateWithExtensionsOwned::<Account>::unpack(source_account.data)
-            .map_err(|_| format!("Could not deserialize token account {}", account))?;
+    mint_address: &Pubkey,
+) -> Result<StateWithExtensionsOwned<Mint>, Error> {
+    let mint_account = config.rpc_client.get_account(mint_address).await?;
+    let mint = StateWithExtensionsOwned::<Mint>::unpack(mint_account.data)?;
+    mint.get_extension::<TransferFeeConfig>()
+        .map_err(|_| format!("Error: Mint {} does not have transfer fees", mint_address))?;
+    Ok(mint)
+}
 
-        let delegate = if let COption::Some(delegate) = source_state.base.delegate {
-            Some(delegate)
-        } else {
-            None
-        };
+async fn command_harvest_withheld_tokens_to_mint(
+    config: &Config<'_>,
+    mint_address: Pubkey,
+    sources: Vec<Pubkey>,
+    all: bool,
+    signer_info: &CliSignerInfo,
+) -> CommandResult {
+    if config.sign_only {
+        return Err(
+            "Error: Finding the withheld amounts to harvest is not possible with `--sign-only`"
+                .into(),
+        );
+    }
+    let mint_info = config.get_mint_info(&mint_address, None).await?;
+    get_transfer_fee_mint(config, &mint_address).await?;
 
-        config.check_owner(&account, &source_account.owner)?;
-        (delegate, source_account.owner)
+    let withheld_amount = |token_account: &StateWithExtensionsOwned<Account>| {
+        token_account
+            .get_extension::<TransferFeeAmount>()
+            .map(|transfer_fee_amount| u64::from(transfer_fee_amount.withheld_amount))
+            .unwrap_or(0)
+    };
+    let accounts = if all {
+        get_all_token_accounts_of_mint(config, &mint_info)
+            .await?
+            .into_iter()
+            .map(|(address, token_account)| (address, withheld_amount(&token_account)))
+            .filter(|(_, amount)| *amount > 0)
+            .collect::<Vec<_>>()
     } else {
-        (delegate, config.program_id)
+        let mut accounts = vec![];
+        for (address, token_account) in sources
+            .iter()
+            .zip(config.get_multiple_accounts_checked(&sources).await?)
+        {
+            let token_account =
+                token_account.ok_or_else(|| format!("Could not find token account {}", address))?;
+            if token_account.base.mint != mint_address {
+                return Err(format!(
+                    "Error: Account {} does not hold {} tokens",
+                    address, mint_address
+                )
+                .into());
+            }
+            accounts.push((*address, withheld_amount(&token_account)));
+        }
+        accounts
     };
 
-    if let Some(delegate) = delegate {
-        println_display(
-            config,
-            format!(
-                "Revoking approval\n  Account: {}\n  Delegate: {}",
-                account, delegate
-            ),
-        );
-    } else {
-        return Err(format!("No delegate on account {}", account).into());
+    let mut total: u64 = 0;
+    let mut harvested = vec![];
+    let mut instructions = vec![];
+    for (address, amount) in accounts {
+        total = total.saturating_add(amount);
+        harvested.push(CliAccountBalance {
+            address: address.to_string(),
+            amount: token_amount_to_ui_amount(amount, mint_info.decimals),
+        });
+        instructions.push(harvest_withheld_tokens_to_mint(
+            &mint_info.program_id,
+            &mint_address,
+            &[&address],
+        )?);
     }
 
-    let instructions = vec![revoke(
-        &program_id,
-        &account,
-        &owner,
+    let transactions = batch_instructions(config, instructions)
+        .into_iter()
+        .map(|tx_instructions| (tx_instructions, 0))
+        .collect::<Vec<_>>();
+    let progress = Progress::transactions(config, transactions.len());
+    let results = handle_txs(signer_info, config, transactions, &progress).await;
+    let (signatures, simulations, failures) = split_batch_results(results);
+
+    finish_batch(
+        config
+            .output_format
+            .formatted_string(&CliHarvestedWithheldTokens {
+                mint: mint_address.to_string(),
+                total: token_amount_to_ui_amount(total, mint_info.decimals),
+                accounts: harvested,
+                signatures,
+                simulations,
+            }),
+        failures,
+    )
+}
+
+async fn command_withdraw_withheld_tokens(
+    config: &Config<'_>,
+    mint_address: Pubkey,
+    destination: Pubkey,
+    withdraw_withheld_authority: Pubkey,
+    bulk_signers: BulkSigners,
+) -> CommandResult {
+    let mint_info = config.get_mint_info(&mint_address, None).await?;
+    let withheld_amount = if !config.sign_only {
+        config
+            .check_account(&destination, Some(mint_address))
+            .await?;
+        let mint = get_transfer_fee_mint(config, &mint_address).await?;
+        let transfer_fee_config = mint.get_extension::<TransferFeeConfig>()?;
+        if Option::<Pubkey>::from(transfer_fee_config.withdraw_withheld_authority)
+            != Some(withdraw_withheld_authority)
+        {
+            return Err(format!(
+                "Error: {} is not the withdraw withheld authority of mint {}",
+                withdraw_withheld_authority, mint_address
+            )
+            .into());
+        }
+        let withheld_amount = u64::from(transfer_fee_config.withheld_amount);
+        if withheld_amount == 0 {
+            return Err(format!(
+                "Error: Mint {} holds no withheld tokens. \
+                 Collect them from the token accounts with `harvest-withheld-tokens-to-mint` first",
+                mint_address
+            )
+            .into());
+        }
+        Some(withheld_amount)
+    } else {
+        None
+    };
+
+    println_display(
+        config,
+        format!(
+            "Withdrawing withheld tokens\n  Mint: {}\n  Destination: {}",
+            mint_address, destination
+        ),
+    );
+
+    let instructions = vec![withdraw_withheld_tokens_from_mint(
+        &mint_info.program_id,
+        &mint_address,
+        &destination,
+        &withdraw_withheld_authority,
         &config.multisigner_pubkeys,
     )?];
     let tx_return = handle_tx(
@@ -1380,57 +3418,105 @@ async fn command_revoke(
         instructions,
     )
     .await?;
+    let amount = withheld_amount.map(|amount| {
+        spl_token_2022::amount_to_ui_amount_string_trimmed(amount, mint_info.decimals)
+    });
+    let mint = mint_address.to_string();
+    let destination = destination.to_string();
     Ok(match tx_return {
         TransactionReturnData::CliSignature(signature) => {
-            config.output_format.formatted_string(&signature)
+            config
+                .output_format
+                .formatted_string(&CliWithdrawnWithheldTokens {
+                    mint,
+                    destination,
+                    amount,
+                    transaction_data: signature,
+                })
         }
-        TransactionReturnData::CliSignOnlyData(sign_only_data) => {
-            config.output_format.formatted_string(&sign_only_data)
+        TransactionReturnData::CliSignOnlyData(sign_only_data) => config
+            .output_format
+            .formatted_string(&CliWithdrawnWithheldTokens {
+                mint,
+                destination,
+                amount,
+                transaction_data: sign_only_data,
+            }),
+        TransactionReturnData::CliSimulation(simulation) => {
+            config
+                .output_format
+                .formatted_string(&CliWithdrawnWithheldTokens {
+                    mint,
+                    destination,
+                    amount,
+                    transaction_data: simulation,
+                })
         }
     })
 }
 
-async fn command_close(
+fn native_mint(program_id: &Pubkey) -> Result<Pubkey, Error> {
+    if program_id == &spl_token_2022::id() {
+        Ok(spl_token_2022::native_mint::id())
+    } else if program_id == &spl_token::id() {
+        Ok(spl_token::native_mint::id())
+    } else {
+        Err(format!("Error: unknown token program id {}", program_id).into())
+    }
+}
+
+async fn command_wrap(
     config: &Config<'_>,
-    account: Pubkey,
-    close_authority: Pubkey,
-    recipient: Pubkey,
+    sol: f64,
+    wallet_address: Pubkey,
+    owner: Pubkey,
+    wrapped_sol_account: Option<Pubkey>,
     bulk_signers: BulkSigners,
 ) -> CommandResult {
-    let (is_recipient_wrapped, program_id) = if config.sign_only {
-        (false, config.program_id)
-    } else {
-        let source_account = config.rpc_client.get_account(&account).await?;
-        let source_state = StateWithExtensionsOwned::<Account>::unpack(source_account.data)
-            .map_err(|_| format!("Could not deserialize token account {}", account))?;
-        let source_amount = source_state.base.amount;
+    let lamports = sol_to_lamports(sol);
 
-        if !source_state.base.is_native() && source_amount > 0 {
-            return Err(format!(
-                "Account {} still has {} tokens; empty the account in order to close it.",
-                account, source_amount,
-            )
-            .into());
-        }
-        config.check_owner(&account, &source_account.owner)?;
+    let native_mint = native_mint(&config.program_id)?;
+    let instructions = if let Some(wrapped_sol_account) = wrapped_sol_account {
+        println_display(
+            config,
+            format!("Wrapping {} SOL into {}", sol, wrapped_sol_account),
+        );
+        vec![
+            system_instruction::create_account(
+                &wallet_address,
+                &wrapped_sol_account,
+                lamports,
+                Account::LEN as u64,
+                &config.program_id,
+            ),
+            initialize_account(
+                &config.program_id,
+                &wrapped_sol_account,
+                &native_mint,
+                &owner,
+            )?,
+        ]
+    } else {
+        let account =
+            get_associated_token_address_with_program_id(&owner, &native_mint, &config.program_id);
 
-        let recipient_account = config.rpc_client.get_token_account(&recipient).await?;
-        let is_recipient_wrapped = recipient_account.map(|x| x.is_native).unwrap_or(false);
-        (is_recipient_wrapped, source_account.owner)
+        // Creating the account does nothing if it already exists, and syncing afterwards counts
+        // the new lamports either way, so wrapping again simply tops the account up
+        println_display(config, format!("Wrapping {} SOL into {}", sol, account));
+        vec![
+            create_associated_token_account_idempotent(
+                &config.fee_payer,
+                &owner,
+                &native_mint,
+                &config.program_id,
+            ),
+            system_instruction::transfer(&wallet_address, &account, lamports),
+            sync_native(&config.program_id, &account)?,
+        ]
     };
-
-    let mut instructions = vec![close_account(
-        &program_id,
-        &account,
-        &recipient,
-        &close_authority,
-        &config.multisigner_pubkeys,
-    )?];
-
-    if is_recipient_wrapped {
-        instructions.push(sync_native(&program_id, &recipient)?);
+    if !config.sign_only {
+        check_wallet_balance(config, &wallet_address, lamports).await?;
     }
-
     let tx_return = handle_tx(
         &CliSignerInfo {
             signers: bulk_signers,
@@ -1448,333 +3534,549 @@ async fn command_close(
         TransactionReturnData::CliSignOnlyData(sign_only_data) => {
             config.output_format.formatted_string(&sign_only_data)
         }
+        TransactionReturnData::CliSimulation(simulation) => {
+            config.output_format.formatted_string(&simulation)
+        }
     })
 }
 
-async fn command_balance(config: &Config<'_>, address: Pubkey) -> CommandResult {
-    let balance = config
-        .rpc_client
-        .get_token_account_balance(&address)
-        .await
-        .map_err(|_| format!("Could not find token account {}", address))?;
-    let cli_token_amount = CliTokenAmount { amount: balance };
-    Ok(config.output_format.formatted_string(&cli_token_amount))
-}
-
-async fn command_supply(config: &Config<'_>, address: Pubkey) -> CommandResult {
-    let supply = config.rpc_client.get_token_supply(&address).await?;
-    let cli_token_amount = CliTokenAmount { amount: supply };
-    Ok(config.output_format.formatted_string(&cli_token_amount))
-}
-
-async fn command_accounts(
+async fn command_unwrap(
     config: &Config<'_>,
-    token: Option<Pubkey>,
-    owner: Pubkey,
+    wallet_address: Pubkey,
+    address: Option<Pubkey>,
+    recipient: Pubkey,
+    all: bool,
+    bulk_signers: BulkSigners,
 ) -> CommandResult {
-    let program_id = if let Some(token) = token {
-        validate_mint(config, token).await?
+    let use_associated_account = address.is_none() && !all;
+    let native_mint = native_mint(&config.program_id)?;
+    let addresses = if all {
+        if config.sign_only {
+            return Err(
+                "Error: Finding wrapped SOL accounts with `--all` is not possible \
+                        with `--sign-only`, specify the account to unwrap instead"
+                    .into(),
+            );
+        }
+        let addresses = config
+            .rpc_client
+            .get_token_accounts_by_owner(&wallet_address, TokenAccountsFilter::Mint(native_mint))
+            .await?
+            .into_iter()
+            .map(|keyed_account| {
+                keyed_account
+                    .pubkey
+                    .parse::<Pubkey>()
+                    .unwrap_or_else(|err| panic!("Invalid token account: {}", err))
+            })
+            .collect::<Vec<_>>();
+        if addresses.is_empty() {
+            return Err(format!("No wrapped SOL accounts owned by {}", wallet_address).into());
+        }
+        addresses
     } else {
-        config.program_id
+        vec![address.unwrap_or_else(|| {
+            get_associated_token_address_with_program_id(
+                &wallet_address,
+                &native_mint,
+                &config.program_id,
+            )
+        })]
     };
-    let accounts = config
-        .rpc_client
-        .get_token_accounts_by_owner(
-            &owner,
-            match token {
-                Some(token) => TokenAccountsFilter::Mint(token),
-                None => TokenAccountsFilter::ProgramId(program_id),
-            },
-        )
-        .await?;
-    if accounts.is_empty() {
-        println!("None");
-        return Ok("".to_string());
-    }
 
-    let (mint_accounts, unsupported_accounts, max_len_balance, includes_aux) =
-        sort_and_parse_token_accounts(&owner, accounts, &program_id);
-    let aux_len = if includes_aux { 10 } else { 0 };
+    let mut instructions = vec![];
+    for address in addresses {
+        println_display(config, format!("Unwrapping {}", address));
+        if !config.sign_only {
+            let lamports = config.rpc_client.get_balance(&address).await?;
+            if lamports == 0 {
+                if use_associated_account {
+                    return Err("No wrapped SOL in associated account; did you mean to specify an auxiliary address?".to_string().into());
+                } else {
+                    return Err(format!("No wrapped SOL in {}", address).into());
+                }
+            }
+            config
+                .check_account(&address, Some(native_mint))
+                .await
+                .map_err(|_| format!("Error: {} is not a wrapped SOL account", address))?;
+            println_display(
+                config,
+                format!("  Amount: {} SOL", lamports_to_sol(lamports)),
+            );
+        }
+        instructions.push(close_account(
+            &config.program_id,
+            &address,
+            &recipient,
+            &wallet_address,
+            &config.multisigner_pubkeys,
+        )?);
+    }
+    println_display(config, format!("  Recipient: {}", &recipient));
+
+    // Close as many accounts as fit into each transaction
+    let mut transactions: Vec<Vec<Instruction>> = vec![];
+    for instruction in instructions {
+        if let Some(tx_instructions) = transactions.last_mut() {
+            tx_instructions.push(instruction.clone());
+            if transaction_fits(config, tx_instructions) {
+                continue;
+            }
+            tx_instructions.pop();
+        }
+        transactions.push(vec![instruction]);
+    }
 
-    let cli_token_accounts = CliTokenAccounts {
-        accounts: mint_accounts
-            .into_iter()
-            .map(|(_mint, accounts_list)| accounts_list)
-            .collect(),
-        unsupported_accounts,
-        max_len_balance,
-        aux_len,
-        token_is_some: token.is_some(),
+    let signer_info = CliSignerInfo {
+        signers: bulk_signers,
     };
-    Ok(config.output_format.formatted_string(&cli_token_accounts))
+    let transactions = transactions
+        .into_iter()
+        .map(|tx_instructions| (tx_instructions, 0))
+        .collect::<Vec<_>>();
+    let progress = Progress::transactions(config, transactions.len());
+    let mut result = String::new();
+    let mut failures = vec![];
+    for tx_return in handle_txs(&signer_info, config, transactions, &progress).await {
+        result += &match tx_return {
+            Ok(TransactionReturnData::CliSignature(signature)) => {
+                config.output_format.formatted_string(&signature)
+            }
+            Ok(TransactionReturnData::CliSignOnlyData(sign_only_data)) => {
+                config.output_format.formatted_string(&sign_only_data)
+            }
+            Ok(TransactionReturnData::CliSimulation(simulation)) => {
+                config.output_format.formatted_string(&simulation)
+            }
+            Err(err) => {
+                failures.push(err.to_string());
+                continue;
+            }
+        };
+    }
+    finish_batch(result, failures)
 }
 
-async fn command_address(
+#[allow(clippy::too_many_arguments)]
+async fn command_approve(
     config: &Config<'_>,
-    token: Option<Pubkey>,
+    account: Pubkey,
     owner: Pubkey,
+    ui_amount: f64,
+    delegate: Pubkey,
+    mint_address: Option<Pubkey>,
+    mint_decimals: Option<u8>,
+    use_unchecked_instruction: bool,
+    bulk_signers: BulkSigners,
 ) -> CommandResult {
-    let mut cli_address = CliWalletAddress {
-        wallet_address: owner.to_string(),
-        ..CliWalletAddress::default()
-    };
-    if let Some(token) = token {
-        let program_id = validate_mint(config, token).await?;
-        let associated_token_address =
-            get_associated_token_address_with_program_id(&owner, &token, &program_id);
-        cli_address.associated_token_address = Some(associated_token_address.to_string());
-    }
-    Ok(config.output_format.formatted_string(&cli_address))
-}
-
-async fn command_account_info(config: &Config<'_>, address: Pubkey) -> CommandResult {
-    let account = config
-        .rpc_client
-        .get_token_account(&address)
-        .await
-        .map_err(|_| format!("Could not find token account {}", address))?
-        .unwrap();
-    let mint = Pubkey::from_str(&account.mint).unwrap();
-    let owner = Pubkey::from_str(&account.owner).unwrap();
-    let program_id = config.rpc_client.get_account(&address).await?.owner;
-    let is_associated =
-        get_associated_token_address_with_program_id(&owner, &mint, &program_id) == address;
-    let cli_token_account = CliTokenAccount {
-        address: address.to_string(),
-        is_associated,
-        account,
-    };
-    Ok(config.output_format.formatted_string(&cli_token_account))
-}
+    println_display(
+        config,
+        format!(
+            "Approve {} tokens\n  Account: {}\n  Delegate: {}",
+            ui_amount, account, delegate
+        ),
+    );
 
-async fn get_multisig(config: &Config<'_>, address: &Pubkey) -> Result<Multisig, Error> {
-    let account = config.rpc_client.get_account(address).await?;
-    Multisig::unpack(&account.data).map_err(|e| e.into())
-}
+    let mint_address = config.check_account(&account, mint_address).await?;
+    let mint_info = config.get_mint_info(&mint_address, mint_decimals).await?;
+    let amount = spl_token::ui_amount_to_amount(ui_amount, mint_info.decimals);
 
-async fn command_multisig(config: &Config<'_>, address: Pubkey) -> CommandResult {
-    let multisig = get_multisig(config, &address).await?;
-    let n = multisig.n as usize;
-    assert!(n <= multisig.signers.len());
-    let cli_multisig = CliMultisig {
-        address: address.to_string(),
-        m: multisig.m,
-        n: multisig.n,
-        signers: multisig
-            .signers
-            .iter()
-            .enumerate()
-            .filter_map(|(i, signer)| {
-                if i < n {
-                    Some(signer.to_string())
-                } else {
-                    None
-                }
-            })
-            .collect(),
+    let instructions = if use_unchecked_instruction {
+        vec![approve(
+            &mint_info.program_id,
+            &account,
+            &delegate,
+            &owner,
+            &config.multisigner_pubkeys,
+            amount,
+        )?]
+    } else {
+        vec![approve_checked(
+            &mint_info.program_id,
+            &account,
+            &mint_info.address,
+            &delegate,
+            &owner,
+            &config.multisigner_pubkeys,
+            amount,
+            mint_info.decimals,
+        )?]
     };
-    Ok(config.output_format.formatted_string(&cli_multisig))
+    let tx_return = handle_tx(
+        &CliSignerInfo {
+            signers: bulk_signers,
+        },
+        config,
+        false,
+        0,
+        instructions,
+    )
+    .await?;
+    Ok(match tx_return {
+        TransactionReturnData::CliSignature(signature) => {
+            config.output_format.formatted_string(&signature)
+        }
+        TransactionReturnData::CliSignOnlyData(sign_only_data) => {
+            config.output_format.formatted_string(&sign_only_data)
+        }
+        TransactionReturnData::CliSimulation(simulation) => {
+            config.output_format.formatted_string(&simulation)
+        }
+    })
 }
 
-async fn command_gc(
+async fn command_revoke(
     config: &Config<'_>,
+    account: Pubkey,
     owner: Pubkey,
-    close_empty_associated_accounts: bool,
+    delegate: Option<Pubkey>,
     bulk_signers: BulkSigners,
 ) -> CommandResult {
-    println_display(
-        config,
-        format!(
-            "Fetching token accounts associated with program {}",
-            config.program_id
-        ),
-    );
-    let accounts = config
-        .rpc_client
-        .get_token_accounts_by_owner(&owner, TokenAccountsFilter::ProgramId(config.program_id))
-        .await?;
-    if accounts.is_empty() {
-        println_display(config, "Nothing to do".to_string());
-        return Ok("".to_string());
-    }
+    let (delegate, program_id) = if !config.sign_only {
+        let source_account = config.rpc_client.get_account(&account).await?;
+        let source_state = StateWithExtensionsOwned::<Account>::unpack(source_account.data)
+            .map_err(|_| format!("Could not deserialize token account {}", account))?;
 
-    let minimum_balance_for_rent_exemption = if !config.sign_only {
-        config
-            .rpc_client
-            .get_minimum_balance_for_rent_exemption(Account::LEN)
-            .await?
+        let delegate = if let COption::Some(delegate) = source_state.base.delegate {
+            Some(delegate)
+        } else {
+            None
+        };
+
+        config.check_owner(&account, &source_account.owner)?;
+        (delegate, source_account.owner)
     } else {
-        0
+        (delegate, config.program_id)
     };
 
-    let mut accounts_by_token = HashMap::new();
+    if let Some(delegate) = delegate {
+        println_display(
+            config,
+            format!(
+                "Revoking approval\n  Account: {}\n  Delegate: {}",
+                account, delegate
+            ),
+        );
+    } else {
+        return Err(format!("No delegate on account {}", account).into());
+    }
 
-    for keyed_account in accounts {
-        if let UiAccountData::Json(parsed_account) = keyed_account.account.data {
-            if is_supported_program(&parsed_account.program) {
-                if let Ok(TokenAccountType::Account(ui_token_account)) =
-                    serde_json::from_value(parsed_account.parsed)
-                {
-                    let frozen = ui_token_account.state == UiAccountState::Frozen;
+    let instructions = vec![revoke(
+        &program_id,
+        &account,
+        &owner,
+        &config.multisigner_pubkeys,
+    )?];
+    let tx_return = handle_tx(
+        &CliSignerInfo {
+            signers: bulk_signers,
+        },
+        config,
+        false,
+        0,
+        instructions,
+    )
+    .await?;
+    Ok(match tx_return {
+        TransactionReturnData::CliSignature(signature) => {
+            config.output_format.formatted_string(&signature)
+        }
+        TransactionReturnData::CliSignOnlyData(sign_only_data) => {
+            config.output_format.formatted_string(&sign_only_data)
+        }
+        TransactionReturnData::CliSimulation(simulation) => {
+            config.output_format.formatted_string(&simulation)
+        }
+    })
+}
 
-                    let token = ui_token_account
-                        .mint
-                        .parse::<Pubkey>()
-                        .unwrap_or_else(|err| panic!("Invalid mint: {}", err));
-                    let token_account = keyed_account
+// Approve the same delegate for the same amount on each account. The accounts may hold different
+// tokens, under either token program.
+async fn command_approve_many(
+    config: &Config<'_>,
+    owner: Pubkey,
+    delegate: Pubkey,
+    amount: u64,
+    accounts: Option<Vec<Pubkey>>,
+    token: Option<Pubkey>,
+    signer_info: &CliSignerInfo,
+) -> CommandResult {
+    if config.sign_only {
+        return Err("Error: Approving many accounts is not possible with `--sign-only`".into());
+    }
+    let addresses = match accounts {
+        Some(accounts) => accounts,
+        None => {
+            let token = token.unwrap();
+            let addresses = config
+                .rpc_client
+                .get_token_accounts_by_owner(&owner, TokenAccountsFilter::Mint(token))
+                .await?
+                .into_iter()
+                .map(|keyed_account| {
+                    keyed_account
                         .pubkey
                         .parse::<Pubkey>()
-                        .unwrap_or_else(|err| panic!("Invalid token account: {}", err));
-                    let token_amount = ui_token_account
-                        .token_amount
-                        .amount
-                        .parse::<u64>()
-                        .unwrap_or_else(|err| panic!("Invalid token amount: {}", err));
-
-                    let close_authority = ui_token_account.close_authority.map_or(owner, |s| {
-                        s.parse::<Pubkey>()
-                            .unwrap_or_else(|err| panic!("Invalid close authority: {}", err))
-                    });
-
-                    let entry = accounts_by_token.entry(token).or_insert_with(HashMap::new);
-                    entry.insert(
-                        token_account,
-                        (
-                            token_amount,
-                            ui_token_account.token_amount.decimals,
-                            frozen,
-                            close_authority,
-                        ),
-                    );
-                }
+                        .unwrap_or_else(|err| panic!("Invalid token account: {}", err))
+                })
+                .collect::<Vec<_>>();
+            if addresses.is_empty() {
+                return Err(format!("No {} token accounts owned by {}", token, owner).into());
+            }
+            addresses
+        }
+    };
+    let accounts = config.get_multiple_accounts(&addresses).await?;
+
+    // Each account is checked against its own token program rather than the configured one, so
+    // a mix of Token and Token-2022 accounts can be approved together
+    let mut token_accounts = vec![];
+    for (address, account) in addresses.into_iter().zip(accounts) {
+        let account = account.ok_or(TokenCliError::TokenAccountNotFound(address))?;
+        if account.owner != spl_token::id() && account.owner != spl_token_2022::id() {
+            return Err(format!(
+                "Error: Account {} is owned by {}, not a token program",
+                address, account.owner
+            )
+            .into());
+        }
+        let token_account = StateWithExtensionsOwned::<Account>::unpack(account.data)
+            .map_err(|_| format!("Could not deserialize token account {}", address))?;
+        if token_account.base.owner != owner {
+            return Err(TokenCliError::WrongAccountOwner {
+                account: address,
+                owner: token_account.base.owner,
+                expected: owner,
             }
+            .into());
         }
+        token_accounts.push((address, account.owner, token_account.base.mint));
+    }
+
+    let mints = token_accounts
+        .iter()
+        .map(|(_, _, mint)| *mint)
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect::<Vec<_>>();
+    let mut mint_decimals = HashMap::new();
+    for (mint, account) in mints
+        .iter()
+        .zip(config.get_multiple_accounts(&mints).await?)
+    {
+        let account = account.ok_or(TokenCliError::MintNotFound(*mint))?;
+        let mint_account = StateWithExtensionsOwned::<Mint>::unpack(account.data)
+            .map_err(|_| TokenCliError::InvalidMint(*mint))?;
+        mint_decimals.insert(*mint, (account.owner, mint_account.base.decimals));
     }
 
+    let mut delegations = vec![];
     let mut instructions = vec![];
-    let mut lamports_needed = 0;
-
-    for (token, accounts) in accounts_by_token.into_iter() {
-        println_display(config, format!("Processing token: {}", token));
-        let associated_token_account =
-            get_associated_token_address_with_program_id(&owner, &token, &config.program_id);
-        let total_balance: u64 = accounts.values().map(|account| account.0).sum();
-
-        if total_balance > 0 && !accounts.contains_key(&associated_token_account) {
-            // Create the associated token account
-            instructions.push(vec![create_associated_token_account(
-                &config.fee_payer,
-                &owner,
-                &token,
-                &config.program_id,
-            )]);
-            lamports_needed += minimum_balance_for_rent_exemption;
+    for (address, program_id, mint) in token_accounts {
+        let (mint_program_id, decimals) = mint_decimals[&mint];
+        if mint_program_id != program_id {
+            return Err(format!(
+                "Error: Mint {} of account {} is owned by {}, not {}",
+                mint, address, mint_program_id, program_id
+            )
+            .into());
         }
 
-        for (address, (amount, decimals, frozen, close_authority)) in accounts {
-            match (
-                address == associated_token_account,
-                close_empty_associated_accounts,
-                total_balance > 0,
-            ) {
-                (true, _, true) => continue, // don't ever close associated token account with amount
-                (true, false, _) => continue, // don't close associated token account if close_empty_associated_accounts isn't set
-                (true, true, false) => println_display(
-                    config,
-                    format!("Closing Account {}", associated_token_account),
-                ),
-                _ => {}
-            }
-
-            if frozen {
-                // leave frozen accounts alone
-                continue;
-            }
-
-            let mut account_instructions = vec![];
-
-            // Sanity check!
-            // we shouldn't ever be here, but if we are here, abort!
-            assert!(amount == 0 || address != associated_token_account);
+        instructions.push(approve_checked(
+            &program_id,
+            &address,
+            &mint,
+            &delegate,
+            &owner,
+            &config.multisigner_pubkeys,
+            amount,
+            decimals,
+        )?);
+        delegations.push(CliDelegation {
+            address: address.to_string(),
+            mint: mint.to_string(),
+            amount,
+            ui_amount: spl_token_2022::amount_to_ui_amount_string_trimmed(amount, decimals),
+        });
+    }
 
-            if amount > 0 {
-                // Transfer the account balance into the associated token account
-                account_instructions.push(transfer_checked(
-                    &config.program_id,
-                    &address,
-                    &token,
-                    &associated_token_account,
-                    &owner,
-                    &config.multisigner_pubkeys,
-                    amount,
-                    decimals,
-                )?);
-            }
-            // Close the account if config.owner is able to
-            if close_authority == owner {
-                account_instructions.push(close_account(
-                    &config.program_id,
-                    &address,
-                    &owner,
-                    &owner,
-                    &config.multisigner_pubkeys,
-                )?);
-            }
+    let transactions = batch_instructions(config, instructions)
+        .into_iter()
+        .map(|tx_instructions| (tx_instructions, 0))
+        .collect::<Vec<_>>();
+    let progress = Progress::transactions(config, transactions.len());
+    let results = handle_txs(signer_info, config, transactions, &progress).await;
+    let (signatures, simulations, failures) = split_batch_results(results);
+
+    finish_batch(
+        config.output_format.formatted_string(&CliApproveMany {
+            delegate: delegate.to_string(),
+            accounts: delegations,
+            signatures,
+            simulations,
+        }),
+        failures,
+    )
+}
 
-            if !account_instructions.is_empty() {
-                instructions.push(account_instructions);
-            }
-        }
+async fn command_revoke_all(
+    config: &Config<'_>,
+    owner: Pubkey,
+    token: Option<Pubkey>,
+    confirm: bool,
+    signer_info: &CliSignerInfo,
+) -> CommandResult {
+    if config.sign_only {
+        return Err("Error: Finding delegated accounts is not possible with `--sign-only`".into());
+    }
+    if !confirm {
+        return Err(format!(
+            "Error: This will revoke the delegate of every {}account owned by {}. \
+             Rerun with `--confirm` to proceed",
+            token.map(|token| format!("{} ", token)).unwrap_or_default(),
+            owner
+        )
+        .into());
     }
 
-    let cli_signer_info = CliSignerInfo {
-        signers: bulk_signers,
+    // Without a mint, the accounts of both token programs are revoked, since an owner's
+    // delegations aren't tied to whichever program is configured
+    let filters = match token {
+        Some(token) => vec![serde_json::json!({ "mint": token.to_string() })],
+        None => [spl_token::id(), spl_token_2022::id()]
+            .iter()
+            .map(|program_id| serde_json::json!({ "programId": program_id.to_string() }))
+            .collect(),
     };
+    let mut keyed_accounts = vec![];
+    for filter in filters {
+        // Requested directly for just the `COption` tag of the delegate, instead of whole
+        // parsed accounts
+        let response: Response<Vec<RpcKeyedAccount>> = config
+            .rpc_client
+            .send(
+                RpcRequest::GetTokenAccountsByOwner,
+                serde_json::json!([
+                    owner.to_string(),
+                    filter,
+                    RpcAccountInfoConfig {
+                        encoding: Some(UiAccountEncoding::Base64),
+                        data_slice: Some(UiDataSliceConfig {
+                            offset: ACCOUNT_DELEGATE_OFFSET,
+                            length: 4,
+                        }),
+                        commitment: Some(config.commitment),
+                        ..RpcAccountInfoConfig::default()
+                    },
+                ]),
+            )
+            .await?;
+        keyed_accounts.extend(response.value);
+    }
 
-    let mut result = String::from("");
-    for tx_instructions in instructions {
-        let tx_return = handle_tx(
-            &cli_signer_info,
-            config,
-            false,
-            lamports_needed,
-            tx_instructions,
-        )
-        .await?;
-        result += &match tx_return {
-            TransactionReturnData::CliSignature(signature) => {
-                config.output_format.formatted_string(&signature)
-            }
-            TransactionReturnData::CliSignOnlyData(sign_only_data) => {
-                config.output_format.formatted_string(&sign_only_data)
-            }
-        };
-        result += "\n";
+    let mut revoked = vec![];
+    let mut instructions = vec![];
+    for keyed_account in keyed_accounts {
+        let address = Pubkey::from_str(&keyed_account.pubkey)?;
+        let account = keyed_account
+            .account
+            .decode::<solana_sdk::account::Account>()
+            .ok_or_else(|| format!("Could not decode account {}", address))?;
+        let program_id = account.owner;
+        // Any set byte in the tag means there is a delegate
+        match account.data.get(..4) {
+            Some(tag) if tag.iter().any(|byte| *byte != 0) => {}
+            _ => continue,
+        }
+        instructions.push(revoke(
+            &program_id,
+            &address,
+            &owner,
+            &config.multisigner_pubkeys,
+        )?);
+        revoked.push(address.to_string());
     }
-    Ok(result)
+
+    let transactions = batch_instructions(config, instructions)
+        .into_iter()
+        .map(|tx_instructions| (tx_instructions, 0))
+        .collect::<Vec<_>>();
+    let progress = Progress::transactions(config, transactions.len());
+    let results = handle_txs(signer_info, config, transactions, &progress).await;
+    let (signatures, simulations, failures) = split_batch_results(results);
+
+    finish_batch(
+        config.output_format.formatted_string(&CliRevokeAll {
+            owner: owner.to_string(),
+            mint: token.map(|token| token.to_string()),
+            revoked,
+            signatures,
+            simulations,
+        }),
+        failures,
+    )
 }
 
-async fn command_sync_native(
-    native_account_address: Pubkey,
-    bulk_signers: Vec<Box<dyn Signer>>,
+async fn command_required_transfer_memos(
     config: &Config<'_>,
+    account: Pubkey,
+    owner: Pubkey,
+    enable: bool,
+    bulk_signers: BulkSigners,
 ) -> CommandResult {
-    let program_id = if config.sign_only {
-        config.program_id
+    if config.program_id != spl_token_2022::id() {
+        return Err(
+            "Error: Required transfer memos are only available for Token-2022 accounts \
+                    (set `--program-id` to the Token-2022 program)"
+                .into(),
+        );
+    }
+    // Accounts are sized for the extensions of their mint, so the memo-transfer extension
+    // usually needs room made for it first. Offline there is no way to tell, and reallocating
+    // an account that already has the extension does nothing.
+    let needs_reallocate = if config.sign_only {
+        enable
     } else {
-        config
-            .rpc_client
-            .get_account(&native_account_address)
-            .await
-            .map_err(|err| {
-                format!(
-                    "Token account {} does not exist: {}",
-                    native_account_address, err
-                )
-            })?
-            .owner
+        let (token_account, _) = config.check_account_full(&account, None).await?;
+        enable && token_account.get_extension::<MemoTransfer>().is_err()
     };
 
+    println_display(
+        config,
+        format!(
+            "{} required transfer memos for {}",
+            if enable { "Enabling" } else { "Disabling" },
+            account
+        ),
+    );
+
+    let mut instructions = vec![];
+    if needs_reallocate {
+        instructions.push(reallocate(
+            &config.program_id,
+            &account,
+            &config.fee_payer,
+            &owner,
+            &config.multisigner_pubkeys,
+            &[ExtensionType::MemoTransfer],
+        )?);
+    }
+    instructions.push(if enable {
+        enable_required_transfer_memos(
+            &config.program_id,
+            &account,
+            &owner,
+            &config.multisigner_pubkeys,
+        )?
+    } else {
+        disable_required_transfer_memos(
+            &config.program_id,
+            &account,
+            &owner,
+            &config.multisigner_pubkeys,
+        )?
+    });
     let tx_return = handle_tx(
         &CliSignerInfo {
             signers: bulk_signers,
@@ -1782,7 +4084,7 @@ async fn command_sync_native(
         config,
         false,
         0,
-        vec![sync_native(&program_id, &native_account_address)?],
+        instructions,
     )
     .await?;
     Ok(match tx_return {
@@ -1792,1929 +4094,10716 @@ async fn command_sync_native(
         TransactionReturnData::CliSignOnlyData(sign_only_data) => {
             config.output_format.formatted_string(&sign_only_data)
         }
+        TransactionReturnData::CliSimulation(simulation) => {
+            config.output_format.formatted_string(&simulation)
+        }
     })
 }
 
-struct SignOnlyNeedsFullMintSpec {}
-impl offline::ArgsConfig for SignOnlyNeedsFullMintSpec {
-    fn sign_only_arg<'a, 'b>(&self, arg: Arg<'a, 'b>) -> Arg<'a, 'b> {
-        arg.requires_all(&[MINT_ADDRESS_ARG.name, MINT_DECIMALS_ARG.name])
-    }
-}
+async fn command_close(
+    config: &Config<'_>,
+    account: Pubkey,
+    close_authority: Pubkey,
+    recipient: Pubkey,
+    bulk_signers: BulkSigners,
+) -> CommandResult {
+    let (is_recipient_wrapped, program_id) = if config.sign_only {
+        (false, config.program_id)
+    } else {
+        let source_account = config.rpc_client.get_account(&account).await?;
+        let source_state = StateWithExtensionsOwned::<Account>::unpack(source_account.data)
+            .map_err(|_| format!("Could not deserialize token account {}", account))?;
+        let source_amount = source_state.base.amount;
 
-struct SignOnlyNeedsMintDecimals {}
-impl offline::ArgsConfig for SignOnlyNeedsMintDecimals {
-    fn sign_only_arg<'a, 'b>(&self, arg: Arg<'a, 'b>) -> Arg<'a, 'b> {
-        arg.requires_all(&[MINT_DECIMALS_ARG.name])
-    }
-}
+        if !source_state.base.is_native() && source_amount > 0 {
+            return Err(format!(
+                "Account {} still has {} tokens; empty the account in order to close it.",
+                account, source_amount,
+            )
+            .into());
+        }
+        config.check_owner(&account, &source_account.owner)?;
 
-struct SignOnlyNeedsMintAddress {}
-impl offline::ArgsConfig for SignOnlyNeedsMintAddress {
-    fn sign_only_arg<'a, 'b>(&self, arg: Arg<'a, 'b>) -> Arg<'a, 'b> {
-        arg.requires_all(&[MINT_ADDRESS_ARG.name])
-    }
-}
+        let recipient_account = config.rpc_client.get_token_account(&recipient).await?;
+        let is_recipient_wrapped = recipient_account.map(|x| x.is_native).unwrap_or(false);
+        (is_recipient_wrapped, source_account.owner)
+    };
 
-struct SignOnlyNeedsDelegateAddress {}
-impl offline::ArgsConfig for SignOnlyNeedsDelegateAddress {
-    fn sign_only_arg<'a, 'b>(&self, arg: Arg<'a, 'b>) -> Arg<'a, 'b> {
-        arg.requires_all(&[DELEGATE_ADDRESS_ARG.name])
+    let mut instructions = vec![close_account(
+        &program_id,
+        &account,
+        &recipient,
+        &close_authority,
+        &config.multisigner_pubkeys,
+    )?];
+
+    if is_recipient_wrapped {
+        instructions.push(sync_native(&program_id, &recipient)?);
     }
-}
 
-fn minimum_signers_help_string() -> String {
-    format!(
-        "The minimum number of signers required to allow the operation. [{} <= M <= N]",
-        MIN_SIGNERS
+    let tx_return = handle_tx(
+        &CliSignerInfo {
+            signers: bulk_signers,
+        },
+        config,
+        false,
+        0,
+        instructions,
     )
+    .await?;
+    Ok(match tx_return {
+        TransactionReturnData::CliSignature(signature) => {
+            config.output_format.formatted_string(&signature)
+        }
+        TransactionReturnData::CliSignOnlyData(sign_only_data) => {
+            config.output_format.formatted_string(&sign_only_data)
+        }
+        TransactionReturnData::CliSimulation(simulation) => {
+            config.output_format.formatted_string(&simulation)
+        }
+    })
 }
 
-fn multisig_member_help_string() -> String {
-    format!(
-        "The public keys for each of the N signing members of this account. [{} <= N <= {}]",
-        MIN_SIGNERS, MAX_SIGNERS
+async fn command_close_mint(
+    config: &Config<'_>,
+    token: Pubkey,
+    close_authority: Pubkey,
+    recipient: Pubkey,
+    bulk_signers: BulkSigners,
+) -> CommandResult {
+    let program_id = if config.sign_only {
+        config.program_id
+    } else {
+        let mint_info = config.get_mint_info(&token, None).await?;
+        let mint_account = config.rpc_client.get_account(&token).await?;
+        let mint = StateWithExtensionsOwned::<Mint>::unpack(mint_account.data)?;
+        if mint.base.supply > 0 {
+            return Err(format!(
+                "Error: Mint {} still has a supply of {} tokens; burn them in order to close it",
+                token,
+                spl_token_2022::amount_to_ui_amount_string_trimmed(
+                    mint.base.supply,
+                    mint_info.decimals
+                )
+            )
+            .into());
+        }
+        let mint_close_authority = mint
+            .get_extension::<MintCloseAuthority>()
+            .map(|extension| Option::<Pubkey>::from(extension.close_authority))
+            .map_err(|_| {
+                format!(
+                    "Error: Mint {} does not have the close authority extension, \
+                     so it can't be closed",
+                    token
+                )
+            })?;
+        if mint_close_authority != Some(close_authority) {
+            return Err(format!(
+                "Error: {} is not the close authority of mint {}",
+                close_authority, token
+            )
+            .into());
+        }
+        mint_info.program_id
+    };
+
+    println_display(
+        config,
+        format!("Closing mint {}\n  Recipient: {}", token, recipient),
+    );
+
+    let instructions = vec![close_account(
+        &program_id,
+        &token,
+        &recipient,
+        &close_authority,
+        &config.multisigner_pubkeys,
+    )?];
+    let tx_return = handle_tx(
+        &CliSignerInfo {
+            signers: bulk_signers,
+        },
+        config,
+        false,
+        0,
+        instructions,
     )
+    .await?;
+    Ok(match tx_return {
+        TransactionReturnData::CliSignature(signature) => {
+            config.output_format.formatted_string(&signature)
+        }
+        TransactionReturnData::CliSignOnlyData(sign_only_data) => {
+            config.output_format.formatted_string(&sign_only_data)
+        }
+        TransactionReturnData::CliSimulation(simulation) => {
+            config.output_format.formatted_string(&simulation)
+        }
+    })
 }
 
-fn app<'a, 'b>(
-    default_decimals: &'a str,
-    default_program_id: &'a str,
-    minimum_signers_help: &'b str,
-    multisig_member_help: &'b str,
-) -> App<'a, 'b> {
-    App::new(crate_name!())
-        .about(crate_description!())
-        .version(crate_version!())
-        .setting(AppSettings::SubcommandRequiredElseHelp)
-        .arg({
-            let arg = Arg::with_name("config_file")
-                .short("C")
-                .long("config")
-                .value_name("PATH")
-                .takes_value(true)
-                .global(true)
-                .help("Configuration file to use");
-            if let Some(ref config_file) = *solana_cli_config::CONFIG_FILE {
-                arg.default_value(config_file)
+// Load the ElGamal keypair from `elgamal_keypair_path`, generating and saving a new one there if
+// the file doesn't exist yet and `generate` is set. Without a path, the keypair is derived from a
+// signature of the owner over the token account address, so nothing needs to be stored.
+fn confidential_transfer_elgamal_keypair(
+    owner_signer: &dyn Signer,
+    account: &Pubkey,
+    elgamal_keypair_path: Option<&str>,
+    generate: bool,
+) -> Result<ElGamalKeypair, Error> {
+    match elgamal_keypair_path {
+        Some(path) if !generate || std::path::Path::new(path).exists() => {
+            ElGamalKeypair::read_json_file(path).map_err(|err| {
+                format!("Unable to read ElGamal keypair file {}: {}", path, err).into()
+            })
+        }
+        Some(path) => {
+            let elgamal_keypair = ElGamalKeypair::new_rand();
+            elgamal_keypair
+                .write_json_file(path)
+                .map_err(|err| format!("Unable to write ElGamal keypair file {}: {}", path, err))?;
+            Ok(elgamal_keypair)
+        }
+        None => ElGamalKeypair::new(owner_signer, account).map_err(|err| {
+            if owner_signer.is_interactive() {
+                hardware_wallet_derivation_error(owner_signer, "ElGamal keypair", err, true)
             } else {
-                arg
+                format!(
+                    "Unable to derive an ElGamal keypair from the owner, \
+                     use `--elgamal-keypair` instead: {}",
+                    err
+                )
+                .into()
             }
-        })
-        .arg(
-            Arg::with_name("verbose")
-                .short("v")
-                .long("verbose")
-                .takes_value(false)
-                .global(true)
-                .help("Show additional information"),
-        )
-        .arg(
-            Arg::with_name("output_format")
-                .long("output")
-                .value_name("FORMAT")
-                .global(true)
-                .takes_value(true)
-                .possible_values(&["json", "json-compact"])
-                .help("Return information in specified output format"),
+        }),
+    }
+}
+
+// The authenticated encryption key only protects the owner's copy of the available balance, so it
+// is always derived from the owner
+fn confidential_transfer_ae_key(
+    owner_signer: &dyn Signer,
+    account: &Pubkey,
+) -> Result<AeKey, Error> {
+    AeKey::new(owner_signer, account).map_err(|err| {
+        if owner_signer.is_interactive() {
+            hardware_wallet_derivation_error(owner_signer, "encryption key", err, false)
+        } else {
+            format!(
+                "Unable to derive an encryption key from the owner {}: {}",
+                owner_signer.pubkey(),
+                err
+            )
+            .into()
+        }
+    })
+}
+
+// Confidential transfer keys are derived from the owner's signature of a message that names no
+// real program. Hardware wallets only sign such messages if their app supports it, so a failure
+// there usually means the firmware or app settings need attention rather than a broken key.
+fn hardware_wallet_derivation_error(
+    owner_signer: &dyn Signer,
+    key_name: &str,
+    err: SignerError,
+    has_keypair_file_fallback: bool,
+) -> Error {
+    let fallback = if has_keypair_file_fallback {
+        ", or pass `--elgamal-keypair` to use a keypair file instead"
+    } else {
+        ""
+    };
+    match err {
+        SignerError::UserCancel(_) => format!(
+            "Error: Deriving the {} was rejected on the hardware wallet {}",
+            key_name,
+            owner_signer.pubkey()
         )
-        .arg(
-            Arg::with_name("program_id")
-                .short("p")
-                .long("program-id")
-                .value_name("ADDRESS")
-                .takes_value(true)
-                .global(true)
-                .default_value(default_program_id)
-                .validator(is_valid_pubkey)
-                .help("SPL Token program id"),
+        .into(),
+        err => format!(
+            "Error: The hardware wallet {} could not derive the {}: {}. \
+             Update the device firmware and its Solana app, and enable blind signing \
+             in the app settings{}",
+            owner_signer.pubkey(),
+            key_name,
+            err,
+            fallback
         )
-        .arg(
-            Arg::with_name("json_rpc_url")
-                .short("u")
-                .long("url")
-                .value_name("URL_OR_MONIKER")
-                .takes_value(true)
-                .global(true)
-                .validator(is_url_or_moniker)
-                .help(
-                    "URL for Solana's JSON RPC or moniker (or their first letter): \
-                       [mainnet-beta, testnet, devnet, localhost] \
-                    Default from the configuration file."
+        .into(),
+    }
+}
+
+async fn get_confidential_transfer_account(
+    config: &Config<'_>,
+    account: &Pubkey,
+) -> Result<ConfidentialTransferAccount, Error> {
+    let token_account = config.rpc_client.get_account(account).await?;
+    let token_account = StateWithExtensionsOwned::<Account>::unpack(token_account.data)?;
+    let extension = token_account
+        .get_extension::<ConfidentialTransferAccount>()
+        .map_err(|_| {
+            format!(
+                "Error: Account {} is not configured for confidential transfers",
+                account
+            )
+        })?;
+    Ok(*extension)
+}
+
+fn decrypt_available_balance(
+    account: &Pubkey,
+    extension: &ConfidentialTransferAccount,
+    ae_key: &AeKey,
+) -> Result<u64, Error> {
+    let decryptable_available_balance: AeCiphertext = extension
+        .decryptable_available_balance
+        .try_into()
+        .map_err(|_| format!("Could not parse the available balance of {}", account))?;
+    Ok(decryptable_available_balance
+        .decrypt(ae_key)
+        .ok_or_else(|| format!("Could not decrypt the available balance of {}", account))?)
+}
+
+fn decrypt_pending_balance(
+    account: &Pubkey,
+    extension: &ConfidentialTransferAccount,
+    elgamal_keypair: &ElGamalKeypair,
+) -> Result<u64, Error> {
+    let decrypt_error = || format!("Could not decrypt the pending balance of {}", account);
+    let pending_balance_lo = extension
+        .pending_balance_lo
+        .decrypt(&elgamal_keypair.secret)
+        .ok_or_else(decrypt_error)?;
+    let pending_balance_hi = extension
+        .pending_balance_hi
+        .decrypt(&elgamal_keypair.secret)
+        .ok_or_else(decrypt_error)?;
+    pending_balance_lo
+        .checked_add(pending_balance_hi << confidential_transfer::PENDING_BALANCE_LO_BIT_LENGTH)
+        .ok_or_else(|| decrypt_error().into())
+}
+
+// Build the instruction moving the pending balance of `account` into its available balance, or
+// `None` if no deposits or transfers have been credited since the last time it was applied
+#[allow(clippy::too_many_arguments)]
+fn apply_pending_balance_instruction(
+    config: &Config<'_>,
+    program_id: &Pubkey,
+    account: &Pubkey,
+    extension: &ConfidentialTransferAccount,
+    owner: &Pubkey,
+    elgamal_keypair: &ElGamalKeypair,
+    ae_key: &AeKey,
+) -> Result<Option<Instruction>, Error> {
+    let pending_balance_credit_counter = u64::from(extension.pending_balance_credit_counter);
+    if pending_balance_credit_counter == 0 {
+        return Ok(None);
+    }
+    let available_balance = decrypt_available_balance(account, extension, ae_key)?;
+    let pending_balance = decrypt_pending_balance(account, extension, elgamal_keypair)?;
+    let new_available_balance = available_balance
+        .checked_add(pending_balance)
+        .ok_or("Error: Applying the pending balance would overflow the available balance")?;
+    Ok(Some(
+        confidential_transfer::instruction::apply_pending_balance(
+            program_id,
+            account,
+            pending_balance_credit_counter,
+            ae_key.encrypt(new_available_balance),
+            owner,
+            &config.multisigner_pubkeys,
+        )?,
+    ))
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn command_configure_confidential_transfer_account(
+    config: &Config<'_>,
+    account: Pubkey,
+    mint_address: Option<Pubkey>,
+    owner: Pubkey,
+    elgamal_pubkey: ElGamalPubkey,
+    decryptable_zero_balance: AeCiphertext,
+    maximum_pending_balance_credit_counter: u64,
+    bulk_signers: BulkSigners,
+) -> CommandResult {
+    let (mint_address, token_account) = if config.sign_only {
+        (mint_address.unwrap_or_default(), None)
+    } else {
+        let (token_account, _) = config.check_account_full(&account, mint_address).await?;
+        (token_account.base.mint, Some(token_account))
+    };
+    let mint_info = config.get_mint_info(&mint_address, None).await?;
+    let encryption_pubkey: EncryptionPubkey = elgamal_pubkey.into();
+
+    println_display(
+        config,
+        format!(
+            "Configuring confidential transfers for account {}\n  ElGamal pubkey: {}",
+            account,
+            base64::encode(encryption_pubkey.0)
+        ),
+    );
+
+    if let Some(token_account) = token_account {
+        if token_account
+            .get_extension::<ConfidentialTransferAccount>()
+            .is_ok()
+        {
+            return Err(format!(
+                "Error: Account {} is already configured for confidential transfers",
+                account
+            )
+            .into());
+        }
+
+        let mint = config.rpc_client.get_account(&mint_address).await?;
+        let mint = StateWithExtensionsOwned::<Mint>::unpack(mint.data)?;
+        let confidential_transfer_mint =
+            mint.get_extension::<ConfidentialTransferMint>()
+                .map_err(|_| {
+                    format!(
+                        "Error: Mint {} does not support confidential transfers",
+                        mint_address
+                    )
+                })?;
+        if !bool::from(confidential_transfer_mint.auto_approve_new_accounts) {
+            println_display(
+                config,
+                format!(
+                    "  The account must be approved by the confidential transfer authority {} \
+                     before it can be used",
+                    confidential_transfer_mint.authority
                 ),
+            );
+        }
+    }
+
+    // Reallocate is a no-op if the account already has room for the extension
+    let instructions = vec![
+        reallocate(
+            &mint_info.program_id,
+            &account,
+            &config.fee_payer,
+            &owner,
+            &config.multisigner_pubkeys,
+            &[ExtensionType::ConfidentialTransferAccount],
+        )?,
+        confidential_transfer::instruction::configure_account(
+            &mint_info.program_id,
+            &account,
+            &mint_info.address,
+            encryption_pubkey,
+            decryptable_zero_balance,
+            maximum_pending_balance_credit_counter,
+            &owner,
+            &config.multisigner_pubkeys,
+        )?,
+    ];
+
+    let tx_return = handle_tx(
+        &CliSignerInfo {
+            signers: bulk_signers,
+        },
+        config,
+        false,
+        0,
+        instructions,
+    )
+    .await?;
+    Ok(match tx_return {
+        TransactionReturnData::CliSignature(signature) => {
+            config.output_format.formatted_string(&signature)
+        }
+        TransactionReturnData::CliSignOnlyData(sign_only_data) => {
+            config.output_format.formatted_string(&sign_only_data)
+        }
+        TransactionReturnData::CliSimulation(simulation) => {
+            config.output_format.formatted_string(&simulation)
+        }
+    })
+}
+
+async fn command_deposit_confidential(
+    config: &Config<'_>,
+    account: Pubkey,
+    mint_address: Option<Pubkey>,
+    ui_amount: f64,
+    mint_decimals: Option<u8>,
+    owner: Pubkey,
+    bulk_signers: BulkSigners,
+) -> CommandResult {
+    let mint_address = config.check_account(&account, mint_address).await?;
+    let mint_info = config.get_mint_info(&mint_address, mint_decimals).await?;
+    let amount = spl_token::ui_amount_to_amount(ui_amount, mint_info.decimals);
+    if amount >> confidential_transfer::MAXIMUM_DEPOSIT_TRANSFER_AMOUNT_BIT_LENGTH != 0 {
+        return Err(format!(
+            "Error: Confidential deposits are limited to {} bits, {} is too large",
+            confidential_transfer::MAXIMUM_DEPOSIT_TRANSFER_AMOUNT_BIT_LENGTH,
+            ui_amount
         )
-        .arg(fee_payer_arg().global(true))
-        .arg(
-            Arg::with_name("use_unchecked_instruction")
-                .long("use-unchecked-instruction")
-                .takes_value(false)
-                .global(true)
-                .hidden(true)
-                .help("Use unchecked instruction if appropriate. Supports transfer, burn, mint, and approve."),
-        )
-        .bench_subcommand()
-        .subcommand(SubCommand::with_name(CommandName::CreateToken.into()).about("Create a new token")
-                .arg(
-                    Arg::with_name("token_keypair")
-                        .value_name("TOKEN_KEYPAIR")
-                        .validator(is_valid_signer)
-                        .takes_value(true)
-                        .index(1)
-                        .help(
-                            "Specify the token keypair. \
-                             This may be a keypair file or the ASK keyword. \
-                             [default: randomly generated keypair]"
-                        ),
-                )
-                .arg(
-                    Arg::with_name("mint_authority")
-                        .long("mint-authority")
-                        .alias("owner")
-                        .value_name("ADDRESS")
-                        .validator(is_valid_pubkey)
-                        .takes_value(true)
-                        .help(
-                            "Specify the mint authority address. \
-                             Defaults to the client keypair address."
-                        ),
-                )
-                .arg(
-                    Arg::with_name("decimals")
-                        .long("decimals")
-                        .validator(is_mint_decimals)
-                        .value_name("DECIMALS")
-                        .takes_value(true)
-                        .default_value(default_decimals)
-                        .help("Number of base 10 digits to the right of the decimal place"),
-                )
-                .arg(
-                    Arg::with_name("enable_freeze")
-                        .long("enable-freeze")
-                        .takes_value(false)
-                        .help(
-                            "Enable the mint authority to freeze associated token accounts."
-                        ),
-                )
-                .nonce_args(true)
-                .arg(memo_arg())
-                .offline_args(),
+        .into());
+    }
+
+    println_display(
+        config,
+        format!(
+            "Depositing {} tokens into the confidential balance\n  Account: {}",
+            ui_amount, account
+        ),
+    );
+
+    let instructions = vec![confidential_transfer::instruction::deposit(
+        &mint_info.program_id,
+        &account,
+        &mint_info.address,
+        &account,
+        amount,
+        mint_info.decimals,
+        &owner,
+        &config.multisigner_pubkeys,
+    )?];
+    let tx_return = handle_tx(
+        &CliSignerInfo {
+            signers: bulk_signers,
+        },
+        config,
+        false,
+        0,
+        instructions,
+    )
+    .await?;
+    Ok(match tx_return {
+        TransactionReturnData::CliSignature(signature) => {
+            config.output_format.formatted_string(&signature)
+        }
+        TransactionReturnData::CliSignOnlyData(sign_only_data) => {
+            config.output_format.formatted_string(&sign_only_data)
+        }
+        TransactionReturnData::CliSimulation(simulation) => {
+            config.output_format.formatted_string(&simulation)
+        }
+    })
+}
+
+async fn command_apply_pending_balance(
+    config: &Config<'_>,
+    account: Pubkey,
+    owner: Pubkey,
+    elgamal_keypair: ElGamalKeypair,
+    ae_key: AeKey,
+    bulk_signers: BulkSigners,
+) -> CommandResult {
+    if config.sign_only {
+        return Err("Error: Applying a pending balance requires decrypting it, \
+             which is not possible with `--sign-only`"
+            .into());
+    }
+    let mint_address = config.check_account(&account, None).await?;
+    let mint_info = config.get_mint_info(&mint_address, None).await?;
+    let extension = get_confidential_transfer_account(config, &account).await?;
+    let instruction = match apply_pending_balance_instruction(
+        config,
+        &mint_info.program_id,
+        &account,
+        &extension,
+        &owner,
+        &elgamal_keypair,
+        &ae_key,
+    )? {
+        Some(instruction) => instruction,
+        None => {
+            println_display(
+                config,
+                format!("No pending balance to apply for {}", account),
+            );
+            return Ok("".to_string());
+        }
+    };
+
+    let pending_balance = decrypt_pending_balance(&account, &extension, &elgamal_keypair)?;
+    println_display(
+        config,
+        format!(
+            "Applying pending balance of {} tokens\n  Account: {}",
+            spl_token_2022::amount_to_ui_amount_string_trimmed(pending_balance, mint_info.decimals),
+            account
+        ),
+    );
+
+    let tx_return = handle_tx(
+        &CliSignerInfo {
+            signers: bulk_signers,
+        },
+        config,
+        false,
+        0,
+        vec![instruction],
+    )
+    .await?;
+    Ok(match tx_return {
+        TransactionReturnData::CliSignature(signature) => {
+            config.output_format.formatted_string(&signature)
+        }
+        TransactionReturnData::CliSignOnlyData(sign_only_data) => {
+            config.output_format.formatted_string(&sign_only_data)
+        }
+        TransactionReturnData::CliSimulation(simulation) => {
+            config.output_format.formatted_string(&simulation)
+        }
+    })
+}
+
+// Interest accrues continuously from the rate set in the mint, so the balance stored on-chain
+// has to be scaled by the cluster's current time to get the amount it is actually worth
+async fn ui_amount_with_interest(
+    config: &Config<'_>,
+    mint: &StateWithExtensionsOwned<Mint>,
+    amount: u64,
+) -> Result<Option<String>, Error> {
+    let interest_bearing_config = match mint.get_extension::<InterestBearingConfig>() {
+        Ok(interest_bearing_config) => interest_bearing_config,
+        Err(_) => return Ok(None),
+    };
+    let clock_account = config.rpc_client.get_account(&sysvar::clock::id()).await?;
+    let clock: Clock = bincode::deserialize(&clock_account.data)?;
+    Ok(interest_bearing_config.amount_to_ui_amount(
+        amount,
+        mint.base.decimals,
+        clock.unix_timestamp,
+    ))
+}
+
+async fn command_balance(config: &Config<'_>, address: Pubkey) -> CommandResult {
+    let balance = config
+        .rpc_client
+        .get_token_account_balance(&address)
+        .await
+        .map_err(|_| format!("Could not find token account {}", address))?;
+
+    let account = config.rpc_client.get_account(&address).await?;
+    let ui_amount_with_interest = if account.owner == spl_token_2022::id() {
+        let token_account = StateWithExtensionsOwned::<Account>::unpack(account.data)?;
+        let mint_account = config
+            .rpc_client
+            .get_account(&token_account.base.mint)
+            .await?;
+        let mint = StateWithExtensionsOwned::<Mint>::unpack(mint_account.data)?;
+        ui_amount_with_interest(config, &mint, token_account.base.amount).await?
+    } else {
+        None
+    };
+
+    let cli_token_amount = CliTokenAmount {
+        amount: balance,
+        ui_amount_with_interest,
+    };
+    Ok(config.output_format.formatted_string(&cli_token_amount))
+}
+
+async fn command_balance_all_for_owner(
+    config: &Config<'_>,
+    token: Pubkey,
+    owner: Pubkey,
+) -> CommandResult {
+    let mint_info = config.get_mint_info(&token, None).await?;
+    let addresses = config
+        .rpc_client
+        .get_token_accounts_by_owner(&owner, TokenAccountsFilter::Mint(token))
+        .await?
+        .into_iter()
+        .map(|keyed_account| {
+            keyed_account
+                .pubkey
+                .parse::<Pubkey>()
+                .unwrap_or_else(|err| panic!("Invalid token account: {}", err))
+        })
+        .collect::<Vec<_>>();
+
+    let mut total: u64 = 0;
+    let mut accounts = vec![];
+    for (address, token_account) in addresses
+        .iter()
+        .zip(config.get_multiple_accounts_checked(&addresses).await?)
+    {
+        // the account may have been closed since it was listed
+        let amount = match token_account {
+            Some(token_account) => token_account.base.amount,
+            None => continue,
+        };
+        total = total.saturating_add(amount);
+        accounts.push(CliAccountBalance {
+            address: address.to_string(),
+            amount: token_amount_to_ui_amount(amount, mint_info.decimals),
+        });
+    }
+
+    Ok(config.output_format.formatted_string(&CliOwnerBalance {
+        owner: owner.to_string(),
+        mint: token.to_string(),
+        total: token_amount_to_ui_amount(total, mint_info.decimals),
+        accounts,
+    }))
+}
+
+// Token-2022 answers `AmountToUiAmount` and `UiAmountToAmount` through return data, which the
+// runtime also logs as "Program return: <program id> <base64 data>"
+async fn simulate_return_data(
+    config: &Config<'_>,
+    instruction: Instruction,
+) -> Result<Vec<u8>, Error> {
+    let program_id = instruction.program_id;
+    let message = Message::new(&[instruction], Some(&config.fee_payer));
+    let mut transaction = Transaction::new_unsigned(message);
+    transaction.message.recent_blockhash = config.rpc_client.get_latest_blockhash().await?;
+    let result = config
+        .rpc_client
+        .simulate_transaction_with_config(
+            &transaction,
+            RpcSimulateTransactionConfig {
+                sig_verify: false,
+                ..RpcSimulateTransactionConfig::default()
+            },
         )
-        .subcommand(
-            SubCommand::with_name(CommandName::CreateAccount.into())
-                .about("Create a new token account")
-                .arg(
-                    Arg::with_name("token")
-                        .validator(is_valid_pubkey)
-                        .value_name("TOKEN_ADDRESS")
-                        .takes_value(true)
-                        .index(1)
-                        .required(true)
-                        .help("The token that the account will hold"),
-                )
-                .arg(
-                    Arg::with_name("account_keypair")
-                        .value_name("ACCOUNT_KEYPAIR")
-                        .validator(is_valid_signer)
-                        .takes_value(true)
-                        .index(2)
-                        .help(
-                            "Specify the account keypair. \
-                             This may be a keypair file or the ASK keyword. \
-                             [default: associated token account for --owner]"
-                        ),
-                )
-                .arg(owner_address_arg())
-                .nonce_args(true)
-                .offline_args(),
+        .await?
+        .value;
+    if let Some(err) = result.err {
+        return Err(format!("Error: Simulation failed: {}", err).into());
+    }
+    let prefix = format!("Program return: {} ", program_id);
+    let data = result
+        .logs
+        .unwrap_or_default()
+        .iter()
+        .rev()
+        .find_map(|log| log.strip_prefix(&prefix).map(|data| data.to_string()))
+        .ok_or_else(|| format!("Error: Program {} did not return any data", program_id))?;
+    Ok(base64::decode(data)?)
+}
+
+async fn is_interest_bearing(config: &Config<'_>, mint_info: &MintInfo) -> Result<bool, Error> {
+    if mint_info.program_id != spl_token_2022::id() {
+        return Ok(false);
+    }
+    let mint_account = config.rpc_client.get_account(&mint_info.address).await?;
+    let mint = StateWithExtensionsOwned::<Mint>::unpack(mint_account.data)?;
+    Ok(mint.get_extension::<InterestBearingConfig>().is_ok())
+}
+
+async fn command_amount_to_ui_amount(
+    config: &Config<'_>,
+    token: Pubkey,
+    amount: u64,
+) -> CommandResult {
+    let mint_info = config.get_mint_info(&token, None).await?;
+    let ui_amount = if is_interest_bearing(config, &mint_info).await? {
+        let instruction = amount_to_ui_amount(&mint_info.program_id, &token, amount)?;
+        String::from_utf8(simulate_return_data(config, instruction).await?)
+            .map_err(|_| "Error: The returned UI amount is not valid UTF-8")?
+    } else {
+        spl_token_2022::amount_to_ui_amount_string_trimmed(amount, mint_info.decimals)
+    };
+    Ok(config.output_format.formatted_string(&CliUiAmount {
+        mint: token.to_string(),
+        amount: amount.to_string(),
+        ui_amount,
+    }))
+}
+
+async fn command_ui_amount_to_amount(
+    config: &Config<'_>,
+    token: Pubkey,
+    ui_amount: &str,
+) -> CommandResult {
+    let mint_info = config.get_mint_info(&token, None).await?;
+    let amount = if is_interest_bearing(config, &mint_info).await? {
+        let instruction = ui_amount_to_amount(&mint_info.program_id, &token, ui_amount)?;
+        let data = simulate_return_data(config, instruction).await?;
+        let data: [u8; 8] = data
+            .as_slice()
+            .try_into()
+            .map_err(|_| "Error: The returned amount is not a u64")?;
+        u64::from_le_bytes(data)
+    } else {
+        spl_token_2022::try_ui_amount_into_amount(ui_amount.to_string(), mint_info.decimals)
+            .map_err(|_| format!("Error: Invalid amount {}", ui_amount))?
+    };
+    Ok(config.output_format.formatted_string(&CliRawAmount {
+        mint: token.to_string(),
+        amount: amount.to_string(),
+        ui_amount: ui_amount.to_string(),
+    }))
+}
+
+async fn command_supply(
+    config: &Config<'_>,
+    address: Pubkey,
+    show_withheld: bool,
+) -> CommandResult {
+    let supply = config.rpc_client.get_token_supply(&address).await?;
+    let withheld = if show_withheld {
+        let mint_info = config.get_mint_info(&address, None).await?;
+        let mint = get_transfer_fee_mint(config, &address).await?;
+        let withheld_in_mint =
+            u64::from(mint.get_extension::<TransferFeeConfig>()?.withheld_amount);
+        // Fees stay in the recipient accounts until harvested, so only a full scan finds them
+        let withheld_in_accounts = get_all_token_accounts_of_mint(config, &mint_info)
+            .await?
+            .iter()
+            .filter_map(|(_, token_account)| {
+                token_account
+                    .get_extension::<TransferFeeAmount>()
+                    .ok()
+                    .map(|transfer_fee_amount| u64::from(transfer_fee_amount.withheld_amount))
+            })
+            .fold(0u64, |total, amount| total.saturating_add(amount));
+        Some(CliWithheldSupply {
+            in_mint: token_amount_to_ui_amount(withheld_in_mint, mint_info.decimals),
+            in_accounts: token_amount_to_ui_amount(withheld_in_accounts, mint_info.decimals),
+        })
+    } else {
+        None
+    };
+    let cli_supply = CliSupply {
+        amount: supply,
+        withheld,
+    };
+    Ok(config.output_format.formatted_string(&cli_supply))
+}
+
+async fn command_accounts(
+    config: &Config<'_>,
+    token: Option<Pubkey>,
+    owner: Pubkey,
+) -> CommandResult {
+    // A mint filter lists that mint's accounts whichever token program it belongs to, regardless
+    // of `--program-id`
+    let program_id = if let Some(token) = token {
+        let mint = config
+            .rpc_client
+            .get_account(&token)
+            .await
+            .map_err(|_| format!("Mint account not found {:?}", token))?;
+        if (mint.owner != spl_token::id() && mint.owner != spl_token_2022::id())
+            || StateWithExtensionsOwned::<Mint>::unpack(mint.data).is_err()
+        {
+            return Err(format!("Invalid mint account {:?}", token).into());
+        }
+        mint.owner
+    } else {
+        config.program_id
+    };
+    let accounts = config
+        .rpc_client
+        .get_token_accounts_by_owner(
+            &owner,
+            match token {
+                Some(token) => TokenAccountsFilter::Mint(token),
+                None => TokenAccountsFilter::ProgramId(program_id),
+            },
         )
-        .subcommand(
-            SubCommand::with_name(CommandName::CreateMultisig.into())
-                .about("Create a new account describing an M:N multisignature")
-                .arg(
-                    Arg::with_name("minimum_signers")
-                        .value_name("MINIMUM_SIGNERS")
-                        .validator(is_multisig_minimum_signers)
-                        .takes_value(true)
-                        .index(1)
-                        .required(true)
-                        .help(minimum_signers_help),
-                )
-                .arg(
-                    Arg::with_name("multisig_member")
-                        .value_name("MULTISIG_MEMBER_PUBKEY")
-                        .validator(is_valid_pubkey)
-                        .takes_value(true)
-                        .index(2)
-                        .required(true)
-                        .min_values(MIN_SIGNERS as u64)
-                        .max_values(MAX_SIGNERS as u64)
-                        .help(multisig_member_help),
-                )
-                .arg(
-                    Arg::with_name("address_keypair")
-                        .long("address-keypair")
-                        .value_name("ADDRESS_KEYPAIR")
-                        .validator(is_valid_signer)
-                        .takes_value(true)
-                        .help(
-                            "Specify the address keypair. \
-                             This may be a keypair file or the ASK keyword. \
-                             [default: randomly generated keypair]"
-                        ),
-                )
-                .nonce_args(true)
+        .await?;
+    if accounts.is_empty() {
+        if !config.json_lines {
+            println!("None");
+        }
+        return Ok("".to_string());
+    }
+
+    let (mint_accounts, unsupported_accounts, max_len_balance, includes_aux) =
+        sort_and_parse_token_accounts(&owner, accounts, &program_id);
+    let aux_len = if includes_aux { 10 } else { 0 };
+
+    if config.json_lines {
+        return Ok(mint_accounts
+            .values()
+            .flatten()
+            .map(|token_account| {
+                serde_json::to_string(&CliTokenAccountLine::from(token_account)).unwrap()
+            })
+            .collect::<Vec<_>>()
+            .join("\n"));
+    }
+
+    let cli_token_accounts = CliTokenAccounts {
+        accounts: mint_accounts
+            .into_iter()
+            .map(|(_mint, accounts_list)| accounts_list)
+            .collect(),
+        unsupported_accounts,
+        max_len_balance,
+        aux_len,
+        token_is_some: token.is_some(),
+    };
+    Ok(config.output_format.formatted_string(&cli_token_accounts))
+}
+
+async fn command_address(
+    config: &Config<'_>,
+    token: Option<Pubkey>,
+    owner: Pubkey,
+    offline: bool,
+) -> CommandResult {
+    let mut cli_address = CliWalletAddress {
+        wallet_address: owner.to_string(),
+        ..CliWalletAddress::default()
+    };
+    if let Some(token) = token {
+        // The mint can't be fetched offline, so the token program has to be given
+        let program_id = if offline || config.sign_only {
+            config.program_id
+        } else {
+            validate_mint(config, token).await?
+        };
+        let associated_token_address =
+            get_associated_token_address_with_program_id(&owner, &token, &program_id);
+        cli_address.mint = Some(token.to_string());
+        cli_address.program_id = Some(program_id.to_string());
+        cli_address.associated_token_address = Some(associated_token_address.to_string());
+    }
+    Ok(config.output_format.formatted_string(&cli_address))
+}
+
+async fn command_account_info(config: &Config<'_>, address: Pubkey) -> CommandResult {
+    let account = config
+        .rpc_client
+        .get_token_account(&address)
+        .await
+        .map_err(|_| format!("Could not find token account {}", address))?
+        .unwrap();
+    let mint = Pubkey::from_str(&account.mint).unwrap();
+    let owner = Pubkey::from_str(&account.owner).unwrap();
+    let raw_account = config.rpc_client.get_account(&address).await?;
+    let program_id = raw_account.owner;
+    let is_associated =
+        get_associated_token_address_with_program_id(&owner, &mint, &program_id) == address;
+    let required_transfer_memos = if program_id == spl_token_2022::id() {
+        let token_account = StateWithExtensionsOwned::<Account>::unpack(raw_account.data)?;
+        Some(
+            token_account
+                .get_extension::<MemoTransfer>()
+                .map(|memo_transfer| bool::from(memo_transfer.require_incoming_transfer_memos))
+                .unwrap_or(false),
+        )
+    } else {
+        None
+    };
+    let cli_token_account = CliTokenAccount {
+        address: address.to_string(),
+        is_associated,
+        account,
+        required_transfer_memos,
+    };
+    Ok(config.output_format.formatted_string(&cli_token_account))
+}
+
+async fn get_multisig(config: &Config<'_>, address: &Pubkey) -> Result<Multisig, Error> {
+    let account = config.rpc_client.get_account(address).await?;
+    Multisig::unpack(&account.data).map_err(|e| e.into())
+}
+
+// Put together a transaction from a message and signatures collected from its signers one by one,
+// typically multisig members each running `--sign-only` on their own machine
+async fn command_combine_signatures(
+    config: &Config<'_>,
+    message: &str,
+    signatures: Vec<(Pubkey, Signature)>,
+) -> CommandResult {
+    let message_data = base64::decode(message.trim())
+        .map_err(|err| format!("Message is not valid base64: {}", err))?;
+    let message: Message = bincode::deserialize(&message_data)
+        .map_err(|err| format!("Message could not be decoded: {}", err))?;
+    let num_required_signatures = message.header.num_required_signatures as usize;
+    let mut transaction = Transaction::new_unsigned(message);
+    // The header is only trusted to index into the account keys once it's been checked
+    if transaction.sanitize().is_err() {
+        return Err("Error: The decoded message is malformed".into());
+    }
+
+    for (pubkey, signature) in signatures {
+        let position = transaction.message.account_keys[..num_required_signatures]
+            .iter()
+            .position(|signer| *signer == pubkey)
+            .ok_or_else(|| format!("Error: {} is not a signer of this transaction", pubkey))?;
+        if !signature.verify(pubkey.as_ref(), &message_data) {
+            return Err(format!(
+                "Error: Signature for {} does not verify against the message",
+                pubkey
+            )
+            .into());
+        }
+        transaction.signatures[position] = signature;
+    }
+
+    let missing_signers = transaction.message.account_keys[..num_required_signatures]
+        .iter()
+        .zip(&transaction.signatures)
+        .filter(|(_, signature)| **signature == Signature::default())
+        .map(|(signer, _)| signer.to_string())
+        .collect::<Vec<_>>();
+    if !missing_signers.is_empty() {
+        return Err(format!(
+            "Error: Transaction is missing signatures from {}",
+            missing_signers.join(", ")
+        )
+        .into());
+    }
+
+    // Every signer has signed at this point, but a multisig the transaction acts for may still
+    // need more of its members than were included when the message was built
+    let accounts = &transaction.message.account_keys[num_required_signatures..];
+    let signers = &transaction.message.account_keys[..num_required_signatures];
+    for (address, account) in accounts
+        .iter()
+        .zip(config.get_multiple_accounts(accounts).await?)
+    {
+        let multisig = match account {
+            Some(account)
+                if (account.owner == spl_token::id() || account.owner == spl_token_2022::id())
+                    && account.data.len() == Multisig::LEN =>
+            {
+                match Multisig::unpack(&account.data) {
+                    Ok(multisig) => multisig,
+                    Err(_) => continue,
+                }
+            }
+            _ => continue,
+        };
+        let signed = multisig.signers[..multisig.n as usize]
+            .iter()
+            .filter(|member| signers.contains(member))
+            .count();
+        if signed < multisig.m as usize {
+            return Err(format!(
+                "Error: Multisig {} requires {} signatures, but only {} of its members signed",
+                address, multisig.m, signed
+            )
+            .into());
+        }
+    }
+
+    if let Some(output_file) = &config.output_file {
+        write_transaction_file(output_file, &transaction)?;
+    }
+    Ok(config
+        .output_format
+        .formatted_string(&CliCombinedTransaction {
+            signers: signers.iter().map(|signer| signer.to_string()).collect(),
+            transaction: base64::encode(bincode::serialize(&transaction)?),
+        }))
+}
+
+async fn command_multisig(
+    config: &Config<'_>,
+    address: Pubkey,
+    expect_threshold: Option<u8>,
+    expect_signers: Option<Vec<Pubkey>>,
+) -> CommandResult {
+    let multisig = get_multisig(config, &address).await?;
+    let n = multisig.n as usize;
+    assert!(n <= multisig.signers.len());
+    if let Some(expect_threshold) = expect_threshold {
+        if multisig.m != expect_threshold {
+            return Err(format!(
+                "Multisig {} requires {} signers, expected {}",
+                address, multisig.m, expect_threshold
+            )
+            .into());
+        }
+    }
+    // The order of the members doesn't matter to the program, only who they are
+    if let Some(expect_signers) = expect_signers {
+        let signers = &multisig.signers[..n];
+        let missing = expect_signers
+            .iter()
+            .filter(|signer| !signers.contains(signer))
+            .map(|signer| signer.to_string())
+            .collect::<Vec<_>>();
+        let unexpected = signers
+            .iter()
+            .filter(|signer| !expect_signers.contains(signer))
+            .map(|signer| signer.to_string())
+            .collect::<Vec<_>>();
+        if !missing.is_empty() || !unexpected.is_empty() || expect_signers.len() != n {
+            return Err(format!(
+                "Multisig {} has {} signers, expected {}. Missing: [{}], unexpected: [{}]",
+                address,
+                n,
+                expect_signers.len(),
+                missing.join(", "),
+                unexpected.join(", ")
+            )
+            .into());
+        }
+    }
+    let cli_multisig = CliMultisig {
+        address: address.to_string(),
+        m: multisig.m,
+        n: multisig.n,
+        signers: multisig
+            .signers
+            .iter()
+            .enumerate()
+            .filter_map(|(i, signer)| {
+                if i < n {
+                    Some(signer.to_string())
+                } else {
+                    None
+                }
+            })
+            .collect(),
+    };
+    Ok(config.output_format.formatted_string(&cli_multisig))
+}
+
+fn cli_transfer_fee(transfer_fee: &TransferFee) -> CliTransferFee {
+    CliTransferFee {
+        epoch: u64::from(transfer_fee.epoch),
+        maximum_fee: u64::from(transfer_fee.maximum_fee),
+        transfer_fee_basis_points: u16::from(transfer_fee.transfer_fee_basis_points),
+    }
+}
+
+// An all-zero ElGamal pubkey means the corresponding feature is disabled
+fn cli_encryption_pubkey(pubkey: &EncryptionPubkey) -> Option<String> {
+    if pubkey.0 == [0; 32] {
+        None
+    } else {
+        Some(base64::encode(pubkey.0))
+    }
+}
+
+fn cli_mint_extension(
+    mint: &StateWithExtensionsOwned<Mint>,
+    extension_type: ExtensionType,
+) -> Result<CliMintExtension, ProgramError> {
+    let extension = match extension_type {
+        ExtensionType::TransferFeeConfig => {
+            let config = mint.get_extension::<TransferFeeConfig>()?;
+            CliMintExtension::TransferFeeConfig(CliTransferFeeConfig {
+                transfer_fee_config_authority: Option::<Pubkey>::from(
+                    config.transfer_fee_config_authority,
+                )
+                .map(|pubkey| pubkey.to_string()),
+                withdraw_withheld_authority: Option::<Pubkey>::from(
+                    config.withdraw_withheld_authority,
+                )
+                .map(|pubkey| pubkey.to_string()),
+                withheld_amount: u64::from(config.withheld_amount),
+                older_transfer_fee: cli_transfer_fee(&config.older_transfer_fee),
+                newer_transfer_fee: cli_transfer_fee(&config.newer_transfer_fee),
+            })
+        }
+        ExtensionType::MintCloseAuthority => {
+            let close_authority = mint.get_extension::<MintCloseAuthority>()?;
+            CliMintExtension::MintCloseAuthority(CliMintCloseAuthority {
+                close_authority: Option::<Pubkey>::from(close_authority.close_authority)
+                    .map(|pubkey| pubkey.to_string()),
+            })
+        }
+        ExtensionType::ConfidentialTransferMint => {
+            let confidential_mint = mint.get_extension::<ConfidentialTransferMint>()?;
+            CliMintExtension::ConfidentialTransferMint(CliConfidentialTransferMint {
+                authority: confidential_mint.authority.to_string(),
+                auto_approve_new_accounts: bool::from(confidential_mint.auto_approve_new_accounts),
+                auditor_encryption_pubkey: cli_encryption_pubkey(
+                    &confidential_mint.auditor_encryption_pubkey,
+                ),
+                withdraw_withheld_authority_encryption_pubkey: cli_encryption_pubkey(
+                    &confidential_mint.withdraw_withheld_authority_encryption_pubkey,
+                ),
+            })
+        }
+        ExtensionType::DefaultAccountState => {
+            let default_account_state = mint.get_extension::<DefaultAccountState>()?;
+            let account_state = match AccountState::try_from(default_account_state.state)
+                .map_err(|_| ProgramError::InvalidAccountData)?
+            {
+                AccountState::Uninitialized => UiAccountState::Uninitialized,
+                AccountState::Initialized => UiAccountState::Initialized,
+                AccountState::Frozen => UiAccountState::Frozen,
+            };
+            CliMintExtension::DefaultAccountState(CliDefaultAccountState { account_state })
+        }
+        ExtensionType::NonTransferable => CliMintExtension::NonTransferable {},
+        ExtensionType::InterestBearingConfig => {
+            let config = mint.get_extension::<InterestBearingConfig>()?;
+            CliMintExtension::InterestBearingConfig(CliInterestBearingConfig {
+                rate_authority: Option::<Pubkey>::from(config.rate_authority)
+                    .map(|pubkey| pubkey.to_string()),
+                initialization_timestamp: i64::from(config.initialization_timestamp),
+                pre_update_average_rate: i16::from(config.pre_update_average_rate),
+                last_update_timestamp: i64::from(config.last_update_timestamp),
+                current_rate: i16::from(config.current_rate),
+            })
+        }
+        _ => return Err(ProgramError::InvalidAccountData),
+    };
+    Ok(extension)
+}
+
+fn cli_account_extension(
+    token_account: &StateWithExtensionsOwned<Account>,
+    extension_type: ExtensionType,
+) -> Result<CliAccountExtension, ProgramError> {
+    let extension = match extension_type {
+        ExtensionType::ImmutableOwner => CliAccountExtension::ImmutableOwner {},
+        ExtensionType::MemoTransfer => {
+            let memo_transfer = token_account.get_extension::<MemoTransfer>()?;
+            CliAccountExtension::MemoTransfer(CliMemoTransfer {
+                require_incoming_transfer_memos: bool::from(
+                    memo_transfer.require_incoming_transfer_memos,
+                ),
+            })
+        }
+        ExtensionType::ConfidentialTransferAccount => {
+            let confidential_account =
+                token_account.get_extension::<ConfidentialTransferAccount>()?;
+            CliAccountExtension::ConfidentialTransferAccount(CliConfidentialTransferAccount {
+                approved: bool::from(confidential_account.approved),
+                encryption_pubkey: cli_encryption_pubkey(&confidential_account.encryption_pubkey),
+                allow_balance_credits: bool::from(confidential_account.allow_balance_credits),
+                pending_balance_credit_counter: u64::from(
+                    confidential_account.pending_balance_credit_counter,
+                ),
+                maximum_pending_balance_credit_counter: u64::from(
+                    confidential_account.maximum_pending_balance_credit_counter,
+                ),
+            })
+        }
+        ExtensionType::TransferFeeAmount => {
+            let transfer_fee_amount = token_account.get_extension::<TransferFeeAmount>()?;
+            CliAccountExtension::TransferFeeAmount(CliTransferFeeAmount {
+                withheld_amount: u64::from(transfer_fee_amount.withheld_amount),
+            })
+        }
+        _ => return Err(ProgramError::InvalidAccountData),
+    };
+    Ok(extension)
+}
+
+// Mints and token accounts can't be mistaken for one another: a token account is too long to be
+// a plain mint, and anything longer records which of the two it is
+async fn command_display(config: &Config<'_>, address: Pubkey) -> CommandResult {
+    let account = config
+        .rpc_client
+        .get_account_with_commitment(&address, config.commitment)
+        .await?
+        .value
+        .ok_or_else(|| format!("Account {} not found", address))?;
+    config.check_owner(&address, &account.owner)?;
+    if let Ok(mint) = StateWithExtensionsOwned::<Mint>::unpack(account.data.clone()) {
+        display_mint(config, address, account.owner, mint).await
+    } else if let Ok(token_account) = StateWithExtensionsOwned::<Account>::unpack(account.data) {
+        display_token_account(config, address, account.owner, token_account).await
+    } else {
+        Err(format!("Account {} is not a valid mint or token account", address).into())
+    }
+}
+
+async fn display_mint(
+    config: &Config<'_>,
+    address: Pubkey,
+    program_id: Pubkey,
+    mint: StateWithExtensionsOwned<Mint>,
+) -> CommandResult {
+    let extensions = mint
+        .get_extension_types()?
+        .into_iter()
+        .map(|extension_type| {
+            cli_mint_extension(&mint, extension_type).unwrap_or_else(|err| {
+                CliMintExtension::Unparseable(CliUnparseableExtension {
+                    extension_type: format!("{:?}", extension_type),
+                    error: err.to_string(),
+                })
+            })
+        })
+        .collect();
+    let supply_with_interest = ui_amount_with_interest(config, &mint, mint.base.supply).await?;
+
+    let cli_mint_info = CliMintInfo {
+        address: address.to_string(),
+        program_id: program_id.to_string(),
+        supply: spl_token_2022::amount_to_ui_amount_string_trimmed(
+            mint.base.supply,
+            mint.base.decimals,
+        ),
+        supply_with_interest,
+        decimals: mint.base.decimals,
+        mint_authority: Option::<Pubkey>::from(mint.base.mint_authority)
+            .map(|pubkey| pubkey.to_string()),
+        freeze_authority: Option::<Pubkey>::from(mint.base.freeze_authority)
+            .map(|pubkey| pubkey.to_string()),
+        extensions,
+    };
+    Ok(config.output_format.formatted_string(&cli_mint_info))
+}
+
+async fn display_token_account(
+    config: &Config<'_>,
+    address: Pubkey,
+    program_id: Pubkey,
+    token_account: StateWithExtensionsOwned<Account>,
+) -> CommandResult {
+    let extensions = token_account
+        .get_extension_types()?
+        .into_iter()
+        .map(|extension_type| {
+            cli_account_extension(&token_account, extension_type).unwrap_or_else(|err| {
+                CliAccountExtension::Unparseable(CliUnparseableExtension {
+                    extension_type: format!("{:?}", extension_type),
+                    error: err.to_string(),
+                })
+            })
+        })
+        .collect();
+    let account = token_account.base;
+    let decimals = config.get_mint_info(&account.mint, None).await?.decimals;
+    let delegate = Option::<Pubkey>::from(account.delegate);
+    let state = match account.state {
+        AccountState::Uninitialized => UiAccountState::Uninitialized,
+        AccountState::Initialized => UiAccountState::Initialized,
+        AccountState::Frozen => UiAccountState::Frozen,
+    };
+
+    let cli_token_account_info = CliTokenAccountInfo {
+        address: address.to_string(),
+        program_id: program_id.to_string(),
+        mint: account.mint.to_string(),
+        owner: account.owner.to_string(),
+        is_associated: get_associated_token_address_with_program_id(
+            &account.owner,
+            &account.mint,
+            &program_id,
+        ) == address,
+        amount: spl_token_2022::amount_to_ui_amount_string_trimmed(account.amount, decimals),
+        decimals,
+        delegate: delegate.map(|pubkey| pubkey.to_string()),
+        delegated_amount: delegate.map(|_| {
+            spl_token_2022::amount_to_ui_amount_string_trimmed(account.delegated_amount, decimals)
+        }),
+        state,
+        is_native: account.is_native(),
+        close_authority: Option::<Pubkey>::from(account.close_authority)
+            .map(|pubkey| pubkey.to_string()),
+        extensions,
+    };
+    Ok(config
+        .output_format
+        .formatted_string(&cli_token_account_info))
+}
+
+async fn command_close_empty_accounts(
+    config: &Config<'_>,
+    owner: Pubkey,
+    signer_info: &CliSignerInfo,
+) -> CommandResult {
+    if config.sign_only {
+        return Err("Error: Finding empty accounts is not possible with `--sign-only`".into());
+    }
+    println_display(
+        config,
+        format!(
+            "Fetching token accounts associated with program {}",
+            config.program_id
+        ),
+    );
+    let addresses = config
+        .rpc_client
+        .get_token_accounts_by_owner(&owner, TokenAccountsFilter::ProgramId(config.program_id))
+        .await?
+        .into_iter()
+        .map(|keyed_account| {
+            keyed_account
+                .pubkey
+                .parse::<Pubkey>()
+                .unwrap_or_else(|err| panic!("Invalid token account: {}", err))
+        })
+        .collect::<Vec<_>>();
+    let accounts = config.get_multiple_accounts(&addresses).await?;
+
+    let mut closed = vec![];
+    let mut skipped = vec![];
+    let mut lamports_reclaimed = 0;
+    let mut instructions = vec![];
+    for (address, account) in addresses.into_iter().zip(accounts) {
+        let account = match account {
+            Some(account) => account,
+            None => continue,
+        };
+        let lamports = account.lamports;
+        let token_account = match StateWithExtensionsOwned::<Account>::unpack(account.data) {
+            Ok(token_account) => token_account,
+            Err(_) => continue,
+        };
+        if token_account.base.amount > 0 {
+            continue;
+        }
+
+        let reason = if token_account.base.is_frozen() {
+            Some("account is frozen".to_string())
+        } else if token_account.base.delegate.is_some() {
+            Some("account has a delegate".to_string())
+        } else if token_account.base.close_authority.unwrap_or(owner) != owner {
+            Some("account has a different close authority".to_string())
+        } else if token_account
+            .get_extension::<TransferFeeAmount>()
+            .map(|extension| extension.closable().is_err())
+            .unwrap_or(false)
+        {
+            Some("account has withheld transfer fees, which must be withdrawn first".to_string())
+        } else if token_account
+            .get_extension::<ConfidentialTransferAccount>()
+            .map(|extension| extension.closable().is_err())
+            .unwrap_or(false)
+        {
+            Some("account has a confidential balance".to_string())
+        } else {
+            None
+        };
+        if let Some(reason) = reason {
+            println_display(config, format!("Skipping {}: {}", address, reason));
+            skipped.push(CliSkippedAccount {
+                address: address.to_string(),
+                reason,
+            });
+            continue;
+        }
+
+        instructions.push(close_account(
+            &config.program_id,
+            &address,
+            &owner,
+            &owner,
+            &config.multisigner_pubkeys,
+        )?);
+        closed.push(address.to_string());
+        lamports_reclaimed += lamports;
+    }
+
+    let transactions = batch_instructions(config, instructions)
+        .into_iter()
+        .map(|tx_instructions| (tx_instructions, 0))
+        .collect::<Vec<_>>();
+    let progress = Progress::transactions(config, transactions.len());
+    let results = handle_txs(signer_info, config, transactions, &progress).await;
+    let (signatures, simulations, failures) = split_batch_results(results);
+
+    finish_batch(
+        config
+            .output_format
+            .formatted_string(&CliCloseEmptyAccounts {
+                owner: owner.to_string(),
+                closed,
+                skipped,
+                lamports_reclaimed,
+                signatures,
+                simulations,
+            }),
+        failures,
+    )
+}
+
+// The transaction may be given as the base64 of itself or of just its message, as printed by
+// `--dump-transaction-message`, or as a file holding either, like the one `--output-file` writes
+fn command_decode_transaction(config: &Config<'_>, transaction: &str) -> CommandResult {
+    let encoded = if std::path::Path::new(transaction).is_file() {
+        std::fs::read_to_string(transaction)
+            .map_err(|err| format!("Unable to read transaction file {}: {}", transaction, err))?
+    } else {
+        transaction.to_string()
+    };
+    let bytes = base64::decode(encoded.trim())
+        .map_err(|err| format!("Transaction is not valid base64: {}", err))?;
+    let transaction = match bincode::deserialize::<Transaction>(&bytes) {
+        Ok(transaction) => transaction,
+        Err(_) => Transaction::new_unsigned(
+            bincode::deserialize::<Message>(&bytes)
+                .map_err(|err| format!("Not a transaction or message: {}", err))?,
+        ),
+    };
+    if transaction.sanitize().is_err() {
+        return Err("Error: The decoded transaction is malformed".into());
+    }
+    Ok(config
+        .output_format
+        .formatted_string(&decode_transaction(&transaction)))
+}
+
+// Report the status of a transaction, failing unless it succeeded at the configured commitment
+async fn command_confirm(config: &Config<'_>, signature: Signature) -> CommandResult {
+    let status = config
+        .rpc_client
+        .get_signature_statuses_with_history(&[signature])
+        .await?
+        .value
+        .into_iter()
+        .next()
+        .flatten()
+        .ok_or_else(|| format!("Transaction {} not found", signature))?;
+
+    let satisfied = status.satisfies_commitment(config.commitment);
+    let cli_status = CliSignatureStatus {
+        signature: signature.to_string(),
+        slot: status.slot,
+        confirmations: status.confirmations,
+        err: status.err.clone(),
+        confirmation_status: status.confirmation_status.clone(),
+    };
+    let output = config.output_format.formatted_string(&cli_status);
+    let message = if status.err.is_some() {
+        format!("Error: Transaction {} failed", signature)
+    } else if !satisfied {
+        format!(
+            "Error: Transaction {} has not reached {:?} commitment yet",
+            signature, config.commitment.commitment
+        )
+    } else {
+        return Ok(output);
+    };
+    Err(TokenCliError::Incomplete { output, message }.into())
+}
+
+// Write every account of the given mints, as read at a single slot per mint, to a snapshot file
+// that is hashed and signed by the fee payer, so that later changes to it can be detected
+async fn command_export_accounts(
+    config: &Config<'_>,
+    mut mints: Vec<Pubkey>,
+    out: &str,
+    bulk_signers: BulkSigners,
+) -> CommandResult {
+    if config.sign_only {
+        return Err("Error: Exporting accounts is not possible with `--sign-only`".into());
+    }
+    mints.sort();
+    mints.dedup();
+
+    let mut snapshot_mints = vec![];
+    let mut slot = 0;
+    let mut account_count = 0;
+    for mint in mints {
+        // `--mints` may mix both token programs, so each mint's own program is used rather than
+        // the one detected from the first
+        let mint_account = config
+            .rpc_client
+            .get_account(&mint)
+            .await
+            .map_err(|_| format!("Mint account not found {:?}", mint))?;
+        let mint_state = match StateWithExtensionsOwned::<Mint>::unpack(mint_account.data) {
+            Ok(mint_state)
+                if mint_account.owner == spl_token::id()
+                    || mint_account.owner == spl_token_2022::id() =>
+            {
+                mint_state
+            }
+            _ => return Err(format!("Invalid mint account {:?}", mint).into()),
+        };
+        let mint_info = MintInfo {
+            program_id: mint_account.owner,
+            address: mint,
+            decimals: mint_state.base.decimals,
+        };
+        let progress = Progress::spinner(
+            config,
+            format!("Fetching all token accounts of mint {}", mint),
+        );
+        let mut filters = vec![RpcFilterType::Memcmp(Memcmp {
+            offset: 0,
+            bytes: MemcmpEncodedBytes::Base58(mint.to_string()),
+            encoding: None,
+        })];
+        if mint_info.program_id == spl_token::id() {
+            filters.push(RpcFilterType::DataSize(Account::LEN as u64));
+        }
+        // Requested directly, since only the raw RPC response has the slot it was read at
+        let response: Response<Vec<RpcKeyedAccount>> = config
+            .rpc_client
+            .send(
+                RpcRequest::GetProgramAccounts,
+                serde_json::json!([
+                    mint_info.program_id.to_string(),
+                    RpcProgramAccountsConfig {
+                        filters: Some(filters),
+                        account_config: RpcAccountInfoConfig {
+                            encoding: Some(UiAccountEncoding::Base64),
+                            commitment: Some(config.commitment),
+                            ..RpcAccountInfoConfig::default()
+                        },
+                        with_context: Some(true),
+                    }
+                ]),
+            )
+            .await?;
+
+        let mut accounts = vec![];
+        for keyed_account in response.value {
+            let address = Pubkey::from_str(&keyed_account.pubkey)?;
+            let account = keyed_account
+                .account
+                .decode::<solana_sdk::account::Account>()
+                .ok_or_else(|| format!("Could not decode account {}", address))?;
+            if account.owner != mint_info.program_id {
+                return Err(TokenCliError::WrongProgramOwner {
+                    account: address,
+                    owner: account.owner,
+                    program_id: mint_info.program_id,
+                }
+                .into());
+            }
+            let token_account = match StateWithExtensionsOwned::<Account>::unpack(account.data) {
+                Ok(token_account) => token_account,
+                Err(_) => continue,
+            };
+            accounts.push(CliSnapshotAccount {
+                address: address.to_string(),
+                owner: token_account.base.owner.to_string(),
+                amount: token_account.base.amount.to_string(),
+                ui_amount: spl_token_2022::amount_to_ui_amount_string_trimmed(
+                    token_account.base.amount,
+                    mint_info.decimals,
+                ),
+            });
+        }
+        progress.finish_with_message(format!("{} token accounts scanned", accounts.len()));
+        accounts.sort_by(|a, b| a.address.cmp(&b.address));
+
+        slot = slot.max(response.context.slot);
+        account_count += accounts.len();
+        snapshot_mints.push(CliSnapshotMint {
+            mint: mint.to_string(),
+            program_id: mint_info.program_id.to_string(),
+            decimals: mint_info.decimals,
+            slot: response.context.slot,
+            accounts,
+        });
+    }
+
+    let body = CliAccountsSnapshotBody {
+        version: 1,
+        slot,
+        mints: snapshot_mints,
+    };
+    let hash = solana_sdk::hash::hash(&serde_json::to_vec(&body)?);
+    let signer = bulk_signers
+        .iter()
+        .find(|signer| signer.pubkey() == config.fee_payer)
+        .ok_or("Error: The fee payer keypair is needed to sign the snapshot")?;
+    let signature = signer.try_sign_message(hash.as_ref())?;
+    let snapshot = CliAccountsSnapshot {
+        body,
+        hash: hash.to_string(),
+        signer: config.fee_payer.to_string(),
+        signature: signature.to_string(),
+    };
+    std::fs::write(out, serde_json::to_string_pretty(&snapshot)? + "\n")
+        .map_err(|err| format!("Unable to write snapshot file {}: {}", out, err))?;
+
+    Ok(config.output_format.formatted_string(&CliExportAccounts {
+        file: out.to_string(),
+        slot,
+        accounts: account_count,
+        hash: snapshot.hash,
+        signature: snapshot.signature,
+    }))
+}
+
+// Where the authorities sit in the base mint layout, shared by both programs: each is a 4-byte
+// `COption` tag followed by the pubkey
+const MINT_AUTHORITY_OFFSET: usize = 4;
+const FREEZE_AUTHORITY_OFFSET: usize = 50;
+
+// Find the mints of both token programs where `authority` is the mint or freeze authority, or
+// just the one `role` asks for
+async fn command_find_mints(
+    config: &Config<'_>,
+    authority: Pubkey,
+    role: Option<AuthorityType>,
+) -> CommandResult {
+    let progress = Progress::spinner(
+        config,
+        format!(
+            "Scanning both token programs for mints of authority {}, this may take a while",
+            authority
+        ),
+    );
+    let offsets = match role {
+        Some(AuthorityType::MintTokens) => vec![MINT_AUTHORITY_OFFSET],
+        Some(AuthorityType::FreezeAccount) => vec![FREEZE_AUTHORITY_OFFSET],
+        _ => vec![MINT_AUTHORITY_OFFSET, FREEZE_AUTHORITY_OFFSET],
+    };
+
+    let mut mints: Vec<CliFoundMint> = vec![];
+    for program_id in [spl_token::id(), spl_token_2022::id()] {
+        for offset in &offsets {
+            // Only the base mint, whatever extensions follow it
+            let accounts = scan_program_accounts(
+                config,
+                &program_id,
+                AccountType::Mint,
+                vec![RpcFilterType::Memcmp(Memcmp {
+                    offset: *offset,
+                    bytes: MemcmpEncodedBytes::Base58(authority.to_string()),
+                    encoding: None,
+                })],
+                0,
+                Mint::LEN,
+            )
+            .await?;
+            for (address, data) in accounts {
+                let mint = match Mint::unpack(&data) {
+                    Ok(mint) => mint,
+                    Err(_) => continue,
+                };
+                if mints
+                    .iter()
+                    .any(|found| found.address == address.to_string())
+                {
+                    continue;
+                }
+                mints.push(CliFoundMint {
+                    address: address.to_string(),
+                    program_id: program_id.to_string(),
+                    decimals: mint.decimals,
+                    supply: mint.supply.to_string(),
+                    ui_supply: spl_token_2022::amount_to_ui_amount_string_trimmed(
+                        mint.supply,
+                        mint.decimals,
+                    ),
+                    mint_authority: mint.mint_authority == COption::Some(authority),
+                    freeze_authority: mint.freeze_authority == COption::Some(authority),
+                });
+            }
+        }
+    }
+    progress.finish_with_message(format!("{} mints found", mints.len()));
+
+    Ok(config.output_format.formatted_string(&CliFoundMints {
+        authority: authority.to_string(),
+        mints,
+    }))
+}
+
+// Run a command once per owner given through an owner manifest, grouping the output by owner.
+// A single owner gets the command's output unchanged.
+async fn for_each_owner<F, Fut>(config: &Config<'_>, owners: &[Pubkey], command: F) -> CommandResult
+where
+    F: Fn(Pubkey) -> Fut,
+    Fut: Future<Output = CommandResult>,
+{
+    if let [owner] = owners {
+        return command(*owner).await;
+    }
+    if config.json_lines {
+        // every line already names its owner, so there is nothing to group
+        let mut lines = vec![];
+        for owner in owners {
+            let output = command(*owner).await?;
+            if !output.is_empty() {
+                lines.push(output);
+            }
+        }
+        return Ok(lines.join("\n"));
+    }
+    let mut cli_owner_results = CliOwnerResults { owners: vec![] };
+    for owner in owners {
+        match command(*owner).await {
+            Ok(output) => cli_owner_results
+                .owners
+                .push(CliOwnerResult::new(owner.to_string(), output)),
+            Err(err) => {
+                if !cli_owner_results.owners.is_empty() {
+                    println!(
+                        "{}",
+                        config.output_format.formatted_string(&cli_owner_results)
+                    );
+                }
+                return Err(format!("Error: failed for owner {}: {}", owner, err).into());
+            }
+        }
+    }
+    Ok(config.output_format.formatted_string(&cli_owner_results))
+}
+
+async fn command_gc(
+    config: &Config<'_>,
+    owner: Pubkey,
+    close_empty_associated_accounts: bool,
+    bulk_signers: BulkSigners,
+) -> CommandResult {
+    println_display(
+        config,
+        format!(
+            "Fetching token accounts associated with program {}",
+            config.program_id
+        ),
+    );
+    let accounts = config
+        .rpc_client
+        .get_token_accounts_by_owner(&owner, TokenAccountsFilter::ProgramId(config.program_id))
+        .await?;
+    if accounts.is_empty() {
+        println_display(config, "Nothing to do".to_string());
+        return Ok("".to_string());
+    }
+
+    let minimum_balance_for_rent_exemption = if !config.sign_only {
+        config
+            .rpc_client
+            .get_minimum_balance_for_rent_exemption(Account::LEN)
+            .await?
+    } else {
+        0
+    };
+
+    let mut accounts_by_token = HashMap::new();
+
+    for keyed_account in accounts {
+        if let UiAccountData::Json(parsed_account) = keyed_account.account.data {
+            if is_supported_program(&parsed_account.program) {
+                if let Ok(TokenAccountType::Account(ui_token_account)) =
+                    serde_json::from_value(parsed_account.parsed)
+                {
+                    let frozen = ui_token_account.state == UiAccountState::Frozen;
+
+                    let token = ui_token_account
+                        .mint
+                        .parse::<Pubkey>()
+                        .unwrap_or_else(|err| panic!("Invalid mint: {}", err));
+                    let token_account = keyed_account
+                        .pubkey
+                        .parse::<Pubkey>()
+                        .unwrap_or_else(|err| panic!("Invalid token account: {}", err));
+                    let token_amount = ui_token_account
+                        .token_amount
+                        .amount
+                        .parse::<u64>()
+                        .unwrap_or_else(|err| panic!("Invalid token amount: {}", err));
+
+                    let close_authority = ui_token_account.close_authority.map_or(owner, |s| {
+                        s.parse::<Pubkey>()
+                            .unwrap_or_else(|err| panic!("Invalid close authority: {}", err))
+                    });
+
+                    let entry = accounts_by_token.entry(token).or_insert_with(HashMap::new);
+                    entry.insert(
+                        token_account,
+                        (
+                            token_amount,
+                            ui_token_account.token_amount.decimals,
+                            frozen,
+                            close_authority,
+                        ),
+                    );
+                }
+            }
+        }
+    }
+
+    let mut instructions = vec![];
+    let mut lamports_needed = 0;
+
+    for (token, accounts) in accounts_by_token.into_iter() {
+        println_display(config, format!("Processing token: {}", token));
+        let associated_token_account =
+            get_associated_token_address_with_program_id(&owner, &token, &config.program_id);
+        let total_balance: u64 = accounts.values().map(|account| account.0).sum();
+
+        if total_balance > 0 && !accounts.contains_key(&associated_token_account) {
+            // Create the associated token account
+            instructions.push(vec![create_associated_token_account(
+                &config.fee_payer,
+                &owner,
+                &token,
+                &config.program_id,
+            )]);
+            lamports_needed += minimum_balance_for_rent_exemption;
+        }
+
+        for (address, (amount, decimals, frozen, close_authority)) in accounts {
+            match (
+                address == associated_token_account,
+                close_empty_associated_accounts,
+                total_balance > 0,
+            ) {
+                (true, _, true) => continue, // don't ever close associated token account with amount
+                (true, false, _) => continue, // don't close associated token account if close_empty_associated_accounts isn't set
+                (true, true, false) => println_display(
+                    config,
+                    format!("Closing Account {}", associated_token_account),
+                ),
+                _ => {}
+            }
+
+            if frozen {
+                // leave frozen accounts alone
+                continue;
+            }
+
+            let mut account_instructions = vec![];
+
+            // Sanity check!
+            // we shouldn't ever be here, but if we are here, abort!
+            assert!(amount == 0 || address != associated_token_account);
+
+            if amount > 0 {
+                // Transfer the account balance into the associated token account
+                account_instructions.push(transfer_checked(
+                    &config.program_id,
+                    &address,
+                    &token,
+                    &associated_token_account,
+                    &owner,
+                    &config.multisigner_pubkeys,
+                    amount,
+                    decimals,
+                )?);
+            }
+            // Close the account if config.owner is able to
+            if close_authority == owner {
+                account_instructions.push(close_account(
+                    &config.program_id,
+                    &address,
+                    &owner,
+                    &owner,
+                    &config.multisigner_pubkeys,
+                )?);
+            }
+
+            if !account_instructions.is_empty() {
+                instructions.push(account_instructions);
+            }
+        }
+    }
+
+    let cli_signer_info = CliSignerInfo {
+        signers: bulk_signers,
+    };
+
+    let mut result = String::from("");
+    for tx_instructions in instructions {
+        let tx_return = handle_tx(
+            &cli_signer_info,
+            config,
+            false,
+            lamports_needed,
+            tx_instructions,
+        )
+        .await?;
+        result += &match tx_return {
+            TransactionReturnData::CliSignature(signature) => {
+                config.output_format.formatted_string(&signature)
+            }
+            TransactionReturnData::CliSignOnlyData(sign_only_data) => {
+                config.output_format.formatted_string(&sign_only_data)
+            }
+            TransactionReturnData::CliSimulation(simulation) => {
+                config.output_format.formatted_string(&simulation)
+            }
+        };
+        result += "\n";
+    }
+    Ok(result)
+}
+
+async fn command_sync_native(
+    native_account_address: Pubkey,
+    bulk_signers: Vec<Box<dyn Signer>>,
+    config: &Config<'_>,
+) -> CommandResult {
+    let (program_id, balance_before) = if config.sign_only {
+        (config.program_id, None)
+    } else {
+        let account = config
+            .rpc_client
+            .get_account(&native_account_address)
+            .await
+            .map_err(|err| {
+                format!(
+                    "Token account {} does not exist: {}",
+                    native_account_address, err
+                )
+            })?;
+        // Token and Token-2022 each have their own native mint, so the instruction must go to
+        // the program that owns the account
+        let program_id = account.owner;
+        let token_account = StateWithExtensionsOwned::<Account>::unpack(account.data)
+            .map_err(|_| format!("Could not find token account {}", native_account_address))?;
+        if native_mint(&program_id).ok() != Some(token_account.base.mint) {
+            return Err(format!(
+                "Error: {} is not a wrapped SOL account",
+                native_account_address
+            )
+            .into());
+        }
+        (program_id, Some(token_account.base.amount))
+    };
+
+    let tx_return = handle_tx(
+        &CliSignerInfo {
+            signers: bulk_signers,
+        },
+        config,
+        false,
+        0,
+        vec![sync_native(&program_id, &native_account_address)?],
+    )
+    .await?;
+
+    let balance_before = balance_before.map(|amount| lamports_to_sol(amount).to_string());
+    let address = native_account_address.to_string();
+    Ok(match tx_return {
+        TransactionReturnData::CliSignature(signature) => {
+            let balance_after = config
+                .rpc_client
+                .get_token_account_balance(&native_account_address)
+                .await?
+                .ui_amount_string;
+            config.output_format.formatted_string(&CliSyncNative {
+                address,
+                balance_before,
+                balance_after: Some(balance_after),
+                transaction_data: signature,
+            })
+        }
+        TransactionReturnData::CliSignOnlyData(sign_only_data) => {
+            config.output_format.formatted_string(&CliSyncNative {
+                address,
+                balance_before,
+                balance_after: None,
+                transaction_data: sign_only_data,
+            })
+        }
+        TransactionReturnData::CliSimulation(simulation) => {
+            config.output_format.formatted_string(&CliSyncNative {
+                address,
+                balance_before,
+                balance_after: None,
+                transaction_data: simulation,
+            })
+        }
+    })
+}
+
+struct SignOnlyNeedsFullMintSpec {}
+impl offline::ArgsConfig for SignOnlyNeedsFullMintSpec {
+    fn sign_only_arg<'a, 'b>(&self, arg: Arg<'a, 'b>) -> Arg<'a, 'b> {
+        arg.requires_all(&[MINT_ADDRESS_ARG.name, MINT_DECIMALS_ARG.name])
+    }
+}
+
+struct SignOnlyNeedsMintDecimals {}
+impl offline::ArgsConfig for SignOnlyNeedsMintDecimals {
+    fn sign_only_arg<'a, 'b>(&self, arg: Arg<'a, 'b>) -> Arg<'a, 'b> {
+        arg.requires_all(&[MINT_DECIMALS_ARG.name])
+    }
+}
+
+struct SignOnlyNeedsMintAddress {}
+impl offline::ArgsConfig for SignOnlyNeedsMintAddress {
+    fn sign_only_arg<'a, 'b>(&self, arg: Arg<'a, 'b>) -> Arg<'a, 'b> {
+        arg.requires_all(&[MINT_ADDRESS_ARG.name])
+    }
+}
+
+struct SignOnlyNeedsDelegateAddress {}
+impl offline::ArgsConfig for SignOnlyNeedsDelegateAddress {
+    fn sign_only_arg<'a, 'b>(&self, arg: Arg<'a, 'b>) -> Arg<'a, 'b> {
+        arg.requires_all(&[DELEGATE_ADDRESS_ARG.name])
+    }
+}
+
+fn minimum_signers_help_string() -> String {
+    format!(
+        "The minimum number of signers required to allow the operation. [{} <= M <= N]",
+        MIN_SIGNERS
+    )
+}
+
+fn multisig_member_help_string() -> String {
+    format!(
+        "The public keys for each of the N signing members of this account. [{} <= N <= {}]",
+        MIN_SIGNERS, MAX_SIGNERS
+    )
+}
+
+fn app<'a, 'b>(
+    default_decimals: &'a str,
+    default_program_id: &'a str,
+    minimum_signers_help: &'b str,
+    multisig_member_help: &'b str,
+) -> App<'a, 'b> {
+    App::new(crate_name!())
+        .about(crate_description!())
+        .version(crate_version!())
+        .setting(AppSettings::SubcommandRequiredElseHelp)
+        .arg({
+            let arg = Arg::with_name("config_file")
+                .short("C")
+                .long("config")
+                .value_name("PATH")
+                .takes_value(true)
+                .global(true)
+                .help("Configuration file to use");
+            if let Some(ref config_file) = *solana_cli_config::CONFIG_FILE {
+                arg.default_value(config_file)
+            } else {
+                arg
+            }
+        })
+        .arg(
+            Arg::with_name("verbose")
+                .short("v")
+                .long("verbose")
+                .takes_value(false)
+                .global(true)
+                .help("Show additional information"),
+        )
+        .arg(
+            Arg::with_name("quiet")
+                .short("q")
+                .long("quiet")
+                .takes_value(false)
+                .global(true)
+                .conflicts_with("verbose")
+                .help("Do not show progress while scanning accounts or sending transactions"),
+        )
+        .arg(
+            Arg::with_name("output_format")
+                .long("output")
+                .value_name("FORMAT")
+                .global(true)
+                .takes_value(true)
+                .possible_values(&["json", "json-compact", "jsonl"])
+                .help("Return information in specified output format. \
+                       `jsonl` prints one JSON object per line for commands that list \
+                       accounts, and compact JSON otherwise"),
+        )
+        .arg(
+            Arg::with_name("program_id")
+                .short("p")
+                .long("program-id")
+                .alias("token-program")
+                .value_name("ADDRESS_OR_KEYWORD")
+                .takes_value(true)
+                .global(true)
+                .default_value(default_program_id)
+                .validator(is_program_id)
+                .help("SPL Token program id, or one of the keywords `token`, \
+                       `token-2022` or `tk22`"),
+        )
+        .arg(
+            Arg::with_name("json_rpc_url")
+                .short("u")
+                .long("url")
+                .value_name("URL_OR_MONIKER")
+                .takes_value(true)
+                .global(true)
+                .validator(is_url_or_moniker_list)
+                .help(
+                    "URL for Solana's JSON RPC or moniker (or their first letter): \
+                       [mainnet-beta, testnet, devnet, localhost] \
+                    Default from the configuration file. \
+                    Separate several with commas to fall back on the next one \
+                    whenever an endpoint can't be reached."
+                ),
+        )
+        .arg(fee_payer_arg().global(true))
+        .arg(
+            Arg::with_name("output_file")
+                .long("output-file")
+                .value_name("PATH")
+                .takes_value(true)
+                .global(true)
+                .help("Write the signed transaction to this file as base64. \
+                       With --sign-only, the partially signed transaction is written instead"),
+        )
+        .arg(
+            Arg::with_name("input_file")
+                .long("input-file")
+                .value_name("PATH")
+                .takes_value(true)
+                .global(true)
+                .help("Add signatures to the transaction stored in this file by --output-file. \
+                       The remaining arguments must describe the same transaction"),
+        )
+        .arg(
+            Arg::with_name("max_sign_attempts")
+                .long("max-sign-attempts")
+                .value_name("COUNT")
+                .takes_value(true)
+                .global(true)
+                .validator(is_parsable::<u32>)
+                .help("Try a hardware wallet this many times in all when it is locked, \
+                       disconnected or the prompt on it is rejected [default: 1]"),
+        )
+        .arg(
+            Arg::with_name("account_source")
+                .long("account-source")
+                .value_name("PATH")
+                .takes_value(true)
+                .global(true)
+                .help("Read mints and token accounts from this file before asking the RPC node, \
+                       as dumped by `solana account --output json`, one account or an array"),
+        )
+        .arg(
+            Arg::with_name("compute_unit_limit")
+                .long("with-compute-unit-limit")
+                .value_name("UNITS")
+                .takes_value(true)
+                .global(true)
+                .validator(is_parsable::<u32>)
+                .help("Set a compute unit limit for the transaction"),
+        )
+        .arg(
+            Arg::with_name("compute_unit_price")
+                .long("with-compute-unit-price")
+                .value_name("MICRO_LAMPORTS")
+                .takes_value(true)
+                .global(true)
+                .validator(is_parsable::<u64>)
+                .help("Set a compute unit price for the transaction, in micro-lamports per compute unit"),
+        )
+        .arg(
+            Arg::with_name("compute_budget_from_simulation")
+                .long("with-compute-budget-from-simulation")
+                .takes_value(false)
+                .global(true)
+                .conflicts_with("compute_unit_limit")
+                .help("Simulate each transaction before sending it, and set its compute unit \
+                       limit to the units it consumed plus a margin. \
+                       Not possible with --sign-only"),
+        )
+        .arg(
+            Arg::with_name("compute_unit_margin")
+                .long("compute-unit-margin")
+                .value_name("PERCENT")
+                .takes_value(true)
+                .global(true)
+                .validator(is_parsable::<u32>)
+                .requires("compute_budget_from_simulation")
+                .help("Margin to add to the simulated compute units, in percent [default: 10]"),
+        )
+        .arg(
+            Arg::with_name("simulate")
+                .long("simulate")
+                .takes_value(false)
+                .global(true)
+                .conflicts_with(SIGN_ONLY_ARG.name)
+                .help("Simulate the transaction instead of sending it, \
+                       and print the resulting logs and compute units consumed"),
+        )
+        .arg(
+            Arg::with_name("skip_fee_payer_balance_check")
+                .long("skip-fee-payer-balance-check")
+                .takes_value(false)
+                .global(true)
+                .help("Do not check that the fee payer can cover transaction fees and rent \
+                       before sending"),
+        )
+        .arg(memo_arg().global(true))
+        .arg(
+            Arg::with_name("no_wait")
+                .long("no-wait")
+                .takes_value(false)
+                .global(true)
+                .help("Return signatures immediately after submitting transactions, \
+                       instead of waiting for confirmations"),
+        )
+        .arg(
+            Arg::with_name("commitment")
+                .long("commitment")
+                .value_name("COMMITMENT_LEVEL")
+                .takes_value(true)
+                .possible_values(&["processed", "confirmed", "finalized"])
+                .default_value("confirmed")
+                .global(true)
+                .help("Commitment level for reading accounts, and for the confirmation of \
+                       sent transactions. With --no-wait transactions aren't confirmed at all, \
+                       and only the reads use it"),
+        )
+        .arg(
+            Arg::with_name("offline")
+                .long("offline")
+                .takes_value(false)
+                .global(true)
+                .conflicts_with_all(&["simulate", "no_wait", "input_file"])
+                .help("Never use the network, for airgapped machines. Implies --sign-only, \
+                       and anything that would be fetched, like the blockhash or the decimals of \
+                       a token, must be given explicitly. With `address`, the token program \
+                       given by --program-id is used"),
+        )
+        .arg(
+            Arg::with_name("relayer_sign_only")
+                .long("relayer-sign-only")
+                .takes_value(false)
+                .global(true)
+                .requires("fee_payer")
+                .conflicts_with_all(&["offline", "simulate", "no_wait", "input_file"])
+                .help("Leave the transaction for a relayer to pay for: --fee-payer is the \
+                       address of the relayer, and the transaction is signed by everyone else \
+                       but not sent. Implies --sign-only, but the decimals of the token and the \
+                       blockhash are still fetched when not given. The output includes the \
+                       partially signed transaction, whose first signature is left empty for the \
+                       relayer. The relayer must sign the message of that transaction exactly as \
+                       it is, put its signature first while keeping the others, and send it \
+                       before the blockhash expires, unless --nonce is used"),
+        )
+        .arg(
+            Arg::with_name("strict")
+                .long("strict")
+                .takes_value(false)
+                .global(true)
+                .help("Fail instead of warning when the given --multisig-signer set \
+                       can't reach the multisig's threshold. The multisig is looked up \
+                       over RPC, so the check is skipped with --sign-only when fully offline"),
+        )
+        .arg(
+            Arg::with_name("rpc_max_retries")
+                .long("rpc-max-retries")
+                .value_name("COUNT")
+                .takes_value(true)
+                .global(true)
+                .default_value("3")
+                .validator(is_parsable::<u32>)
+                .help("How many times to resend a transaction after a transient RPC error, \
+                       such as a rate limit or a node falling behind, backing off \
+                       exponentially between attempts"),
+        )
+        .arg(
+            Arg::with_name("use_unchecked_instruction")
+                .long("use-unchecked-instruction")
+                .takes_value(false)
+                .global(true)
+                .hidden(true)
+                .help("Use unchecked instruction if appropriate. Supports transfer, burn, mint, and approve."),
+        )
+        .bench_subcommand()
+        .subcommand(SubCommand::with_name(CommandName::CreateToken.into())
+                .about("Create a new token. With `--sign-only` the cluster's rent can't be \
+                        queried, so the mint is funded by the default rent parameters")
+                .arg(
+                    Arg::with_name("token_keypair")
+                        .value_name("TOKEN_KEYPAIR")
+                        .validator(is_valid_signer)
+                        .takes_value(true)
+                        .index(1)
+                        .help(
+                            "Specify the token keypair. \
+                             This may be a keypair file or the ASK keyword. \
+                             [default: randomly generated keypair]"
+                        ),
+                )
+                .arg(
+                    Arg::with_name("address_signer")
+                        .long("address-signer")
+                        .value_name("TOKEN_KEYPAIR")
+                        .validator(is_valid_signer)
+                        .takes_value(true)
+                        .conflicts_with("token_keypair")
+                        .help(
+                            "Same as the TOKEN_KEYPAIR argument, to create the token at the \
+                             address of a pre-generated keypair, such as a vanity address"
+                        ),
+                )
+                .arg(
+                    Arg::with_name("mint_authority")
+                        .long("mint-authority")
+                        .alias("owner")
+                        .value_name("ADDRESS")
+                        .validator(is_valid_pubkey)
+                        .takes_value(true)
+                        .help(
+                            "Specify the mint authority address. \
+                             Defaults to the client keypair address."
+                        ),
+                )
+                .arg(
+                    Arg::with_name("decimals")
+                        .long("decimals")
+                        .validator(is_mint_decimals)
+                        .value_name("DECIMALS")
+                        .takes_value(true)
+                        .default_value(default_decimals)
+                        .help("Number of base 10 digits to the right of the decimal place"),
+                )
+                .arg(
+                    Arg::with_name("enable_freeze")
+                        .long("enable-freeze")
+                        .takes_value(false)
+                        .help(
+                            "Enable the mint authority to freeze associated token accounts."
+                        ),
+                )
+                .arg(
+                    Arg::with_name("default_account_state")
+                        .long("default-account-state")
+                        .value_name("STATE")
+                        .takes_value(true)
+                        .possible_values(&["initialized", "frozen"])
+                        .help(
+                            "The state new token accounts start in. \
+                             Requires Token-2022, and `--enable-freeze` for `frozen`."
+                        ),
+                )
+                .arg(
+                    Arg::with_name("interest_rate")
+                        .long("interest-rate")
+                        .value_name("RATE_BPS")
+                        .takes_value(true)
+                        .allow_hyphen_values(true)
+                        .validator(is_parsable::<i16>)
+                        .help(
+                            "Make the token interest-bearing, accruing continuously at this \
+                             annual rate in basis points. Negative rates are allowed. \
+                             The mint authority becomes the rate authority. Requires Token-2022."
+                        ),
+                )
+                .arg(
+                    Arg::with_name("enable_non_transferable")
+                        .long("enable-non-transferable")
+                        .takes_value(false)
+                        .help(
+                            "Permanently prevent the token from being transferred \
+                             between accounts. Requires Token-2022."
+                        ),
+                )
+                .arg(
+                    Arg::with_name("dry_run")
+                        .long("dry-run")
+                        .takes_value(false)
+                        .help(
+                            "Only print the size of the mint account with the chosen \
+                             extensions and the lamports needed to make it rent-exempt, \
+                             without creating it"
+                        ),
+                )
+                .nonce_args(true)
+                .offline_args(),
+        )
+        .subcommand(
+            SubCommand::with_name(CommandName::CreateAccount.into())
+                .about("Create a new token account")
+                .arg(
+                    Arg::with_name("token")
+                        .validator(is_valid_pubkey)
+                        .value_name("TOKEN_ADDRESS")
+                        .takes_value(true)
+                        .index(1)
+                        .required(true)
+                        .help("The token that the account will hold"),
+                )
+                .arg(
+                    Arg::with_name("account_keypair")
+                        .value_name("ACCOUNT_KEYPAIR")
+                        .validator(is_valid_signer)
+                        .takes_value(true)
+                        .index(2)
+                        .help(
+                            "Specify the account keypair. \
+                             This may be a keypair file or the ASK keyword. \
+                             [default: associated token account for --owner]"
+                        ),
+                )
+                .arg(
+                    Arg::with_name("seed")
+                        .long("seed")
+                        .value_name("SEED")
+                        .takes_value(true)
+                        .validator(is_derived_address_seed)
+                        .help(
+                            "Create the account at the address derived from the owner, this \
+                             seed and the token program, instead of an account keypair or the \
+                             associated token account. The owner must sign. \
+                             [default owner: the client keypair]"
+                        ),
+                )
+                // At most one of them, as they both give the address of the account
+                .group(
+                    ArgGroup::with_name("explicit_account")
+                        .args(&["account_keypair", "seed"])
+                )
+                .arg(
+                    Arg::with_name("idempotent")
+                        .long("idempotent")
+                        .takes_value(false)
+                        .conflicts_with("explicit_account")
+                        .help(
+                            "Succeed without doing anything if the associated token account \
+                             already exists"
+                        ),
+                )
+                .arg(
+                    Arg::with_name("immutable_owner")
+                        .long("immutable-owner")
+                        .takes_value(false)
+                        .requires("explicit_account")
+                        .help(
+                            "Prevent the owner of the account from ever being changed. \
+                             Only supported by the Token-2022 program, whose associated token \
+                             accounts always have an immutable owner"
+                        ),
+                )
+                .arg(owner_address_arg())
+                .nonce_args(true)
+                .offline_args(),
+        )
+        .subcommand(
+            SubCommand::with_name(CommandName::RecreateAta.into())
+                .about("Create the associated token account under the program that owns the \
+                        token if it's missing, and move the owner's other balances of the \
+                        token into it")
+                .arg(
+                    Arg::with_name("token")
+                        .validator(is_valid_pubkey)
+                        .value_name("TOKEN_ADDRESS")
+                        .takes_value(true)
+                        .index(1)
+                        .required(true)
+                        .help("The token of the associated token account"),
+                )
+                .arg(
+                    Arg::with_name("from")
+                        .long("from")
+                        .validator(is_valid_pubkey)
+                        .value_name("TOKEN_ACCOUNT_ADDRESS")
+                        .takes_value(true)
+                        .multiple(true)
+                        .number_of_values(1)
+                        .help("A token account of the owner to move into the associated token \
+                               account. May be given more than once \
+                               [default: every other account of the token, with `--confirm`]"),
+                )
+                .arg(
+                    Arg::with_name("confirm")
+                        .long("confirm")
+                        .takes_value(false)
+                        .conflicts_with("from")
+                        .help("Confirm moving the balances of all other accounts of the token"),
+                )
+                .arg(
+                    owner_keypair_arg()
+                )
+                .arg(multisig_signer_arg())
+                .nonce_args(true),
+        )
+        .subcommand(
+            SubCommand::with_name(CommandName::RecoverNested.into())
+                .about("Recover the tokens of an associated token account that was created \
+                        for one of the owner's associated token accounts instead of the owner")
+                .arg(
+                    Arg::with_name("owner_token")
+                        .validator(is_valid_pubkey)
+                        .value_name("OWNER_TOKEN_ADDRESS")
+                        .takes_value(true)
+                        .index(1)
+                        .required(true)
+                        .help("The token of the owner's associated token account that owns the \
+                               nested account"),
+                )
+                .arg(
+                    Arg::with_name("nested_token")
+                        .validator(is_valid_pubkey)
+                        .value_name("NESTED_TOKEN_ADDRESS")
+                        .takes_value(true)
+                        .index(2)
+                        .required(true)
+                        .help("The token held by the nested account"),
+                )
+                .arg(
+                    owner_keypair_arg()
+                        .help("Keypair of the owner of the associated token account that owns \
+                               the nested account. Defaults to the client keypair."),
+                )
+                .nonce_args(true),
+        )
+        .subcommand(
+            SubCommand::with_name(CommandName::CreateMultisig.into())
+                .about("Create a new account describing an M:N multisignature")
+                .arg(
+                    Arg::with_name("minimum_signers")
+                        .value_name("MINIMUM_SIGNERS")
+                        .validator(is_multisig_minimum_signers)
+                        .takes_value(true)
+                        .index(1)
+                        .required(true)
+                        .help(minimum_signers_help),
+                )
+                .arg(
+                    Arg::with_name("multisig_member")
+                        .value_name("MULTISIG_MEMBER_PUBKEY")
+                        .validator(is_valid_pubkey)
+                        .takes_value(true)
+                        .index(2)
+                        .required_unless("signers_file")
+                        .min_values(MIN_SIGNERS as u64)
+                        .max_values(MAX_SIGNERS as u64)
+                        .help(multisig_member_help),
+                )
+                .arg(
+                    Arg::with_name("signers_file")
+                        .long("signers-file")
+                        .value_name("PATH")
+                        .takes_value(true)
+                        .help("File listing the public keys of the signing members, \
+                               one per line. Lines starting with # are ignored. \
+                               Any MULTISIG_MEMBER_PUBKEY arguments are added to the list"),
+                )
+                .arg(
+                    Arg::with_name("address_keypair")
+                        .long("address-keypair")
+                        .value_name("ADDRESS_KEYPAIR")
+                        .validator(is_valid_signer)
+                        .takes_value(true)
+                        .help(
+                            "Specify the address keypair. \
+                             This may be a keypair file or the ASK keyword. \
+                             [default: randomly generated keypair]"
+                        ),
+                )
+                .nonce_args(true)
+                .offline_args(),
+        )
+        .subcommand(
+            SubCommand::with_name(CommandName::CombineSignatures.into())
+                .about("Combine signatures collected with --sign-only into a transaction \
+                        ready to broadcast")
+                .arg(
+                    Arg::with_name("message")
+                        .value_name("MESSAGE")
+                        .takes_value(true)
+                        .index(1)
+                        .required(true)
+                        .help("The base64 transaction message, as printed by \
+                               --dump-transaction-message"),
+                )
+                .arg(
+                    Arg::with_name("signature")
+                        .value_name("PUBKEY=SIGNATURE")
+                        .validator(is_pubkey_sig)
+                        .takes_value(true)
+                        .index(2)
+                        .multiple(true)
+                        .required(true)
+                        .help("Signatures returned by each signer's --sign-only run"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name(CommandName::Authorize.into())
+                .about("Authorize a new signing keypair to a token or token account")
+                .arg(
+                    Arg::with_name("address")
+                        .validator(is_valid_pubkey)
+                        .value_name("TOKEN_ADDRESS")
+                        .takes_value(true)
+                        .index(1)
+                        .required(true)
+                        .help("The address of the token account"),
+                )
+                .arg(
+                    Arg::with_name("authority_type")
+                        .value_name("AUTHORITY_TYPE")
+                        .takes_value(true)
+                        .possible_values(&[
+                            "mint", "freeze", "owner", "close", "close-account",
+                            "close-mint", "transfer-fee-config", "withheld-withdraw",
+                            "interest-rate", "confidential-transfer-mint",
+                        ])
+                        .index(2)
+                        .required(true)
+                        .help("The new authority type. \
+                            Token mints support `mint` and `freeze` authorities, \
+                            and `close` for mints with the close authority extension; \
+                            `transfer-fee-config` and `withheld-withdraw` for mints with \
+                            transfer fees, `interest-rate` for interest-bearing mints and \
+                            `confidential-transfer-mint` for confidential transfer mints, \
+                            whose new authority must sign as well; \
+                            Token accounts support `owner` and `close` authorities. \
+                            With `--sign-only` the account can't be fetched to tell which \
+                            `close` means, so use `close-mint` or `close-account` instead"),
+                )
+                .arg(
+                    Arg::with_name("new_authority")
+                        .validator(is_valid_pubkey)
+                        .value_name("AUTHORITY_ADDRESS")
+                        .takes_value(true)
+                        .index(3)
+                        .required_unless("disable")
+                        .help("The address of the new authority"),
+                )
+                .arg(
+                    Arg::with_name("authority")
+                        .long("authority")
+                        .alias("owner")
+                        .value_name("KEYPAIR")
+                        .validator(is_valid_signer)
+                        .takes_value(true)
+                        .help(
+                            "Specify the current authority keypair. \
+                             Defaults to the client keypair."
+                        ),
+                )
+                .arg(
+                    Arg::with_name("disable")
+                        .long("disable")
+                        .takes_value(false)
+                        .conflicts_with("new_authority")
+                        .help("Disable mint, freeze, or close functionality by setting authority to None.")
+                )
+                .arg(
+                    Arg::with_name("force")
+                        .long("force")
+                        .hidden(true)
+                        .help("Force re-authorize the wallet's associate token account. Don't use this flag"),
+                )
+                .arg(multisig_signer_arg())
+                .nonce_args(true)
+                .offline_args(),
+        )
+        .subcommand(
+            SubCommand::with_name(CommandName::Transfer.into())
+                .about("Transfer tokens between accounts")
+                .arg(
+                    Arg::with_name("token")
+                        .validator(is_valid_pubkey)
+                        .value_name("TOKEN_ADDRESS")
+                        .takes_value(true)
+                        .index(1)
+                        .required(true)
+                        .help("Token to transfer"),
+                )
+                .arg(
+                    Arg::with_name("amount")
+                        .validator(is_amount_with_suffix_or_all)
+                        .value_name("TOKEN_AMOUNT")
+                        .takes_value(true)
+                        .index(2)
+                        .required(true)
+                        .help("Amount to send, in tokens; accepts keyword ALL, \
+                               a k, m or b suffix for thousands, millions or billions, \
+                               or a percentage of the current balance like 25%, rounded down"),
+                )
+                .arg(
+                    Arg::with_name("recipient")
+                        .validator(is_valid_pubkey)
+                        .value_name("RECIPIENT_ADDRESS or RECIPIENT_TOKEN_ACCOUNT_ADDRESS")
+                        .takes_value(true)
+                        .index(3)
+                        .required(true)
+                        .help("If a token account address is provided, use it as the recipient. \
+                               Otherwise assume the recipient address is a user wallet and transfer to \
+                               the associated token account")
+                )
+                .arg(
+                    Arg::with_name("from")
+                        .validator(is_valid_pubkey)
+                        .value_name("SENDER_TOKEN_ACCOUNT_ADDRESS")
+                        .takes_value(true)
+                        .long("from")
+                        .help("Specify the sending token account \
+                            [default: owner's associated token account]")
+                )
+                .arg(owner_keypair_arg_with_value_name("SENDER_TOKEN_OWNER_KEYPAIR")
+                        .help(
+                            "Specify the owner of the sending token account. \
+                            This may be a keypair file, the ASK keyword. \
+                            Defaults to the client keypair.",
+                        ),
+                )
+                .arg(
+                    Arg::with_name("allow_unfunded_recipient")
+                        .long("allow-unfunded-recipient")
+                        .takes_value(false)
+                        .help("Complete the transfer even if the recipient address is not funded")
+                )
+                .arg(
+                    Arg::with_name("allow_empty_recipient")
+                        .long("allow-empty-recipient")
+                        .takes_value(false)
+                        .hidden(true) // Deprecated, use --allow-unfunded-recipient instead
+                )
+                .arg(
+                    Arg::with_name("fund_recipient")
+                        .long("fund-recipient")
+                        .takes_value(false)
+                        .help("Create the associated token account for the recipient if doesn't already exist")
+                )
+                .arg(
+                    Arg::with_name("allow_non_system_account_recipient")
+                        .long("allow-non-system-account-recipient")
+                        .takes_value(false)
+                        .help("Send tokens to the recipient even if the recipient is not a wallet owned by System Program."),
+                )
+                .arg(
+                    Arg::with_name("recipient_is_ata_owner")
+                        .long("recipient-is-ata-owner")
+                        .takes_value(false)
+                        .requires("sign_only")
+                        .help("In sign-only mode, specifies that the recipient is the owner of the associated token account rather than an actual token account"),
+                )
+                .arg(
+                    Arg::with_name("recipient_is_token_account")
+                        .long("recipient-is-token-account")
+                        .takes_value(false)
+                        .conflicts_with_all(&["recipient_is_owner", "recipient_is_ata_owner"])
+                        .help("Send to the recipient address itself, which must be a token account of this token"),
+                )
+                .arg(
+                    Arg::with_name("recipient_is_owner")
+                        .long("recipient-is-owner")
+                        .takes_value(false)
+                        .help("Send to the associated token account of the recipient address, even if it is a token account itself"),
+                )
+                .arg(
+                    Arg::with_name("expected_fee")
+                        .long("expected-fee")
+                        .validator(is_amount)
+                        .value_name("TOKEN_AMOUNT")
+                        .takes_value(true)
+                        .conflicts_with("use_unchecked_instruction")
+                        .help(
+                            "Abort unless the transfer fee of a fee-bearing token is exactly \
+                             this amount. The token program enforces it as well, so the \
+                             transfer also fails if the fee changes before it lands. \
+                             In sign-only mode the fee isn't computed ahead of time, \
+                             and only the on-chain check applies."
+                        ),
+                )
+                .arg(multisig_signer_arg())
+                .arg(mint_decimals_arg())
+                .arg(show_balances_arg())
+                .nonce_args(true)
+                .offline_args_config(&SignOnlyNeedsMintDecimals{}),
+        )
+        .subcommand(
+            SubCommand::with_name(CommandName::BatchTransfer.into())
+                .about("Transfer tokens to many recipients listed in a CSV file")
+                .arg(
+                    Arg::with_name("token")
+                        .validator(is_valid_pubkey)
+                        .value_name("TOKEN_ADDRESS")
+                        .takes_value(true)
+                        .index(1)
+                        .required(true)
+                        .help("Token to transfer"),
+                )
+                .arg(
+                    Arg::with_name("file")
+                        .value_name("FILE")
+                        .takes_value(true)
+                        .index(2)
+                        .required(true)
+                        .help("CSV file with one `RECIPIENT,AMOUNT[,DECIMALS[,FEE_PAYER]]` transfer \
+                               per line. Recipients are handled as in `transfer`: token account \
+                               addresses are used directly, wallet addresses receive into their \
+                               associated token account. FEE_PAYER is a keypair source like \
+                               `--fee-payer`, paying for that row instead of the default fee payer"),
+                )
+                .arg(
+                    Arg::with_name("from")
+                        .validator(is_valid_pubkey)
+                        .value_name("SENDER_TOKEN_ACCOUNT_ADDRESS")
+                        .takes_value(true)
+                        .long("from")
+                        .help("Specify the sending token account \
+                            [default: owner's associated token account]")
+                )
+                .arg(owner_keypair_arg_with_value_name("SENDER_TOKEN_OWNER_KEYPAIR")
+                        .help(
+                            "Specify the owner of the sending token account. \
+                            This may be a keypair file, the ASK keyword. \
+                            Defaults to the client keypair.",
+                        ),
+                )
+                .arg(
+                    Arg::with_name("allow_unfunded_recipient")
+                        .long("allow-unfunded-recipient")
+                        .takes_value(false)
+                        .help("Complete the transfers even if a recipient address is not funded")
+                )
+                .arg(
+                    Arg::with_name("fund_recipient")
+                        .long("fund-recipient")
+                        .takes_value(false)
+                        .help("Create the associated token accounts for recipients if they don't already exist")
+                )
+                .arg(
+                    Arg::with_name("recipient_is_ata_owner")
+                        .long("recipient-is-ata-owner")
+                        .takes_value(false)
+                        .requires("sign_only")
+                        .help("In sign-only mode, specifies that the recipients are owners of associated token accounts rather than actual token accounts"),
+                )
+                .arg(multisig_signer_arg())
+                .arg(mint_decimals_arg())
+                .nonce_args(true)
+                .offline_args(),
+        )
+        .subcommand(
+            SubCommand::with_name(CommandName::Burn.into())
+                .about("Burn tokens from an account")
+                .arg(
+                    Arg::with_name("source")
+                        .validator(is_valid_pubkey)
+                        .value_name("SOURCE_TOKEN_ACCOUNT_ADDRESS")
+                        .takes_value(true)
+                        .index(1)
+                        .required(true)
+                        .help("The token account address to burn from"),
+                )
+                .arg(
+                    Arg::with_name("amount")
+                        .validator(is_amount)
+                        .value_name("TOKEN_AMOUNT")
+                        .takes_value(true)
+                        .index(2)
+                        .required(true)
+                        .help("Amount to burn, in tokens"),
+                )
+                .arg(owner_keypair_arg_with_value_name("SOURCE_TOKEN_OWNER_KEYPAIR")
+                        .help(
+                            "Specify the source token owner account. \
+                            This may be a keypair file, the ASK keyword. \
+                            Defaults to the client keypair.",
+                        ),
+                )
+                .arg(multisig_signer_arg())
+                .mint_args()
+                .arg(show_balances_arg())
+                .nonce_args(true)
+                .offline_args_config(&SignOnlyNeedsFullMintSpec{}),
+        )
+        .subcommand(
+            SubCommand::with_name(CommandName::Mint.into())
+                .about("Mint new tokens")
+                .arg(
+                    Arg::with_name("token")
+                        .validator(is_valid_pubkey)
+                        .value_name("TOKEN_ADDRESS")
+                        .takes_value(true)
+                        .index(1)
+                        .required(true)
+                        .help("The token to mint"),
+                )
+                .arg(
+                    Arg::with_name("amount")
+                        .validator(is_amount)
+                        .value_name("TOKEN_AMOUNT")
+                        .takes_value(true)
+                        .index(2)
+                        .required(true)
+                        .help("Amount to mint, in tokens"),
+                )
+                .arg(
+                    Arg::with_name("recipient")
+                        .validator(is_valid_pubkey)
+                        .value_name("RECIPIENT_ADDRESS")
+                        .takes_value(true)
+                        .index(3)
+                        .help("The token account address of the recipient, or the owner of its \
+                               associated token account. In sign-only mode, an owner is only \
+                               recognized along with --fund-recipient \
+                               [default: associated token account for the client keypair]"),
+                )
+                .arg(
+                    Arg::with_name("fund_recipient")
+                        .long("fund-recipient")
+                        .takes_value(false)
+                        .help("Create the associated token account of the recipient if it \
+                               doesn't exist yet"),
+                )
+                .arg(
+                    Arg::with_name("mint_authority")
+                        .long("mint-authority")
+                        .alias("owner")
+                        .value_name("KEYPAIR")
+                        .validator(is_valid_signer)
+                        .takes_value(true)
+                        .help(
+                            "Specify the mint authority keypair. \
+                             This may be a keypair file or the ASK keyword. \
+                             For a multisig mint authority, give its address \
+                             along with the --multisig-signer members. \
+                             Defaults to the client keypair."
+                        ),
+                )
+                .arg(mint_decimals_arg())
+                .arg(multisig_signer_arg())
+                .arg(show_balances_arg())
+                .nonce_args(true)
+                .offline_args_config(&SignOnlyNeedsMintDecimals{}),
+        )
+        .subcommand(
+            SubCommand::with_name(CommandName::Freeze.into())
+                .about("Freeze a token account")
+                .arg(
+                    Arg::with_name("account")
+                        .validator(is_valid_pubkey)
+                        .value_name("TOKEN_ACCOUNT_ADDRESS")
+                        .takes_value(true)
+                        .index(1)
+                        .required(true)
+                        .help("The address of the token account to freeze"),
+                )
+                .arg(
+                    Arg::with_name("freeze_authority")
+                        .long("freeze-authority")
+                        .alias("owner")
+                        .value_name("KEYPAIR")
+                        .validator(is_valid_signer)
+                        .takes_value(true)
+                        .help(
+                            "Specify the freeze authority keypair. \
+                             This may be a keypair file or the ASK keyword. \
+                             Defaults to the client keypair."
+                        ),
+                )
+                .arg(mint_address_arg())
+                .arg(multisig_signer_arg())
+                .nonce_args(true)
+                .offline_args_config(&SignOnlyNeedsMintAddress{}),
+        )
+        .subcommand(
+            SubCommand::with_name(CommandName::Thaw.into())
+                .about("Thaw a token account")
+                .arg(
+                    Arg::with_name("account")
+                        .validator(is_valid_pubkey)
+                        .value_name("TOKEN_ACCOUNT_ADDRESS")
+                        .takes_value(true)
+                        .index(1)
+                        .required(true)
+                        .help("The address of the token account to thaw"),
+                )
+                .arg(
+                    Arg::with_name("freeze_authority")
+                        .long("freeze-authority")
+                        .alias("owner")
+                        .value_name("KEYPAIR")
+                        .validator(is_valid_signer)
+                        .takes_value(true)
+                        .help(
+                            "Specify the freeze authority keypair. \
+                             This may be a keypair file or the ASK keyword. \
+                             Defaults to the client keypair."
+                        ),
+                )
+                .arg(mint_address_arg())
+                .arg(multisig_signer_arg())
+                .nonce_args(true)
+                .offline_args_config(&SignOnlyNeedsMintAddress{}),
+        )
+        .subcommand(
+            SubCommand::with_name(CommandName::FreezeAll.into())
+                .about("Freeze every account of a token")
+                .arg(
+                    Arg::with_name("token")
+                        .validator(is_valid_pubkey)
+                        .value_name("TOKEN_MINT_ADDRESS")
+                        .takes_value(true)
+                        .index(1)
+                        .required(true)
+                        .help("The token whose accounts to freeze"),
+                )
+                .arg(
+                    Arg::with_name("freeze_authority")
+                        .long("freeze-authority")
+                        .value_name("KEYPAIR")
+                        .validator(is_valid_signer)
+                        .takes_value(true)
+                        .help(
+                            "Specify the freeze authority keypair. \
+                             This may be a keypair file or the ASK keyword. \
+                             Defaults to the client keypair."
+                        ),
+                )
+                .arg(
+                    Arg::with_name("confirm")
+                        .long("confirm")
+                        .takes_value(false)
+                        .help("Confirm changing every account of the token. \
+                               Finding them requires a `getProgramAccounts` query, \
+                               which can be slow or disabled on public RPC nodes."),
+                )
+                .arg(multisig_signer_arg())
+                .nonce_args(true)
+        )
+        .subcommand(
+            SubCommand::with_name(CommandName::ThawAll.into())
+                .about("Thaw every account of a token")
+                .arg(
+                    Arg::with_name("token")
+                        .validator(is_valid_pubkey)
+                        .value_name("TOKEN_MINT_ADDRESS")
+                        .takes_value(true)
+                        .index(1)
+                        .required(true)
+                        .help("The token whose accounts to thaw"),
+                )
+                .arg(
+                    Arg::with_name("freeze_authority")
+                        .long("freeze-authority")
+                        .value_name("KEYPAIR")
+                        .validator(is_valid_signer)
+                        .takes_value(true)
+                        .help(
+                            "Specify the freeze authority keypair. \
+                             This may be a keypair file or the ASK keyword. \
+                             Defaults to the client keypair."
+                        ),
+                )
+                .arg(
+                    Arg::with_name("confirm")
+                        .long("confirm")
+                        .takes_value(false)
+                        .help("Confirm changing every account of the token. \
+                               Finding them requires a `getProgramAccounts` query, \
+                               which can be slow or disabled on public RPC nodes."),
+                )
+                .arg(multisig_signer_arg())
+                .nonce_args(true)
+        )
+        .subcommand(
+            SubCommand::with_name(CommandName::SetTransferFee.into())
+                .about("Set the transfer fee for a token with the transfer fee extension")
+                .arg(
+                    Arg::with_name("token")
+                        .validator(is_valid_pubkey)
+                        .value_name("TOKEN_ADDRESS")
+                        .takes_value(true)
+                        .index(1)
+                        .required(true)
+                        .help("The token to update the transfer fee of"),
+                )
+                .arg(
+                    Arg::with_name("transfer_fee_basis_points")
+                        .validator(is_transfer_fee_basis_points)
+                        .value_name("FEE_IN_BASIS_POINTS")
+                        .takes_value(true)
+                        .index(2)
+                        .required(true)
+                        .help("The new transfer fee in basis points, at most 10000"),
+                )
+                .arg(
+                    Arg::with_name("maximum_fee")
+                        .validator(is_amount)
+                        .value_name("TOKEN_AMOUNT")
+                        .takes_value(true)
+                        .index(3)
+                        .required(true)
+                        .help("The new maximum fee for a single transfer, in tokens"),
+                )
+                .arg(
+                    Arg::with_name("transfer_fee_authority")
+                        .long("transfer-fee-authority")
+                        .value_name("KEYPAIR")
+                        .validator(is_valid_signer)
+                        .takes_value(true)
+                        .help(
+                            "Specify the transfer fee config authority keypair. \
+                             This may be a keypair file or the ASK keyword. \
+                             Defaults to the client keypair."
+                        ),
+                )
+                .arg(mint_decimals_arg())
+                .arg(multisig_signer_arg())
+                .nonce_args(true)
+                .offline_args_config(&SignOnlyNeedsMintDecimals{}),
+        )
+        .subcommand(
+            SubCommand::with_name(CommandName::HarvestWithheldTokensToMint.into())
+                .about("Collect the transfer fees withheld in token accounts into their mint")
+                .arg(
+                    Arg::with_name("token")
+                        .validator(is_valid_pubkey)
+                        .value_name("TOKEN_ADDRESS")
+                        .takes_value(true)
+                        .index(1)
+                        .required(true)
+                        .help("The token with transfer fees"),
+                )
+                .arg(
+                    Arg::with_name("source")
+                        .validator(is_valid_pubkey)
+                        .value_name("ACCOUNT_ADDRESS")
+                        .takes_value(true)
+                        .multiple(true)
+                        .index(2)
+                        .required_unless("all")
+                        .help("The token accounts to harvest the withheld fees of"),
+                )
+                .arg(
+                    Arg::with_name("all")
+                        .long("all")
+                        .takes_value(false)
+                        .conflicts_with("source")
+                        .help(
+                            "Harvest every account of the token holding withheld fees. \
+                             Finding them queries the whole token program, \
+                             which public RPC nodes may reject."
+                        ),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name(CommandName::WithdrawWithheldTokens.into())
+                .about("Withdraw the transfer fees harvested into a mint")
+                .arg(
+                    Arg::with_name("token")
+                        .validator(is_valid_pubkey)
+                        .value_name("TOKEN_ADDRESS")
+                        .takes_value(true)
+                        .index(1)
+                        .required(true)
+                        .help("The token with transfer fees"),
+                )
+                .arg(
+                    Arg::with_name("destination")
+                        .validator(is_valid_pubkey)
+                        .value_name("DESTINATION_ACCOUNT_ADDRESS")
+                        .takes_value(true)
+                        .index(2)
+                        .required(true)
+                        .help("The token account to receive the withheld fees"),
+                )
+                .arg(
+                    Arg::with_name("withdraw_withheld_authority")
+                        .long("withdraw-withheld-authority")
+                        .value_name("KEYPAIR")
+                        .validator(is_valid_signer)
+                        .takes_value(true)
+                        .help(
+                            "Specify the token's withdraw withheld authority. \
+                             This may be a keypair file or the ASK keyword. \
+                             Defaults to the client keypair."
+                        ),
+                )
+                .arg(multisig_signer_arg())
+                .nonce_args(true)
+                .offline_args(),
+        )
+        .subcommand(
+            SubCommand::with_name(CommandName::UpdateDefaultAccountState.into())
+                .about("Update the state new accounts of a token start in, \
+                        for a token with the default account state extension")
+                .arg(
+                    Arg::with_name("token")
+                        .validator(is_valid_pubkey)
+                        .value_name("TOKEN_ADDRESS")
+                        .takes_value(true)
+                        .index(1)
+                        .required(true)
+                        .help("The token to update"),
+                )
+                .arg(
+                    Arg::with_name("state")
+                        .value_name("STATE")
+                        .takes_value(true)
+                        .possible_values(&["initialized", "frozen"])
+                        .index(2)
+                        .required(true)
+                        .help("The new default account state"),
+                )
+                .arg(
+                    Arg::with_name("freeze_authority")
+                        .long("freeze-authority")
+                        .value_name("KEYPAIR")
+                        .validator(is_valid_signer)
+                        .takes_value(true)
+                        .help(
+                            "Specify the token's freeze authority. \
+                             This may be a keypair file or the ASK keyword. \
+                             Defaults to the client keypair."
+                        ),
+                )
+                .arg(multisig_signer_arg())
+                .nonce_args(true)
+                .offline_args(),
+        )
+        .subcommand(
+            SubCommand::with_name(CommandName::UpdateInterestRate.into())
+                .about("Update the interest rate of an interest-bearing token")
+                .arg(
+                    Arg::with_name("token")
+                        .validator(is_valid_pubkey)
+                        .value_name("TOKEN_ADDRESS")
+                        .takes_value(true)
+                        .index(1)
+                        .required(true)
+                        .help("The interest-bearing token to update"),
+                )
+                .arg(
+                    Arg::with_name("rate")
+                        .value_name("RATE_BPS")
+                        .takes_value(true)
+                        .allow_hyphen_values(true)
+                        .validator(is_parsable::<i16>)
+                        .index(2)
+                        .required(true)
+                        .help("The new annual interest rate in basis points, may be negative"),
+                )
+                .arg(
+                    Arg::with_name("rate_authority")
+                        .long("rate-authority")
+                        .value_name("KEYPAIR")
+                        .validator(is_valid_signer)
+                        .takes_value(true)
+                        .help(
+                            "Specify the token's rate authority. \
+                             This may be a keypair file or the ASK keyword. \
+                             Defaults to the client keypair."
+                        ),
+                )
+                .arg(multisig_signer_arg())
+                .nonce_args(true)
+                .offline_args(),
+        )
+        .subcommand(
+            SubCommand::with_name(CommandName::ConfigureConfidentialTransferAccount.into())
+                .about("Configure a token account for confidential transfers")
+                .arg(
+                    Arg::with_name("account")
+                        .validator(is_valid_pubkey)
+                        .value_name("TOKEN_ACCOUNT_ADDRESS")
+                        .takes_value(true)
+                        .index(1)
+                        .required(true)
+                        .help("The address of the token account to configure"),
+                )
+                .arg(owner_keypair_arg())
+                .arg(
+                    Arg::with_name("elgamal_keypair")
+                        .long("elgamal-keypair")
+                        .value_name("PATH")
+                        .takes_value(true)
+                        .help(
+                            "ElGamal keypair file used to encrypt the confidential balance. \
+                             If the file does not exist, a new keypair is generated and written \
+                             to it, so the same keypair can be reused across accounts. \
+                             [default: derived from the owner keypair and the account address, \
+                             nothing is written to disk. A hardware wallet owner is asked to \
+                             approve the derivation on the device]"
+                        ),
+                )
+                .arg(
+                    Arg::with_name("maximum_pending_balance_credit_counter")
+                        .long("maximum-pending-balance-credit-counter")
+                        .value_name("COUNT")
+                        .validator(is_parsable::<u64>)
+                        .takes_value(true)
+                        .default_value("65536")
+                        .help(
+                            "The maximum number of incoming deposits and transfers before the \
+                             pending balance must be applied. Each credit adds up to 16 bits to \
+                             the low half of the pending balance, so with the default the sum \
+                             still fits the range that can be decrypted"
+                        ),
+                )
+                .arg(mint_address_arg())
+                .arg(multisig_signer_arg())
+                .nonce_args(true)
+                .offline_args_config(&SignOnlyNeedsMintAddress{}),
+        )
+        .subcommand(
+            SubCommand::with_name(CommandName::DepositConfidential.into())
+                .about("Deposit tokens from the public balance of a token account into its \
+                        pending confidential balance")
+                .arg(
+                    Arg::with_name("account")
+                        .validator(is_valid_pubkey)
+                        .value_name("TOKEN_ACCOUNT_ADDRESS")
+                        .takes_value(true)
+                        .index(1)
+                        .required(true)
+                        .help("The address of the token account configured for confidential transfers"),
+                )
+                .arg(
+                    Arg::with_name("amount")
+                        .validator(is_amount)
+                        .value_name("TOKEN_AMOUNT")
+                        .takes_value(true)
+                        .index(2)
+                        .required(true)
+                        .help("Amount to deposit, in tokens"),
+                )
+                .arg(owner_keypair_arg())
+                .arg(mint_address_arg())
+                .arg(mint_decimals_arg())
+                .arg(multisig_signer_arg())
+                .nonce_args(true)
+                .offline_args_config(&SignOnlyNeedsFullMintSpec{}),
+        )
+        .subcommand(
+            SubCommand::with_name(CommandName::ApplyPendingBalance.into())
+                .about("Move the pending confidential balance of a token account into its \
+                        available confidential balance")
+                .arg(
+                    Arg::with_name("account")
+                        .validator(is_valid_pubkey)
+                        .value_name("TOKEN_ACCOUNT_ADDRESS")
+                        .takes_value(true)
+                        .index(1)
+                        .required(true)
+                        .help("The address of the token account configured for confidential transfers"),
+                )
+                .arg(owner_keypair_arg())
+                .arg(
+                    Arg::with_name("elgamal_keypair")
+                        .long("elgamal-keypair")
+                        .value_name("PATH")
+                        .takes_value(true)
+                        .help(
+                            "ElGamal keypair file the account was configured with. \
+                             [default: derived from the owner keypair and the account address]"
+                        ),
+                )
+                .arg(multisig_signer_arg())
+                .nonce_args(true),
+        )
+        .subcommand(
+            SubCommand::with_name(CommandName::Wrap.into())
+                .about("Wrap native SOL in a SOL token account, topping up an existing associated account")
+                .arg(
+                    Arg::with_name("amount")
+                        .validator(is_amount)
+                        .value_name("AMOUNT")
+                        .takes_value(true)
+                        .index(1)
+                        .required(true)
+                        .help("Amount of SOL to wrap"),
+                )
+                .arg(
+                    Arg::with_name("wallet_keypair")
+                        .alias("owner")
+                        .value_name("KEYPAIR")
+                        .validator(is_valid_signer)
+                        .takes_value(true)
+                        .help(
+                            "Specify the keypair for the wallet which will have its native SOL wrapped. \
+                             This wallet will be assigned as the owner of the wrapped SOL token account. \
+                             This may be a keypair file or the ASK keyword. \
+                             Defaults to the client keypair."
+                        ),
+                )
+                .arg(
+                    Arg::with_name("create_aux_account")
+                        .takes_value(false)
+                        .long("create-aux-account")
+                        .help("Wrap SOL in an auxiliary account instead of associated token account"),
+                )
+                .arg(
+                    Arg::with_name("recipient")
+                        .long("recipient")
+                        .validator(is_valid_pubkey)
+                        .value_name("RECIPIENT_WALLET_ADDRESS")
+                        .takes_value(true)
+                        .help("Owner of the wrapped SOL account. \
+                               An existing associated wrapped SOL account is topped up. \
+                               Defaults to the wallet"),
+                )
+                .nonce_args(true)
+                .offline_args(),
+        )
+        .subcommand(
+            SubCommand::with_name(CommandName::Unwrap.into())
+                .about("Unwrap a SOL token account")
+                .arg(
+                    Arg::with_name("address")
+                        .validator(is_valid_pubkey)
+                        .value_name("TOKEN_ACCOUNT_ADDRESS")
+                        .takes_value(true)
+                        .index(1)
+                        .help("The address of the auxiliary token account to unwrap \
+                            [default: associated token account for --owner]"),
+                )
+                .arg(
+                    Arg::with_name("wallet_keypair")
+                        .alias("owner")
+                        .value_name("KEYPAIR")
+                        .validator(is_valid_signer)
+                        .takes_value(true)
+                        .help(
+                            "Specify the keypair for the wallet which owns the wrapped SOL. \
+                             This wallet will receive the unwrapped SOL unless `--recipient` \
+                             is given. \
+                             This may be a keypair file or the ASK keyword. \
+                             Defaults to the client keypair."
+                        ),
+                )
+                .arg(
+                    Arg::with_name("recipient")
+                        .long("recipient")
+                        .validator(is_valid_pubkey)
+                        .value_name("RECIPIENT_ADDRESS")
+                        .takes_value(true)
+                        .help("The address to send the unwrapped SOL to \
+                            [default: the wallet owning the wrapped SOL]"),
+                )
+                .arg(
+                    Arg::with_name("all")
+                        .long("all")
+                        .takes_value(false)
+                        .conflicts_with("address")
+                        .help("Unwrap every wrapped SOL account owned by the wallet"),
+                )
+                .arg(multisig_signer_arg())
+                .nonce_args(true)
+                .offline_args(),
+        )
+        .subcommand(
+            SubCommand::with_name(CommandName::Approve.into())
+                .about("Approve a delegate for a token account")
+                .arg(
+                    Arg::with_name("account")
+                        .validator(is_valid_pubkey)
+                        .value_name("TOKEN_ACCOUNT_ADDRESS")
+                        .takes_value(true)
+                        .index(1)
+                        .required(true)
+                        .help("The address of the token account to delegate"),
+                )
+                .arg(
+                    Arg::with_name("amount")
+                        .validator(is_amount)
+                        .value_name("TOKEN_AMOUNT")
+                        .takes_value(true)
+                        .index(2)
+                        .required(true)
+                        .help("Amount to approve, in tokens"),
+                )
+                .arg(
+                    Arg::with_name("delegate")
+                        .validator(is_valid_pubkey)
+                        .value_name("DELEGATE_TOKEN_ACCOUNT_ADDRESS")
+                        .takes_value(true)
+                        .index(3)
+                        .required(true)
+                        .help("The token account address of delegate"),
+                )
+                .arg(
+                    owner_keypair_arg()
+                )
+                .arg(multisig_signer_arg())
+                .mint_args()
+                .nonce_args(true)
+                .offline_args_config(&SignOnlyNeedsFullMintSpec{}),
+        )
+        .subcommand(
+            SubCommand::with_name(CommandName::ApproveMany.into())
+                .about("Approve a delegate for the same amount on several token accounts")
+                .arg(
+                    Arg::with_name("delegate")
+                        .validator(is_valid_pubkey)
+                        .value_name("DELEGATE_ADDRESS")
+                        .takes_value(true)
+                        .index(1)
+                        .required(true)
+                        .help("The delegate to approve"),
+                )
+                .arg(
+                    Arg::with_name("amount")
+                        .validator(is_parsable::<u64>)
+                        .value_name("AMOUNT")
+                        .takes_value(true)
+                        .index(2)
+                        .required(true)
+                        .help("Amount to approve on each account, in base units"),
+                )
+                .arg(
+                    Arg::with_name("accounts")
+                        .validator(is_valid_pubkey)
+                        .value_name("TOKEN_ACCOUNT_ADDRESS")
+                        .takes_value(true)
+                        .multiple(true)
+                        .index(3)
+                        .required_unless("token")
+                        .help("The token accounts to approve the delegate on"),
+                )
+                .arg(
+                    Arg::with_name("token")
+                        .validator(is_valid_pubkey)
+                        .value_name("TOKEN_MINT_ADDRESS")
+                        .takes_value(true)
+                        .long("all")
+                        .conflicts_with("accounts")
+                        .help("Approve the delegate on every account of the owner for this token"),
+                )
+                .arg(owner_keypair_arg())
+                .arg(multisig_signer_arg())
+        )
+        .subcommand(
+            SubCommand::with_name(CommandName::Revoke.into())
+                .about("Revoke a delegate's authority")
+                .arg(
+                    Arg::with_name("account")
+                        .validator(is_valid_pubkey)
+                        .value_name("TOKEN_ACCOUNT_ADDRESS")
+                        .takes_value(true)
+                        .index(1)
+                        .required(true)
+                        .help("The address of the token account"),
+                )
+                .arg(owner_keypair_arg()
+                )
+                .arg(delegate_address_arg())
+                .arg(multisig_signer_arg())
+                .nonce_args(true)
+                .offline_args_config(&SignOnlyNeedsDelegateAddress{}),
+        )
+        .subcommand(
+            SubCommand::with_name(CommandName::RevokeAll.into())
+                .about("Revoke the delegates of all token accounts of an owner")
+                .arg(
+                    Arg::with_name("token")
+                        .validator(is_valid_pubkey)
+                        .value_name("TOKEN_MINT_ADDRESS")
+                        .takes_value(true)
+                        .index(1)
+                        .help("Only revoke the delegates of accounts for this token"),
+                )
+                .arg(
+                    Arg::with_name("confirm")
+                        .long("confirm")
+                        .takes_value(false)
+                        .help("Confirm revoking the delegates of all matching accounts"),
+                )
+                .arg(owner_keypair_arg())
+                .arg(multisig_signer_arg())
+        )
+        .subcommand(
+            SubCommand::with_name(CommandName::EnableRequiredTransferMemos.into())
+                .about("Require a memo on every transfer into a token account")
+                .arg(
+                    Arg::with_name("account")
+                        .validator(is_valid_pubkey)
+                        .value_name("TOKEN_ACCOUNT_ADDRESS")
+                        .takes_value(true)
+                        .index(1)
+                        .required(true)
+                        .help("The address of the Token-2022 account"),
+                )
+                .arg(owner_keypair_arg())
+                .arg(multisig_signer_arg())
+                .nonce_args(true)
+                .offline_args(),
+        )
+        .subcommand(
+            SubCommand::with_name(CommandName::DisableRequiredTransferMemos.into())
+                .about("Stop requiring memos on transfers into a token account")
+                .arg(
+                    Arg::with_name("account")
+                        .validator(is_valid_pubkey)
+                        .value_name("TOKEN_ACCOUNT_ADDRESS")
+                        .takes_value(true)
+                        .index(1)
+                        .required(true)
+                        .help("The address of the Token-2022 account"),
+                )
+                .arg(owner_keypair_arg())
+                .arg(multisig_signer_arg())
+                .nonce_args(true)
+                .offline_args(),
+        )
+        .subcommand(
+            SubCommand::with_name(CommandName::Close.into())
+                .about("Close a token account")
+                .arg(
+                    Arg::with_name("token")
+                        .validator(is_valid_pubkey)
+                        .value_name("TOKEN_ADDRESS")
+                        .takes_value(true)
+                        .index(1)
+                        .required_unless("address")
+                        .help("Token to close. To close a specific account, use the `--address` parameter instead"),
+                )
+                .arg(owner_address_arg())
+                .arg(
+                    Arg::with_name("recipient")
+                        .long("recipient")
+                        .validator(is_valid_pubkey)
+                        .value_name("REFUND_ACCOUNT_ADDRESS")
+                        .takes_value(true)
+                        .help("The address of the account to receive remaining SOL [default: --owner]"),
+                )
+                .arg(
+                    Arg::with_name("close_authority")
+                        .long("close-authority")
+                        .alias("owner")
+                        .value_name("KEYPAIR")
+                        .validator(is_valid_signer)
+                        .takes_value(true)
+                        .help(
+                            "Specify the token's close authority if it has one, \
+                            otherwise specify the token's owner keypair. \
+                            This may be a keypair file, the ASK keyword. \
+                            Defaults to the client keypair.",
+                        ),
+                )
+                .arg(
+                    Arg::with_name("address")
+                        .long("address")
+                        .validator(is_valid_pubkey)
+                        .value_name("TOKEN_ACCOUNT_ADDRESS")
+                        .takes_value(true)
+                        .conflicts_with("token")
+                        .help("Specify the token account to close \
+                            [default: owner's associated token account]"),
+                )
+                .arg(multisig_signer_arg())
+                .nonce_args(true)
+                .offline_args(),
+        )
+        .subcommand(
+            SubCommand::with_name(CommandName::CloseMint.into())
+                .about("Close a token mint with no supply")
+                .arg(
+                    Arg::with_name("token")
+                        .validator(is_valid_pubkey)
+                        .value_name("TOKEN_MINT_ADDRESS")
+                        .takes_value(true)
+                        .index(1)
+                        .required(true)
+                        .help("Token to close"),
+                )
+                .arg(
+                    Arg::with_name("recipient")
+                        .long("recipient")
+                        .validator(is_valid_pubkey)
+                        .value_name("REFUND_ACCOUNT_ADDRESS")
+                        .takes_value(true)
+                        .help("The address of the account to receive remaining SOL \
+                               [default: --close-authority]"),
+                )
+                .arg(
+                    Arg::with_name("close_authority")
+                        .long("close-authority")
+                        .value_name("KEYPAIR")
+                        .validator(is_valid_signer)
+                        .takes_value(true)
+                        .help(
+                            "Specify the token's close authority. \
+                            This may be a keypair file or the ASK keyword. \
+                            Defaults to the client keypair.",
+                        ),
+                )
+                .arg(multisig_signer_arg())
+                .nonce_args(true)
                 .offline_args(),
         )
-        .subcommand(
-            SubCommand::with_name(CommandName::Authorize.into())
-                .about("Authorize a new signing keypair to a token or token account")
-                .arg(
-                    Arg::with_name("address")
-                        .validator(is_valid_pubkey)
-                        .value_name("TOKEN_ADDRESS")
-                        .takes_value(true)
-                        .index(1)
-                        .required(true)
-                        .help("The address of the token account"),
-                )
-                .arg(
-                    Arg::with_name("authority_type")
-                        .value_name("AUTHORITY_TYPE")
-                        .takes_value(true)
-                        .possible_values(&[
-                            "mint", "freeze", "owner", "close",
-                            "close-mint", "transfer-fee-config", "withheld-withdraw",
-                            "interest-rate",
-                        ])
-                        .index(2)
-                        .required(true)
-                        .help("The new authority type. \
-                            Token mints support `mint` and `freeze` authorities;\
-                            Token accounts support `owner` and `close` authorities."),
-                )
-                .arg(
-                    Arg::with_name("new_authority")
-                        .validator(is_valid_pubkey)
-                        .value_name("AUTHORITY_ADDRESS")
-                        .takes_value(true)
-                        .index(3)
-                        .required_unless("disable")
-                        .help("The address of the new authority"),
-                )
-                .arg(
-                    Arg::with_name("authority")
-                        .long("authority")
-                        .alias("owner")
-                        .value_name("KEYPAIR")
-                        .validator(is_valid_signer)
-                        .takes_value(true)
-                        .help(
-                            "Specify the current authority keypair. \
-                             Defaults to the client keypair."
-                        ),
-                )
-                .arg(
-                    Arg::with_name("disable")
-                        .long("disable")
-                        .takes_value(false)
-                        .conflicts_with("new_authority")
-                        .help("Disable mint, freeze, or close functionality by setting authority to None.")
-                )
-                .arg(
-                    Arg::with_name("force")
-                        .long("force")
-                        .hidden(true)
-                        .help("Force re-authorize the wallet's associate token account. Don't use this flag"),
-                )
-                .arg(multisig_signer_arg())
-                .nonce_args(true)
-                .offline_args(),
+        .subcommand(
+            SubCommand::with_name(CommandName::Balance.into())
+                .about("Get token account balance")
+                .arg(
+                    Arg::with_name("token")
+                        .validator(is_valid_pubkey)
+                        .value_name("TOKEN_ADDRESS")
+                        .takes_value(true)
+                        .index(1)
+                        .required_unless("address")
+                        .help("Token of associated account. To query a specific account, use the `--address` parameter instead"),
+                )
+                .arg(owner_address_arg().conflicts_with("address"))
+                .arg(
+                    Arg::with_name("address")
+                        .validator(is_valid_pubkey)
+                        .value_name("TOKEN_ACCOUNT_ADDRESS")
+                        .takes_value(true)
+                        .long("address")
+                        .conflicts_with("token")
+                        .help("Specify the token account to query \
+                            [default: owner's associated token account]"),
+                )
+                .arg(
+                    Arg::with_name("all_for_owner")
+                        .long("all-for-owner")
+                        .takes_value(false)
+                        .requires("token")
+                        .help("Sum the balances of all of the owner's accounts for the token, \
+                               listing each account's balance as well"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name(CommandName::Supply.into())
+                .about("Get token supply")
+                .arg(
+                    Arg::with_name("address")
+                        .validator(is_valid_pubkey)
+                        .value_name("TOKEN_ADDRESS")
+                        .takes_value(true)
+                        .index(1)
+                        .required(true)
+                        .help("The token address"),
+                )
+                .arg(
+                    Arg::with_name("show_withheld")
+                        .long("show-withheld")
+                        .takes_value(false)
+                        .help(
+                            "Also report the transfer fees withheld in the mint and in its \
+                             token accounts. Finding the accounts queries the whole token \
+                             program, which public RPC nodes may reject."
+                        ),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name(CommandName::Accounts.into())
+                .about("List all token accounts by owner")
+                .arg(
+                    Arg::with_name("token")
+                        .validator(is_valid_pubkey)
+                        .value_name("TOKEN_ADDRESS")
+                        .takes_value(true)
+                        .index(1)
+                        .help("Limit results to the given token. [Default: list accounts for all tokens]"),
+                )
+                .arg(
+                    Arg::with_name("mint")
+                        .validator(is_valid_pubkey)
+                        .value_name("MINT_ADDRESS")
+                        .takes_value(true)
+                        .long("mint")
+                        .conflicts_with("token")
+                        .help("Limit results to the given mint, whether it belongs to \
+                               Token or Token-2022. Same as the TOKEN_ADDRESS argument"),
+                )
+                .arg(
+                    owner_address_arg().help(
+                        "Address of the token's owner. Defaults to the client keypair address. \
+                         May also be a file listing one owner per line, to list the accounts \
+                         of each of them.",
+                    ),
+                )
+        )
+        .subcommand(
+            SubCommand::with_name(CommandName::Address.into())
+                .about("Get wallet address, or the associated token address of a token")
+                .arg(
+                    Arg::with_name("token")
+                        .validator(is_valid_pubkey)
+                        .value_name("TOKEN_ADDRESS")
+                        .takes_value(true)
+                        .index(1)
+                        .help("Return the associated token address for the given token. \
+                               [Default: return the client keypair address]")
+                )
+                .arg(
+                    Arg::with_name("token_option")
+                        .validator(is_valid_pubkey)
+                        .value_name("TOKEN_ADDRESS")
+                        .takes_value(true)
+                        .long("token")
+                        .conflicts_with("token")
+                        .hidden(true)
+                        .help("Same as the TOKEN_ADDRESS argument")
+                )
+                .arg(
+                    owner_address_arg()
+                        .help("Return the associated token address for the given owner. \
+                               [Default: return the associated token address for the client keypair]"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name(CommandName::AccountInfo.into())
+                .about("Query details of an SPL Token account by address")
+                .arg(
+                    Arg::with_name("token")
+                        .validator(is_valid_pubkey)
+                        .value_name("TOKEN_ADDRESS")
+                        .takes_value(true)
+                        .index(1)
+                        .conflicts_with("address")
+                        .required_unless("address")
+                        .help("Token of associated account. \
+                               To query a specific account, use the `--address` parameter instead"),
+                )
+                .arg(
+                    owner_address_arg()
+                        .index(2)
+                        .conflicts_with("address")
+                        .help("Owner of the associated account for the specified token. \
+                               To query a specific account, use the `--address` parameter instead. \
+                               Defaults to the client keypair."),
+                )
+                .arg(
+                    Arg::with_name("address")
+                        .validator(is_valid_pubkey)
+                        .value_name("TOKEN_ACCOUNT_ADDRESS")
+                        .takes_value(true)
+                        .long("address")
+                        .conflicts_with("token")
+                        .help("Specify the token account to query"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name(CommandName::MultisigInfo.into())
+                .about("Query details about and SPL Token multisig account by address")
+                .arg(
+                    Arg::with_name("address")
+                    .validator(is_valid_pubkey)
+                    .value_name("MULTISIG_ACCOUNT_ADDRESS")
+                    .takes_value(true)
+                    .index(1)
+                    .required(true)
+                    .help("The address of the SPL Token multisig account to query"),
+                )
+                .arg(
+                    Arg::with_name("expect_threshold")
+                        .long("expect-threshold")
+                        .validator(is_parsable::<u8>)
+                        .value_name("MINIMUM_SIGNERS")
+                        .takes_value(true)
+                        .help("Fail unless the multisig requires exactly this many signers"),
+                )
+                .arg(
+                    Arg::with_name("expect_signers")
+                        .long("expect-signers")
+                        .validator(is_valid_pubkey)
+                        .value_name("SIGNER_ADDRESS")
+                        .takes_value(true)
+                        .multiple(true)
+                        .max_values(MAX_SIGNERS as u64)
+                        .help(
+                            "Fail unless these, in any order, are exactly the signers of the \
+                             multisig"
+                        ),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name(CommandName::Display.into())
+                .about("Query details of an SPL Token mint or token account, including all \
+                        of its extensions")
+                .arg(
+                    Arg::with_name("address")
+                    .validator(is_valid_pubkey)
+                    .value_name("ADDRESS")
+                    .takes_value(true)
+                    .index(1)
+                    .required(true)
+                    .help("The address of the SPL Token mint or token account to query"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name(CommandName::AmountToUiAmount.into())
+                .about("Convert a raw amount of base units into a UI amount, \
+                        including any interest accrued by the mint")
+                .arg(
+                    Arg::with_name("token")
+                    .validator(is_valid_pubkey)
+                    .value_name("TOKEN_MINT_ADDRESS")
+                    .takes_value(true)
+                    .index(1)
+                    .required(true)
+                    .help("The token to convert the amount for"),
+                )
+                .arg(
+                    Arg::with_name("amount")
+                    .validator(is_parsable::<u64>)
+                    .value_name("AMOUNT")
+                    .takes_value(true)
+                    .index(2)
+                    .required(true)
+                    .help("Amount in base units"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name(CommandName::UiAmountToAmount.into())
+                .about("Convert a UI amount into a raw amount of base units, \
+                        including any interest accrued by the mint")
+                .arg(
+                    Arg::with_name("token")
+                    .validator(is_valid_pubkey)
+                    .value_name("TOKEN_MINT_ADDRESS")
+                    .takes_value(true)
+                    .index(1)
+                    .required(true)
+                    .help("The token to convert the amount for"),
+                )
+                .arg(
+                    Arg::with_name("ui_amount")
+                    .validator(is_amount)
+                    .value_name("UI_AMOUNT")
+                    .takes_value(true)
+                    .index(2)
+                    .required(true)
+                    .help("Amount in decimal tokens"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name(CommandName::Gc.into())
+                .about("Cleanup unnecessary token accounts")
+                .arg(owner_keypair_arg())
+                .arg(
+                    Arg::with_name("close_empty_associated_accounts")
+                    .long("close-empty-associated-accounts")
+                    .takes_value(false)
+                    .help("close all empty associated token accounts (to get SOL back)")
+                )
+        )
+        .subcommand(
+            SubCommand::with_name(CommandName::CloseEmptyAccounts.into())
+                .about("Close all empty token accounts of an owner to reclaim their rent")
+                .arg(
+                    owner_keypair_arg().help(
+                        "Keypair of the token's owner. Defaults to the client keypair. \
+                         May also be a file listing one owner keypair per line, \
+                         to sweep the accounts of each of them.",
+                    ),
+                )
+                .arg(multisig_signer_arg())
+        )
+        .subcommand(
+            SubCommand::with_name(CommandName::ExportAccounts.into())
+                .about("Write the accounts of some tokens to a snapshot file, hashed and signed \
+                        by the fee payer")
+                .arg(
+                    Arg::with_name("mints")
+                        .validator(is_valid_pubkey)
+                        .value_name("TOKEN_MINT_ADDRESS")
+                        .takes_value(true)
+                        .long("mints")
+                        .multiple(true)
+                        .required(true)
+                        .help("The tokens to export the accounts of. \
+                               Finding them queries the whole token program, \
+                               which public RPC nodes may reject."),
+                )
+                .arg(
+                    Arg::with_name("out")
+                        .value_name("FILE")
+                        .takes_value(true)
+                        .long("out")
+                        .required(true)
+                        .help("File to write the snapshot to, as JSON"),
+                )
+        )
+        .subcommand(
+            SubCommand::with_name(CommandName::FindMints.into())
+                .about("Find the mints of both token programs with the given mint or \
+                        freeze authority")
+                .arg(
+                    Arg::with_name("authority")
+                        .validator(is_valid_pubkey)
+                        .value_name("AUTHORITY_ADDRESS")
+                        .takes_value(true)
+                        .long("authority")
+                        .required(true)
+                        .help("The authority to look for. \
+                               Finding its mints queries the whole of both token programs, \
+                               which public RPC nodes may reject."),
+                )
+                .arg(
+                    Arg::with_name("role")
+                        .value_name("ROLE")
+                        .takes_value(true)
+                        .long("role")
+                        .possible_values(&["mint", "freeze"])
+                        .help("Only find mints where it is this authority \
+                               [default: either]"),
+                )
+        )
+        .subcommand(
+            SubCommand::with_name(CommandName::DecodeTransaction.into())
+                .about("Show the instructions and signatures of a serialized transaction")
+                .arg(
+                    Arg::with_name("transaction")
+                        .value_name("BASE64_OR_FILE")
+                        .takes_value(true)
+                        .index(1)
+                        .required(true)
+                        .help("The base64 encoded transaction or message, \
+                               or a file containing it"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name(CommandName::Confirm.into())
+                .about("Check that a transaction succeeded and reached the commitment level \
+                        given by --commitment")
+                .arg(
+                    Arg::with_name("signature")
+                        .validator(is_parsable::<Signature>)
+                        .value_name("SIGNATURE")
+                        .takes_value(true)
+                        .index(1)
+                        .required(true)
+                        .help("The signature of the transaction"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name(CommandName::SyncNative.into())
+                .about("Sync a native SOL token account to its underlying lamports")
+                .arg(
+                    owner_address_arg()
+                        .index(1)
+                        .value_name("ACCOUNT_OR_OWNER_ADDRESS")
+                        .conflicts_with("address")
+                        .help("The wrapped SOL token account to sync, or the owner of the \
+                               associated account for the native token. \
+                               Defaults to the client keypair's associated account."),
+                )
+                .arg(
+                    Arg::with_name("address")
+                        .validator(is_valid_pubkey)
+                        .value_name("TOKEN_ACCOUNT_ADDRESS")
+                        .takes_value(true)
+                        .long("address")
+                        .conflicts_with("owner")
+                        .help("Specify the specific token account address to sync"),
+                ),
+        )
+}
+
+// The token program that owns the mint or account a command is about, so that Token-2022 mints
+// and accounts work without passing `--program-id`. Arguments naming anything else, or accounts
+// that can't be fetched, leave the choice to the configured program id.
+async fn detect_program_id(rpc_client: &RpcClient, matches: &ArgMatches<'_>) -> Option<Pubkey> {
+    let address = ["token", "account", "address", "accounts", "mints"]
+        .iter()
+        .find_map(|name| pubkey_of(matches, name))?;
+    let owner = rpc_client.get_account(&address).await.ok()?.owner;
+    if owner == spl_token::id() || owner == spl_token_2022::id() {
+        Some(owner)
+    } else {
+        None
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    let default_decimals = format!("{}", spl_token_2022::native_mint::DECIMALS);
+    let default_program_id = spl_token::id().to_string();
+    let minimum_signers_help = minimum_signers_help_string();
+    let multisig_member_help = multisig_member_help_string();
+    let app_matches = app(
+        &default_decimals,
+        &default_program_id,
+        &minimum_signers_help,
+        &multisig_member_help,
+    )
+    .get_matches();
+
+    let mut wallet_manager = None;
+    let mut bulk_signers: Vec<Box<dyn Signer>> = Vec::new();
+    let mut multisigner_ids = Vec::new();
+
+    let (sub_command, sub_matches) = app_matches.subcommand();
+    let sub_command = CommandName::from_str(sub_command).unwrap();
+    let matches = sub_matches.unwrap();
+
+    let config = {
+        let cli_config = if let Some(config_file) = matches.value_of("config_file") {
+            solana_cli_config::Config::load(config_file).unwrap_or_else(|_| {
+                eprintln!("error: Could not find config file `{}`", config_file);
+                exit(1);
+            })
+        } else {
+            solana_cli_config::Config::default()
+        };
+        let json_rpc_urls = matches
+            .value_of("json_rpc_url")
+            .unwrap_or(&cli_config.json_rpc_url)
+            .split(',')
+            .map(|url| normalize_to_url_if_moniker(url.trim()))
+            .collect::<Vec<_>>();
+        let websocket_url = solana_cli_config::Config::compute_websocket_url(&json_rpc_urls[0]);
+
+        // A local fee payer keypair signs like any other signer. Just the address is enough
+        // when signing offline, or when the fee payer already signed the `--input-file`.
+        let relayer_sign_only = matches.is_present("relayer_sign_only");
+        // The relayer must not be able to sign here, or it would sign the transaction right away
+        if relayer_sign_only
+            && matches
+                .value_of("fee_payer")
+                .and_then(|fee_payer| Pubkey::from_str(fee_payer).ok())
+                .is_none()
+        {
+            eprintln!("error: --relayer-sign-only needs the address of the relayer as --fee-payer");
+            exit(1);
+        }
+        let fee_payer_config = SignerFromPathConfig {
+            allow_null_signer: matches.is_present("input_file") || relayer_sign_only,
+        };
+        let (signer, fee_payer) = signer_from_path_with_config(
+            matches,
+            matches
+                .value_of("fee_payer")
+                .unwrap_or(&cli_config.keypair_path),
+            "fee_payer",
+            &mut wallet_manager,
+            &fee_payer_config,
+        )
+        .map(|s| {
+            let p = s.pubkey();
+            (s, p)
+        })
+        .unwrap_or_else(|e| {
+            eprintln!("error: {}", e);
+            exit(1);
+        });
+        bulk_signers.push(signer);
+
+        let verbose = matches.is_present("verbose");
+        let output_format = matches
+            .value_of("output_format")
+            .map(|value| match value {
+                "json" => OutputFormat::Json,
+                "json-compact" | "jsonl" => OutputFormat::JsonCompact,
+                _ => unreachable!(),
+            })
+            .unwrap_or(if verbose {
+                OutputFormat::DisplayVerbose
+            } else {
+                OutputFormat::Display
+            });
+
+        let json_lines = matches.value_of("output_format") == Some("jsonl");
+
+        let nonce_account = pubkey_of_signer(matches, NONCE_ARG.name, &mut wallet_manager)
+            .unwrap_or_else(|e| {
+                eprintln!("error: {}", e);
+                exit(1);
+            });
+        let nonce_authority = if nonce_account.is_some() {
+            let (signer, nonce_authority) = signer_from_path(
+                matches,
+                matches
+                    .value_of(NONCE_AUTHORITY_ARG.name)
+                    .unwrap_or(&cli_config.keypair_path),
+                NONCE_AUTHORITY_ARG.name,
+                &mut wallet_manager,
+            )
+            .map(|s| {
+                let p = s.pubkey();
+                (s, p)
+            })
+            .unwrap_or_else(|e| {
+                eprintln!("error: {}", e);
+                exit(1);
+            });
+            bulk_signers.push(signer);
+
+            Some(nonce_authority)
+        } else {
+            None
+        };
+
+        let blockhash = value_of(matches, BLOCKHASH_ARG.name);
+
+        let offline = matches.is_present("offline");
+        // Commands that build no transaction need no blockhash
+        if offline
+            && blockhash.is_none()
+            && !matches!(
+                sub_command,
+                CommandName::Address | CommandName::DecodeTransaction
+            )
+        {
+            eprintln!(
+                "error: --offline needs the blockhash to sign with, \
+                 pass it with --blockhash (the nonce's blockhash when using --nonce)"
+            );
+            exit(1);
+        }
+        let sign_only = offline || relayer_sign_only || matches.is_present(SIGN_ONLY_ARG.name);
+        let simulate = matches.is_present("simulate");
+        let fee_payer_balance_check = !matches.is_present("skip_fee_payer_balance_check");
+        let rpc_max_retries = value_t_or_exit!(matches, "rpc_max_retries", u32);
+        let strict = matches.is_present("strict");
+        let quiet = matches.is_present("quiet");
+        let no_wait = matches.is_present("no_wait");
+        if no_wait && sign_only {
+            eprintln!("error: --no-wait can't be used with --sign-only, there is nothing to send");
+            exit(1);
+        }
+        let memo = matches.value_of("memo").map(|memo| memo.to_string());
+        let dump_transaction_message = matches.is_present(DUMP_TRANSACTION_MESSAGE.name);
+        let output_file = matches.value_of("output_file").map(|path| path.to_string());
+        let input_file = matches.value_of("input_file").map(|path| path.to_string());
+        let compute_unit_limit = value_of(matches, "compute_unit_limit");
+        let compute_unit_price = value_of(matches, "compute_unit_price");
+        let compute_unit_margin = if matches.is_present("compute_budget_from_simulation") {
+            Some(value_of(matches, "compute_unit_margin").unwrap_or(DEFAULT_COMPUTE_UNIT_MARGIN))
+        } else {
+            None
+        };
+        // Zero attempts would never sign anything
+        let max_sign_attempts = value_of::<u32>(matches, "max_sign_attempts")
+            .unwrap_or(1)
+            .max(1);
+        let account_source = match matches.value_of("account_source") {
+            Some(path) => read_account_source(path).unwrap_or_else(|err| {
+                eprintln!("error: {}", err);
+                exit(1);
+            }),
+            None => HashMap::new(),
+        };
+
+        let multisig_signers = signers_of(matches, MULTISIG_SIGNER_ARG.name, &mut wallet_manager)
+            .unwrap_or_else(|e| {
+                eprintln!("error: {}", e);
+                exit(1);
+            });
+        if let Some(mut multisig_signers) = multisig_signers {
+            multisig_signers.sort_by(|(_, lp), (_, rp)| lp.cmp(rp));
+            let (signers, pubkeys): (Vec<_>, Vec<_>) = multisig_signers.into_iter().unzip();
+            bulk_signers.extend(signers);
+            multisigner_ids = pubkeys;
+        }
+        let multisigner_pubkeys = multisigner_ids.iter().collect::<Vec<_>>();
+
+        let commitment = match matches.value_of("commitment") {
+            Some("processed") => CommitmentConfig::processed(),
+            Some("finalized") => CommitmentConfig::finalized(),
+            _ => CommitmentConfig::confirmed(),
+        };
+        let rpc_client = Arc::new(if offline {
+            RpcClient::new_sender(OfflineSender, RpcClientConfig::with_commitment(commitment))
+        } else if json_rpc_urls.len() > 1 {
+            RpcClient::new_sender(
+                FailoverSender::new(&json_rpc_urls),
+                RpcClientConfig::with_commitment(commitment),
+            )
+        } else {
+            RpcClient::new_with_commitment(json_rpc_urls.into_iter().next().unwrap(), commitment)
+        });
+
+        let configured_program_id = program_id_of(matches, "program_id").unwrap();
+        let program_id = if matches.occurrences_of("program_id") == 0 && !sign_only {
+            detect_program_id(&rpc_client, matches)
+                .await
+                .unwrap_or(configured_program_id)
+        } else {
+            configured_program_id
+        };
+
+        Config {
+            rpc_client,
+            commitment,
+            websocket_url,
+            output_format,
+            json_lines,
+            fee_payer,
+            default_keypair: KeypairOrPath::Path(cli_config.keypair_path),
+            nonce_account,
+            nonce_authority,
+            blockhash,
+            sign_only,
+            simulate,
+            fee_payer_balance_check,
+            rpc_max_retries,
+            strict,
+            quiet,
+            no_wait,
+            memo,
+            dump_transaction_message,
+            output_file,
+            input_file,
+            compute_unit_limit,
+            compute_unit_price,
+            compute_unit_margin,
+            multisigner_pubkeys,
+            program_id,
+            mint_info_cache: Mutex::new(HashMap::new()),
+            account_source,
+            max_sign_attempts,
+            offline,
+            relayer_sign_only,
+        }
+    };
+
+    solana_logger::setup_with_default("solana=info");
+    let result = match process_command(&sub_command, matches, &config, wallet_manager, bulk_signers)
+        .await
+    {
+        Ok(result) => result,
+        // Scripts reading JSON get the error in the same form as any other output
+        Err(err)
+            if matches!(
+                config.output_format,
+                OutputFormat::Json | OutputFormat::JsonCompact
+            ) =>
+        {
+            let output = match err.downcast_ref::<TokenCliError>() {
+                Some(TokenCliError::Incomplete { output, .. }) => serde_json::from_str(output).ok(),
+                _ => None,
+            };
+            let cli_error = CliError {
+                error: CliErrorDetails {
+                    message: err.to_string(),
+                    kind: error_kind(err.as_ref()),
+                },
+                output,
+            };
+            println!("{}", config.output_format.formatted_string(&cli_error));
+            exit(1);
+        }
+        Err(err) => {
+            if let Some(TokenCliError::Incomplete { output, .. }) =
+                err.downcast_ref::<TokenCliError>()
+            {
+                println!("{}", output);
+            }
+            return Err(err);
+        }
+    };
+    println!("{}", result);
+    Ok(())
+}
+
+fn error_kind(err: &(dyn std::error::Error + 'static)) -> CliErrorKind {
+    if let Some(TokenCliError::Rpc(err)) = err.downcast_ref::<TokenCliError>() {
+        return error_kind(err);
+    }
+    if let Some(err) = err.downcast_ref::<ClientError>() {
+        // A transaction that failed in simulation or on chain comes back from the RPC node, but
+        // it's the program that refused it
+        if err.get_transaction_error().is_some() {
+            CliErrorKind::Program
+        } else {
+            CliErrorKind::Rpc
+        }
+    } else if err.is::<ProgramError>() || err.is::<TransactionError>() {
+        CliErrorKind::Program
+    } else {
+        CliErrorKind::Validation
+    }
+}
+
+async fn process_command<'a>(
+    sub_command: &CommandName,
+    sub_matches: &ArgMatches<'_>,
+    config: &Config<'a>,
+    mut wallet_manager: Option<Arc<RemoteWalletManager>>,
+    mut bulk_signers: Vec<Box<dyn Signer>>,
+) -> CommandResult {
+    match (sub_command, sub_matches) {
+        (CommandName::Bench, arg_matches) => {
+            bench_process_command(
+                arg_matches,
+                config,
+                std::mem::take(&mut bulk_signers),
+                &mut wallet_manager,
+            )
+            .await
+        }
+        (CommandName::CreateToken, arg_matches) => {
+            let decimals = value_t_or_exit!(arg_matches, "decimals", u8);
+            let mint_authority =
+                config.pubkey_or_default(arg_matches, "mint_authority", &mut wallet_manager);
+
+            let (token_signer, token) =
+                get_signer(arg_matches, "token_keypair", &mut wallet_manager)
+                    .or_else(|| get_signer(arg_matches, "address_signer", &mut wallet_manager))
+                    .unwrap_or_else(new_throwaway_signer);
+            bulk_signers.push(token_signer);
+
+            let mut extensions = vec![];
+            if arg_matches.is_present("enable_non_transferable") {
+                extensions.push(MintExtension::NonTransferable);
+            }
+            if let Some(state) = arg_matches.value_of("default_account_state") {
+                let state = parse_account_state(state);
+                if state == AccountState::Frozen && !arg_matches.is_present("enable_freeze") {
+                    return Err(
+                        "Error: Accounts can only start frozen with `--enable-freeze`, \
+                                so that they can be thawed"
+                            .into(),
+                    );
+                }
+                extensions.push(MintExtension::DefaultAccountState(state));
+            }
+            if let Some(rate) = value_t!(arg_matches, "interest_rate", i16).ok() {
+                extensions.push(MintExtension::InterestBearing {
+                    rate_authority: mint_authority,
+                    rate,
+                });
+            }
+            if arg_matches.is_present("dry_run") {
+                return command_create_token_dry_run(config, extensions).await;
+            }
+
+            command_create_token(
+                config,
+                decimals,
+                token,
+                mint_authority,
+                arg_matches.is_present("enable_freeze"),
+                extensions,
+                bulk_signers,
+            )
+            .await
+        }
+        (CommandName::CreateAccount, arg_matches) => {
+            let token = pubkey_of_signer(arg_matches, "token", &mut wallet_manager)
+                .unwrap()
+                .unwrap();
+
+            // No need to add a signer when creating an associated token account
+            let account = get_signer(arg_matches, "account_keypair", &mut wallet_manager).map(
+                |(signer, account)| {
+                    bulk_signers.push(signer);
+                    account
+                },
+            );
+
+            // The owner is the base of a seed-derived address, so it has to sign
+            let seed = arg_matches.value_of("seed");
+            let owner = if seed.is_some() {
+                let (owner_signer, owner) =
+                    config.signer_or_default(arg_matches, "owner", &mut wallet_manager);
+                bulk_signers.push(owner_signer);
+                owner
+            } else {
+                config.pubkey_or_default(arg_matches, "owner", &mut wallet_manager)
+            };
+            let idempotent = arg_matches.is_present("idempotent");
+            let immutable_owner = arg_matches.is_present("immutable_owner");
+            command_create_account(
+                config,
+                token,
+                owner,
+                account,
+                idempotent,
+                immutable_owner,
+                seed,
+                bulk_signers,
+            )
+            .await
+        }
+        (CommandName::RecreateAta, arg_matches) => {
+            let token = pubkey_of_signer(arg_matches, "token", &mut wallet_manager)
+                .unwrap()
+                .unwrap();
+            let (owner_signer, owner) =
+                config.signer_or_default(arg_matches, "owner", &mut wallet_manager);
+            bulk_signers.push(owner_signer);
+            let sources = pubkeys_of_multiple_signers(arg_matches, "from", &mut wallet_manager)
+                .unwrap_or_else(|e| {
+                    eprintln!("error: {}", e);
+                    exit(1);
+                });
+            let confirm = arg_matches.is_present("confirm");
+            command_recreate_ata(config, token, owner, sources, confirm, bulk_signers).await
+        }
+        (CommandName::RecoverNested, arg_matches) => {
+            let owner_token = pubkey_of_signer(arg_matches, "owner_token", &mut wallet_manager)
+                .unwrap()
+                .unwrap();
+            let nested_token = pubkey_of_signer(arg_matches, "nested_token", &mut wallet_manager)
+                .unwrap()
+                .unwrap();
+            let (owner_signer, owner) =
+                config.signer_or_default(arg_matches, "owner", &mut wallet_manager);
+            bulk_signers.push(owner_signer);
+            command_recover_nested(config, owner, owner_token, nested_token, bulk_signers).await
+        }
+        (CommandName::CreateMultisig, arg_matches) => {
+            let minimum_signers = value_of::<u8>(arg_matches, "minimum_signers").unwrap();
+            let mut multisig_members = match arg_matches.value_of("signers_file") {
+                Some(path) => read_multisig_members_file(path)?,
+                None => vec![],
+            };
+            multisig_members.extend(
+                pubkeys_of_multiple_signers(arg_matches, "multisig_member", &mut wallet_manager)
+                    .unwrap_or_else(|e| {
+                        eprintln!("error: {}", e);
+                        exit(1);
+                    })
+                    .unwrap_or_default(),
+            );
+            if multisig_members.len() < MIN_SIGNERS || multisig_members.len() > MAX_SIGNERS {
+                return Err(format!(
+                    "Error: A multisig needs between {} and {} members, {} given",
+                    MIN_SIGNERS,
+                    MAX_SIGNERS,
+                    multisig_members.len()
+                )
+                .into());
+            }
+            if minimum_signers as usize > multisig_members.len() {
+                eprintln!(
+                    "error: MINIMUM_SIGNERS cannot be greater than the number \
+                          of MULTISIG_MEMBERs passed"
+                );
+                exit(1);
+            }
+
+            let (signer, account) = get_signer(arg_matches, "address_keypair", &mut wallet_manager)
+                .unwrap_or_else(new_throwaway_signer);
+            bulk_signers.push(signer);
+
+            command_create_multisig(
+                config,
+                account,
+                minimum_signers,
+                multisig_members,
+                bulk_signers,
+            )
+            .await
+        }
+        (CommandName::CombineSignatures, arg_matches) => {
+            let message = arg_matches.value_of("message").unwrap();
+            let signatures = pubkeys_sigs_of(arg_matches, "signature").unwrap();
+            command_combine_signatures(config, message, signatures).await
+        }
+        (CommandName::Authorize, arg_matches) => {
+            let address = pubkey_of_signer(arg_matches, "address", &mut wallet_manager)
+                .unwrap()
+                .unwrap();
+            let authority_type = arg_matches.value_of("authority_type").unwrap();
+            if authority_type == "confidential-transfer-mint" {
+                let (authority_signer, authority) =
+                    config.signer_or_default(arg_matches, "authority", &mut wallet_manager);
+                bulk_signers.push(authority_signer);
+                // The new authority signs too, so it must be given as a keypair
+                let new_authority = get_signer(arg_matches, "new_authority", &mut wallet_manager)
+                    .map(|(signer, new_authority)| {
+                        bulk_signers.push(signer);
+                        new_authority
+                    });
+                return command_authorize_confidential_transfer_mint(
+                    config,
+                    address,
+                    authority,
+                    new_authority,
+                    bulk_signers,
+                )
+                .await;
+            }
+            if authority_type == "close" && config.sign_only {
+                return Err(
+                    "Error: `close` can mean the close authority of a mint or of a \
+                            token account, which can't be told apart in sign-only mode. \
+                            Use `close-mint` or `close-account` instead"
+                        .into(),
+                );
+            }
+            let authority_type = match authority_type {
+                "mint" => AuthorityType::MintTokens,
+                "freeze" => AuthorityType::FreezeAccount,
+                "owner" => AuthorityType::AccountOwner,
+                "close" | "close-account" => AuthorityType::CloseAccount,
+                "close-mint" => AuthorityType::CloseMint,
+                "transfer-fee-config" => AuthorityType::TransferFeeConfig,
+                "withheld-withdraw" => AuthorityType::WithheldWithdraw,
+                "interest-rate" => AuthorityType::InterestRate,
+                _ => unreachable!(),
+            };
+
+            let (authority_signer, authority) =
+                config.signer_or_default(arg_matches, "authority", &mut wallet_manager);
+            bulk_signers.push(authority_signer);
+
+            let new_authority =
+                pubkey_of_signer(arg_matches, "new_authority", &mut wallet_manager).unwrap();
+            let force_authorize = arg_matches.is_present("force");
+            command_authorize(
+                config,
+                address,
+                authority_type,
+                authority,
+                new_authority,
+                force_authorize,
+                bulk_signers,
+            )
+            .await
+        }
+        (CommandName::Transfer, arg_matches) => {
+            let token = pubkey_of_signer(arg_matches, "token", &mut wallet_manager)
+                .unwrap()
+                .unwrap();
+            let mint_decimals = value_of::<u8>(arg_matches, MINT_DECIMALS_ARG.name);
+            let amount = resolved_value_of(arg_matches, "amount").unwrap();
+            let balance_percentage = parse_balance_percentage(&amount);
+            if balance_percentage.is_some() && config.sign_only {
+                return Err("Error: A percentage amount needs the current balance of the sender, \
+                            which isn't available in sign-only mode. Specify an absolute amount instead"
+                    .into());
+            }
+            let amount = match amount.as_str() {
+                "ALL" => None,
+                _ if balance_percentage.is_some() => None,
+                amount => match expand_amount_suffix(amount) {
+                    Some(_) if config.sign_only && mint_decimals.is_none() => {
+                        return Err(format!(
+                            "Error: Amount {} has a unit suffix, which requires `--{}` \
+                             in sign-only mode",
+                            amount, MINT_DECIMALS_ARG.long
+                        )
+                        .into());
+                    }
+                    Some(expanded) => Some(expanded.parse::<f64>().unwrap()),
+                    None => Some(amount.parse::<f64>().unwrap()),
+                },
+            };
+            let recipient = pubkey_of_signer(arg_matches, "recipient", &mut wallet_manager)
+                .unwrap()
+                .unwrap();
+            let sender = pubkey_of_signer(arg_matches, "from", &mut wallet_manager).unwrap();
+
+            let (owner_signer, owner) =
+                config.signer_or_default(arg_matches, "owner", &mut wallet_manager);
+            bulk_signers.push(owner_signer);
+
+            let fund_recipient = arg_matches.is_present("fund_recipient");
+            let allow_unfunded_recipient = arg_matches.is_present("allow_empty_recipient")
+                || arg_matches.is_present("allow_unfunded_recipient");
+
+            let recipient_kind = if arg_matches.is_present("recipient_is_token_account") {
+                Some(RecipientKind::TokenAccount)
+            } else if arg_matches.is_present("recipient_is_owner")
+                || arg_matches.is_present("recipient_is_ata_owner")
+            {
+                Some(RecipientKind::Owner)
+            } else {
+                None
+            };
+            let use_unchecked_instruction = arg_matches.is_present("use_unchecked_instruction");
+
+            command_transfer(
+                config,
+                token,
+                amount,
+                balance_percentage,
+                recipient,
+                sender,
+                owner,
+                allow_unfunded_recipient,
+                fund_recipient,
+                mint_decimals,
+                recipient_kind,
+                use_unchecked_instruction,
+                amount_of(arg_matches, "expected_fee"),
+                bulk_signers,
+                arg_matches.is_present("allow_non_system_account_recipient"),
+                arg_matches.is_present("show_balances"),
+            )
+            .await
+        }
+        (CommandName::BatchTransfer, arg_matches) => {
+            let token = pubkey_of_signer(arg_matches, "token", &mut wallet_manager)
+                .unwrap()
+                .unwrap();
+            // Fee payers from the file sign like `--fee-payer`, each source resolved only once
+            let fee_payer_config = SignerFromPathConfig {
+                allow_null_signer: config.sign_only || config.input_file.is_some(),
+            };
+            let mut fee_payers: HashMap<String, Pubkey> = HashMap::new();
+            let rows =
+                parse_batch_transfer_file(arg_matches.value_of("file").unwrap(), |source| {
+                    if let Some(fee_payer) = fee_payers.get(source) {
+                        return Ok(*fee_payer);
+                    }
+                    let signer = signer_from_path_with_config(
+                        arg_matches,
+                        source,
+                        "fee_payer",
+                        &mut wallet_manager,
+                        &fee_payer_config,
+                    )?;
+                    let fee_payer = signer.pubkey();
+                    bulk_signers.push(signer);
+                    fee_payers.insert(source.to_string(), fee_payer);
+                    Ok(fee_payer)
+                })?;
+            let sender = pubkey_of_signer(arg_matches, "from", &mut wallet_manager).unwrap();
+
+            let (owner_signer, owner) =
+                config.signer_or_default(arg_matches, "owner", &mut wallet_manager);
+            bulk_signers.push(owner_signer);
+
+            let mint_decimals = value_of::<u8>(arg_matches, MINT_DECIMALS_ARG.name);
+            command_batch_transfer(
+                config,
+                token,
+                rows,
+                sender,
+                owner,
+                arg_matches.is_present("allow_unfunded_recipient"),
+                arg_matches.is_present("fund_recipient"),
+                mint_decimals,
+                arg_matches.is_present("recipient_is_ata_owner"),
+                bulk_signers,
+            )
+            .await
+        }
+        (CommandName::Burn, arg_matches) => {
+            let source = pubkey_of_signer(arg_matches, "source", &mut wallet_manager)
+                .unwrap()
+                .unwrap();
+
+            let (owner_signer, owner) =
+                config.signer_or_default(arg_matches, "owner", &mut wallet_manager);
+            bulk_signers.push(owner_signer);
+
+            let amount = amount_of(arg_matches, "amount").unwrap();
+            let mint_address =
+                pubkey_of_signer(arg_matches, MINT_ADDRESS_ARG.name, &mut wallet_manager).unwrap();
+            let mint_decimals = value_of::<u8>(arg_matches, MINT_DECIMALS_ARG.name);
+            let use_unchecked_instruction = arg_matches.is_present("use_unchecked_instruction");
+            command_burn(
+                config,
+                source,
+                owner,
+                amount,
+                mint_address,
+                mint_decimals,
+                use_unchecked_instruction,
+                arg_matches.is_present("show_balances"),
+                bulk_signers,
+            )
+            .await
+        }
+        (CommandName::Mint, arg_matches) => {
+            let (mint_authority_signer, mint_authority) =
+                config.signer_or_default(arg_matches, "mint_authority", &mut wallet_manager);
+            bulk_signers.push(mint_authority_signer);
+
+            let token = pubkey_of_signer(arg_matches, "token", &mut wallet_manager)
+                .unwrap()
+                .unwrap();
+            let amount = amount_of(arg_matches, "amount").unwrap();
+            let mint_decimals = value_of::<u8>(arg_matches, MINT_DECIMALS_ARG.name);
+            let mint_info = config.get_mint_info(&token, mint_decimals).await?;
+            let fund_recipient = arg_matches.is_present("fund_recipient");
+            // Anything but a token account of the token is taken for the owner of the receiving
+            // account. Without the network to tell, only `--fund-recipient` marks an owner.
+            let (recipient, recipient_owner) =
+                match pubkey_of_signer(arg_matches, "recipient", &mut wallet_manager).unwrap() {
+                    Some(address) if config.sign_only && !fund_recipient => (address, None),
+                    Some(address) if config.sign_only => (address, Some(address)),
+                    Some(address) => {
+                        let is_token_account = config
+                            .rpc_client
+                            .get_account_with_commitment(&address, config.commitment)
+                            .await?
+                            .value
+                            .map(|account| {
+                                account.owner == mint_info.program_id
+                                    && StateWithExtensionsOwned::<Account>::unpack(account.data)
+                                        .is_ok()
+                            })
+                            .unwrap_or(false);
+                        (address, Some(address).filter(|_| !is_token_account))
+                    }
+                    None => {
+                        let owner =
+                            config.pubkey_or_default(arg_matches, "owner", &mut wallet_manager);
+                        (owner, Some(owner))
+                    }
+                };
+            let recipient = match recipient_owner {
+                Some(owner) => get_associated_token_address_with_program_id(
+                    &owner,
+                    &mint_info.address,
+                    &mint_info.program_id,
+                ),
+                None => recipient,
+            };
+            let use_unchecked_instruction = arg_matches.is_present("use_unchecked_instruction");
+            command_mint(
+                config,
+                token,
+                amount,
+                recipient,
+                recipient_owner,
+                fund_recipient,
+                mint_info,
+                mint_authority,
+                use_unchecked_instruction,
+                arg_matches.is_present("show_balances"),
+                bulk_signers,
+            )
+            .await
+        }
+        (CommandName::Freeze, arg_matches) => {
+            let (freeze_authority_signer, freeze_authority) =
+                config.signer_or_default(arg_matches, "freeze_authority", &mut wallet_manager);
+            bulk_signers.push(freeze_authority_signer);
+
+            let account = pubkey_of_signer(arg_matches, "account", &mut wallet_manager)
+                .unwrap()
+                .unwrap();
+            let mint_address =
+                pubkey_of_signer(arg_matches, MINT_ADDRESS_ARG.name, &mut wallet_manager).unwrap();
+            command_freeze(
+                config,
+                account,
+                mint_address,
+                freeze_authority,
+                bulk_signers,
+            )
+            .await
+        }
+        (CommandName::FreezeAll, arg_matches) => {
+            let (freeze_authority_signer, freeze_authority) =
+                config.signer_or_default(arg_matches, "freeze_authority", &mut wallet_manager);
+            bulk_signers.push(freeze_authority_signer);
+
+            let token = pubkey_of_signer(arg_matches, "token", &mut wallet_manager)
+                .unwrap()
+                .unwrap();
+            command_freeze_all(
+                config,
+                token,
+                freeze_authority,
+                arg_matches.is_present("confirm"),
+                &CliSignerInfo {
+                    signers: bulk_signers,
+                },
+            )
+            .await
+        }
+        (CommandName::ThawAll, arg_matches) => {
+            let (freeze_authority_signer, freeze_authority) =
+                config.signer_or_default(arg_matches, "freeze_authority", &mut wallet_manager);
+            bulk_signers.push(freeze_authority_signer);
+
+            let token = pubkey_of_signer(arg_matches, "token", &mut wallet_manager)
+                .unwrap()
+                .unwrap();
+            command_thaw_all(
+                config,
+                token,
+                freeze_authority,
+                arg_matches.is_present("confirm"),
+                &CliSignerInfo {
+                    signers: bulk_signers,
+                },
+            )
+            .await
+        }
+        (CommandName::Thaw, arg_matches) => {
+            let (freeze_authority_signer, freeze_authority) =
+                config.signer_or_default(arg_matches, "freeze_authority", &mut wallet_manager);
+            bulk_signers.push(freeze_authority_signer);
+
+            let account = pubkey_of_signer(arg_matches, "account", &mut wallet_manager)
+                .unwrap()
+                .unwrap();
+            let mint_address =
+                pubkey_of_signer(arg_matches, MINT_ADDRESS_ARG.name, &mut wallet_manager).unwrap();
+            command_thaw(
+                config,
+                account,
+                mint_address,
+                freeze_authority,
+                bulk_signers,
+            )
+            .await
+        }
+        (CommandName::SetTransferFee, arg_matches) => {
+            let token = pubkey_of_signer(arg_matches, "token", &mut wallet_manager)
+                .unwrap()
+                .unwrap();
+            let transfer_fee_basis_points =
+                value_t_or_exit!(arg_matches, "transfer_fee_basis_points", u16);
+            let maximum_fee = amount_of(arg_matches, "maximum_fee").unwrap();
+            let (transfer_fee_authority_signer, transfer_fee_authority) = config.signer_or_default(
+                arg_matches,
+                "transfer_fee_authority",
+                &mut wallet_manager,
+            );
+            bulk_signers.push(transfer_fee_authority_signer);
+            let mint_decimals = value_of::<u8>(arg_matches, MINT_DECIMALS_ARG.name);
+            command_set_transfer_fee(
+                config,
+                token,
+                transfer_fee_basis_points,
+                maximum_fee,
+                transfer_fee_authority,
+                mint_decimals,
+                bulk_signers,
+            )
+            .await
+        }
+        (CommandName::HarvestWithheldTokensToMint, arg_matches) => {
+            let token = pubkey_of_signer(arg_matches, "token", &mut wallet_manager)
+                .unwrap()
+                .unwrap();
+            let sources = pubkeys_of_multiple_signers(arg_matches, "source", &mut wallet_manager)
+                .unwrap_or_else(|e| {
+                    eprintln!("error: {}", e);
+                    exit(1);
+                })
+                .unwrap_or_default();
+            command_harvest_withheld_tokens_to_mint(
+                config,
+                token,
+                sources,
+                arg_matches.is_present("all"),
+                &CliSignerInfo {
+                    signers: bulk_signers,
+                },
+            )
+            .await
+        }
+        (CommandName::WithdrawWithheldTokens, arg_matches) => {
+            let token = pubkey_of_signer(arg_matches, "token", &mut wallet_manager)
+                .unwrap()
+                .unwrap();
+            let destination = pubkey_of_signer(arg_matches, "destination", &mut wallet_manager)
+                .unwrap()
+                .unwrap();
+            let (withdraw_withheld_authority_signer, withdraw_withheld_authority) = config
+                .signer_or_default(
+                    arg_matches,
+                    "withdraw_withheld_authority",
+                    &mut wallet_manager,
+                );
+            bulk_signers.push(withdraw_withheld_authority_signer);
+            command_withdraw_withheld_tokens(
+                config,
+                token,
+                destination,
+                withdraw_withheld_authority,
+                bulk_signers,
+            )
+            .await
+        }
+        (CommandName::UpdateDefaultAccountState, arg_matches) => {
+            let token = pubkey_of_signer(arg_matches, "token", &mut wallet_manager)
+                .unwrap()
+                .unwrap();
+            let state = parse_account_state(arg_matches.value_of("state").unwrap());
+            let (freeze_authority_signer, freeze_authority) =
+                config.signer_or_default(arg_matches, "freeze_authority", &mut wallet_manager);
+            bulk_signers.push(freeze_authority_signer);
+            command_update_default_account_state(
+                config,
+                token,
+                state,
+                freeze_authority,
+                bulk_signers,
+            )
+            .await
+        }
+        (CommandName::UpdateInterestRate, arg_matches) => {
+            let token = pubkey_of_signer(arg_matches, "token", &mut wallet_manager)
+                .unwrap()
+                .unwrap();
+            let rate = value_t_or_exit!(arg_matches, "rate", i16);
+            let (rate_authority_signer, rate_authority) =
+                config.signer_or_default(arg_matches, "rate_authority", &mut wallet_manager);
+            bulk_signers.push(rate_authority_signer);
+            command_update_interest_rate(config, token, rate, rate_authority, bulk_signers).await
+        }
+        (CommandName::ConfigureConfidentialTransferAccount, arg_matches) => {
+            let account = pubkey_of_signer(arg_matches, "account", &mut wallet_manager)
+                .unwrap()
+                .unwrap();
+            let mint_address =
+                pubkey_of_signer(arg_matches, MINT_ADDRESS_ARG.name, &mut wallet_manager).unwrap();
+            let (owner_signer, owner) =
+                config.signer_or_default(arg_matches, "owner", &mut wallet_manager);
+            let elgamal_keypair = confidential_transfer_elgamal_keypair(
+                owner_signer.as_ref(),
+                &account,
+                arg_matches.value_of("elgamal_keypair"),
+                true,
+            )?;
+            let decryptable_zero_balance =
+                confidential_transfer_ae_key(owner_signer.as_ref(), &account)?.encrypt(0);
+            bulk_signers.push(owner_signer);
+            let maximum_pending_balance_credit_counter =
+                value_t_or_exit!(arg_matches, "maximum_pending_balance_credit_counter", u64);
+            command_configure_confidential_transfer_account(
+                config,
+                account,
+                mint_address,
+                owner,
+                elgamal_keypair.public,
+                decryptable_zero_balance,
+                maximum_pending_balance_credit_counter,
+                bulk_signers,
+            )
+            .await
+        }
+        (CommandName::DepositConfidential, arg_matches) => {
+            let account = pubkey_of_signer(arg_matches, "account", &mut wallet_manager)
+                .unwrap()
+                .unwrap();
+            let amount = amount_of(arg_matches, "amount").unwrap();
+            let mint_address =
+                pubkey_of_signer(arg_matches, MINT_ADDRESS_ARG.name, &mut wallet_manager).unwrap();
+            let mint_decimals = value_of::<u8>(arg_matches, MINT_DECIMALS_ARG.name);
+            let (owner_signer, owner) =
+                config.signer_or_default(arg_matches, "owner", &mut wallet_manager);
+            bulk_signers.push(owner_signer);
+            command_deposit_confidential(
+                config,
+                account,
+                mint_address,
+                amount,
+                mint_decimals,
+                owner,
+                bulk_signers,
+            )
+            .await
+        }
+        (CommandName::ApplyPendingBalance, arg_matches) => {
+            let account = pubkey_of_signer(arg_matches, "account", &mut wallet_manager)
+                .unwrap()
+                .unwrap();
+            let (owner_signer, owner) =
+                config.signer_or_default(arg_matches, "owner", &mut wallet_manager);
+            let elgamal_keypair = confidential_transfer_elgamal_keypair(
+                owner_signer.as_ref(),
+                &account,
+                arg_matches.value_of("elgamal_keypair"),
+                false,
+            )?;
+            let ae_key = confidential_transfer_ae_key(owner_signer.as_ref(), &account)?;
+            bulk_signers.push(owner_signer);
+            command_apply_pending_balance(
+                config,
+                account,
+                owner,
+                elgamal_keypair,
+                ae_key,
+                bulk_signers,
+            )
+            .await
+        }
+        (CommandName::Wrap, arg_matches) => {
+            let amount = amount_of(arg_matches, "amount").unwrap();
+            let account = if arg_matches.is_present("create_aux_account") {
+                let (signer, account) = new_throwaway_signer();
+                bulk_signers.push(signer);
+                Some(account)
+            } else {
+                // No need to add a signer when creating an associated token account
+                None
+            };
+
+            let (wallet_signer, wallet_address) =
+                config.signer_or_default(arg_matches, "wallet_keypair", &mut wallet_manager);
+            bulk_signers.push(wallet_signer);
+
+            let owner = pubkey_of_signer(arg_matches, "recipient", &mut wallet_manager)
+                .unwrap()
+                .unwrap_or(wallet_address);
+            command_wrap(config, amount, wallet_address, owner, account, bulk_signers).await
+        }
+        (CommandName::Unwrap, arg_matches) => {
+            let (wallet_signer, wallet_address) =
+                config.signer_or_default(arg_matches, "wallet_keypair", &mut wallet_manager);
+            bulk_signers.push(wallet_signer);
+
+            let address = pubkey_of_signer(arg_matches, "address", &mut wallet_manager).unwrap();
+            let recipient = pubkey_of_signer(arg_matches, "recipient", &mut wallet_manager)
+                .unwrap()
+                .unwrap_or(wallet_address);
+            let all = arg_matches.is_present("all");
+            command_unwrap(
+                config,
+                wallet_address,
+                address,
+                recipient,
+                all,
+                bulk_signers,
+            )
+            .await
+        }
+        (CommandName::Approve, arg_matches) => {
+            let (owner_signer, owner_address) =
+                config.signer_or_default(arg_matches, "owner", &mut wallet_manager);
+            bulk_signers.push(owner_signer);
+
+            let account = pubkey_of_signer(arg_matches, "account", &mut wallet_manager)
+                .unwrap()
+                .unwrap();
+            let amount = amount_of(arg_matches, "amount").unwrap();
+            let delegate = pubkey_of_signer(arg_matches, "delegate", &mut wallet_manager)
+                .unwrap()
+                .unwrap();
+            let mint_address =
+                pubkey_of_signer(arg_matches, MINT_ADDRESS_ARG.name, &mut wallet_manager).unwrap();
+            let mint_decimals = value_of::<u8>(arg_matches, MINT_DECIMALS_ARG.name);
+            let use_unchecked_instruction = arg_matches.is_present("use_unchecked_instruction");
+            command_approve(
+                config,
+                account,
+                owner_address,
+                amount,
+                delegate,
+                mint_address,
+                mint_decimals,
+                use_unchecked_instruction,
+                bulk_signers,
+            )
+            .await
+        }
+        (CommandName::ApproveMany, arg_matches) => {
+            let (owner_signer, owner_address) =
+                config.signer_or_default(arg_matches, "owner", &mut wallet_manager);
+            bulk_signers.push(owner_signer);
+
+            let delegate = pubkey_of_signer(arg_matches, "delegate", &mut wallet_manager)
+                .unwrap()
+                .unwrap();
+            let amount = value_t_or_exit!(arg_matches, "amount", u64);
+            let accounts =
+                pubkeys_of_multiple_signers(arg_matches, "accounts", &mut wallet_manager)
+                    .unwrap_or_else(|e| {
+                        eprintln!("error: {}", e);
+                        exit(1);
+                    });
+            let token = pubkey_of_signer(arg_matches, "token", &mut wallet_manager).unwrap();
+            let signer_info = CliSignerInfo {
+                signers: bulk_signers,
+            };
+            command_approve_many(
+                config,
+                owner_address,
+                delegate,
+                amount,
+                accounts,
+                token,
+                &signer_info,
+            )
+            .await
+        }
+        (CommandName::Revoke, arg_matches) => {
+            let (owner_signer, owner_address) =
+                config.signer_or_default(arg_matches, "owner", &mut wallet_manager);
+            bulk_signers.push(owner_signer);
+
+            let account = pubkey_of_signer(arg_matches, "account", &mut wallet_manager)
+                .unwrap()
+                .unwrap();
+            let delegate_address =
+                pubkey_of_signer(arg_matches, DELEGATE_ADDRESS_ARG.name, &mut wallet_manager)
+                    .unwrap();
+            command_revoke(
+                config,
+                account,
+                owner_address,
+                delegate_address,
+                bulk_signers,
+            )
+            .await
+        }
+        (CommandName::RevokeAll, arg_matches) => {
+            let (owner_signer, owner_address) =
+                config.signer_or_default(arg_matches, "owner", &mut wallet_manager);
+            bulk_signers.push(owner_signer);
+
+            let token = pubkey_of_signer(arg_matches, "token", &mut wallet_manager).unwrap();
+            let confirm = arg_matches.is_present("confirm");
+            let signer_info = CliSignerInfo {
+                signers: bulk_signers,
+            };
+            command_revoke_all(config, owner_address, token, confirm, &signer_info).await
+        }
+        (CommandName::EnableRequiredTransferMemos, arg_matches)
+        | (CommandName::DisableRequiredTransferMemos, arg_matches) => {
+            let (owner_signer, owner) =
+                config.signer_or_default(arg_matches, "owner", &mut wallet_manager);
+            bulk_signers.push(owner_signer);
+
+            let account = pubkey_of_signer(arg_matches, "account", &mut wallet_manager)
+                .unwrap()
+                .unwrap();
+            let enable = *sub_command == CommandName::EnableRequiredTransferMemos;
+            command_required_transfer_memos(config, account, owner, enable, bulk_signers).await
+        }
+        (CommandName::Close, arg_matches) => {
+            let (close_authority_signer, close_authority) =
+                config.signer_or_default(arg_matches, "close_authority", &mut wallet_manager);
+            bulk_signers.push(close_authority_signer);
+
+            let address = config
+                .associated_token_address_or_override(arg_matches, "address", &mut wallet_manager)
+                .await;
+            let recipient = config.pubkey_or_default(arg_matches, "recipient", &mut wallet_manager);
+            command_close(config, address, close_authority, recipient, bulk_signers).await
+        }
+        (CommandName::CloseMint, arg_matches) => {
+            let (close_authority_signer, close_authority) =
+                config.signer_or_default(arg_matches, "close_authority", &mut wallet_manager);
+            bulk_signers.push(close_authority_signer);
+
+            let token = pubkey_of_signer(arg_matches, "token", &mut wallet_manager)
+                .unwrap()
+                .unwrap();
+            let recipient = pubkey_of_signer(arg_matches, "recipient", &mut wallet_manager)
+                .unwrap()
+                .unwrap_or(close_authority);
+            command_close_mint(config, token, close_authority, recipient, bulk_signers).await
+        }
+        (CommandName::Balance, arg_matches) if arg_matches.is_present("all_for_owner") => {
+            let token = pubkey_of_signer(arg_matches, "token", &mut wallet_manager)
+                .unwrap()
+                .unwrap();
+            let owner = config.pubkey_or_default(arg_matches, "owner", &mut wallet_manager);
+            command_balance_all_for_owner(config, token, owner).await
+        }
+        (CommandName::Balance, arg_matches) => {
+            let address = config
+                .associated_token_address_or_override(arg_matches, "address", &mut wallet_manager)
+                .await;
+            command_balance(config, address).await
+        }
+        (CommandName::Supply, arg_matches) => {
+            let address = pubkey_of_signer(arg_matches, "address", &mut wallet_manager)
+                .unwrap()
+                .unwrap();
+            command_supply(config, address, arg_matches.is_present("show_withheld")).await
+        }
+        (CommandName::AmountToUiAmount, arg_matches) => {
+            let token = pubkey_of_signer(arg_matches, "token", &mut wallet_manager)
+                .unwrap()
+                .unwrap();
+            let amount = value_t_or_exit!(arg_matches, "amount", u64);
+            command_amount_to_ui_amount(config, token, amount).await
+        }
+        (CommandName::UiAmountToAmount, arg_matches) => {
+            let token = pubkey_of_signer(arg_matches, "token", &mut wallet_manager)
+                .unwrap()
+                .unwrap();
+            let ui_amount = resolved_value_of(arg_matches, "ui_amount").unwrap();
+            command_ui_amount_to_amount(config, token, &ui_amount).await
+        }
+        (CommandName::Accounts, arg_matches) => {
+            let token = match pubkey_of_signer(arg_matches, "token", &mut wallet_manager).unwrap() {
+                Some(token) => Some(token),
+                None => pubkey_of_signer(arg_matches, "mint", &mut wallet_manager).unwrap(),
+            };
+            let owners = config.pubkeys_or_default(arg_matches, "owner", &mut wallet_manager);
+            for_each_owner(config, &owners, |owner| {
+                command_accounts(config, token, owner)
+            })
+            .await
+        }
+        (CommandName::Address, arg_matches) => {
+            let token = pubkey_of_signer(arg_matches, "token", &mut wallet_manager)
+                .unwrap()
+                .or_else(|| {
+                    pubkey_of_signer(arg_matches, "token_option", &mut wallet_manager).unwrap()
+                });
+            let owner = config.pubkey_or_default(arg_matches, "owner", &mut wallet_manager);
+            let offline = arg_matches.is_present("offline");
+            command_address(config, token, owner, offline).await
+        }
+        (CommandName::AccountInfo, arg_matches) => {
+            let address = config
+                .associated_token_address_or_override(arg_matches, "address", &mut wallet_manager)
+                .await;
+            command_account_info(config, address).await
+        }
+        (CommandName::MultisigInfo, arg_matches) => {
+            let address = pubkey_of_signer(arg_matches, "address", &mut wallet_manager)
+                .unwrap()
+                .unwrap();
+            let expect_threshold = value_t!(arg_matches, "expect_threshold", u8).ok();
+            let expect_signers =
+                pubkeys_of_multiple_signers(arg_matches, "expect_signers", &mut wallet_manager)
+                    .unwrap_or_else(|e| {
+                        eprintln!("error: {}", e);
+                        exit(1);
+                    });
+            command_multisig(config, address, expect_threshold, expect_signers).await
+        }
+        (CommandName::Display, arg_matches) => {
+            let address = pubkey_of_signer(arg_matches, "address", &mut wallet_manager)
+                .unwrap()
+                .unwrap();
+            command_display(config, address).await
+        }
+        (CommandName::Gc, arg_matches) => {
+            match config.output_format {
+                OutputFormat::Json | OutputFormat::JsonCompact => {
+                    eprintln!(
+                        "`spl-token gc` does not support the `--ouput` parameter at this time"
+                    );
+                    exit(1);
+                }
+                _ => {}
+            }
+
+            let close_empty_associated_accounts =
+                arg_matches.is_present("close_empty_associated_accounts");
+
+            let (owner_signer, owner_address) =
+                config.signer_or_default(arg_matches, "owner", &mut wallet_manager);
+            bulk_signers.push(owner_signer);
+
+            command_gc(
+                config,
+                owner_address,
+                close_empty_associated_accounts,
+                bulk_signers,
+            )
+            .await
+        }
+        (CommandName::CloseEmptyAccounts, arg_matches) => {
+            let mut owners = vec![];
+            for (owner_signer, owner) in
+                config.signers_or_default(arg_matches, "owner", &mut wallet_manager)
+            {
+                bulk_signers.push(owner_signer);
+                owners.push(owner);
+            }
+            let signer_info = CliSignerInfo {
+                signers: bulk_signers,
+            };
+            for_each_owner(config, &owners, |owner| {
+                command_close_empty_accounts(config, owner, &signer_info)
+            })
+            .await
+        }
+        (CommandName::DecodeTransaction, arg_matches) => {
+            command_decode_transaction(config, arg_matches.value_of("transaction").unwrap())
+        }
+        (CommandName::Confirm, arg_matches) => {
+            let signature = value_t_or_exit!(arg_matches, "signature", Signature);
+            command_confirm(config, signature).await
+        }
+        (CommandName::ExportAccounts, arg_matches) => {
+            let mints = pubkeys_of_multiple_signers(arg_matches, "mints", &mut wallet_manager)
+                .unwrap_or_else(|e| {
+                    eprintln!("error: {}", e);
+                    exit(1);
+                })
+                .unwrap();
+            let out = arg_matches.value_of("out").unwrap();
+            command_export_accounts(config, mints, out, bulk_signers).await
+        }
+        (CommandName::FindMints, arg_matches) => {
+            let authority = pubkey_of_signer(arg_matches, "authority", &mut wallet_manager)
+                .unwrap()
+                .unwrap();
+            let role = arg_matches.value_of("role").map(|role| match role {
+                "mint" => AuthorityType::MintTokens,
+                "freeze" => AuthorityType::FreezeAccount,
+                _ => unreachable!(),
+            });
+            eprintln!(
+                "Warning: find-mints scans every account of both token programs, \
+                 which is slow and may be refused by public RPC nodes"
+            );
+            command_find_mints(config, authority, role).await
+        }
+        (CommandName::SyncNative, arg_matches) => {
+            let program_id = config.program_id;
+            let native_mint = native_mint(&program_id)?;
+            let positional = pubkey_of_signer(arg_matches, "owner", &mut wallet_manager).unwrap();
+            // The positional address may be the wrapped SOL account itself rather than its owner.
+            // Only a native token account counts, any other address is taken as an owner.
+            let is_token_account = match positional {
+                Some(positional) if !config.sign_only => config
+                    .rpc_client
+                    .get_account(&positional)
+                    .await
+                    .ok()
+                    .and_then(|account| {
+                        let native_mint = native_mint(&account.owner).ok()?;
+                        let token_account =
+                            StateWithExtensionsOwned::<Account>::unpack(account.data).ok()?;
+                        Some(token_account.base.mint == native_mint)
+                    })
+                    .unwrap_or(false),
+                _ => false,
+            };
+            let address = if is_token_account {
+                positional.unwrap()
+            } else {
+                config
+                    .associated_token_address_for_token_or_override(
+                        arg_matches,
+                        "address",
+                        &mut wallet_manager,
+                        Some(native_mint),
+                    )
+                    .await
+            };
+            command_sync_native(address, bulk_signers, config).await
+        }
+    }
+}
+
+fn format_output<T>(command_output: T, command_name: &CommandName, config: &Config) -> String
+where
+    T: Serialize + Display + QuietDisplay + VerboseDisplay,
+{
+    config.output_format.formatted_string(&CommandOutput {
+        command_name: command_name.to_string(),
+        command_output,
+    })
+}
+fn new_message(
+    config: &Config<'_>,
+    instructions: Vec<Instruction>,
+    recent_blockhash: &Hash,
+) -> Message {
+    new_message_with_fee_payer(config, &config.fee_payer, instructions, recent_blockhash)
+}
+
+fn new_message_with_fee_payer(
+    config: &Config<'_>,
+    fee_payer: &Pubkey,
+    instructions: Vec<Instruction>,
+    recent_blockhash: &Hash,
+) -> Message {
+    let mut instructions = instructions;
+    if let Some(text) = &config.memo {
+        // The memo-transfer extension only accepts a memo immediately before the transfer, so
+        // each transfer of a batch gets its own. Without transfers, the memo goes in front of the
+        // first token instruction rather than the whole transaction.
+        let memo = spl_memo::build_memo(text.as_bytes(), &[fee_payer]);
+        if instructions.iter().any(is_token_transfer) {
+            instructions = instructions
+                .into_iter()
+                .flat_map(|instruction| {
+                    if is_token_transfer(&instruction) {
+                        vec![memo.clone(), instruction]
+                    } else {
+                        vec![instruction]
+                    }
+                })
+                .collect();
+        } else {
+            let index = instructions
+                .iter()
+                .position(|instruction| {
+                    instruction.program_id == spl_token::id()
+                        || instruction.program_id == spl_token_2022::id()
+                })
+                .unwrap_or(0);
+            instructions.insert(index, memo);
+        }
+    }
+    let instructions = config
+        .compute_budget_instructions()
+        .into_iter()
+        .chain(instructions)
+        .collect();
+    if let Some(nonce_account) = config.nonce_account.as_ref() {
+        let mut message = Message::new_with_nonce(
+            instructions,
+            Some(fee_payer),
+            nonce_account,
+            config.nonce_authority.as_ref().unwrap(),
+        );
+        message.recent_blockhash = *recent_blockhash;
+        message
+    } else {
+        Message::new_with_blockhash(&instructions, Some(fee_payer), recent_blockhash)
+    }
+}
+
+// The token instructions that move tokens into an account, which is what the memo-transfer
+// extension requires a memo for
+fn is_token_transfer(instruction: &Instruction) -> bool {
+    if instruction.program_id != spl_token::id() && instruction.program_id != spl_token_2022::id() {
+        return false;
+    }
+    match TokenInstruction::unpack(&instruction.data) {
+        Ok(TokenInstruction::Transfer { .. }) | Ok(TokenInstruction::TransferChecked { .. }) => {
+            true
+        }
+        Ok(TokenInstruction::TransferFeeExtension(
+            TransferFeeInstruction::TransferCheckedWithFee { .. },
+        )) => true,
+        _ => false,
+    }
+}
+
+// Check if the instructions fit into a single transaction, once signed
+fn transaction_fits(config: &Config<'_>, instructions: &[Instruction]) -> bool {
+    transaction_fits_with_fee_payer(config, &config.fee_payer, instructions)
+}
+
+fn transaction_fits_with_fee_payer(
+    config: &Config<'_>,
+    fee_payer: &Pubkey,
+    instructions: &[Instruction],
+) -> bool {
+    let mut instructions = instructions.to_vec();
+    if config.compute_unit_margin.is_some() {
+        // Leave room for the compute unit limit that is added once the transaction is simulated
+        instructions.insert(0, ComputeBudgetInstruction::set_compute_unit_limit(0));
+    }
+    let message = new_message_with_fee_payer(config, fee_payer, instructions, &Hash::default());
+    let num_signatures = message.header.num_required_signatures as usize;
+    // compact-u16 signature count, followed by the signatures themselves
+    1 + num_signatures * 64 + message.serialize().len() <= PACKET_DATA_SIZE
+}
+
+// Pack independent instructions into as few transactions as possible
+fn batch_instructions(
+    config: &Config<'_>,
+    instructions: Vec<Instruction>,
+) -> Vec<Vec<Instruction>> {
+    let mut transactions: Vec<Vec<Instruction>> = vec![];
+    for instruction in instructions {
+        if let Some(tx_instructions) = transactions.last_mut() {
+            tx_instructions.push(instruction.clone());
+            if transaction_fits(config, tx_instructions) {
+                continue;
+            }
+            tx_instructions.pop();
+        }
+        transactions.push(vec![instruction]);
+    }
+    transactions
+}
+
+// Sign with the available signers, keeping any signatures already present for the rest
+fn partial_sign_transaction(
+    config: &Config<'_>,
+    transaction: &mut Transaction,
+    signers: &[&dyn Signer],
+    recent_blockhash: Hash,
+) -> Result<(), Error> {
+    let existing_signatures = transaction.signatures.clone();
+    config.with_sign_attempts(
+        || transaction.try_partial_sign(&signers.to_vec(), recent_blockhash),
+        is_retriable_sign_error,
+    )?;
+    for (signature, existing_signature) in
+        transaction.signatures.iter_mut().zip(existing_signatures)
+    {
+        if *signature == Signature::default() {
+            *signature = existing_signature;
+        }
+    }
+    Ok(())
+}
+
+fn read_transaction_file(path: &str) -> Result<Transaction, Error> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|err| format!("Unable to read transaction file {}: {}", path, err))?;
+    let bytes = base64::decode(contents.trim())
+        .map_err(|err| format!("Transaction file {} is not valid base64: {}", path, err))?;
+    let transaction = bincode::deserialize(&bytes)
+        .map_err(|err| format!("Transaction file {} is invalid: {}", path, err))?;
+    Ok(transaction)
+}
+
+fn write_transaction_file(path: &str, transaction: &Transaction) -> Result<(), Error> {
+    let bytes = bincode::serialize(transaction)?;
+    std::fs::write(path, format!("{}\n", base64::encode(bytes)))
+        .map_err(|err| format!("Unable to write transaction file {}: {}", path, err))?;
+    Ok(())
+}
+
+// A durable nonce transaction must use the blockhash stored in the nonce account
+async fn get_nonce_blockhash(config: &Config<'_>, nonce_account: &Pubkey) -> Result<Hash, Error> {
+    let account = config
+        .rpc_client
+        .get_account(nonce_account)
+        .await
+        .map_err(|_| format!("Could not find nonce account {}", nonce_account))?;
+    let data = solana_client::nonce_utils::data_from_account(&account)?;
+    Ok(data.blockhash())
+}
+
+enum TransactionReturnData {
+    CliSignature(CliSignature),
+    CliSignOnlyData(CliSignOnlyTransaction),
+    CliSimulation(CliSimulation),
+}
+const RPC_RETRY_BASE_DELAY: Duration = Duration::from_millis(500);
+const CONFIRMATION_POLL_INTERVAL: Duration = Duration::from_millis(500);
+const DEFAULT_COMPUTE_UNIT_MARGIN: u32 = 10;
+const MAX_COMPUTE_UNIT_LIMIT: u32 = 1_400_000;
+// A little longer than a blockhash stays valid, after which a transaction can't land anymore
+const CONFIRMATION_TIMEOUT: Duration = Duration::from_secs(90);
+
+// Errors that come from the cluster being busy, unreachable or behind rather than from the
+// transaction itself, so that sending it again later may succeed
+fn is_transient_error(err: &ClientError) -> bool {
+    match err.kind() {
+        ClientErrorKind::Io(_) => true,
+        ClientErrorKind::Reqwest(err) => {
+            err.is_timeout()
+                || err.is_connect()
+                || err.status().map(|status| status.as_u16()) == Some(429)
+        }
+        ClientErrorKind::RpcError(RpcError::RpcResponseError {
+            data: RpcResponseErrorData::NodeUnhealthy { .. },
+            ..
+        }) => true,
+        _ => err.get_transaction_error() == Some(TransactionError::BlockhashNotFound),
+    }
+}
+
+// Catch a multisig signer set that can never reach the threshold before anyone spends time
+// signing it offline. Token instructions list the multisig signers right after the multisig they
+// sign for, so the account before them is the candidate. It may just as well be an account that
+// happens to precede a member wallet, which `check_multisig_signers` passes over.
+async fn check_multisig_threshold(config: &Config<'_>, message: &Message) -> Result<(), Error> {
+    if config.multisigner_pubkeys.is_empty() {
+        return Ok(());
+    }
+    let mut multisigs = vec![];
+    for instruction in &message.instructions {
+        let program_id = message.account_keys[instruction.program_id_index as usize];
+        if program_id != spl_token::id() && program_id != spl_token_2022::id() {
+            continue;
+        }
+        let accounts = instruction
+            .accounts
+            .iter()
+            .map(|index| message.account_keys[*index as usize])
+            .collect::<Vec<_>>();
+        if let Some(first_signer) = accounts
+            .iter()
+            .position(|account| config.multisigner_pubkeys.contains(&account))
+        {
+            if first_signer > 0 && !multisigs.contains(&accounts[first_signer - 1]) {
+                multisigs.push(accounts[first_signer - 1]);
+            }
+        }
+    }
+
+    for multisig in multisigs {
+        check_multisig_signers(config, &multisig).await?;
+    }
+    Ok(())
+}
+
+// Make sure enough of the given multisig signers are members of `multisig` to reach its
+// threshold. Falling short is only a warning unless `--strict` is set. An address that isn't a
+// multisig of a token program has nothing to check.
+async fn check_multisig_signers(config: &Config<'_>, multisig: &Pubkey) -> Result<(), Error> {
+    let multisig_info = match config.get_multisig_info(multisig).await {
+        Ok(Some(multisig_info)) => multisig_info,
+        Ok(None) => return Ok(()),
+        // nothing to check against when fully offline
+        Err(_) if config.sign_only => return Ok(()),
+        Err(err) => return Err(err),
+    };
+    let members = config
+        .multisigner_pubkeys
+        .iter()
+        .filter(|signer| multisig_info.signers.contains(**signer))
+        .count();
+    if members < multisig_info.m as usize {
+        let message = format!(
+            "Multisig {} requires {} signers, but only {} of the given multisig signers \
+             are its members",
+            multisig_info.address, multisig_info.m, members
+        );
+        if config.strict {
+            return Err(format!("Error: {}", message).into());
+        }
+        eprintln!("Warning: {}", message);
+    }
+    Ok(())
+}
+
+// The compute units the instructions consume in a simulation, plus `margin` percent. The
+// simulation runs with the maximum limit, so that it can't fail for lack of compute units.
+async fn simulate_compute_unit_limit(
+    config: &Config<'_>,
+    fee_payer: &Pubkey,
+    instructions: &[Instruction],
+    recent_blockhash: &Hash,
+    margin: u32,
+) -> Result<u32, Error> {
+    let mut simulated_instructions = vec![ComputeBudgetInstruction::set_compute_unit_limit(
+        MAX_COMPUTE_UNIT_LIMIT,
+    )];
+    simulated_instructions.extend(instructions.iter().cloned());
+    let message =
+        new_message_with_fee_payer(config, fee_payer, simulated_instructions, recent_blockhash);
+    let result = config
+        .rpc_client
+        .simulate_transaction_with_config(
+            &Transaction::new_unsigned(message),
+            RpcSimulateTransactionConfig {
+                sig_verify: false,
+                ..RpcSimulateTransactionConfig::default()
+            },
+        )
+        .await?
+        .value;
+    if let Some(err) = result.err {
+        return Err(format!("Error: Simulation failed: {}", err).into());
+    }
+    let units_consumed = result
+        .units_consumed
+        .ok_or("Error: The RPC node did not report the compute units consumed")?;
+    let compute_unit_limit = units_consumed.saturating_mul(100 + margin as u64) / 100;
+    Ok(compute_unit_limit.min(MAX_COMPUTE_UNIT_LIMIT as u64) as u32)
+}
+
+async fn handle_tx<'a>(
+    signer_info: &CliSignerInfo,
+    config: &Config<'a>,
+    no_wait: bool,
+    minimum_balance_for_rent_exemption: u64,
+    instructions: Vec<Instruction>,
+) -> Result<TransactionReturnData, Error> {
+    handle_tx_with_progress(
+        signer_info,
+        config,
+        &config.fee_payer,
+        no_wait,
+        minimum_balance_for_rent_exemption,
+        instructions,
+        &Progress::hidden(),
+    )
+    .await
+}
+
+// Like `handle_tx`, for one of many transactions, which may have its own fee payer; a visible
+// progress bar replaces the spinner that is otherwise shown while waiting for the confirmation
+async fn handle_tx_with_progress<'a>(
+    signer_info: &CliSignerInfo,
+    config: &Config<'a>,
+    fee_payer: &Pubkey,
+    no_wait: bool,
+    minimum_balance_for_rent_exemption: u64,
+    instructions: Vec<Instruction>,
+    progress: &Progress,
+) -> Result<TransactionReturnData, Error> {
+    let no_wait = no_wait || config.no_wait;
+    let input_transaction = config
+        .input_file
+        .as_ref()
+        .map(|path| read_transaction_file(path))
+        .transpose()?;
+    let recent_blockhash = if let Some(input_transaction) = &input_transaction {
+        input_transaction.message.recent_blockhash
+    } else if let Some(blockhash) = config.blockhash {
+        blockhash
+    } else if let Some(nonce_account) = &config.nonce_account {
+        get_nonce_blockhash(config, nonce_account).await?
+    } else {
+        config.rpc_client.get_latest_blockhash().await?
+    };
+    let mut instructions = instructions;
+    // Offline signers can't simulate, and the message of an input transaction is already final
+    if let Some(margin) = config.compute_unit_margin {
+        if !config.sign_only && input_transaction.is_none() {
+            let compute_unit_limit = simulate_compute_unit_limit(
+                config,
+                fee_payer,
+                &instructions,
+                &recent_blockhash,
+                margin,
+            )
+            .await?;
+            instructions.insert(
+                0,
+                ComputeBudgetInstruction::set_compute_unit_limit(compute_unit_limit),
+            );
+        }
+    }
+    let message = new_message_with_fee_payer(config, fee_payer, instructions, &recent_blockhash);
+    check_multisig_threshold(config, &message).await?;
+
+    if !config.sign_only && config.fee_payer_balance_check {
+        let fee = config.rpc_client.get_fee_for_message(&message).await?;
+        check_balance_of_fee_payer(config, fee_payer, minimum_balance_for_rent_exemption + fee)
+            .await?;
+    }
+
+    let signers = signer_info.signers_for_message(&message);
+    let mut transaction = if let Some(input_transaction) = input_transaction {
+        if input_transaction.message != message {
+            return Err(format!(
+                "Transaction in {} does not match the requested command",
+                config.input_file.as_ref().unwrap()
+            )
+            .into());
+        }
+        input_transaction
+    } else {
+        Transaction::new_unsigned(message)
+    };
+
+    if config.sign_only {
+        partial_sign_transaction(config, &mut transaction, &signers, recent_blockhash)?;
+        if let Some(output_file) = &config.output_file {
+            write_transaction_file(output_file, &transaction)?;
+        }
+        let sign_only_data = return_signers_data(
+            &transaction,
+            &ReturnSignersConfig {
+                dump_transaction_message: config.dump_transaction_message,
+            },
+        );
+        // Only describes the message, the signed bytes stay exactly as above
+        let (accounts, instructions) = if config.dump_transaction_message {
+            (
+                label_accounts(&transaction.message),
+                decode_transaction(&transaction).instructions,
+            )
+        } else {
+            (vec![], vec![])
+        };
+        // The fee payer always signs first, so the relayer's signature goes in the first slot,
+        // which `partial_sign_transaction` leaves empty for the null signer standing in for it.
+        // The others are over the message as it is, so the relayer must not change any of it.
+        let relayer_transaction = if config.relayer_sign_only {
+            Some(base64::encode(bincode::serialize(&transaction)?))
+        } else {
+            None
+        };
+        Ok(TransactionReturnData::CliSignOnlyData(
+            CliSignOnlyTransaction {
+                sign_only_data,
+                accounts,
+                instructions,
+                relayer_transaction,
+            },
+        ))
+    } else {
+        if config.input_file.is_some() {
+            partial_sign_transaction(config, &mut transaction, &signers, recent_blockhash)?;
+            if !transaction.is_signed() {
+                return Err(
+                    "Transaction is missing signatures; add them with --sign-only first".into(),
+                );
+            }
+        } else {
+            config.with_sign_attempts(
+                || transaction.try_sign(&signers, recent_blockhash),
+                is_retriable_sign_error,
+            )?;
+        }
+        if let Some(output_file) = &config.output_file {
+            write_transaction_file(output_file, &transaction)?;
+        }
+        if config.simulate {
+            let result = config
+                .rpc_client
+                .simulate_transaction(&transaction)
+                .await?
+                .value;
+            return Ok(TransactionReturnData::CliSimulation(CliSimulation {
+                result,
+            }));
+        }
+        progress.sent();
+        let mut retries = 0;
+        let signature = loop {
+            let result = if no_wait {
+                config.rpc_client.send_transaction(&transaction).await
+            } else if progress.is_hidden() {
+                config
+                    .rpc_client
+                    .send_and_confirm_transaction_with_spinner(&transaction)
+                    .await
+            } else {
+                config
+                    .rpc_client
+                    .send_and_confirm_transaction(&transaction)
+                    .await
+            };
+            match result {
+                Err(err) if retries < config.rpc_max_retries && is_transient_error(&err) => {
+                    if err.get_transaction_error() == Some(TransactionError::BlockhashNotFound) {
+                        // A transaction signed elsewhere is stuck with its blockhash
+                        if config.input_file.is_some() {
+                            return Err(err.into());
+                        }
+                        if let Some(blockhash) = config.blockhash {
+                            return Err(format!(
+                                "Blockhash {} is unknown to the cluster, it may have expired; \
+                                 leave out `--blockhash` to use a recent one",
+                                blockhash
+                            )
+                            .into());
+                        }
+                        // A nonce is only advanced by a transaction using it, most likely an
+                        // earlier attempt at this one that landed after all. Signing again with
+                        // the new nonce would make a second, valid transaction.
+                        let previous_signature = transaction.signatures[0];
+                        if config.nonce_account.is_some() {
+                            return Err(format!(
+                                "Nonce transaction {} could not be confirmed: {}. It may have \
+                                 landed already, check it with `spl-token confirm {}` before \
+                                 sending it again",
+                                previous_signature, err, previous_signature
+                            )
+                            .into());
+                        }
+                        // An earlier attempt that timed out may still have landed, in which case
+                        // this is done. Otherwise its blockhash is gone, and the transaction can
+                        // no longer land, so it is safe to sign it again.
+                        match config
+                            .rpc_client
+                            .get_signature_status_with_commitment(
+                                &previous_signature,
+                                config.commitment,
+                            )
+                            .await?
+                        {
+                            Some(Ok(())) => break previous_signature,
+                            Some(Err(err)) => return Err(ClientError::from(err).into()),
+                            None => {}
+                        }
+                        let recent_blockhash = config.rpc_client.get_latest_blockhash().await?;
+                        config.with_sign_attempts(
+                            || transaction.try_sign(&signers, recent_blockhash),
+                            is_retriable_sign_error,
+                        )?;
+                    }
+                    let delay = RPC_RETRY_BASE_DELAY * 2u32.pow(retries);
+                    retries += 1;
+                    progress.eprintln(format!(
+                        "{}; retrying in {}ms ({}/{})",
+                        err,
+                        delay.as_millis(),
+                        retries,
+                        config.rpc_max_retries
+                    ));
+                    tokio::time::sleep(delay).await;
+                }
+                result => break result?,
+            }
+        };
+        if config.no_wait {
+            progress.eprintln(format!(
+                "Warning: Transaction {} was sent but not confirmed, it may still fail",
+                signature
+            ));
+        }
+        Ok(TransactionReturnData::CliSignature(CliSignature {
+            signature: signature.to_string(),
+        }))
+    }
+}
+
+// Send the independent transactions of a batch without waiting for each one to be confirmed
+// before sending the next, then wait for all of them together. Returns one result per
+// transaction, in order. Once a transaction can't be sent the rest of the batch isn't either,
+// while a transaction failing on chain only fails itself.
+async fn handle_txs<'a>(
+    signer_info: &CliSignerInfo,
+    config: &Config<'a>,
+    transactions: Vec<(Vec<Instruction>, u64)>,
+    progress: &Progress,
+) -> Vec<Result<TransactionReturnData, Error>> {
+    let transactions = transactions
+        .into_iter()
+        .map(|(instructions, lamports)| (config.fee_payer, instructions, lamports))
+        .collect();
+    handle_txs_with_fee_payers(signer_info, config, transactions, progress).await
+}
+
+// Like `handle_txs`, with the fee payer of each transaction given alongside it
+async fn handle_txs_with_fee_payers<'a>(
+    signer_info: &CliSignerInfo,
+    config: &Config<'a>,
+    transactions: Vec<(Pubkey, Vec<Instruction>, u64)>,
+    progress: &Progress,
+) -> Vec<Result<TransactionReturnData, Error>> {
+    let sequential = transactions.len() == 1 || config.sign_only || config.simulate;
+    let mut results = vec![];
+    let mut pending = vec![];
+    for (fee_payer, instructions, lamports) in transactions {
+        if matches!(results.last(), Some(Err(_))) {
+            results.push(Err("Not sent".into()));
+            continue;
+        }
+        let result = handle_tx_with_progress(
+            signer_info,
+            config,
+            &fee_payer,
+            !sequential,
+            lamports,
+            instructions,
+            progress,
+        )
+        .await;
+        match &result {
+            Ok(TransactionReturnData::CliSignature(signature))
+                if !sequential && !config.no_wait =>
+            {
+                pending.push((
+                    results.len(),
+                    Signature::from_str(&signature.signature).unwrap(),
+                ));
+            }
+            Ok(_) => progress.confirmed(),
+            Err(_) => {}
+        }
+        results.push(result);
+    }
+
+    let commitment = config.commitment;
+    let deadline = Instant::now() + CONFIRMATION_TIMEOUT;
+    while !pending.is_empty() {
+        tokio::time::sleep(CONFIRMATION_POLL_INTERVAL).await;
+        let mut still_pending = vec![];
+        for chunk in pending.chunks(MAX_GET_SIGNATURE_STATUSES_QUERY_ITEMS) {
+            let signatures = chunk
+                .iter()
+                .map(|(_, signature)| *signature)
+                .collect::<Vec<_>>();
+            let statuses = match config.rpc_client.get_signature_statuses(&signatures).await {
+                Ok(response) => response.value,
+                Err(err) => {
+                    progress.eprintln(format!("Unable to get transaction statuses: {}", err));
+                    vec![None; chunk.len()]
+                }
+            };
+            for ((index, signature), status) in chunk.iter().zip(statuses) {
+                match status {
+                    Some(status) if status.err.is_some() => {
+                        results[*index] = Err(format!(
+                            "Transaction {} failed: {}",
+                            signature,
+                            status.err.unwrap()
+                        )
+                        .into());
+                    }
+                    Some(status) if status.satisfies_commitment(commitment) => {
+                        progress.confirmed();
+                    }
+                    _ => still_pending.push((*index, *signature)),
+                }
+            }
+        }
+        pending = still_pending;
+        if !pending.is_empty() && Instant::now() > deadline {
+            for (index, signature) in pending.drain(..) {
+                results[index] = Err(format!(
+                    "Transaction {} was not confirmed within {}s",
+                    signature,
+                    CONFIRMATION_TIMEOUT.as_secs()
+                )
+                .into());
+            }
+        }
+    }
+    results
+}
+
+// Split the results of a batch that can't be signed offline into its signatures and simulations,
+// plus one line for each transaction that didn't go through
+fn split_batch_results(
+    results: Vec<Result<TransactionReturnData, Error>>,
+) -> (Vec<String>, Vec<CliSimulation>, Vec<String>) {
+    let mut signatures = vec![];
+    let mut simulations = vec![];
+    let mut failures = vec![];
+    for result in results {
+        match result {
+            Ok(TransactionReturnData::CliSignature(signature)) => {
+                signatures.push(signature.signature);
+            }
+            Ok(TransactionReturnData::CliSignOnlyData(_)) => unreachable!(),
+            Ok(TransactionReturnData::CliSimulation(simulation)) => {
+                simulations.push(simulation);
+            }
+            Err(err) => failures.push(err.to_string()),
+        }
+    }
+    (signatures, simulations, failures)
+}
+
+// The output of a batch is shown even when part of it failed, so that it's known which
+// transactions did go through
+fn finish_batch(output: String, failures: Vec<String>) -> CommandResult {
+    if failures.is_empty() {
+        return Ok(output);
+    }
+    Err(TokenCliError::Incomplete {
+        output,
+        message: format!(
+            "Error: {} transaction(s) of the batch failed:\n  {}",
+            failures.len(),
+            failures.join("\n  ")
+        ),
+    }
+    .into())
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        serial_test::parallel,
+        solana_sdk::{
+            bpf_loader,
+            signature::{write_keypair_file, Keypair, Signer},
+        },
+        solana_test_validator::{ProgramInfo, TestValidator, TestValidatorGenesis},
+        std::{env, io::Write, path::PathBuf},
+        tempfile::NamedTempFile,
+    };
+
+    fn clone_keypair(keypair: &Keypair) -> Keypair {
+        Keypair::from_bytes(&keypair.to_bytes()).unwrap()
+    }
+
+    const TEST_DECIMALS: u8 = 0;
+
+    async fn new_validator_for_test() -> (TestValidator, Keypair) {
+        solana_logger::setup();
+        let mut test_validator_genesis = TestValidatorGenesis::default();
+        test_validator_genesis.add_programs_with_path(&[
+            ProgramInfo {
+                program_id: spl_token::id(),
+                loader: bpf_loader::id(),
+                program_path: PathBuf::from("../../target/deploy/spl_token.so"),
+            },
+            ProgramInfo {
+                program_id: spl_associated_token_account::id(),
+                loader: bpf_loader::id(),
+                program_path: PathBuf::from("../../target/deploy/spl_associated_token_account.so"),
+            },
+            ProgramInfo {
+                program_id: spl_token_2022::id(),
+                loader: bpf_loader::id(),
+                program_path: PathBuf::from("../../target/deploy/spl_token_2022.so"),
+            },
+        ]);
+        test_validator_genesis.start_async().await
+    }
+
+    fn test_config<'a>(
+        test_validator: &TestValidator,
+        payer: &Keypair,
+        program_id: &Pubkey,
+    ) -> Config<'a> {
+        let websocket_url = test_validator.rpc_pubsub_url();
+        let rpc_client = Arc::new(test_validator.get_async_rpc_client());
+        Config {
+            commitment: rpc_client.commitment(),
+            rpc_client,
+            websocket_url,
+            output_format: OutputFormat::JsonCompact,
+            json_lines: false,
+            fee_payer: payer.pubkey(),
+            default_keypair: KeypairOrPath::Keypair(clone_keypair(payer)),
+            nonce_account: None,
+            nonce_authority: None,
+            blockhash: None,
+            sign_only: false,
+            simulate: false,
+            fee_payer_balance_check: true,
+            rpc_max_retries: 0,
+            strict: false,
+            quiet: false,
+            no_wait: false,
+            memo: None,
+            dump_transaction_message: false,
+            output_file: None,
+            input_file: None,
+            compute_unit_limit: None,
+            compute_unit_price: None,
+            compute_unit_margin: None,
+            multisigner_pubkeys: vec![],
+            program_id: *program_id,
+            mint_info_cache: Mutex::new(HashMap::new()),
+            account_source: HashMap::new(),
+            max_sign_attempts: 1,
+            offline: false,
+            relayer_sign_only: false,
+        }
+    }
+
+    async fn do_create_native_mint(config: &Config<'_>, program_id: &Pubkey, payer: &Keypair) {
+        if program_id == &spl_token_2022::id() {
+            let native_mint = spl_token_2022::native_mint::id();
+            if config.rpc_client.get_account(&native_mint).await.is_err() {
+                let transaction = Transaction::new_signed_with_payer(
+                    &[create_native_mint(program_id, &payer.pubkey()).unwrap()],
+                    Some(&payer.pubkey()),
+                    &[payer],
+                    config.rpc_client.get_latest_blockhash().await.unwrap(),
+                );
+                config
+                    .rpc_client
+                    .send_and_confirm_transaction(&transaction)
+                    .await
+                    .unwrap();
+            }
+        }
+    }
+
+    async fn create_token(config: &Config<'_>, payer: &Keypair) -> Pubkey {
+        let token = Keypair::new();
+        let token_pubkey = token.pubkey();
+        let bulk_signers: Vec<Box<dyn Signer>> =
+            vec![Box::new(clone_keypair(payer)), Box::new(token)];
+
+        command_create_token(
+            config,
+            TEST_DECIMALS,
+            token_pubkey,
+            payer.pubkey(),
+            false,
+            vec![],
+            bulk_signers,
+        )
+        .await
+        .unwrap();
+        token_pubkey
+    }
+
+    async fn create_token_with_extensions(
+        config: &Config<'_>,
+        payer: &Keypair,
+        token: Keypair,
+        extension_types: &[ExtensionType],
+        extension_instructions: Vec<Instruction>,
+    ) -> Pubkey {
+        let space = ExtensionType::get_account_len::<Mint>(extension_types);
+        let lamports = config
+            .rpc_client
+            .get_minimum_balance_for_rent_exemption(space)
+            .await
+            .unwrap();
+        let mut instructions = vec![system_instruction::create_account(
+            &payer.pubkey(),
+            &token.pubkey(),
+            lamports,
+            space as u64,
+            &config.program_id,
+        )];
+        instructions.extend(extension_instructions);
+        instructions.push(
+            initialize_mint(
+                &config.program_id,
+                &token.pubkey(),
+                &payer.pubkey(),
+                None,
+                TEST_DECIMALS,
+            )
+            .unwrap(),
+        );
+        let transaction = Transaction::new_signed_with_payer(
+            &instructions,
+            Some(&payer.pubkey()),
+            &[payer, &token],
+            config.rpc_client.get_latest_blockhash().await.unwrap(),
+        );
+        config
+            .rpc_client
+            .send_and_confirm_transaction(&transaction)
+            .await
+            .unwrap();
+        token.pubkey()
+    }
+
+    async fn create_token_with_transfer_fee(
+        config: &Config<'_>,
+        payer: &Keypair,
+        transfer_fee_basis_points: u16,
+        maximum_fee: u64,
+    ) -> Pubkey {
+        let token = Keypair::new();
+        let instruction =
+            spl_token_2022::extension::transfer_fee::instruction::initialize_transfer_fee_config(
+                &config.program_id,
+                &token.pubkey(),
+                Some(&payer.pubkey()),
+                Some(&payer.pubkey()),
+                transfer_fee_basis_points,
+                maximum_fee,
+            )
+            .unwrap();
+        create_token_with_extensions(
+            config,
+            payer,
+            token,
+            &[ExtensionType::TransferFeeConfig],
+            vec![instruction],
+        )
+        .await
+    }
+
+    async fn create_confidential_token(config: &Config<'_>, payer: &Keypair) -> Pubkey {
+        let token = Keypair::new();
+        let instruction = confidential_transfer::instruction::initialize_mint(
+            &config.program_id,
+            &token.pubkey(),
+            &ConfidentialTransferMint {
+                authority: payer.pubkey(),
+                auto_approve_new_accounts: true.into(),
+                ..ConfidentialTransferMint::default()
+            },
+        )
+        .unwrap();
+        create_token_with_extensions(
+            config,
+            payer,
+            token,
+            &[ExtensionType::ConfidentialTransferMint],
+            vec![instruction],
+        )
+        .await
+    }
+
+    async fn create_auxiliary_account(
+        config: &Config<'_>,
+        payer: &Keypair,
+        mint: Pubkey,
+    ) -> Pubkey {
+        let auxiliary = Keypair::new();
+        let address = auxiliary.pubkey();
+        let bulk_signers: Vec<Box<dyn Signer>> =
+            vec![Box::new(clone_keypair(payer)), Box::new(auxiliary)];
+        command_create_account(
+            config,
+            mint,
+            payer.pubkey(),
+            Some(address),
+            false,
+            false,
+            None,
+            bulk_signers,
+        )
+        .await
+        .unwrap();
+        address
+    }
+
+    async fn create_associated_account(
+        config: &Config<'_>,
+        payer: &Keypair,
+        mint: Pubkey,
+    ) -> Pubkey {
+        let bulk_signers: Vec<Box<dyn Signer>> = vec![Box::new(clone_keypair(payer))];
+        command_create_account(
+            config,
+            mint,
+            payer.pubkey(),
+            None,
+            false,
+            false,
+            None,
+            bulk_signers,
+        )
+        .await
+        .unwrap();
+        get_associated_token_address_with_program_id(&payer.pubkey(), &mint, &config.program_id)
+    }
+
+    async fn mint_tokens(
+        config: &Config<'_>,
+        payer: &Keypair,
+        mint: Pubkey,
+        ui_amount: f64,
+        recipient: Pubkey,
+    ) {
+        let bulk_signers: Vec<Box<dyn Signer>> = vec![Box::new(clone_keypair(payer))];
+        command_mint(
+            config,
+            mint,
+            ui_amount,
+            recipient,
+            None,
+            false,
+            MintInfo {
+                program_id: config.program_id,
+                address: mint,
+                decimals: TEST_DECIMALS,
+            },
+            payer.pubkey(),
+            false,
+            false,
+            bulk_signers,
+        )
+        .await
+        .unwrap();
+    }
+
+    async fn process_test_command(
+        config: &Config<'_>,
+        payer: &Keypair,
+        args: &[&str],
+    ) -> CommandResult {
+        let default_decimals = format!("{}", spl_token_2022::native_mint::DECIMALS);
+        let default_program_id = spl_token::id().to_string();
+        let minimum_signers_help = minimum_signers_help_string();
+        let multisig_member_help = multisig_member_help_string();
+
+        let app_matches = app(
+            &default_decimals,
+            &default_program_id,
+            &minimum_signers_help,
+            &multisig_member_help,
+        )
+        .get_matches_from(args);
+        let (sub_command, sub_matches) = app_matches.subcommand();
+        let sub_command = CommandName::from_str(sub_command).unwrap();
+        let matches = sub_matches.unwrap();
+
+        let wallet_manager = None;
+        let bulk_signers: Vec<Box<dyn Signer>> = vec![Box::new(clone_keypair(payer))];
+        process_command(&sub_command, matches, config, wallet_manager, bulk_signers).await
+    }
+
+    #[tokio::test]
+    #[parallel(one)]
+    async fn create_token_default() {
+        let (test_validator, payer) = new_validator_for_test().await;
+        for program_id in [spl_token::id(), spl_token_2022::id()] {
+            let config = test_config(&test_validator, &payer, &program_id);
+            let result = process_test_command(
+                &config,
+                &payer,
+                &["spl-token", CommandName::CreateToken.into()],
+            )
+            .await;
+            let value: serde_json::Value = serde_json::from_str(&result.unwrap()).unwrap();
+            let mint =
+                Pubkey::from_str(value["commandOutput"]["address"].as_str().unwrap()).unwrap();
+            let account = config.rpc_client.get_account(&mint).await.unwrap();
+            assert_eq!(account.owner, program_id);
+        }
+    }
+
+    #[tokio::test]
+    #[parallel(two)]
+    async fn check_account_owner() {
+        let (test_validator, payer) = new_validator_for_test().await;
+        let config = test_config(&test_validator, &payer, &spl_token_2022::id());
+        let token = create_token(&config, &payer).await;
+        let account = create_auxiliary_account(&config, &payer, token).await;
+
+        config
+            .check_account_with_owner(&account, Some(token), Some(payer.pubkey()))
+            .await
+            .unwrap();
+        let stranger = Pubkey::new_unique();
+        let err = config
+            .check_account_with_owner(&account, Some(token), Some(stranger))
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "Token account {} is owned by {}, not {}",
+                account,
+                payer.pubkey(),
+                stranger
+            )
+        );
+        // without an expected owner, anyone's account passes as before
+        config.check_account(&account, Some(token)).await.unwrap();
+    }
+
+    #[tokio::test]
+    #[parallel(two)]
+    async fn mint_info_errors() {
+        let (test_validator, payer) = new_validator_for_test().await;
+        let config = test_config(&test_validator, &payer, &spl_token_2022::id());
+        let token = create_token(&config, &payer).await;
+        let account = create_associated_account(&config, &payer, token).await;
+
+        let missing = Pubkey::new_unique();
+        let err = config.get_mint_info(&missing, None).await.unwrap_err();
+        assert!(matches!(err, TokenCliError::MintNotFound(mint) if mint == missing));
+        assert_eq!(
+            err.to_string(),
+            format!("Mint account {} not found", missing)
+        );
+
+        let err = config.get_mint_info(&account, None).await.unwrap_err();
+        assert!(matches!(err, TokenCliError::InvalidMint(mint) if mint == account));
+        assert_eq!(
+            err.to_string(),
+            format!("Account {} is not a valid mint", account)
+        );
+
+        let err = config
+            .get_mint_info(&payer.pubkey(), None)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, TokenCliError::WrongProgramOwner { .. }));
+        assert!(err.to_string().contains("is owned by"));
+
+        let err = config
+            .get_mint_info(&token, Some(TEST_DECIMALS + 1))
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            TokenCliError::DecimalsMismatch {
+                decimals: TEST_DECIMALS,
+                ..
+            }
+        ));
+    }
+
+    #[tokio::test]
+    #[parallel(two)]
+    async fn create_token_at_existing_address() {
+        let (test_validator, payer) = new_validator_for_test().await;
+        let config = test_config(&test_validator, &payer, &spl_token::id());
+        let err = command_create_token(
+            &config,
+            TEST_DECIMALS,
+            payer.pubkey(),
+            payer.pubkey(),
+            false,
+            vec![],
+            vec![Box::new(clone_keypair(&payer))],
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!("Error: Account already exists: {}", payer.pubkey())
+        );
+    }
+
+    #[tokio::test]
+    #[parallel(two)]
+    async fn create_token_dry_run() {
+        let (test_validator, payer) = new_validator_for_test().await;
+        let config = test_config(&test_validator, &payer, &spl_token_2022::id());
+        let result = process_test_command(
+            &config,
+            &payer,
+            &[
+                "spl-token",
+                CommandName::CreateToken.into(),
+                "--enable-non-transferable",
+                "--dry-run",
+            ],
+        )
+        .await;
+        let value: serde_json::Value = serde_json::from_str(&result.unwrap()).unwrap();
+        let space = ExtensionType::get_account_len::<Mint>(&[ExtensionType::NonTransferable]);
+        assert_eq!(value["space"], space);
+        assert_eq!(
+            value["rentExemptLamports"],
+            config
+                .rpc_client
+                .get_minimum_balance_for_rent_exemption(space)
+                .await
+                .unwrap()
+        );
+        assert_eq!(value["extensions"][0], "NonTransferable");
+    }
+
+    #[tokio::test]
+    #[parallel(two)]
+    async fn create_token_non_transferable() {
+        let (test_validator, payer) = new_validator_for_test().await;
+        let config = test_config(&test_validator, &payer, &spl_token_2022::id());
+        let result = process_test_command(
+            &config,
+            &payer,
+            &[
+                "spl-token",
+                CommandName::CreateToken.into(),
+                "--enable-non-transferable",
+            ],
+        )
+        .await;
+        let value: serde_json::Value = serde_json::from_str(&result.unwrap()).unwrap();
+        let token = Pubkey::from_str(value["commandOutput"]["address"].as_str().unwrap()).unwrap();
+        let account = config.rpc_client.get_account(&token).await.unwrap();
+        let mint = StateWithExtensionsOwned::<Mint>::unpack(account.data).unwrap();
+        assert_eq!(
+            mint.get_extension_types().unwrap(),
+            vec![ExtensionType::NonTransferable]
+        );
+
+        let source = create_associated_account(&config, &payer, token).await;
+        let destination = create_auxiliary_account(&config, &payer, token).await;
+        mint_tokens(&config, &payer, token, 10.0, source).await;
+        process_test_command(
+            &config,
+            &payer,
+            &[
+                "spl-token",
+                CommandName::Transfer.into(),
+                &token.to_string(),
+                "1",
+                &destination.to_string(),
+            ],
+        )
+        .await
+        .unwrap_err();
+
+        // the original token program has no extensions
+        let config = test_config(&test_validator, &payer, &spl_token::id());
+        process_test_command(
+            &config,
+            &payer,
+            &[
+                "spl-token",
+                CommandName::CreateToken.into(),
+                "--enable-non-transferable",
+            ],
+        )
+        .await
+        .unwrap_err();
+    }
+
+    #[tokio::test]
+    #[parallel(two)]
+    async fn default_account_state() {
+        let (test_validator, payer) = new_validator_for_test().await;
+        let config = test_config(&test_validator, &payer, &spl_token_2022::id());
+        let result = process_test_command(
+            &config,
+            &payer,
+            &[
+                "spl-token",
+                CommandName::CreateToken.into(),
+                "--enable-freeze",
+                "--default-account-state",
+                "frozen",
+            ],
+        )
+        .await;
+        let value: serde_json::Value = serde_json::from_str(&result.unwrap()).unwrap();
+        let token = Pubkey::from_str(value["commandOutput"]["address"].as_str().unwrap()).unwrap();
+        let account = create_associated_account(&config, &payer, token).await;
+        let account = config.rpc_client.get_account(&account).await.unwrap();
+        let token_account = StateWithExtensionsOwned::<Account>::unpack(account.data).unwrap();
+        assert_eq!(token_account.base.state, AccountState::Frozen);
+
+        process_test_command(
+            &config,
+            &payer,
+            &[
+                "spl-token",
+                CommandName::UpdateDefaultAccountState.into(),
+                &token.to_string(),
+                "initialized",
+            ],
+        )
+        .await
+        .unwrap();
+        let account = create_auxiliary_account(&config, &payer, token).await;
+        let account = config.rpc_client.get_account(&account).await.unwrap();
+        let token_account = StateWithExtensionsOwned::<Account>::unpack(account.data).unwrap();
+        assert_eq!(token_account.base.state, AccountState::Initialized);
+
+        let plain_token = create_token(&config, &payer).await;
+        process_test_command(
+            &config,
+            &payer,
+            &[
+                "spl-token",
+                CommandName::UpdateDefaultAccountState.into(),
+                &plain_token.to_string(),
+                "frozen",
+            ],
+        )
+        .await
+        .unwrap_err();
+    }
+
+    #[tokio::test]
+    #[parallel(two)]
+    async fn interest_bearing() {
+        let (test_validator, payer) = new_validator_for_test().await;
+        let config = test_config(&test_validator, &payer, &spl_token_2022::id());
+        let result = process_test_command(
+            &config,
+            &payer,
+            &[
+                "spl-token",
+                CommandName::CreateToken.into(),
+                "--interest-rate",
+                "500",
+            ],
+        )
+        .await;
+        let value: serde_json::Value = serde_json::from_str(&result.unwrap()).unwrap();
+        let token = Pubkey::from_str(value["commandOutput"]["address"].as_str().unwrap()).unwrap();
+        let account = create_associated_account(&config, &payer, token).await;
+        mint_tokens(&config, &payer, token, 100.0, account).await;
+
+        process_test_command(
+            &config,
+            &payer,
+            &[
+                "spl-token",
+                CommandName::UpdateInterestRate.into(),
+                &token.to_string(),
+                "-250",
+            ],
+        )
+        .await
+        .unwrap();
+        let mint_account = config.rpc_client.get_account(&token).await.unwrap();
+        let mint = StateWithExtensionsOwned::<Mint>::unpack(mint_account.data).unwrap();
+        let extension = mint.get_extension::<InterestBearingConfig>().unwrap();
+        assert_eq!(i16::from(extension.current_rate), -250);
+        assert_eq!(
+            Option::<Pubkey>::from(extension.rate_authority),
+            Some(payer.pubkey())
+        );
+
+        let result = process_test_command(
+            &config,
+            &payer,
+            &["spl-token", CommandName::Balance.into(), &token.to_string()],
+        )
+        .await;
+        let value: serde_json::Value = serde_json::from_str(&result.unwrap()).unwrap();
+        assert_eq!(value["amount"], "100");
+        assert!(value["uiAmountWithInterest"].is_string());
+
+        let plain_token = create_token(&config, &payer).await;
+        process_test_command(
+            &config,
+            &payer,
+            &[
+                "spl-token",
+                CommandName::UpdateInterestRate.into(),
+                &plain_token.to_string(),
+                "100",
+            ],
+        )
+        .await
+        .unwrap_err();
+    }
+
+    #[tokio::test]
+    #[parallel(two)]
+    async fn convert_amounts() {
+        let (test_validator, payer) = new_validator_for_test().await;
+        let config = test_config(&test_validator, &payer, &spl_token_2022::id());
+        let plain_token = create_token(&config, &payer).await;
+        // a zero rate accrues nothing, so the simulated conversion must match the plain one
+        let result = process_test_command(
+            &config,
+            &payer,
+            &[
+                "spl-token",
+                CommandName::CreateToken.into(),
+                "--interest-rate",
+                "0",
+            ],
+        )
+        .await;
+        let value: serde_json::Value = serde_json::from_str(&result.unwrap()).unwrap();
+        let interest_token =
+            Pubkey::from_str(value["commandOutput"]["address"].as_str().unwrap()).unwrap();
+
+        for token in [plain_token, interest_token] {
+            let result = process_test_command(
+                &config,
+                &payer,
+                &[
+                    "spl-token",
+                    CommandName::AmountToUiAmount.into(),
+                    &token.to_string(),
+                    "42",
+                ],
+            )
+            .await;
+            let value: serde_json::Value = serde_json::from_str(&result.unwrap()).unwrap();
+            assert_eq!(value["uiAmount"], "42");
+
+            let result = process_test_command(
+                &config,
+                &payer,
+                &[
+                    "spl-token",
+                    CommandName::UiAmountToAmount.into(),
+                    &token.to_string(),
+                    "42",
+                ],
+            )
+            .await;
+            let value: serde_json::Value = serde_json::from_str(&result.unwrap()).unwrap();
+            assert_eq!(value["amount"], "42");
+        }
+    }
+
+    #[tokio::test]
+    #[parallel(two)]
+    async fn transfer_with_memo() {
+        let (test_validator, payer) = new_validator_for_test().await;
+        let mut config = test_config(&test_validator, &payer, &spl_token_2022::id());
+        let token = create_token(&config, &payer).await;
+        let source = create_associated_account(&config, &payer, token).await;
+        let destination = create_auxiliary_account(&config, &payer, token).await;
+        mint_tokens(&config, &payer, token, 10.0, source).await;
+
+        config.memo = Some("deposit 42".to_string());
+        let token_instruction = transfer(
+            &spl_token_2022::id(),
+            &source,
+            &destination,
+            &payer.pubkey(),
+            &[],
+            1,
+        )
+        .unwrap();
+        let message = new_message(
+            &config,
+            vec![
+                system_instruction::transfer(&payer.pubkey(), &destination, 1),
+                token_instruction,
+            ],
+            &Hash::default(),
+        );
+        assert_eq!(message.instructions.len(), 3);
+        let memo_instruction = &message.instructions[1];
+        assert_eq!(
+            message.account_keys[memo_instruction.program_id_index as usize],
+            spl_memo::id()
+        );
+        assert_eq!(memo_instruction.data, b"deposit 42");
+
+        // each transfer of a batch is preceded by its own memo
+        let token_instruction = transfer(
+            &spl_token_2022::id(),
+            &source,
+            &destination,
+            &payer.pubkey(),
+            &[],
+            1,
+        )
+        .unwrap();
+        let message = new_message(
+            &config,
+            vec![token_instruction.clone(), token_instruction],
+            &Hash::default(),
+        );
+        assert_eq!(message.instructions.len(), 4);
+        for memo_instruction in [&message.instructions[0], &message.instructions[2]] {
+            assert_eq!(
+                message.account_keys[memo_instruction.program_id_index as usize],
+                spl_memo::id()
+            );
+        }
+
+        process_test_command(
+            &config,
+            &payer,
+            &[
+                "spl-token",
+                CommandName::Transfer.into(),
+                &token.to_string(),
+                "1",
+                &destination.to_string(),
+            ],
+        )
+        .await
+        .unwrap();
+        let account = config.rpc_client.get_account(&destination).await.unwrap();
+        let token_account = StateWithExtensionsOwned::<Account>::unpack(account.data).unwrap();
+        assert_eq!(token_account.base.amount, 1);
+    }
+
+    #[tokio::test]
+    #[parallel(two)]
+    async fn required_transfer_memos() {
+        let (test_validator, payer) = new_validator_for_test().await;
+        let mut config = test_config(&test_validator, &payer, &spl_token_2022::id());
+        let token = create_token(&config, &payer).await;
+        let source = create_auxiliary_account(&config, &payer, token).await;
+        let destination = create_associated_account(&config, &payer, token).await;
+        mint_tokens(&config, &payer, token, 10.0, source).await;
+
+        process_test_command(
+            &config,
+            &payer,
+            &[
+                "spl-token",
+                CommandName::EnableRequiredTransferMemos.into(),
+                &destination.to_string(),
+            ],
+        )
+        .await
+        .unwrap();
+        let result = process_test_command(
+            &config,
+            &payer,
+            &[
+                "spl-token",
+                CommandName::AccountInfo.into(),
+                "--address",
+                &destination.to_string(),
+            ],
+        )
+        .await;
+        let value: serde_json::Value = serde_json::from_str(&result.unwrap()).unwrap();
+        assert_eq!(value["requiredTransferMemos"], true);
+
+        let transfer_args = [
+            "spl-token",
+            CommandName::Transfer.into(),
+            "--from",
+            &source.to_string(),
+            &token.to_string(),
+            "1",
+            &destination.to_string(),
+        ];
+        process_test_command(&config, &payer, &transfer_args)
+            .await
+            .unwrap_err();
+        config.memo = Some("memo".to_string());
+        process_test_command(&config, &payer, &transfer_args)
+            .await
+            .unwrap();
+        config.memo = None;
+
+        process_test_command(
+            &config,
+            &payer,
+            &[
+                "spl-token",
+                CommandName::DisableRequiredTransferMemos.into(),
+                &destination.to_string(),
+            ],
+        )
+        .await
+        .unwrap();
+        process_test_command(&config, &payer, &transfer_args)
+            .await
+            .unwrap();
+        let account = config.rpc_client.get_account(&destination).await.unwrap();
+        let token_account = StateWithExtensionsOwned::<Account>::unpack(account.data).unwrap();
+        assert_eq!(token_account.base.amount, 2);
+    }
+
+    #[tokio::test]
+    #[parallel(two)]
+    async fn harvest_and_withdraw_withheld_tokens() {
+        let (test_validator, payer) = new_validator_for_test().await;
+        let config = test_config(&test_validator, &payer, &spl_token_2022::id());
+        let token = create_token_with_transfer_fee(&config, &payer, 100, 50).await;
+        let source = create_associated_account(&config, &payer, token).await;
+        let destination = create_auxiliary_account(&config, &payer, token).await;
+        mint_tokens(&config, &payer, token, 1000.0, source).await;
+        process_test_command(
+            &config,
+            &payer,
+            &[
+                "spl-token",
+                CommandName::Transfer.into(),
+                &token.to_string(),
+                "100",
+                &destination.to_string(),
+            ],
+        )
+        .await
+        .unwrap();
+
+        let result = process_test_command(
+            &config,
+            &payer,
+            &[
+                "spl-token",
+                CommandName::HarvestWithheldTokensToMint.into(),
+                &token.to_string(),
+                "--all",
+            ],
+        )
+        .await;
+        let value: serde_json::Value = serde_json::from_str(&result.unwrap()).unwrap();
+        assert_eq!(value["amount"], "1");
+        assert_eq!(value["accounts"][0]["address"], destination.to_string());
+
+        let result = process_test_command(
+            &config,
+            &payer,
+            &[
+                "spl-token",
+                CommandName::WithdrawWithheldTokens.into(),
+                &token.to_string(),
+                &source.to_string(),
+            ],
+        )
+        .await;
+        let value: serde_json::Value = serde_json::from_str(&result.unwrap()).unwrap();
+        assert_eq!(value["amount"], "1");
+        let account = config.rpc_client.get_account(&source).await.unwrap();
+        let token_account = StateWithExtensionsOwned::<Account>::unpack(account.data).unwrap();
+        assert_eq!(token_account.base.amount, 901);
+
+        // nothing is left to withdraw
+        process_test_command(
+            &config,
+            &payer,
+            &[
+                "spl-token",
+                CommandName::WithdrawWithheldTokens.into(),
+                &token.to_string(),
+                &source.to_string(),
+            ],
+        )
+        .await
+        .unwrap_err();
+    }
+
+    #[tokio::test]
+    #[parallel(two)]
+    async fn transfer_recipient_kind() {
+        let (test_validator, payer) = new_validator_for_test().await;
+        let config = test_config(&test_validator, &payer, &spl_token_2022::id());
+        let token = create_token(&config, &payer).await;
+        let source = create_associated_account(&config, &payer, token).await;
+        let destination = create_auxiliary_account(&config, &payer, token).await;
+        mint_tokens(&config, &payer, token, 10.0, source).await;
+
+        process_test_command(
+            &config,
+            &payer,
+            &[
+                "spl-token",
+                CommandName::Transfer.into(),
+                &token.to_string(),
+                "1",
+                &destination.to_string(),
+                "--recipient-is-token-account",
+            ],
+        )
+        .await
+        .unwrap();
+        let account = config.rpc_client.get_account(&destination).await.unwrap();
+        let token_account = StateWithExtensionsOwned::<Account>::unpack(account.data).unwrap();
+        assert_eq!(token_account.base.amount, 1);
+
+        // a wallet is not a token account
+        process_test_command(
+            &config,
+            &payer,
+            &[
+                "spl-token",
+                CommandName::Transfer.into(),
+                &token.to_string(),
+                "1",
+                &payer.pubkey().to_string(),
+                "--recipient-is-token-account",
+            ],
+        )
+        .await
+        .unwrap_err();
+
+        process_test_command(
+            &config,
+            &payer,
+            &[
+                "spl-token",
+                CommandName::Transfer.into(),
+                &token.to_string(),
+                "1",
+                &payer.pubkey().to_string(),
+                "--recipient-is-owner",
+            ],
+        )
+        .await
+        .unwrap();
+        let account = config.rpc_client.get_account(&source).await.unwrap();
+        let token_account = StateWithExtensionsOwned::<Account>::unpack(account.data).unwrap();
+        assert_eq!(token_account.base.amount, 9);
+    }
+
+    #[tokio::test]
+    #[parallel(two)]
+    async fn transfer_expected_fee() {
+        let (test_validator, payer) = new_validator_for_test().await;
+        let config = test_config(&test_validator, &payer, &spl_token_2022::id());
+        let token = create_token_with_transfer_fee(&config, &payer, 100, 50).await;
+        let source = create_associated_account(&config, &payer, token).await;
+        let destination = create_auxiliary_account(&config, &payer, token).await;
+        mint_tokens(&config, &payer, token, 1000.0, source).await;
+
+        let token = token.to_string();
+        let destination_address = destination.to_string();
+        let transfer_args = |expected_fee: &'static str| {
+            [
+                "spl-token",
+                CommandName::Transfer.into(),
+                token.as_str(),
+                "100",
+                destination_address.as_str(),
+                "--expected-fee",
+                expected_fee,
+            ]
+        };
+        process_test_command(&config, &payer, &transfer_args("2"))
+            .await
+            .unwrap_err();
+        process_test_command(&config, &payer, &transfer_args("1"))
+            .await
+            .unwrap();
+        let account = config.rpc_client.get_account(&destination).await.unwrap();
+        let token_account = StateWithExtensionsOwned::<Account>::unpack(account.data).unwrap();
+        assert_eq!(token_account.base.amount, 99);
+    }
+
+    #[tokio::test]
+    #[parallel(two)]
+    async fn detect_token_program() {
+        let (test_validator, payer) = new_validator_for_test().await;
+        let default_decimals = format!("{}", spl_token_2022::native_mint::DECIMALS);
+        let default_program_id = spl_token::id().to_string();
+        let minimum_signers_help = minimum_signers_help_string();
+        let multisig_member_help = multisig_member_help_string();
+        for program_id in [spl_token::id(), spl_token_2022::id()] {
+            let config = test_config(&test_validator, &payer, &program_id);
+            let token = create_token(&config, &payer).await;
+            let account = create_associated_account(&config, &payer, token).await;
+            let wallet = Keypair::new().pubkey();
+
+            for (args, expected) in [
+                (
+                    vec!["spl-token", CommandName::Supply.into(), &token.to_string()],
+                    Some(program_id),
+                ),
+                (
+                    vec![
+                        "spl-token",
+                        CommandName::Balance.into(),
+                        "--address",
+                        &account.to_string(),
+                    ],
+                    Some(program_id),
+                ),
+                (
+                    vec![
+                        "spl-token",
+                        CommandName::Accounts.into(),
+                        "--owner",
+                        &wallet.to_string(),
+                    ],
+                    None,
+                ),
+            ] {
+                let app_matches = app(
+                    &default_decimals,
+                    &default_program_id,
+                    &minimum_signers_help,
+                    &multisig_member_help,
+                )
+                .get_matches_from(args);
+                let matches = app_matches.subcommand().1.unwrap();
+                assert_eq!(
+                    detect_program_id(&config.rpc_client, matches).await,
+                    expected
+                );
+            }
+        }
+    }
+
+    #[tokio::test]
+    #[parallel(two)]
+    async fn recreate_ata() {
+        let (test_validator, payer) = new_validator_for_test().await;
+        for program_id in [spl_token::id(), spl_token_2022::id()] {
+            let config = test_config(&test_validator, &payer, &program_id);
+            let token = create_token(&config, &payer).await;
+            let auxiliary = create_auxiliary_account(&config, &payer, token).await;
+            mint_tokens(&config, &payer, token, 100.0, auxiliary).await;
+
+            // the accounts to drain are listed until confirmed
+            let result = process_test_command(
+                &config,
+                &payer,
+                &[
+                    "spl-token",
+                    CommandName::RecreateAta.into(),
+                    &token.to_string(),
+                ],
+            )
+            .await;
+            assert!(result
+                .unwrap_err()
+                .to_string()
+                .contains(&auxiliary.to_string()));
+
+            let result = process_test_command(
+                &config,
+                &payer,
+                &[
+                    "spl-token",
+                    CommandName::RecreateAta.into(),
+                    &token.to_string(),
+                    "--confirm",
+                ],
+            )
+            .await
+            .unwrap();
+            let value: serde_json::Value = serde_json::from_str(&result).unwrap();
+            assert_eq!(value["created"], true);
+            assert_eq!(value["moved"][0]["address"], auxiliary.to_string());
+            assert_eq!(value["moved"][0]["amount"], 100);
+            let associated =
+                get_associated_token_address_with_program_id(&payer.pubkey(), &token, &program_id);
+            let account = config.rpc_client.get_account(&associated).await.unwrap();
+            let token_account = StateWithExtensionsOwned::<Account>::unpack(account.data).unwrap();
+            assert_eq!(token_account.base.amount, 100);
+            let account = config.rpc_client.get_account(&auxiliary).await.unwrap();
+            let token_account = StateWithExtensionsOwned::<Account>::unpack(account.data).unwrap();
+            assert_eq!(token_account.base.amount, 0);
+
+            let result = process_test_command(
+                &config,
+                &payer,
+                &[
+                    "spl-token",
+                    CommandName::RecreateAta.into(),
+                    &token.to_string(),
+                ],
+            )
+            .await
+            .unwrap();
+            let value: serde_json::Value = serde_json::from_str(&result).unwrap();
+            assert_eq!(value["created"], false);
+            assert!(value["moved"].as_array().unwrap().is_empty());
+            assert!(value["signatures"].as_array().unwrap().is_empty());
+
+            // named sources are moved without confirmation, and only those
+            let other = create_auxiliary_account(&config, &payer, token).await;
+            mint_tokens(&config, &payer, token, 10.0, auxiliary).await;
+            mint_tokens(&config, &payer, token, 20.0, other).await;
+            process_test_command(
+                &config,
+                &payer,
+                &[
+                    "spl-token",
+                    CommandName::RecreateAta.into(),
+                    &token.to_string(),
+                    "--from",
+                    &auxiliary.to_string(),
+                ],
+            )
+            .await
+            .unwrap();
+            let account = config.rpc_client.get_account(&associated).await.unwrap();
+            let token_account = StateWithExtensionsOwned::<Account>::unpack(account.data).unwrap();
+            assert_eq!(token_account.base.amount, 110);
+            let account = config.rpc_client.get_account(&other).await.unwrap();
+            let token_account = StateWithExtensionsOwned::<Account>::unpack(account.data).unwrap();
+            assert_eq!(token_account.base.amount, 20);
+        }
+    }
+
+    #[tokio::test]
+    #[parallel(two)]
+    async fn recover_nested_account() {
+        let (test_validator, payer) = new_validator_for_test().await;
+        for program_id in [spl_token::id(), spl_token_2022::id()] {
+            let config = test_config(&test_validator, &payer, &program_id);
+            let owner_token = create_token(&config, &payer).await;
+            let owner_associated = create_associated_account(&config, &payer, owner_token).await;
+            let nested_token = create_token(&config, &payer).await;
+            let bulk_signers: Vec<Box<dyn Signer>> = vec![Box::new(clone_keypair(&payer))];
+            command_create_account(
+                &config,
+                nested_token,
+                owner_associated,
+                None,
+                false,
+                false,
+                None,
+                bulk_signers,
+            )
+            .await
+            .unwrap();
+            let nested = get_associated_token_address_with_program_id(
+                &owner_associated,
+                &nested_token,
+                &program_id,
+            );
+            mint_tokens(&config, &payer, nested_token, 100.0, nested).await;
+
+            process_test_command(
+                &config,
+                &payer,
+                &[
+                    "spl-token",
+                    CommandName::RecoverNested.into(),
+                    &owner_token.to_string(),
+                    &nested_token.to_string(),
+                ],
+            )
+            .await
+            .unwrap();
+            let destination = get_associated_token_address_with_program_id(
+                &payer.pubkey(),
+                &nested_token,
+                &program_id,
+            );
+            let account = config.rpc_client.get_account(&destination).await.unwrap();
+            let token_account = StateWithExtensionsOwned::<Account>::unpack(account.data).unwrap();
+            assert_eq!(token_account.base.amount, 100);
+            assert!(config.rpc_client.get_account(&nested).await.is_err());
+        }
+    }
+
+    #[tokio::test]
+    #[parallel(two)]
+    async fn mint_to_owner() {
+        let (test_validator, payer) = new_validator_for_test().await;
+        for program_id in [spl_token::id(), spl_token_2022::id()] {
+            let config = test_config(&test_validator, &payer, &program_id);
+            let token = create_token(&config, &payer).await;
+            let auxiliary = create_auxiliary_account(&config, &payer, token).await;
+            let owner = Keypair::new().pubkey();
+
+            // A token account receives the tokens itself
+            process_test_command(
+                &config,
+                &payer,
+                &[
+                    "spl-token",
+                    CommandName::Mint.into(),
+                    &token.to_string(),
+                    "10",
+                    &auxiliary.to_string(),
+                ],
+            )
+            .await
+            .unwrap();
+            let account = config.rpc_client.get_account(&auxiliary).await.unwrap();
+            let token_account = StateWithExtensionsOwned::<Account>::unpack(account.data).unwrap();
+            assert_eq!(token_account.base.amount, 10);
+
+            // Any other address owns the receiving associated token account
+            let args = [
+                "spl-token",
+                CommandName::Mint.into(),
+                &token.to_string(),
+                "20",
+                &owner.to_string(),
+            ];
+            process_test_command(&config, &payer, &args)
+                .await
+                .unwrap_err();
+            let mut args = args.to_vec();
+            args.push("--fund-recipient");
+            process_test_command(&config, &payer, &args).await.unwrap();
+            process_test_command(&config, &payer, &args).await.unwrap();
+            let associated =
+                get_associated_token_address_with_program_id(&owner, &token, &program_id);
+            let account = config.rpc_client.get_account(&associated).await.unwrap();
+            let token_account = StateWithExtensionsOwned::<Account>::unpack(account.data).unwrap();
+            assert_eq!(token_account.base.amount, 40);
+        }
+    }
+
+    #[tokio::test]
+    #[parallel(two)]
+    async fn transfer_show_balances() {
+        let (test_validator, payer) = new_validator_for_test().await;
+        for program_id in [spl_token::id(), spl_token_2022::id()] {
+            let config = test_config(&test_validator, &payer, &program_id);
+            let token = create_token(&config, &payer).await;
+            let source = create_associated_account(&config, &payer, token).await;
+            mint_tokens(&config, &payer, token, 100.0, source).await;
+            let recipient = Keypair::new().pubkey();
+
+            let result = process_test_command(
+                &config,
+                &payer,
+                &[
+                    "spl-token",
+                    CommandName::Transfer.into(),
+                    &token.to_string(),
+                    "10",
+                    &recipient.to_string(),
+                    "--fund-recipient",
+                    "--allow-unfunded-recipient",
+                    "--show-balances",
+                ],
+            )
+            .await
+            .unwrap();
+            let value: serde_json::Value = serde_json::from_str(&result).unwrap();
+            let destination =
+                get_associated_token_address_with_program_id(&recipient, &token, &program_id);
+            assert_eq!(
+                value["balances"],
+                serde_json::json!([
+                    {
+                        "address": source.to_string(),
+                        "before": "100",
+                        "after": "90",
+                        "change": "-10",
+                    },
+                    {
+                        "address": destination.to_string(),
+                        "before": null,
+                        "after": "10",
+                        "change": "+10",
+                    },
+                ])
+            );
+            assert!(value["signature"].is_string());
+        }
+    }
+
+    #[tokio::test]
+    #[parallel(two)]
+    async fn transfer_no_wait() {
+        let (test_validator, payer) = new_validator_for_test().await;
+        for program_id in [spl_token::id(), spl_token_2022::id()] {
+            let mut config = test_config(&test_validator, &payer, &program_id);
+            let token = create_token(&config, &payer).await;
+            let source = create_associated_account(&config, &payer, token).await;
+            let destination = create_auxiliary_account(&config, &payer, token).await;
+            mint_tokens(&config, &payer, token, 100.0, source).await;
+
+            config.no_wait = true;
+            let result = process_test_command(
+                &config,
+                &payer,
+                &[
+                    "spl-token",
+                    CommandName::Transfer.into(),
+                    &token.to_string(),
+                    "10",
+                    &destination.to_string(),
+                ],
+            )
+            .await
+            .unwrap();
+            let value: serde_json::Value = serde_json::from_str(&result).unwrap();
+            let signature = Signature::from_str(value["signature"].as_str().unwrap()).unwrap();
+            loop {
+                if let Some(status) = config
+                    .rpc_client
+                    .get_signature_status(&signature)
+                    .await
+                    .unwrap()
+                {
+                    status.unwrap();
+                    break;
+                }
+                tokio::time::sleep(Duration::from_millis(100)).await;
+            }
+            let account = config.rpc_client.get_account(&destination).await.unwrap();
+            let token_account = StateWithExtensionsOwned::<Account>::unpack(account.data).unwrap();
+            assert_eq!(token_account.base.amount, 10);
+        }
+    }
+
+    #[tokio::test]
+    #[parallel(two)]
+    async fn error_kinds() {
+        let (test_validator, payer) = new_validator_for_test().await;
+        let config = test_config(&test_validator, &payer, &spl_token::id());
+        let err = process_test_command(
+            &config,
+            &payer,
+            &[
+                "spl-token",
+                CommandName::Supply.into(),
+                &Pubkey::new_unique().to_string(),
+            ],
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(error_kind(err.as_ref()), CliErrorKind::Validation);
+
+        let err: Error = ClientError::from(std::io::Error::new(
+            std::io::ErrorKind::ConnectionRefused,
+            "connection refused",
+        ))
+        .into();
+        assert_eq!(error_kind(err.as_ref()), CliErrorKind::Rpc);
+
+        let err: Error = ProgramError::InvalidArgument.into();
+        assert_eq!(error_kind(err.as_ref()), CliErrorKind::Program);
+    }
+
+    #[test]
+    fn incomplete_batch_keeps_output() {
+        let output = r#"{"signatures":["a"]}"#.to_string();
+        assert_eq!(finish_batch(output.clone(), vec![]).unwrap(), output);
+
+        let err = finish_batch(output.clone(), vec!["b: failed".to_string()]).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("1 transaction(s) of the batch failed"));
+        match err.downcast_ref::<TokenCliError>() {
+            Some(TokenCliError::Incomplete {
+                output: partial, ..
+            }) => assert_eq!(*partial, output),
+            _ => panic!("expected the partial output with the error"),
+        }
+    }
+
+    #[tokio::test]
+    #[parallel(two)]
+    async fn address() {
+        let (test_validator, payer) = new_validator_for_test().await;
+        for program_id in [spl_token::id(), spl_token_2022::id()] {
+            let mut config = test_config(&test_validator, &payer, &program_id);
+            config.output_format = OutputFormat::JsonCompact;
+            let token = create_token(&config, &payer).await;
+            let owner = Pubkey::new_unique();
+
+            let result = process_test_command(
+                &config,
+                &payer,
+                &[
+                    "spl-token",
+                    CommandName::Address.into(),
+                    &token.to_string(),
+                    "--owner",
+                    &owner.to_string(),
+                ],
+            )
+            .await
+            .unwrap();
+            let value: serde_json::Value = serde_json::from_str(&result).unwrap();
+            let associated =
+                get_associated_token_address_with_program_id(&owner, &token, &program_id);
+            assert_eq!(value["walletAddress"], owner.to_string());
+            assert_eq!(value["mint"], token.to_string());
+            assert_eq!(value["programId"], program_id.to_string());
+            assert_eq!(value["associatedTokenAddress"], associated.to_string());
+
+            // offline, the mint doesn't need to exist
+            let mint = Pubkey::new_unique();
+            let result = process_test_command(
+                &config,
+                &payer,
+                &[
+                    "spl-token",
+                    CommandName::Address.into(),
+                    &mint.to_string(),
+                    "--offline",
+                ],
+            )
+            .await
+            .unwrap();
+            let value: serde_json::Value = serde_json::from_str(&result).unwrap();
+            let associated =
+                get_associated_token_address_with_program_id(&payer.pubkey(), &mint, &program_id);
+            assert_eq!(value["programId"], program_id.to_string());
+            assert_eq!(value["associatedTokenAddress"], associated.to_string());
+        }
+    }
+
+    #[tokio::test]
+    #[parallel(two)]
+    async fn approve_many() {
+        let (test_validator, payer) = new_validator_for_test().await;
+        for program_id in [spl_token::id(), spl_token_2022::id()] {
+            let config = test_config(&test_validator, &payer, &program_id);
+            let token = create_token(&config, &payer).await;
+            let associated = create_associated_account(&config, &payer, token).await;
+            let auxiliary = create_auxiliary_account(&config, &payer, token).await;
+            let delegate = Pubkey::new_unique();
+
+            process_test_command(
+                &config,
+                &payer,
+                &[
+                    "spl-token",
+                    CommandName::ApproveMany.into(),
+                    &delegate.to_string(),
+                    "10",
+                    &associated.to_string(),
+                    &auxiliary.to_string(),
+                ],
+            )
+            .await
+            .unwrap();
+            for address in [associated, auxiliary] {
+                let account = config.rpc_client.get_account(&address).await.unwrap();
+                let token_account =
+                    StateWithExtensionsOwned::<Account>::unpack(account.data).unwrap();
+                assert_eq!(token_account.base.delegate, COption::Some(delegate));
+                assert_eq!(token_account.base.delegated_amount, 10);
+            }
+
+            let delegate = Pubkey::new_unique();
+            process_test_command(
+                &config,
+                &payer,
+                &[
+                    "spl-token",
+                    CommandName::ApproveMany.into(),
+                    &delegate.to_string(),
+                    "20",
+                    "--all",
+                    &token.to_string(),
+                ],
+            )
+            .await
+            .unwrap();
+            for address in [associated, auxiliary] {
+                let account = config.rpc_client.get_account(&address).await.unwrap();
+                let token_account =
+                    StateWithExtensionsOwned::<Account>::unpack(account.data).unwrap();
+                assert_eq!(token_account.base.delegate, COption::Some(delegate));
+                assert_eq!(token_account.base.delegated_amount, 20);
+            }
+        }
+
+        // Accounts of both token programs in one invocation
+        let config = test_config(&test_validator, &payer, &spl_token::id());
+        let config_2022 = test_config(&test_validator, &payer, &spl_token_2022::id());
+        let token = create_token(&config, &payer).await;
+        let token_2022 = create_token(&config_2022, &payer).await;
+        let account = create_associated_account(&config, &payer, token).await;
+        let account_2022 = create_associated_account(&config_2022, &payer, token_2022).await;
+        let delegate = Pubkey::new_unique();
+        process_test_command(
+            &config,
+            &payer,
+            &[
+                "spl-token",
+                CommandName::ApproveMany.into(),
+                &delegate.to_string(),
+                "30",
+                &account.to_string(),
+                &account_2022.to_string(),
+            ],
+        )
+        .await
+        .unwrap();
+        for address in [account, account_2022] {
+            let account = config.rpc_client.get_account(&address).await.unwrap();
+            let token_account = StateWithExtensionsOwned::<Account>::unpack(account.data).unwrap();
+            assert_eq!(token_account.base.delegate, COption::Some(delegate));
+            assert_eq!(token_account.base.delegated_amount, 30);
+        }
+    }
+
+    #[tokio::test]
+    #[parallel(two)]
+    async fn revoke_all() {
+        let (test_validator, payer) = new_validator_for_test().await;
+        for program_id in [spl_token::id(), spl_token_2022::id()] {
+            let mut config = test_config(&test_validator, &payer, &program_id);
+            let token = create_token(&config, &payer).await;
+            let delegated = create_associated_account(&config, &payer, token).await;
+            let _undelegated = create_auxiliary_account(&config, &payer, token).await;
+            let delegate = Pubkey::new_unique();
+            process_test_command(
+                &config,
+                &payer,
+                &[
+                    "spl-token",
+                    CommandName::ApproveMany.into(),
+                    &delegate.to_string(),
+                    "10",
+                    &delegated.to_string(),
+                ],
+            )
+            .await
+            .unwrap();
+
+            let args = &[
+                "spl-token",
+                CommandName::RevokeAll.into(),
+                &token.to_string(),
+            ];
+            process_test_command(&config, &payer, args)
+                .await
+                .unwrap_err();
+
+            config.output_format = OutputFormat::JsonCompact;
+            let result = process_test_command(
+                &config,
+                &payer,
+                &[
+                    "spl-token",
+                    CommandName::RevokeAll.into(),
+                    &token.to_string(),
+                    "--confirm",
+                ],
+            )
+            .await
+            .unwrap();
+            let value: serde_json::Value = serde_json::from_str(&result).unwrap();
+            assert_eq!(value["revoked"], serde_json::json!([delegated.to_string()]));
+            let account = config.rpc_client.get_account(&delegated).await.unwrap();
+            let token_account = StateWithExtensionsOwned::<Account>::unpack(account.data).unwrap();
+            assert_eq!(token_account.base.delegate, COption::None);
+        }
+
+        // Without a mint, the delegations under both token programs are revoked
+        let mut config = test_config(&test_validator, &payer, &spl_token::id());
+        let config_2022 = test_config(&test_validator, &payer, &spl_token_2022::id());
+        let token = create_token(&config, &payer).await;
+        let token_2022 = create_token(&config_2022, &payer).await;
+        let account = create_associated_account(&config, &payer, token).await;
+        let account_2022 = create_associated_account(&config_2022, &payer, token_2022).await;
+        process_test_command(
+            &config,
+            &payer,
+            &[
+                "spl-token",
+                CommandName::ApproveMany.into(),
+                &Pubkey::new_unique().to_string(),
+                "10",
+                &account.to_string(),
+                &account_2022.to_string(),
+            ],
         )
-        .subcommand(
-            SubCommand::with_name(CommandName::Transfer.into())
-                .about("Transfer tokens between accounts")
-                .arg(
-                    Arg::with_name("token")
-                        .validator(is_valid_pubkey)
-                        .value_name("TOKEN_ADDRESS")
-                        .takes_value(true)
-                        .index(1)
-                        .required(true)
-                        .help("Token to transfer"),
-                )
-                .arg(
-                    Arg::with_name("amount")
-                        .validator(is_amount_or_all)
-                        .value_name("TOKEN_AMOUNT")
-                        .takes_value(true)
-                        .index(2)
-                        .required(true)
-                        .help("Amount to send, in tokens; accepts keyword ALL"),
-                )
-                .arg(
-                    Arg::with_name("recipient")
-                        .validator(is_valid_pubkey)
-                        .value_name("RECIPIENT_ADDRESS or RECIPIENT_TOKEN_ACCOUNT_ADDRESS")
-                        .takes_value(true)
-                        .index(3)
-                        .required(true)
-                        .help("If a token account address is provided, use it as the recipient. \
-                               Otherwise assume the recipient address is a user wallet and transfer to \
-                               the associated token account")
-                )
-                .arg(
-                    Arg::with_name("from")
-                        .validator(is_valid_pubkey)
-                        .value_name("SENDER_TOKEN_ACCOUNT_ADDRESS")
-                        .takes_value(true)
-                        .long("from")
-                        .help("Specify the sending token account \
-                            [default: owner's associated token account]")
-                )
-                .arg(owner_keypair_arg_with_value_name("SENDER_TOKEN_OWNER_KEYPAIR")
-                        .help(
-                            "Specify the owner of the sending token account. \
-                            This may be a keypair file, the ASK keyword. \
-                            Defaults to the client keypair.",
-                        ),
-                )
-                .arg(
-                    Arg::with_name("allow_unfunded_recipient")
-                        .long("allow-unfunded-recipient")
-                        .takes_value(false)
-                        .help("Complete the transfer even if the recipient address is not funded")
-                )
-                .arg(
-                    Arg::with_name("allow_empty_recipient")
-                        .long("allow-empty-recipient")
-                        .takes_value(false)
-                        .hidden(true) // Deprecated, use --allow-unfunded-recipient instead
-                )
-                .arg(
-                    Arg::with_name("fund_recipient")
-                        .long("fund-recipient")
-                        .takes_value(false)
-                        .help("Create the associated token account for the recipient if doesn't already exist")
-                )
-                .arg(
-                    Arg::with_name("no_wait")
-                        .long("no-wait")
-                        .takes_value(false)
-                        .help("Return signature immediately after submitting the transaction, instead of waiting for confirmations"),
-                )
-                .arg(
-                    Arg::with_name("allow_non_system_account_recipient")
-                        .long("allow-non-system-account-recipient")
-                        .takes_value(false)
-                        .help("Send tokens to the recipient even if the recipient is not a wallet owned by System Program."),
-                )
-                .arg(
-                    Arg::with_name("recipient_is_ata_owner")
-                        .long("recipient-is-ata-owner")
-                        .takes_value(false)
-                        .requires("sign_only")
-                        .help("In sign-only mode, specifies that the recipient is the owner of the associated token account rather than an actual token account"),
-                )
-                .arg(multisig_signer_arg())
-                .arg(mint_decimals_arg())
-                .nonce_args(true)
-                .arg(memo_arg())
-                .offline_args_config(&SignOnlyNeedsMintDecimals{}),
+        .await
+        .unwrap();
+        config.output_format = OutputFormat::JsonCompact;
+        let result = process_test_command(
+            &config,
+            &payer,
+            &["spl-token", CommandName::RevokeAll.into(), "--confirm"],
         )
-        .subcommand(
-            SubCommand::with_name(CommandName::Burn.into())
-                .about("Burn tokens from an account")
-                .arg(
-                    Arg::with_name("source")
-                        .validator(is_valid_pubkey)
-                        .value_name("SOURCE_TOKEN_ACCOUNT_ADDRESS")
-                        .takes_value(true)
-                        .index(1)
-                        .required(true)
-                        .help("The token account address to burn from"),
-                )
-                .arg(
-                    Arg::with_name("amount")
-                        .validator(is_amount)
-                        .value_name("TOKEN_AMOUNT")
-                        .takes_value(true)
-                        .index(2)
-                        .required(true)
-                        .help("Amount to burn, in tokens"),
-                )
-                .arg(owner_keypair_arg_with_value_name("SOURCE_TOKEN_OWNER_KEYPAIR")
-                        .help(
-                            "Specify the source token owner account. \
-                            This may be a keypair file, the ASK keyword. \
-                            Defaults to the client keypair.",
-                        ),
-                )
-                .arg(multisig_signer_arg())
-                .mint_args()
-                .nonce_args(true)
-                .arg(memo_arg())
-                .offline_args_config(&SignOnlyNeedsFullMintSpec{}),
+        .await
+        .unwrap();
+        let value: serde_json::Value = serde_json::from_str(&result).unwrap();
+        let revoked = value["revoked"].as_array().unwrap();
+        assert!(revoked.contains(&serde_json::json!(account.to_string())));
+        assert!(revoked.contains(&serde_json::json!(account_2022.to_string())));
+    }
+
+    #[test]
+    fn program_id_keywords() {
+        let default_decimals = format!("{}", spl_token_2022::native_mint::DECIMALS);
+        let default_program_id = spl_token::id().to_string();
+        let minimum_signers_help = minimum_signers_help_string();
+        let multisig_member_help = multisig_member_help_string();
+        let app = app(
+            &default_decimals,
+            &default_program_id,
+            &minimum_signers_help,
+            &multisig_member_help,
+        );
+
+        let program_id = spl_token_2022::id().to_string();
+        for (args, expected) in [
+            (vec!["spl-token", "accounts"], spl_token::id()),
+            (
+                vec!["spl-token", "accounts", "--program-id", "token"],
+                spl_token::id(),
+            ),
+            (
+                vec!["spl-token", "accounts", "--program-id", "token-2022"],
+                spl_token_2022::id(),
+            ),
+            (
+                vec!["spl-token", "accounts", "--token-program", "tk22"],
+                spl_token_2022::id(),
+            ),
+            (
+                vec!["spl-token", "accounts", "--token-program", &program_id],
+                spl_token_2022::id(),
+            ),
+        ] {
+            let matches = app.clone().get_matches_from_safe(args).unwrap();
+            let (_, sub_matches) = matches.subcommand();
+            assert_eq!(
+                program_id_of(sub_matches.unwrap(), "program_id"),
+                Some(expected)
+            );
+        }
+
+        let err = app
+            .get_matches_from_safe(["spl-token", "accounts", "--program-id", "token2022"])
+            .unwrap_err();
+        assert!(err.message.contains("token, token-2022, tk22"));
+    }
+
+    #[test]
+    fn commitment_arg() {
+        let default_decimals = format!("{}", spl_token_2022::native_mint::DECIMALS);
+        let default_program_id = spl_token::id().to_string();
+        let minimum_signers_help = minimum_signers_help_string();
+        let multisig_member_help = multisig_member_help_string();
+        let app = app(
+            &default_decimals,
+            &default_program_id,
+            &minimum_signers_help,
+            &multisig_member_help,
+        );
+
+        let matches = app
+            .clone()
+            .get_matches_from_safe(["spl-token", "accounts", "--commitment", "finalized"])
+            .unwrap();
+        let (_, sub_matches) = matches.subcommand();
+        assert_eq!(
+            sub_matches.unwrap().value_of("commitment"),
+            Some("finalized")
+        );
+        assert!(app
+            .get_matches_from_safe(["spl-token", "accounts", "--commitment", "recent"])
+            .is_err());
+    }
+
+    #[test]
+    fn env_args() {
+        let default_decimals = format!("{}", spl_token_2022::native_mint::DECIMALS);
+        let default_program_id = spl_token::id().to_string();
+        let minimum_signers_help = minimum_signers_help_string();
+        let multisig_member_help = multisig_member_help_string();
+        let app = app(
+            &default_decimals,
+            &default_program_id,
+            &minimum_signers_help,
+            &multisig_member_help,
+        );
+        let address = Pubkey::new_unique();
+        let recipient = address.to_string();
+        env::set_var("SPL_TOKEN_TEST_ADDRESS", &recipient);
+        env::set_var("SPL_TOKEN_TEST_AMOUNT", "1.5k");
+        env::remove_var("SPL_TOKEN_TEST_UNSET");
+
+        // only the pubkey and amount arguments are resolved
+        let matches = app
+            .clone()
+            .get_matches_from_safe([
+                "spl-token",
+                CommandName::Transfer.into(),
+                "env:SPL_TOKEN_TEST_ADDRESS",
+                "env:SPL_TOKEN_TEST_AMOUNT",
+                "--from=env:SPL_TOKEN_TEST_ADDRESS",
+                recipient.as_str(),
+                "--with-memo",
+                "env:SPL_TOKEN_TEST_UNSET",
+            ])
+            .unwrap();
+        let (_, sub_matches) = matches.subcommand();
+        let sub_matches = sub_matches.unwrap();
+        let mut wallet_manager = None;
+        assert_eq!(
+            pubkey_of_signer(sub_matches, "token", &mut wallet_manager).unwrap(),
+            Some(address)
+        );
+        assert_eq!(
+            pubkey_of_signer(sub_matches, "from", &mut wallet_manager).unwrap(),
+            Some(address)
+        );
+        assert_eq!(
+            resolved_value_of(sub_matches, "amount").as_deref(),
+            Some("1.5k")
+        );
+        assert_eq!(
+            sub_matches.value_of("memo"),
+            Some("env:SPL_TOKEN_TEST_UNSET")
+        );
+
+        let err = app
+            .get_matches_from_safe([
+                "spl-token",
+                CommandName::Transfer.into(),
+                "env:SPL_TOKEN_TEST_UNSET",
+                "1",
+                recipient.as_str(),
+            ])
+            .unwrap_err();
+        assert!(err.to_string().contains("SPL_TOKEN_TEST_UNSET"));
+    }
+
+    #[tokio::test]
+    #[parallel(two)]
+    async fn confirm() {
+        let (test_validator, payer) = new_validator_for_test().await;
+        let config = test_config(&test_validator, &payer, &spl_token::id());
+        let token = create_token(&config, &payer).await;
+        let source = create_associated_account(&config, &payer, token).await;
+        let destination = create_auxiliary_account(&config, &payer, token).await;
+        mint_tokens(&config, &payer, token, 100.0, source).await;
+
+        let result = process_test_command(
+            &config,
+            &payer,
+            &[
+                "spl-token",
+                CommandName::Transfer.into(),
+                &token.to_string(),
+                "10",
+                &destination.to_string(),
+            ],
         )
-        .subcommand(
-            SubCommand::with_name(CommandName::Mint.into())
-                .about("Mint new tokens")
-                .arg(
-                    Arg::with_name("token")
-                        .validator(is_valid_pubkey)
-                        .value_name("TOKEN_ADDRESS")
-                        .takes_value(true)
-                        .index(1)
-                        .required(true)
-                        .help("The token to mint"),
-                )
-                .arg(
-                    Arg::with_name("amount")
-                        .validator(is_amount)
-                        .value_name("TOKEN_AMOUNT")
-                        .takes_value(true)
-                        .index(2)
-                        .required(true)
-                        .help("Amount to mint, in tokens"),
-                )
-                .arg(
-                    Arg::with_name("recipient")
-                        .validator(is_valid_pubkey)
-                        .value_name("RECIPIENT_TOKEN_ACCOUNT_ADDRESS")
-                        .takes_value(true)
-                        .index(3)
-                        .help("The token account address of recipient [default: associated token account for --owner]"),
-                )
-                .arg(
-                    Arg::with_name("mint_authority")
-                        .long("mint-authority")
-                        .alias("owner")
-                        .value_name("KEYPAIR")
-                        .validator(is_valid_signer)
-                        .takes_value(true)
-                        .help(
-                            "Specify the mint authority keypair. \
-                             This may be a keypair file or the ASK keyword. \
-                             Defaults to the client keypair."
-                        ),
-                )
-                .arg(mint_decimals_arg())
-                .arg(multisig_signer_arg())
-                .nonce_args(true)
-                .offline_args_config(&SignOnlyNeedsMintDecimals{}),
+        .await
+        .unwrap();
+        let value: serde_json::Value = serde_json::from_str(&result).unwrap();
+        let signature = value["signature"].as_str().unwrap();
+
+        let result = process_test_command(
+            &config,
+            &payer,
+            &["spl-token", CommandName::Confirm.into(), signature],
         )
-        .subcommand(
-            SubCommand::with_name(CommandName::Freeze.into())
-                .about("Freeze a token account")
-                .arg(
-                    Arg::with_name("account")
-                        .validator(is_valid_pubkey)
-                        .value_name("TOKEN_ACCOUNT_ADDRESS")
-                        .takes_value(true)
-                        .index(1)
-                        .required(true)
-                        .help("The address of the token account to freeze"),
-                )
-                .arg(
-                    Arg::with_name("freeze_authority")
-                        .long("freeze-authority")
-                        .alias("owner")
-                        .value_name("KEYPAIR")
-                        .validator(is_valid_signer)
-                        .takes_value(true)
-                        .help(
-                            "Specify the freeze authority keypair. \
-                             This may be a keypair file or the ASK keyword. \
-                             Defaults to the client keypair."
-                        ),
-                )
-                .arg(mint_address_arg())
-                .arg(multisig_signer_arg())
-                .nonce_args(true)
-                .offline_args_config(&SignOnlyNeedsMintAddress{}),
+        .await
+        .unwrap();
+        let value: serde_json::Value = serde_json::from_str(&result).unwrap();
+        assert_eq!(value["signature"], signature);
+        assert!(value["slot"].as_u64().unwrap() > 0);
+        assert!(value["err"].is_null());
+
+        process_test_command(
+            &config,
+            &payer,
+            &[
+                "spl-token",
+                CommandName::Confirm.into(),
+                &Signature::new_unique().to_string(),
+            ],
         )
-        .subcommand(
-            SubCommand::with_name(CommandName::Thaw.into())
-                .about("Thaw a token account")
-                .arg(
-                    Arg::with_name("account")
-                        .validator(is_valid_pubkey)
-                        .value_name("TOKEN_ACCOUNT_ADDRESS")
-                        .takes_value(true)
-                        .index(1)
-                        .required(true)
-                        .help("The address of the token account to thaw"),
-                )
-                .arg(
-                    Arg::with_name("freeze_authority")
-                        .long("freeze-authority")
-                        .alias("owner")
-                        .value_name("KEYPAIR")
-                        .validator(is_valid_signer)
-                        .takes_value(true)
-                        .help(
-                            "Specify the freeze authority keypair. \
-                             This may be a keypair file or the ASK keyword. \
-                             Defaults to the client keypair."
-                        ),
-                )
-                .arg(mint_address_arg())
-                .arg(multisig_signer_arg())
-                .nonce_args(true)
-                .offline_args_config(&SignOnlyNeedsMintAddress{}),
+        .await
+        .unwrap_err();
+    }
+
+    #[tokio::test]
+    #[parallel(two)]
+    async fn compute_budget_from_simulation() {
+        let (test_validator, payer) = new_validator_for_test().await;
+        let mut config = test_config(&test_validator, &payer, &spl_token::id());
+        let token = create_token(&config, &payer).await;
+        let source = create_associated_account(&config, &payer, token).await;
+        let destination = create_auxiliary_account(&config, &payer, token).await;
+        mint_tokens(&config, &payer, token, 100.0, source).await;
+
+        let instruction = transfer_checked(
+            &spl_token::id(),
+            &source,
+            &token,
+            &destination,
+            &payer.pubkey(),
+            &[],
+            1,
+            TEST_DECIMALS,
         )
-        .subcommand(
-            SubCommand::with_name(CommandName::Wrap.into())
-                .about("Wrap native SOL in a SOL token account")
-                .arg(
-                    Arg::with_name("amount")
-                        .validator(is_amount)
-                        .value_name("AMOUNT")
-                        .takes_value(true)
-                        .index(1)
-                        .required(true)
-                        .help("Amount of SOL to wrap"),
-                )
-                .arg(
-                    Arg::with_name("wallet_keypair")
-                        .alias("owner")
-                        .value_name("KEYPAIR")
-                        .validator(is_valid_signer)
-                        .takes_value(true)
-                        .help(
-                            "Specify the keypair for the wallet which will have its native SOL wrapped. \
-                             This wallet will be assigned as the owner of the wrapped SOL token account. \
-                             This may be a keypair file or the ASK keyword. \
-                             Defaults to the client keypair."
-                        ),
-                )
-                .arg(
-                    Arg::with_name("create_aux_account")
-                        .takes_value(false)
-                        .long("create-aux-account")
-                        .help("Wrap SOL in an auxiliary account instead of associated token account"),
-                )
-                .nonce_args(true)
-                .offline_args(),
+        .unwrap();
+        let blockhash = config.rpc_client.get_latest_blockhash().await.unwrap();
+        let limit = simulate_compute_unit_limit(
+            &config,
+            &payer.pubkey(),
+            &[instruction.clone()],
+            &blockhash,
+            0,
         )
-        .subcommand(
-            SubCommand::with_name(CommandName::Unwrap.into())
-                .about("Unwrap a SOL token account")
-                .arg(
-                    Arg::with_name("address")
-                        .validator(is_valid_pubkey)
-                        .value_name("TOKEN_ACCOUNT_ADDRESS")
-                        .takes_value(true)
-                        .index(1)
-                        .help("The address of the auxiliary token account to unwrap \
-                            [default: associated token account for --owner]"),
-                )
-                .arg(
-                    Arg::with_name("wallet_keypair")
-                        .alias("owner")
-                        .value_name("KEYPAIR")
-                        .validator(is_valid_signer)
-                        .takes_value(true)
-                        .help(
-                            "Specify the keypair for the wallet which owns the wrapped SOL. \
-                             This wallet will receive the unwrapped SOL. \
-                             This may be a keypair file or the ASK keyword. \
-                             Defaults to the client keypair."
-                        ),
-                )
-                .arg(multisig_signer_arg())
-                .nonce_args(true)
-                .offline_args(),
+        .await
+        .unwrap();
+        let limit_with_margin =
+            simulate_compute_unit_limit(&config, &payer.pubkey(), &[instruction], &blockhash, 50)
+                .await
+                .unwrap();
+        assert!(limit > 0 && limit < MAX_COMPUTE_UNIT_LIMIT);
+        assert_eq!(limit_with_margin, limit * 150 / 100);
+
+        config.compute_unit_margin = Some(DEFAULT_COMPUTE_UNIT_MARGIN);
+        process_test_command(
+            &config,
+            &payer,
+            &[
+                "spl-token",
+                CommandName::Transfer.into(),
+                &token.to_string(),
+                "10",
+                &destination.to_string(),
+            ],
         )
-        .subcommand(
-            SubCommand::with_name(CommandName::Approve.into())
-                .about("Approve a delegate for a token account")
-                .arg(
-                    Arg::with_name("account")
-                        .validator(is_valid_pubkey)
-                        .value_name("TOKEN_ACCOUNT_ADDRESS")
-                        .takes_value(true)
-                        .index(1)
-                        .required(true)
-                        .help("The address of the token account to delegate"),
-                )
-                .arg(
-                    Arg::with_name("amount")
-                        .validator(is_amount)
-                        .value_name("TOKEN_AMOUNT")
-                        .takes_value(true)
-                        .index(2)
-                        .required(true)
-                        .help("Amount to approve, in tokens"),
-                )
-                .arg(
-                    Arg::with_name("delegate")
-                        .validator(is_valid_pubkey)
-                        .value_name("DELEGATE_TOKEN_ACCOUNT_ADDRESS")
-                        .takes_value(true)
-                        .index(3)
-                        .required(true)
-                        .help("The token account address of delegate"),
-                )
-                .arg(
-                    owner_keypair_arg()
+        .await
+        .unwrap();
+        let account = config.rpc_client.get_account(&destination).await.unwrap();
+        let token_account = StateWithExtensionsOwned::<Account>::unpack(account.data).unwrap();
+        assert_eq!(token_account.base.amount, 10);
+    }
+
+    #[tokio::test]
+    #[parallel(two)]
+    async fn decode_serialized_transaction() {
+        let (test_validator, payer) = new_validator_for_test().await;
+        let config = test_config(&test_validator, &payer, &spl_token_2022::id());
+        let token = Pubkey::new_unique();
+        let owner = Pubkey::new_unique();
+        let source = Pubkey::new_unique();
+        let destination =
+            get_associated_token_address_with_program_id(&owner, &token, &spl_token_2022::id());
+        let message = Message::new(
+            &[
+                create_associated_token_account_idempotent(
+                    &payer.pubkey(),
+                    &owner,
+                    &token,
+                    &spl_token_2022::id(),
+                ),
+                transfer_checked(
+                    &spl_token_2022::id(),
+                    &source,
+                    &token,
+                    &destination,
+                    &payer.pubkey(),
+                    &[],
+                    10,
+                    2,
                 )
-                .arg(multisig_signer_arg())
-                .mint_args()
-                .nonce_args(true)
-                .offline_args_config(&SignOnlyNeedsFullMintSpec{}),
+                .unwrap(),
+            ],
+            Some(&payer.pubkey()),
+        );
+        let mut transaction = Transaction::new_unsigned(message.clone());
+
+        let result = process_test_command(
+            &config,
+            &payer,
+            &[
+                "spl-token",
+                CommandName::DecodeTransaction.into(),
+                &base64::encode(message.serialize()),
+            ],
         )
-        .subcommand(
-            SubCommand::with_name(CommandName::Revoke.into())
-                .about("Revoke a delegate's authority")
-                .arg(
-                    Arg::with_name("account")
-                        .validator(is_valid_pubkey)
-                        .value_name("TOKEN_ACCOUNT_ADDRESS")
-                        .takes_value(true)
-                        .index(1)
-                        .required(true)
-                        .help("The address of the token account"),
-                )
-                .arg(owner_keypair_arg()
-                )
-                .arg(delegate_address_arg())
-                .arg(multisig_signer_arg())
-                .nonce_args(true)
-                .offline_args_config(&SignOnlyNeedsDelegateAddress{}),
+        .await
+        .unwrap();
+        let value: serde_json::Value = serde_json::from_str(&result).unwrap();
+        assert_eq!(value["signers"][0]["address"], payer.pubkey().to_string());
+        assert!(value["signers"][0]["signature"].is_null());
+        let instructions = value["instructions"].as_array().unwrap();
+        assert_eq!(
+            instructions[0]["program"],
+            "Associated token account program"
+        );
+        assert_eq!(instructions[0]["instruction"], "CreateIdempotent");
+        assert_eq!(
+            instructions[1]["instruction"],
+            "TransferChecked { amount: 10, decimals: 2 }"
+        );
+        assert_eq!(instructions[1]["accounts"][2]["writable"], true);
+
+        transaction.sign(&[&payer], Hash::new_unique());
+        let file = NamedTempFile::new().unwrap();
+        write_transaction_file(file.path().to_str().unwrap(), &transaction).unwrap();
+        let result = process_test_command(
+            &config,
+            &payer,
+            &[
+                "spl-token",
+                CommandName::DecodeTransaction.into(),
+                file.path().to_str().unwrap(),
+            ],
         )
-        .subcommand(
-            SubCommand::with_name(CommandName::Close.into())
-                .about("Close a token account")
-                .arg(
-                    Arg::with_name("token")
-                        .validator(is_valid_pubkey)
-                        .value_name("TOKEN_ADDRESS")
-                        .takes_value(true)
-                        .index(1)
-                        .required_unless("address")
-                        .help("Token to close. To close a specific account, use the `--address` parameter instead"),
-                )
-                .arg(owner_address_arg())
-                .arg(
-                    Arg::with_name("recipient")
-                        .long("recipient")
-                        .validator(is_valid_pubkey)
-                        .value_name("REFUND_ACCOUNT_ADDRESS")
-                        .takes_value(true)
-                        .help("The address of the account to receive remaining SOL [default: --owner]"),
-                )
-                .arg(
-                    Arg::with_name("close_authority")
-                        .long("close-authority")
-                        .alias("owner")
-                        .value_name("KEYPAIR")
-                        .validator(is_valid_signer)
-                        .takes_value(true)
-                        .help(
-                            "Specify the token's close authority if it has one, \
-                            otherwise specify the token's owner keypair. \
-                            This may be a keypair file, the ASK keyword. \
-                            Defaults to the client keypair.",
+        .await
+        .unwrap();
+        let value: serde_json::Value = serde_json::from_str(&result).unwrap();
+        assert_eq!(
+            value["signers"][0]["signature"],
+            transaction.signatures[0].to_string()
+        );
+    }
+
+    #[tokio::test]
+    #[parallel(two)]
+    async fn sign_only_labels_accounts() {
+        let (test_validator, payer) = new_validator_for_test().await;
+        for program_id in [spl_token::id(), spl_token_2022::id()] {
+            let mut config = test_config(&test_validator, &payer, &program_id);
+            let token = create_token(&config, &payer).await;
+            let source = create_associated_account(&config, &payer, token).await;
+            let destination = create_auxiliary_account(&config, &payer, token).await;
+            mint_tokens(&config, &payer, token, 100.0, source).await;
+
+            let blockhash = Hash::new_unique();
+            config.sign_only = true;
+            config.blockhash = Some(blockhash);
+            config.dump_transaction_message = true;
+            let result = process_test_command(
+                &config,
+                &payer,
+                &[
+                    "spl-token",
+                    CommandName::Transfer.into(),
+                    &token.to_string(),
+                    "10",
+                    &destination.to_string(),
+                    "--mint-decimals",
+                    &TEST_DECIMALS.to_string(),
+                    "--sign-only",
+                    "--blockhash",
+                    &blockhash.to_string(),
+                    "--dump-transaction-message",
+                ],
+            )
+            .await
+            .unwrap();
+            let value: serde_json::Value = serde_json::from_str(&result).unwrap();
+            assert_eq!(value["blockhash"], blockhash.to_string());
+            assert!(value["message"].is_string());
+            let label = |address: &Pubkey| {
+                value["accounts"]
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .find(|account| account["address"] == address.to_string())
+                    .unwrap()["label"]
+                    .clone()
+            };
+            assert_eq!(label(&payer.pubkey()), "fee payer");
+            assert_eq!(label(&token), "mint");
+            assert_eq!(
+                label(&source),
+                format!("associated token account of {}", payer.pubkey())
+            );
+            assert!(label(&destination).is_null());
+            assert_eq!(
+                label(&program_id),
+                decode::program_name(&program_id).unwrap().to_string()
+            );
+            let instructions = value["instructions"].as_array().unwrap();
+            assert_eq!(
+                instructions.last().unwrap()["instruction"],
+                "TransferChecked { amount: 10, decimals: 0 }"
+            );
+        }
+    }
+
+    #[tokio::test]
+    #[parallel(two)]
+    async fn account_source() {
+        let (test_validator, payer) = new_validator_for_test().await;
+        for program_id in [spl_token::id(), spl_token_2022::id()] {
+            let mut config = test_config(&test_validator, &payer, &program_id);
+            // Neither account exists on the validator
+            let mint = Pubkey::new_unique();
+            let token_account = Pubkey::new_unique();
+            let mut mint_data = vec![0; Mint::LEN];
+            Mint::pack(
+                Mint {
+                    mint_authority: COption::Some(payer.pubkey()),
+                    decimals: 6,
+                    is_initialized: true,
+                    ..Mint::default()
+                },
+                &mut mint_data,
+            )
+            .unwrap();
+            let mut account_data = vec![0; Account::LEN];
+            Account::pack(
+                Account {
+                    mint,
+                    owner: payer.pubkey(),
+                    state: AccountState::Initialized,
+                    ..Account::default()
+                },
+                &mut account_data,
+            )
+            .unwrap();
+            let keyed_accounts = vec![(mint, mint_data), (token_account, account_data)]
+                .into_iter()
+                .map(|(address, data)| {
+                    let account = solana_sdk::account::Account {
+                        lamports: 1,
+                        data,
+                        owner: program_id,
+                        ..solana_sdk::account::Account::default()
+                    };
+                    RpcKeyedAccount {
+                        pubkey: address.to_string(),
+                        account: solana_account_decoder::UiAccount::encode(
+                            &address,
+                            &account,
+                            UiAccountEncoding::Base64,
+                            None,
+                            None,
                         ),
-                )
-                .arg(
-                    Arg::with_name("address")
-                        .long("address")
-                        .validator(is_valid_pubkey)
-                        .value_name("TOKEN_ACCOUNT_ADDRESS")
-                        .takes_value(true)
-                        .conflicts_with("token")
-                        .help("Specify the token account to close \
-                            [default: owner's associated token account]"),
-                )
-                .arg(multisig_signer_arg())
-                .nonce_args(true)
-                .offline_args(),
+                    }
+                })
+                .collect::<Vec<_>>();
+            let mut file = NamedTempFile::new().unwrap();
+            file.write_all(serde_json::to_string(&keyed_accounts).unwrap().as_bytes())
+                .unwrap();
+            config.account_source = read_account_source(file.path().to_str().unwrap()).unwrap();
+
+            let mint_info = config.get_mint_info(&mint, None).await.unwrap();
+            assert_eq!(mint_info.decimals, 6);
+            assert_eq!(mint_info.program_id, program_id);
+            assert_eq!(
+                config.check_account(&token_account, None).await.unwrap(),
+                mint
+            );
+        }
+    }
+
+    #[tokio::test]
+    #[parallel(two)]
+    async fn offline_transfer() {
+        let (test_validator, payer) = new_validator_for_test().await;
+        let mut config = test_config(&test_validator, &payer, &spl_token::id());
+        let token = create_token(&config, &payer).await;
+        let destination = create_auxiliary_account(&config, &payer, token).await;
+
+        // Any request the command makes would fail
+        config.rpc_client = Arc::new(RpcClient::new_sender(
+            OfflineSender,
+            RpcClientConfig::default(),
+        ));
+        let blockhash = Hash::new_unique();
+        config.offline = true;
+        config.sign_only = true;
+        config.blockhash = Some(blockhash);
+        let token_string = token.to_string();
+        let destination_string = destination.to_string();
+        let blockhash_string = blockhash.to_string();
+        let args = [
+            "spl-token",
+            CommandName::Transfer.into(),
+            &token_string,
+            "10",
+            &destination_string,
+            "--offline",
+            "--blockhash",
+            &blockhash_string,
+        ];
+        let err = process_test_command(&config, &payer, &args)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("--mint-decimals"));
+
+        let args = [&args[..], &["--mint-decimals", "0"][..]].concat();
+        let result = process_test_command(&config, &payer, &args).await.unwrap();
+        let value: serde_json::Value = serde_json::from_str(&result).unwrap();
+        assert_eq!(value["blockhash"], blockhash.to_string());
+    }
+
+    #[tokio::test]
+    #[parallel(two)]
+    async fn relayer_sign_only_transfer() {
+        let (test_validator, payer) = new_validator_for_test().await;
+        let mut config = test_config(&test_validator, &payer, &spl_token::id());
+        let token = create_token(&config, &payer).await;
+        let source = create_associated_account(&config, &payer, token).await;
+        let destination = create_auxiliary_account(&config, &payer, token).await;
+        mint_tokens(&config, &payer, token, 100.0, source).await;
+
+        let relayer = Keypair::new();
+        let blockhash = config.rpc_client.get_latest_blockhash().await.unwrap();
+        let funding = Transaction::new_signed_with_payer(
+            &[system_instruction::transfer(
+                &payer.pubkey(),
+                &relayer.pubkey(),
+                LAMPORTS_PER_SOL,
+            )],
+            Some(&payer.pubkey()),
+            &[&payer],
+            blockhash,
+        );
+        config
+            .rpc_client
+            .send_and_confirm_transaction(&funding)
+            .await
+            .unwrap();
+
+        config.sign_only = true;
+        config.relayer_sign_only = true;
+        config.fee_payer = relayer.pubkey();
+        let result = process_test_command(
+            &config,
+            &payer,
+            &[
+                "spl-token",
+                CommandName::Transfer.into(),
+                &token.to_string(),
+                "10",
+                &destination.to_string(),
+                "--relayer-sign-only",
+                "--fee-payer",
+                &relayer.pubkey().to_string(),
+            ],
         )
-        .subcommand(
-            SubCommand::with_name(CommandName::Balance.into())
-                .about("Get token account balance")
-                .arg(
-                    Arg::with_name("token")
-                        .validator(is_valid_pubkey)
-                        .value_name("TOKEN_ADDRESS")
-                        .takes_value(true)
-                        .index(1)
-                        .required_unless("address")
-                        .help("Token of associated account. To query a specific account, use the `--address` parameter instead"),
-                )
-                .arg(owner_address_arg().conflicts_with("address"))
-                .arg(
-                    Arg::with_name("address")
-                        .validator(is_valid_pubkey)
-                        .value_name("TOKEN_ACCOUNT_ADDRESS")
-                        .takes_value(true)
-                        .long("address")
-                        .conflicts_with("token")
-                        .help("Specify the token account to query \
-                            [default: owner's associated token account]"),
-                ),
+        .await
+        .unwrap();
+        let value: serde_json::Value = serde_json::from_str(&result).unwrap();
+        assert_eq!(
+            value["absent"],
+            serde_json::json!([relayer.pubkey().to_string()])
+        );
+        let bytes = base64::decode(value["relayerTransaction"].as_str().unwrap()).unwrap();
+        let mut transaction: Transaction = bincode::deserialize(&bytes).unwrap();
+        assert_eq!(transaction.message.account_keys[0], relayer.pubkey());
+        assert_eq!(transaction.signatures[0], Signature::default());
+
+        // What the relayer does with it
+        let blockhash = transaction.message.recent_blockhash;
+        transaction.partial_sign(&[&relayer], blockhash);
+        config
+            .rpc_client
+            .send_and_confirm_transaction(&transaction)
+            .await
+            .unwrap();
+        let account = config.rpc_client.get_account(&destination).await.unwrap();
+        let token_account = StateWithExtensionsOwned::<Account>::unpack(account.data).unwrap();
+        assert_eq!(token_account.base.amount, 10);
+    }
+
+    #[tokio::test]
+    #[parallel(two)]
+    async fn bench_rpc() {
+        let (test_validator, payer) = new_validator_for_test().await;
+        let config = test_config(&test_validator, &payer, &spl_token::id());
+        let result = process_test_command(
+            &config,
+            &payer,
+            &[
+                "spl-token",
+                CommandName::Bench.into(),
+                "rpc",
+                "--iterations",
+                "3",
+            ],
         )
-        .subcommand(
-            SubCommand::with_name(CommandName::Supply.into())
-                .about("Get token supply")
-                .arg(
-                    Arg::with_name("address")
-                        .validator(is_valid_pubkey)
-                        .value_name("TOKEN_ADDRESS")
-                        .takes_value(true)
-                        .index(1)
-                        .required(true)
-                        .help("The token address"),
-                ),
+        .await
+        .unwrap();
+        let value: serde_json::Value = serde_json::from_str(&result).unwrap();
+        assert_eq!(value["iterations"], 3);
+        for request in ["getAccount", "getLatestBlockhash", "simulateTransaction"] {
+            let latency = &value[request];
+            assert_eq!(latency["samplesMs"].as_array().unwrap().len(), 3);
+            assert!(latency["p50Ms"].as_f64().unwrap() <= latency["p95Ms"].as_f64().unwrap());
+        }
+    }
+
+    #[tokio::test]
+    #[parallel(two)]
+    async fn find_mints() {
+        let (test_validator, payer) = new_validator_for_test().await;
+        let config = test_config(&test_validator, &payer, &spl_token::id());
+        let token = create_token(&config, &payer).await;
+        let config = test_config(&test_validator, &payer, &spl_token_2022::id());
+        let token_2022 = create_token(&config, &payer).await;
+
+        let result = process_test_command(
+            &config,
+            &payer,
+            &[
+                "spl-token",
+                CommandName::FindMints.into(),
+                "--authority",
+                &payer.pubkey().to_string(),
+                "--role",
+                "mint",
+            ],
         )
-        .subcommand(
-            SubCommand::with_name(CommandName::Accounts.into())
-                .about("List all token accounts by owner")
-                .arg(
-                    Arg::with_name("token")
-                        .validator(is_valid_pubkey)
-                        .value_name("TOKEN_ADDRESS")
-                        .takes_value(true)
-                        .index(1)
-                        .help("Limit results to the given token. [Default: list accounts for all tokens]"),
-                )
-                .arg(owner_address_arg())
+        .await
+        .unwrap();
+        let value: serde_json::Value = serde_json::from_str(&result).unwrap();
+        let found = value["mints"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|mint| mint["address"].as_str().unwrap().to_string())
+            .collect::<Vec<_>>();
+        assert!(found.contains(&token.to_string()));
+        assert!(found.contains(&token_2022.to_string()));
+    }
+
+    #[tokio::test]
+    #[parallel(two)]
+    async fn create_token_with_compute_budget() {
+        let (test_validator, payer) = new_validator_for_test().await;
+        let mut config = test_config(&test_validator, &payer, &spl_token_2022::id());
+        config.compute_unit_limit = Some(200_000);
+        config.compute_unit_price = Some(10);
+        let result = process_test_command(
+            &config,
+            &payer,
+            &["spl-token", CommandName::CreateToken.into()],
         )
-        .subcommand(
-            SubCommand::with_name(CommandName::Address.into())
-                .about("Get wallet address")
-                .arg(
-                    Arg::with_name("token")
-                        .validator(is_valid_pubkey)
-                        .value_name("TOKEN_ADDRESS")
-                        .takes_value(true)
-                        .long("token")
-                        .requires("verbose")
-                        .help("Return the associated token address for the given token. \
-                               [Default: return the client keypair address]")
-                )
-                .arg(
-                    owner_address_arg()
-                        .requires("token")
-                        .help("Return the associated token address for the given owner. \
-                               [Default: return the associated token address for the client keypair]"),
+        .await;
+        let value: serde_json::Value = serde_json::from_str(&result.unwrap()).unwrap();
+        let mint = Pubkey::from_str(value["commandOutput"]["address"].as_str().unwrap()).unwrap();
+        let account = config.rpc_client.get_account(&mint).await.unwrap();
+        assert_eq!(account.owner, spl_token_2022::id());
+
+        let message = new_message(&config, vec![], &Hash::default());
+        assert_eq!(message.instructions.len(), 2);
+    }
+
+    #[test]
+    fn transient_errors() {
+        assert!(is_transient_error(&ClientError::from(
+            ClientErrorKind::TransactionError(TransactionError::BlockhashNotFound)
+        )));
+        assert!(is_transient_error(&ClientError::from(
+            ClientErrorKind::RpcError(RpcError::RpcResponseError {
+                code: -32005,
+                message: "Node is behind".to_string(),
+                data: RpcResponseErrorData::NodeUnhealthy {
+                    num_slots_behind: Some(100)
+                },
+            })
+        )));
+        assert!(!is_transient_error(&ClientError::from(
+            ClientErrorKind::TransactionError(TransactionError::AccountNotFound)
+        )));
+        assert!(!is_transient_error(&ClientError::from(
+            ClientErrorKind::Custom("unable to confirm transaction".to_string())
+        )));
+    }
+
+    #[tokio::test]
+    #[parallel(two)]
+    async fn rpc_failover() {
+        let (test_validator, _payer) = new_validator_for_test().await;
+        // nothing listens on the first endpoint
+        let urls = vec!["http://127.0.0.1:1".to_string(), test_validator.rpc_url()];
+        let rpc_client = RpcClient::new_sender(
+            FailoverSender::new(&urls),
+            RpcClientConfig::with_commitment(CommitmentConfig::confirmed()),
+        );
+        rpc_client.get_latest_blockhash().await.unwrap();
+        assert_eq!(rpc_client.url(), test_validator.rpc_url());
+
+        let urls = vec!["http://127.0.0.1:1".to_string()];
+        let rpc_client = RpcClient::new_sender(
+            FailoverSender::new(&urls),
+            RpcClientConfig::with_commitment(CommitmentConfig::confirmed()),
+        );
+        assert!(rpc_client.get_latest_blockhash().await.is_err());
+    }
+
+    #[tokio::test]
+    #[parallel(two)]
+    async fn fee_payer_balance_check() {
+        let (test_validator, payer) = new_validator_for_test().await;
+        let mut config = test_config(&test_validator, &payer, &spl_token::id());
+        let fee_payer = Keypair::new();
+        config.fee_payer = fee_payer.pubkey();
+        for fee_payer_balance_check in [true, false] {
+            config.fee_payer_balance_check = fee_payer_balance_check;
+            let token = Keypair::new();
+            let token_pubkey = token.pubkey();
+            let bulk_signers: Vec<Box<dyn Signer>> = vec![
+                Box::new(clone_keypair(&fee_payer)),
+                Box::new(clone_keypair(&payer)),
+                Box::new(token),
+            ];
+            let err = command_create_token(
+                &config,
+                TEST_DECIMALS,
+                token_pubkey,
+                payer.pubkey(),
+                false,
+                vec![],
+                bulk_signers,
+            )
+            .await
+            .unwrap_err();
+            assert_eq!(
+                err.to_string().contains("has insufficient balance"),
+                fee_payer_balance_check
+            );
+        }
+    }
+
+    #[tokio::test]
+    #[parallel(one)]
+    async fn simulate() {
+        let (test_validator, payer) = new_validator_for_test().await;
+        for program_id in [spl_token::id(), spl_token_2022::id()] {
+            let mut config = test_config(&test_validator, &payer, &program_id);
+            let token = create_token(&config, &payer).await;
+            let account = create_associated_account(&config, &payer, token).await;
+            config.simulate = true;
+            let result = process_test_command(
+                &config,
+                &payer,
+                &[
+                    "spl-token",
+                    CommandName::Mint.into(),
+                    &token.to_string(),
+                    "100",
+                ],
+            )
+            .await;
+            let value: serde_json::Value = serde_json::from_str(&result.unwrap()).unwrap();
+            assert_eq!(value["err"], serde_json::Value::Null);
+            assert!(!value["logs"].as_array().unwrap().is_empty());
+            let balance = config
+                .rpc_client
+                .get_token_account_balance(&account)
+                .await
+                .unwrap();
+            assert_eq!(balance.amount, "0");
+        }
+    }
+
+    #[tokio::test]
+    #[parallel(one)]
+    async fn create_token_with_nonce() {
+        let (test_validator, payer) = new_validator_for_test().await;
+        for program_id in [spl_token::id(), spl_token_2022::id()] {
+            let mut config = test_config(&test_validator, &payer, &program_id);
+            let nonce = Keypair::new();
+            let nonce_rent = config
+                .rpc_client
+                .get_minimum_balance_for_rent_exemption(solana_sdk::nonce::State::size())
+                .await
+                .unwrap();
+            let transaction = Transaction::new_signed_with_payer(
+                &system_instruction::create_nonce_account(
+                    &payer.pubkey(),
+                    &nonce.pubkey(),
+                    &payer.pubkey(),
+                    nonce_rent,
                 ),
+                Some(&payer.pubkey()),
+                &[&payer, &nonce],
+                config.rpc_client.get_latest_blockhash().await.unwrap(),
+            );
+            config
+                .rpc_client
+                .send_and_confirm_transaction(&transaction)
+                .await
+                .unwrap();
+            let nonce_blockhash = get_nonce_blockhash(&config, &nonce.pubkey()).await.unwrap();
+
+            let token = Keypair::new();
+            let token_pubkey = token.pubkey();
+            let file = NamedTempFile::new().unwrap();
+            let path = file.path().to_str().unwrap().to_string();
+            config.nonce_account = Some(nonce.pubkey());
+            config.nonce_authority = Some(payer.pubkey());
+            config.blockhash = Some(nonce_blockhash);
+            config.sign_only = true;
+            config.output_file = Some(path.clone());
+            command_create_token(
+                &config,
+                TEST_DECIMALS,
+                token_pubkey,
+                payer.pubkey(),
+                false,
+                vec![],
+                vec![Box::new(clone_keypair(&payer)), Box::new(token)],
+            )
+            .await
+            .unwrap();
+
+            config.blockhash = None;
+            config.sign_only = false;
+            config.output_file = None;
+            config.input_file = Some(path);
+            command_create_token(
+                &config,
+                TEST_DECIMALS,
+                token_pubkey,
+                payer.pubkey(),
+                false,
+                vec![],
+                vec![Box::new(clone_keypair(&payer))],
+            )
+            .await
+            .unwrap();
+
+            let account = config.rpc_client.get_account(&token_pubkey).await.unwrap();
+            assert_eq!(account.owner, program_id);
+            let new_nonce_blockhash = get_nonce_blockhash(&config, &nonce.pubkey()).await.unwrap();
+            assert_ne!(new_nonce_blockhash, nonce_blockhash);
+        }
+    }
+
+    #[tokio::test]
+    #[parallel(two)]
+    async fn supply() {
+        let (test_validator, payer) = new_validator_for_test().await;
+        for program_id in [spl_token::id(), spl_token_2022::id()] {
+            let config = test_config(&test_validator, &payer, &program_id);
+            let token = create_token(&config, &payer).await;
+            let result = process_test_command(
+                &config,
+                &payer,
+                &["spl-token", CommandName::Supply.into(), &token.to_string()],
+            )
+            .await;
+            let value: serde_json::Value = serde_json::from_str(&result.unwrap()).unwrap();
+            assert_eq!(value["amount"], "0");
+            assert_eq!(value["uiAmountString"], "0");
+        }
+    }
+
+    #[tokio::test]
+    #[parallel(two)]
+    async fn supply_show_withheld() {
+        let (test_validator, payer) = new_validator_for_test().await;
+        let config = test_config(&test_validator, &payer, &spl_token_2022::id());
+        let token = create_token_with_transfer_fee(&config, &payer, 100, 50).await;
+        let source = create_associated_account(&config, &payer, token).await;
+        let destination = create_auxiliary_account(&config, &payer, token).await;
+        mint_tokens(&config, &payer, token, 1000.0, source).await;
+        process_test_command(
+            &config,
+            &payer,
+            &[
+                "spl-token",
+                CommandName::Transfer.into(),
+                &token.to_string(),
+                "100",
+                &destination.to_string(),
+            ],
         )
-        .subcommand(
-            SubCommand::with_name(CommandName::AccountInfo.into())
-                .about("Query details of an SPL Token account by address")
-                .arg(
-                    Arg::with_name("token")
-                        .validator(is_valid_pubkey)
-                        .value_name("TOKEN_ADDRESS")
-                        .takes_value(true)
-                        .index(1)
-                        .conflicts_with("address")
-                        .required_unless("address")
-                        .help("Token of associated account. \
-                               To query a specific account, use the `--address` parameter instead"),
-                )
-                .arg(
-                    owner_address_arg()
-                        .index(2)
-                        .conflicts_with("address")
-                        .help("Owner of the associated account for the specified token. \
-                               To query a specific account, use the `--address` parameter instead. \
-                               Defaults to the client keypair."),
-                )
-                .arg(
-                    Arg::with_name("address")
-                        .validator(is_valid_pubkey)
-                        .value_name("TOKEN_ACCOUNT_ADDRESS")
-                        .takes_value(true)
-                        .long("address")
-                        .conflicts_with("token")
-                        .help("Specify the token account to query"),
-                ),
+        .await
+        .unwrap();
+
+        let token_address = token.to_string();
+        let supply_args = [
+            "spl-token",
+            CommandName::Supply.into(),
+            token_address.as_str(),
+            "--show-withheld",
+        ];
+        let result = process_test_command(&config, &payer, &supply_args).await;
+        let value: serde_json::Value = serde_json::from_str(&result.unwrap()).unwrap();
+        assert_eq!(value["amount"], "1000");
+        assert_eq!(value["withheld"]["inMint"]["amount"], "0");
+        assert_eq!(value["withheld"]["inAccounts"]["amount"], "1");
+
+        process_test_command(
+            &config,
+            &payer,
+            &[
+                "spl-token",
+                CommandName::HarvestWithheldTokensToMint.into(),
+                &token.to_string(),
+                &destination.to_string(),
+            ],
         )
-        .subcommand(
-            SubCommand::with_name(CommandName::MultisigInfo.into())
-                .about("Query details about and SPL Token multisig account by address")
-                .arg(
-                    Arg::with_name("address")
-                    .validator(is_valid_pubkey)
-                    .value_name("MULTISIG_ACCOUNT_ADDRESS")
-                    .takes_value(true)
-                    .index(1)
-                    .required(true)
-                    .help("The address of the SPL Token multisig account to query"),
-                ),
+        .await
+        .unwrap();
+        let result = process_test_command(&config, &payer, &supply_args).await;
+        let value: serde_json::Value = serde_json::from_str(&result.unwrap()).unwrap();
+        assert_eq!(value["withheld"]["inMint"]["amount"], "1");
+        assert_eq!(value["withheld"]["inAccounts"]["amount"], "0");
+
+        let plain_token = create_token(&config, &payer).await;
+        process_test_command(
+            &config,
+            &payer,
+            &[
+                "spl-token",
+                CommandName::Supply.into(),
+                &plain_token.to_string(),
+                "--show-withheld",
+            ],
+        )
+        .await
+        .unwrap_err();
+    }
+
+    #[tokio::test]
+    #[parallel(one)]
+    async fn create_account_default() {
+        let (test_validator, payer) = new_validator_for_test().await;
+        for program_id in [spl_token::id(), spl_token_2022::id()] {
+            let config = test_config(&test_validator, &payer, &program_id);
+            let token = create_token(&config, &payer).await;
+            let result = process_test_command(
+                &config,
+                &payer,
+                &[
+                    "spl-token",
+                    CommandName::CreateAccount.into(),
+                    &token.to_string(),
+                ],
+            )
+            .await;
+            result.unwrap();
+        }
+    }
+
+    #[tokio::test]
+    #[parallel(two)]
+    async fn create_account_idempotent() {
+        let (test_validator, payer) = new_validator_for_test().await;
+        for program_id in [spl_token::id(), spl_token_2022::id()] {
+            let config = test_config(&test_validator, &payer, &program_id);
+            let token = create_token(&config, &payer).await;
+            let token_string = token.to_string();
+            let args = [
+                "spl-token",
+                CommandName::CreateAccount.into(),
+                &token_string,
+                "--idempotent",
+            ];
+            let result = process_test_command(&config, &payer, &args).await.unwrap();
+            let value: serde_json::Value = serde_json::from_str(&result).unwrap();
+            assert_eq!(value["existed"], false);
+            assert!(value["signature"].is_string());
+
+            let result = process_test_command(&config, &payer, &args).await.unwrap();
+            let value: serde_json::Value = serde_json::from_str(&result).unwrap();
+            assert_eq!(value["existed"], true);
+            assert_eq!(
+                value["address"],
+                get_associated_token_address_with_program_id(&payer.pubkey(), &token, &program_id)
+                    .to_string()
+            );
+        }
+    }
+
+    #[tokio::test]
+    #[parallel(two)]
+    async fn create_account_with_keypair() {
+        let (test_validator, payer) = new_validator_for_test().await;
+        let config = test_config(&test_validator, &payer, &spl_token_2022::id());
+        // accounts for this mint need room for the withheld transfer fees
+        let token = create_token_with_transfer_fee(&config, &payer, 100, 50).await;
+        let account = Keypair::new();
+        let account_file = NamedTempFile::new().unwrap();
+        write_keypair_file(&account, &account_file).unwrap();
+        process_test_command(
+            &config,
+            &payer,
+            &[
+                "spl-token",
+                CommandName::CreateAccount.into(),
+                &token.to_string(),
+                account_file.path().to_str().unwrap(),
+            ],
         )
-        .subcommand(
-            SubCommand::with_name(CommandName::Gc.into())
-                .about("Cleanup unnecessary token accounts")
-                .arg(owner_keypair_arg())
-                .arg(
-                    Arg::with_name("close_empty_associated_accounts")
-                    .long("close-empty-associated-accounts")
-                    .takes_value(false)
-                    .help("close all empty associated token accounts (to get SOL back)")
-                )
+        .await
+        .unwrap();
+
+        let account_data = config
+            .rpc_client
+            .get_account(&account.pubkey())
+            .await
+            .unwrap();
+        let token_account = StateWithExtensionsOwned::<Account>::unpack(account_data.data).unwrap();
+        assert_eq!(token_account.base.mint, token);
+        assert_eq!(token_account.base.owner, payer.pubkey());
+        token_account.get_extension::<TransferFeeAmount>().unwrap();
+    }
+
+    #[tokio::test]
+    #[parallel(two)]
+    async fn create_account_with_immutable_owner() {
+        let (test_validator, payer) = new_validator_for_test().await;
+        for program_id in [spl_token::id(), spl_token_2022::id()] {
+            let config = test_config(&test_validator, &payer, &program_id);
+            let token = create_token(&config, &payer).await;
+            let account = Keypair::new();
+            let account_file = NamedTempFile::new().unwrap();
+            write_keypair_file(&account, &account_file).unwrap();
+            let result = process_test_command(
+                &config,
+                &payer,
+                &[
+                    "spl-token",
+                    CommandName::CreateAccount.into(),
+                    &token.to_string(),
+                    account_file.path().to_str().unwrap(),
+                    "--immutable-owner",
+                ],
+            )
+            .await;
+            if program_id == spl_token::id() {
+                result.unwrap_err();
+                continue;
+            }
+            result.unwrap();
+
+            let result = process_test_command(
+                &config,
+                &payer,
+                &[
+                    "spl-token",
+                    CommandName::Display.into(),
+                    &account.pubkey().to_string(),
+                ],
+            )
+            .await;
+            let value: serde_json::Value = serde_json::from_str(&result.unwrap()).unwrap();
+            assert_eq!(value["isAssociated"], false);
+            assert_eq!(
+                value["extensions"],
+                serde_json::json!([{ "immutableOwner": {} }])
+            );
+        }
+    }
+
+    #[tokio::test]
+    #[parallel(two)]
+    async fn create_account_with_seed() {
+        let (test_validator, payer) = new_validator_for_test().await;
+        for program_id in [spl_token::id(), spl_token_2022::id()] {
+            let config = test_config(&test_validator, &payer, &program_id);
+            let token = create_token(&config, &payer).await;
+            let args = [
+                "spl-token",
+                CommandName::CreateAccount.into(),
+                &token.to_string(),
+                "--seed",
+                "savings",
+            ];
+            let result = process_test_command(&config, &payer, &args).await.unwrap();
+            let value: serde_json::Value = serde_json::from_str(&result).unwrap();
+            let address =
+                Pubkey::create_with_seed(&payer.pubkey(), "savings", &program_id).unwrap();
+            assert_eq!(value["address"], address.to_string());
+
+            let account = config.rpc_client.get_account(&address).await.unwrap();
+            assert_eq!(account.owner, program_id);
+            let token_account = StateWithExtensionsOwned::<Account>::unpack(account.data).unwrap();
+            assert_eq!(token_account.base.mint, token);
+            assert_eq!(token_account.base.owner, payer.pubkey());
+
+            // The same seed always gives the same account
+            process_test_command(&config, &payer, &args)
+                .await
+                .unwrap_err();
+        }
+
+        // Offline, a Token-2022 account can't be sized for its mint
+        let mut config = test_config(&test_validator, &payer, &spl_token_2022::id());
+        let token = create_token(&config, &payer).await;
+        config.sign_only = true;
+        let result = process_test_command(
+            &config,
+            &payer,
+            &[
+                "spl-token",
+                CommandName::CreateAccount.into(),
+                &token.to_string(),
+                "--seed",
+                "offline",
+            ],
         )
-        .subcommand(
-            SubCommand::with_name(CommandName::SyncNative.into())
-                .about("Sync a native SOL token account to its underlying lamports")
-                .arg(
-                    owner_address_arg()
-                        .index(1)
-                        .conflicts_with("address")
-                        .help("Owner of the associated account for the native token. \
-                               To query a specific account, use the `--address` parameter instead. \
-                               Defaults to the client keypair."),
-                )
-                .arg(
-                    Arg::with_name("address")
-                        .validator(is_valid_pubkey)
-                        .value_name("TOKEN_ACCOUNT_ADDRESS")
-                        .takes_value(true)
-                        .long("address")
-                        .conflicts_with("owner")
-                        .help("Specify the specific token account address to sync"),
-                ),
+        .await;
+        assert!(result.unwrap_err().to_string().contains("--sign-only"));
+    }
+
+    #[tokio::test]
+    #[parallel(two)]
+    async fn account_info() {
+        let (test_validator, payer) = new_validator_for_test().await;
+        for program_id in [spl_token::id(), spl_token_2022::id()] {
+            let config = test_config(&test_validator, &payer, &program_id);
+            let token = create_token(&config, &payer).await;
+            let _account = create_associated_account(&config, &payer, token).await;
+            let result = process_test_command(
+                &config,
+                &payer,
+                &[
+                    "spl-token",
+                    CommandName::AccountInfo.into(),
+                    &token.to_string(),
+                ],
+            )
+            .await;
+            let value: serde_json::Value = serde_json::from_str(&result.unwrap()).unwrap();
+            let account = get_associated_token_address_with_program_id(
+                &payer.pubkey(),
+                &token,
+                &config.program_id,
+            );
+            assert_eq!(value["address"], account.to_string());
+            assert_eq!(value["mint"], token.to_string());
+            assert_eq!(value["isAssociated"], true);
+            assert_eq!(value["isNative"], false);
+            assert_eq!(value["owner"], payer.pubkey().to_string());
+            assert_eq!(value["state"], "initialized");
+        }
+    }
+
+    #[tokio::test]
+    #[parallel(one)]
+    async fn display() {
+        let (test_validator, payer) = new_validator_for_test().await;
+        for program_id in [spl_token::id(), spl_token_2022::id()] {
+            let config = test_config(&test_validator, &payer, &program_id);
+            let token = create_token(&config, &payer).await;
+            let result = process_test_command(
+                &config,
+                &payer,
+                &["spl-token", CommandName::Display.into(), &token.to_string()],
+            )
+            .await;
+            let value: serde_json::Value = serde_json::from_str(&result.unwrap()).unwrap();
+            assert_eq!(value["address"], token.to_string());
+            assert_eq!(value["programId"], program_id.to_string());
+            assert_eq!(value["decimals"], TEST_DECIMALS);
+            assert_eq!(value["mintAuthority"], payer.pubkey().to_string());
+            assert_eq!(value["freezeAuthority"], serde_json::Value::Null);
+            assert_eq!(value["extensions"], serde_json::json!([]));
+        }
+    }
+
+    #[tokio::test]
+    #[parallel(one)]
+    async fn display_token_account() {
+        let (test_validator, payer) = new_validator_for_test().await;
+        for program_id in [spl_token::id(), spl_token_2022::id()] {
+            let config = test_config(&test_validator, &payer, &program_id);
+            let token = create_token(&config, &payer).await;
+            let account = create_associated_account(&config, &payer, token).await;
+            mint_tokens(&config, &payer, token, 10.0, account).await;
+            let result = process_test_command(
+                &config,
+                &payer,
+                &[
+                    "spl-token",
+                    CommandName::Display.into(),
+                    &account.to_string(),
+                ],
+            )
+            .await;
+            let value: serde_json::Value = serde_json::from_str(&result.unwrap()).unwrap();
+            assert_eq!(value["address"], account.to_string());
+            assert_eq!(value["programId"], program_id.to_string());
+            assert_eq!(value["mint"], token.to_string());
+            assert_eq!(value["owner"], payer.pubkey().to_string());
+            assert_eq!(value["isAssociated"], true);
+            assert_eq!(value["amount"], "10");
+            assert_eq!(value["state"], "initialized");
+            // Token-2022 associated token accounts always have an immutable owner
+            if program_id == spl_token_2022::id() {
+                assert_eq!(
+                    value["extensions"],
+                    serde_json::json!([{ "immutableOwner": {} }])
+                );
+            } else {
+                assert_eq!(value["extensions"], serde_json::json!([]));
+            }
+        }
+    }
+
+    #[tokio::test]
+    #[parallel(one)]
+    async fn configure_confidential_transfer_account() {
+        let (test_validator, payer) = new_validator_for_test().await;
+        let config = test_config(&test_validator, &payer, &spl_token_2022::id());
+        let token = create_confidential_token(&config, &payer).await;
+        let account = create_associated_account(&config, &payer, token).await;
+        process_test_command(
+            &config,
+            &payer,
+            &[
+                "spl-token",
+                CommandName::ConfigureConfidentialTransferAccount.into(),
+                &account.to_string(),
+            ],
         )
-}
+        .await
+        .unwrap();
 
-#[tokio::main]
-async fn main() -> Result<(), Error> {
-    let default_decimals = format!("{}", spl_token_2022::native_mint::DECIMALS);
-    let default_program_id = spl_token::id().to_string();
-    let minimum_signers_help = minimum_signers_help_string();
-    let multisig_member_help = multisig_member_help_string();
-    let app_matches = app(
-        &default_decimals,
-        &default_program_id,
-        &minimum_signers_help,
-        &multisig_member_help,
-    )
-    .get_matches();
+        let elgamal_keypair = ElGamalKeypair::new(&payer, &account).unwrap();
+        let token_account = config.rpc_client.get_account(&account).await.unwrap();
+        let token_account =
+            StateWithExtensionsOwned::<Account>::unpack(token_account.data).unwrap();
+        let extension = token_account
+            .get_extension::<ConfidentialTransferAccount>()
+            .unwrap();
+        assert!(bool::from(extension.approved));
+        assert_eq!(
+            extension.encryption_pubkey,
+            EncryptionPubkey::from(elgamal_keypair.public)
+        );
+    }
+
+    #[tokio::test]
+    #[parallel(two)]
+    async fn deposit_confidential() {
+        let (test_validator, payer) = new_validator_for_test().await;
+        let config = test_config(&test_validator, &payer, &spl_token_2022::id());
+        let token = create_confidential_token(&config, &payer).await;
+        let account = create_associated_account(&config, &payer, token).await;
+        mint_tokens(&config, &payer, token, 100.0, account).await;
+        process_test_command(
+            &config,
+            &payer,
+            &[
+                "spl-token",
+                CommandName::ConfigureConfidentialTransferAccount.into(),
+                &account.to_string(),
+            ],
+        )
+        .await
+        .unwrap();
+        process_test_command(
+            &config,
+            &payer,
+            &[
+                "spl-token",
+                CommandName::DepositConfidential.into(),
+                &account.to_string(),
+                "10",
+            ],
+        )
+        .await
+        .unwrap();
 
-    let mut wallet_manager = None;
-    let mut bulk_signers: Vec<Box<dyn Signer>> = Vec::new();
-    let mut multisigner_ids = Vec::new();
+        let token_account = config.rpc_client.get_account(&account).await.unwrap();
+        let token_account =
+            StateWithExtensionsOwned::<Account>::unpack(token_account.data).unwrap();
+        assert_eq!(token_account.base.amount, 90);
+        let extension = token_account
+            .get_extension::<ConfidentialTransferAccount>()
+            .unwrap();
+        assert_eq!(u64::from(extension.pending_balance_credit_counter), 1);
 
-    let (sub_command, sub_matches) = app_matches.subcommand();
-    let sub_command = CommandName::from_str(sub_command).unwrap();
-    let matches = sub_matches.unwrap();
+        process_test_command(
+            &config,
+            &payer,
+            &[
+                "spl-token",
+                CommandName::ApplyPendingBalance.into(),
+                &account.to_string(),
+            ],
+        )
+        .await
+        .unwrap();
+        let ae_key = AeKey::new(&payer, &account).unwrap();
+        let extension = get_confidential_transfer_account(&config, &account)
+            .await
+            .unwrap();
+        let available_balance = decrypt_available_balance(&account, &extension, &ae_key).unwrap();
+        assert_eq!(available_balance, 10);
+    }
 
-    let config = {
-        let cli_config = if let Some(config_file) = matches.value_of("config_file") {
-            solana_cli_config::Config::load(config_file).unwrap_or_else(|_| {
-                eprintln!("error: Could not find config file `{}`", config_file);
-                exit(1);
-            })
-        } else {
-            solana_cli_config::Config::default()
-        };
-        let json_rpc_url = normalize_to_url_if_moniker(
-            matches
-                .value_of("json_rpc_url")
-                .unwrap_or(&cli_config.json_rpc_url),
+    #[tokio::test]
+    #[parallel(two)]
+    async fn apply_pending_balance_above_16_bits() {
+        let (test_validator, payer) = new_validator_for_test().await;
+        let config = test_config(&test_validator, &payer, &spl_token_2022::id());
+        let token = create_confidential_token(&config, &payer).await;
+        let account = create_associated_account(&config, &payer, token).await;
+        mint_tokens(&config, &payer, token, 100_000.0, account).await;
+        process_test_command(
+            &config,
+            &payer,
+            &[
+                "spl-token",
+                CommandName::ConfigureConfidentialTransferAccount.into(),
+                &account.to_string(),
+            ],
+        )
+        .await
+        .unwrap();
+
+        // the program splits pending amounts into their low 16 bits and the rest
+        process_test_command(
+            &config,
+            &payer,
+            &[
+                "spl-token",
+                CommandName::DepositConfidential.into(),
+                &account.to_string(),
+                "70000",
+            ],
+        )
+        .await
+        .unwrap();
+        let extension = get_confidential_transfer_account(&config, &account)
+            .await
+            .unwrap();
+        let elgamal_keypair = ElGamalKeypair::new(&payer, &account).unwrap();
+        assert_eq!(
+            decrypt_pending_balance(&account, &extension, &elgamal_keypair).unwrap(),
+            70000
         );
-        let websocket_url = solana_cli_config::Config::compute_websocket_url(&json_rpc_url);
 
-        let (signer, fee_payer) = signer_from_path(
-            matches,
-            matches
-                .value_of("fee_payer")
-                .unwrap_or(&cli_config.keypair_path),
-            "fee_payer",
-            &mut wallet_manager,
+        process_test_command(
+            &config,
+            &payer,
+            &[
+                "spl-token",
+                CommandName::ApplyPendingBalance.into(),
+                &account.to_string(),
+            ],
         )
-        .map(|s| {
-            let p = s.pubkey();
-            (s, p)
-        })
-        .unwrap_or_else(|e| {
-            eprintln!("error: {}", e);
-            exit(1);
-        });
-        bulk_signers.push(signer);
+        .await
+        .unwrap();
+        let ae_key = AeKey::new(&payer, &account).unwrap();
+        let extension = get_confidential_transfer_account(&config, &account)
+            .await
+            .unwrap();
+        let available_balance = decrypt_available_balance(&account, &extension, &ae_key).unwrap();
+        assert_eq!(available_balance, 70000);
+    }
 
-        let verbose = matches.is_present("verbose");
-        let output_format = matches
-            .value_of("output_format")
-            .map(|value| match value {
-                "json" => OutputFormat::Json,
-                "json-compact" => OutputFormat::JsonCompact,
-                _ => unreachable!(),
-            })
-            .unwrap_or(if verbose {
-                OutputFormat::DisplayVerbose
-            } else {
-                OutputFormat::Display
-            });
+    #[tokio::test]
+    #[parallel(two)]
+    async fn apply_pending_balance() {
+        let (test_validator, payer) = new_validator_for_test().await;
+        let config = test_config(&test_validator, &payer, &spl_token_2022::id());
+        let token = create_confidential_token(&config, &payer).await;
+        let account = create_associated_account(&config, &payer, token).await;
+        mint_tokens(&config, &payer, token, 100.0, account).await;
+        process_test_command(
+            &config,
+            &payer,
+            &[
+                "spl-token",
+                CommandName::ConfigureConfidentialTransferAccount.into(),
+                &account.to_string(),
+            ],
+        )
+        .await
+        .unwrap();
 
-        let nonce_account = pubkey_of_signer(matches, NONCE_ARG.name, &mut wallet_manager)
-            .unwrap_or_else(|e| {
-                eprintln!("error: {}", e);
-                exit(1);
-            });
-        let nonce_authority = if nonce_account.is_some() {
-            let (signer, nonce_authority) = signer_from_path(
-                matches,
-                matches
-                    .value_of(NONCE_AUTHORITY_ARG.name)
-                    .unwrap_or(&cli_config.keypair_path),
-                NONCE_AUTHORITY_ARG.name,
-                &mut wallet_manager,
-            )
-            .map(|s| {
-                let p = s.pubkey();
-                (s, p)
-            })
-            .unwrap_or_else(|e| {
-                eprintln!("error: {}", e);
-                exit(1);
-            });
-            bulk_signers.push(signer);
+        // nothing pending yet
+        let result = process_test_command(
+            &config,
+            &payer,
+            &[
+                "spl-token",
+                CommandName::ApplyPendingBalance.into(),
+                &account.to_string(),
+            ],
+        )
+        .await
+        .unwrap();
+        assert_eq!(result, "");
 
-            Some(nonce_authority)
-        } else {
-            None
-        };
+        process_test_command(
+            &config,
+            &payer,
+            &[
+                "spl-token",
+                CommandName::DepositConfidential.into(),
+                &account.to_string(),
+                "30",
+            ],
+        )
+        .await
+        .unwrap();
+        process_test_command(
+            &config,
+            &payer,
+            &[
+                "spl-token",
+                CommandName::ApplyPendingBalance.into(),
+                &account.to_string(),
+            ],
+        )
+        .await
+        .unwrap();
 
-        let sign_only = matches.is_present(SIGN_ONLY_ARG.name);
-        let dump_transaction_message = matches.is_present(DUMP_TRANSACTION_MESSAGE.name);
-        let program_id = pubkey_of(matches, "program_id").unwrap();
+        let ae_key = AeKey::new(&payer, &account).unwrap();
+        let extension = get_confidential_transfer_account(&config, &account)
+            .await
+            .unwrap();
+        assert_eq!(u64::from(extension.pending_balance_credit_counter), 0);
+        let available_balance = decrypt_available_balance(&account, &extension, &ae_key).unwrap();
+        assert_eq!(available_balance, 30);
+    }
 
-        let multisig_signers = signers_of(matches, MULTISIG_SIGNER_ARG.name, &mut wallet_manager)
-            .unwrap_or_else(|e| {
-                eprintln!("error: {}", e);
-                exit(1);
-            });
-        if let Some(mut multisig_signers) = multisig_signers {
-            multisig_signers.sort_by(|(_, lp), (_, rp)| lp.cmp(rp));
-            let (signers, pubkeys): (Vec<_>, Vec<_>) = multisig_signers.into_iter().unzip();
-            bulk_signers.extend(signers);
-            multisigner_ids = pubkeys;
+    #[tokio::test]
+    #[parallel(two)]
+    async fn set_transfer_fee() {
+        let (test_validator, payer) = new_validator_for_test().await;
+        let config = test_config(&test_validator, &payer, &spl_token_2022::id());
+        let token = create_token_with_transfer_fee(&config, &payer, 100, 50).await;
+        process_test_command(
+            &config,
+            &payer,
+            &[
+                "spl-token",
+                CommandName::SetTransferFee.into(),
+                &token.to_string(),
+                "250",
+                "75",
+            ],
+        )
+        .await
+        .unwrap();
+        let account = config.rpc_client.get_account(&token).await.unwrap();
+        let mint = StateWithExtensionsOwned::<Mint>::unpack(account.data).unwrap();
+        let transfer_fee_config = mint.get_extension::<TransferFeeConfig>().unwrap();
+        let newer_transfer_fee = &transfer_fee_config.newer_transfer_fee;
+        assert_eq!(u16::from(newer_transfer_fee.transfer_fee_basis_points), 250);
+        assert_eq!(u64::from(newer_transfer_fee.maximum_fee), 75);
+
+        let result = command_set_transfer_fee(
+            &config,
+            token,
+            MAX_FEE_BASIS_POINTS + 1,
+            75.0,
+            payer.pubkey(),
+            None,
+            vec![Box::new(clone_keypair(&payer))],
+        )
+        .await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    #[parallel(one)]
+    async fn balance() {
+        let (test_validator, payer) = new_validator_for_test().await;
+        for program_id in [spl_token::id(), spl_token_2022::id()] {
+            let config = test_config(&test_validator, &payer, &program_id);
+            let token = create_token(&config, &payer).await;
+            let _account = create_associated_account(&config, &payer, token).await;
+            let result = process_test_command(
+                &config,
+                &payer,
+                &["spl-token", CommandName::Balance.into(), &token.to_string()],
+            )
+            .await;
+            let value: serde_json::Value = serde_json::from_str(&result.unwrap()).unwrap();
+            assert_eq!(value["amount"], "0");
+            assert_eq!(value["uiAmountString"], "0");
         }
-        let multisigner_pubkeys = multisigner_ids.iter().collect::<Vec<_>>();
+    }
 
-        let rpc_client = Arc::new(RpcClient::new_with_commitment(
-            json_rpc_url,
-            CommitmentConfig::confirmed(),
-        ));
-        Config {
-            rpc_client,
-            websocket_url,
-            output_format,
-            fee_payer,
-            default_keypair: KeypairOrPath::Path(cli_config.keypair_path),
-            nonce_account,
-            nonce_authority,
-            sign_only,
-            dump_transaction_message,
-            multisigner_pubkeys,
-            program_id,
+    #[tokio::test]
+    #[parallel(two)]
+    async fn balance_all_for_owner() {
+        let (test_validator, payer) = new_validator_for_test().await;
+        for program_id in [spl_token::id(), spl_token_2022::id()] {
+            let config = test_config(&test_validator, &payer, &program_id);
+            let token = create_token(&config, &payer).await;
+            let associated = create_associated_account(&config, &payer, token).await;
+            let auxiliary = create_auxiliary_account(&config, &payer, token).await;
+            mint_tokens(&config, &payer, token, 30.0, associated).await;
+            mint_tokens(&config, &payer, token, 12.0, auxiliary).await;
+            let result = process_test_command(
+                &config,
+                &payer,
+                &[
+                    "spl-token",
+                    CommandName::Balance.into(),
+                    &token.to_string(),
+                    "--all-for-owner",
+                ],
+            )
+            .await;
+            let value: serde_json::Value = serde_json::from_str(&result.unwrap()).unwrap();
+            assert_eq!(value["amount"], "42");
+            assert_eq!(value["uiAmountString"], "42");
+            let accounts = value["accounts"].as_array().unwrap();
+            assert_eq!(accounts.len(), 2);
+            for (address, amount) in [(associated, "30"), (auxiliary, "12")] {
+                assert!(accounts.iter().any(|account| {
+                    account["address"] == address.to_string() && account["amount"] == amount
+                }));
+            }
+        }
+    }
+
+    #[tokio::test]
+    #[parallel(two)]
+    async fn mint() {
+        let (test_validator, payer) = new_validator_for_test().await;
+        for program_id in [spl_token::id(), spl_token_2022::id()] {
+            let config = test_config(&test_validator, &payer, &program_id);
+            let token = create_token(&config, &payer).await;
+            let account = create_associated_account(&config, &payer, token).await;
+            let result = process_test_command(
+                &config,
+                &payer,
+                &[
+                    "spl-token",
+                    CommandName::Mint.into(),
+                    &token.to_string(),
+                    "100",
+                ],
+            )
+            .await;
+            result.unwrap();
+            let account = config.rpc_client.get_account(&account).await.unwrap();
+            let token_account = StateWithExtensionsOwned::<Account>::unpack(account.data).unwrap();
+            assert_eq!(token_account.base.amount, 100);
+            assert_eq!(token_account.base.mint, token);
+            assert_eq!(token_account.base.owner, payer.pubkey());
         }
-    };
-
-    solana_logger::setup_with_default("solana=info");
-    let result =
-        process_command(&sub_command, matches, &config, wallet_manager, bulk_signers).await?;
-    println!("{}", result);
-    Ok(())
-}
+    }
 
-async fn process_command<'a>(
-    sub_command: &CommandName,
-    sub_matches: &ArgMatches<'_>,
-    config: &Config<'a>,
-    mut wallet_manager: Option<Arc<RemoteWalletManager>>,
-    mut bulk_signers: Vec<Box<dyn Signer>>,
-) -> CommandResult {
-    match (sub_command, sub_matches) {
-        (CommandName::Bench, arg_matches) => {
-            bench_process_command(
-                arg_matches,
-                config,
-                std::mem::take(&mut bulk_signers),
-                &mut wallet_manager,
+    #[tokio::test]
+    #[parallel(one)]
+    async fn balance_after_mint() {
+        let (test_validator, payer) = new_validator_for_test().await;
+        for program_id in [spl_token::id(), spl_token_2022::id()] {
+            let config = test_config(&test_validator, &payer, &program_id);
+            let token = create_token(&config, &payer).await;
+            let account = create_associated_account(&config, &payer, token).await;
+            let ui_amount = 100.0;
+            mint_tokens(&config, &payer, token, ui_amount, account).await;
+            let result = process_test_command(
+                &config,
+                &payer,
+                &["spl-token", CommandName::Balance.into(), &token.to_string()],
             )
-            .await
+            .await;
+            let value: serde_json::Value = serde_json::from_str(&result.unwrap()).unwrap();
+            assert_eq!(value["amount"], format!("{}", ui_amount));
+            assert_eq!(value["uiAmountString"], format!("{}", ui_amount));
         }
-        (CommandName::CreateToken, arg_matches) => {
-            let decimals = value_t_or_exit!(arg_matches, "decimals", u8);
-            let mint_authority =
-                config.pubkey_or_default(arg_matches, "mint_authority", &mut wallet_manager);
-            let memo = value_t!(arg_matches, "memo", String).ok();
-
-            let (token_signer, token) =
-                get_signer(arg_matches, "token_keypair", &mut wallet_manager)
-                    .unwrap_or_else(new_throwaway_signer);
-            bulk_signers.push(token_signer);
+    }
 
-            command_create_token(
-                config,
-                decimals,
-                token,
-                mint_authority,
-                arg_matches.is_present("enable_freeze"),
-                memo,
-                bulk_signers,
+    #[tokio::test]
+    #[parallel(two)]
+    async fn accounts() {
+        let (test_validator, payer) = new_validator_for_test().await;
+        for program_id in [spl_token::id(), spl_token_2022::id()] {
+            let config = test_config(&test_validator, &payer, &program_id);
+            let token1 = create_token(&config, &payer).await;
+            let _account1 = create_associated_account(&config, &payer, token1).await;
+            let token2 = create_token(&config, &payer).await;
+            let _account2 = create_associated_account(&config, &payer, token2).await;
+            let token3 = create_token(&config, &payer).await;
+            let result = process_test_command(
+                &config,
+                &payer,
+                &["spl-token", CommandName::Accounts.into()],
             )
             .await
+            .unwrap();
+            assert!(result.contains(&token1.to_string()));
+            assert!(result.contains(&token2.to_string()));
+            assert!(!result.contains(&token3.to_string()));
         }
-        (CommandName::CreateAccount, arg_matches) => {
-            let token = pubkey_of_signer(arg_matches, "token", &mut wallet_manager)
-                .unwrap()
-                .unwrap();
+    }
 
-            // No need to add a signer when creating an associated token account
-            let account = get_signer(arg_matches, "account_keypair", &mut wallet_manager).map(
-                |(signer, account)| {
-                    bulk_signers.push(signer);
-                    account
-                },
+    #[tokio::test]
+    #[parallel(one)]
+    async fn wrap() {
+        let (test_validator, payer) = new_validator_for_test().await;
+        for program_id in [spl_token::id(), spl_token_2022::id()] {
+            let native_mint = native_mint(&program_id).unwrap();
+            let config = test_config(&test_validator, &payer, &program_id);
+            do_create_native_mint(&config, &program_id, &payer).await;
+            let _result = process_test_command(
+                &config,
+                &payer,
+                &["spl-token", CommandName::Wrap.into(), "0.5"],
+            )
+            .await
+            .unwrap();
+            let account = get_associated_token_address_with_program_id(
+                &payer.pubkey(),
+                &native_mint,
+                &config.program_id,
             );
+            let account_data = config.rpc_client.get_account(&account).await.unwrap();
+            let token_account =
+                StateWithExtensionsOwned::<Account>::unpack(account_data.data).unwrap();
+            assert_eq!(token_account.base.mint, native_mint);
+            assert_eq!(token_account.base.owner, payer.pubkey());
+            assert!(token_account.base.is_native());
+            assert_eq!(token_account.base.amount, sol_to_lamports(0.5));
 
-            let owner = config.pubkey_or_default(arg_matches, "owner", &mut wallet_manager);
-            command_create_account(config, token, owner, account, bulk_signers).await
-        }
-        (CommandName::CreateMultisig, arg_matches) => {
-            let minimum_signers = value_of::<u8>(arg_matches, "minimum_signers").unwrap();
-            let multisig_members =
-                pubkeys_of_multiple_signers(arg_matches, "multisig_member", &mut wallet_manager)
-                    .unwrap_or_else(|e| {
-                        eprintln!("error: {}", e);
-                        exit(1);
-                    })
-                    .unwrap();
-            if minimum_signers as usize > multisig_members.len() {
-                eprintln!(
-                    "error: MINIMUM_SIGNERS cannot be greater than the number \
-                          of MULTISIG_MEMBERs passed"
-                );
-                exit(1);
-            }
-
-            let (signer, account) = get_signer(arg_matches, "address_keypair", &mut wallet_manager)
-                .unwrap_or_else(new_throwaway_signer);
-            bulk_signers.push(signer);
+            // wrapping again tops up the same account
+            process_test_command(
+                &config,
+                &payer,
+                &["spl-token", CommandName::Wrap.into(), "0.25"],
+            )
+            .await
+            .unwrap();
+            let account_data = config.rpc_client.get_account(&account).await.unwrap();
+            let token_account =
+                StateWithExtensionsOwned::<Account>::unpack(account_data.data).unwrap();
+            assert_eq!(token_account.base.amount, sol_to_lamports(0.75));
 
-            command_create_multisig(
-                config,
-                account,
-                minimum_signers,
-                multisig_members,
-                bulk_signers,
+            let recipient = Pubkey::new_unique();
+            process_test_command(
+                &config,
+                &payer,
+                &[
+                    "spl-token",
+                    CommandName::Wrap.into(),
+                    "0.1",
+                    "--recipient",
+                    &recipient.to_string(),
+                ],
             )
             .await
+            .unwrap();
+            let account = get_associated_token_address_with_program_id(
+                &recipient,
+                &native_mint,
+                &config.program_id,
+            );
+            let account_data = config.rpc_client.get_account(&account).await.unwrap();
+            let token_account =
+                StateWithExtensionsOwned::<Account>::unpack(account_data.data).unwrap();
+            assert_eq!(token_account.base.owner, recipient);
+            assert_eq!(token_account.base.amount, sol_to_lamports(0.1));
         }
-        (CommandName::Authorize, arg_matches) => {
-            let address = pubkey_of_signer(arg_matches, "address", &mut wallet_manager)
-                .unwrap()
-                .unwrap();
-            let authority_type = arg_matches.value_of("authority_type").unwrap();
-            let authority_type = match authority_type {
-                "mint" => AuthorityType::MintTokens,
-                "freeze" => AuthorityType::FreezeAccount,
-                "owner" => AuthorityType::AccountOwner,
-                "close" => AuthorityType::CloseAccount,
-                "close-mint" => AuthorityType::CloseMint,
-                "transfer-fee-config" => AuthorityType::TransferFeeConfig,
-                "withheld-withdraw" => AuthorityType::WithheldWithdraw,
-                "interest-rate" => AuthorityType::InterestRate,
-                _ => unreachable!(),
-            };
-
-            let (authority_signer, authority) =
-                config.signer_or_default(arg_matches, "authority", &mut wallet_manager);
-            bulk_signers.push(authority_signer);
+    }
 
-            let new_authority =
-                pubkey_of_signer(arg_matches, "new_authority", &mut wallet_manager).unwrap();
-            let force_authorize = arg_matches.is_present("force");
-            command_authorize(
-                config,
-                address,
-                authority_type,
-                authority,
-                new_authority,
-                force_authorize,
+    #[tokio::test]
+    #[parallel(two)]
+    async fn unwrap() {
+        let (test_validator, payer) = new_validator_for_test().await;
+        for program_id in [spl_token::id(), spl_token_2022::id()] {
+            let config = test_config(&test_validator, &payer, &program_id);
+            do_create_native_mint(&config, &program_id, &payer).await;
+            let (signer, account) = new_throwaway_signer();
+            let bulk_signers: Vec<Box<dyn Signer>> = vec![Box::new(clone_keypair(&payer)), signer];
+            command_wrap(
+                &config,
+                0.5,
+                payer.pubkey(),
+                payer.pubkey(),
+                Some(account),
                 bulk_signers,
             )
             .await
+            .unwrap();
+            let result = process_test_command(
+                &config,
+                &payer,
+                &[
+                    "spl-token",
+                    CommandName::Unwrap.into(),
+                    &account.to_string(),
+                ],
+            )
+            .await;
+            result.unwrap();
+            config.rpc_client.get_account(&account).await.unwrap_err();
         }
-        (CommandName::Transfer, arg_matches) => {
-            let token = pubkey_of_signer(arg_matches, "token", &mut wallet_manager)
-                .unwrap()
-                .unwrap();
-            let amount = match arg_matches.value_of("amount").unwrap() {
-                "ALL" => None,
-                amount => Some(amount.parse::<f64>().unwrap()),
-            };
-            let recipient = pubkey_of_signer(arg_matches, "recipient", &mut wallet_manager)
-                .unwrap()
-                .unwrap();
-            let sender = pubkey_of_signer(arg_matches, "from", &mut wallet_manager).unwrap();
-
-            let (owner_signer, owner) =
-                config.signer_or_default(arg_matches, "owner", &mut wallet_manager);
-            bulk_signers.push(owner_signer);
-
-            let mint_decimals = value_of::<u8>(arg_matches, MINT_DECIMALS_ARG.name);
-            let fund_recipient = arg_matches.is_present("fund_recipient");
-            let allow_unfunded_recipient = arg_matches.is_present("allow_empty_recipient")
-                || arg_matches.is_present("allow_unfunded_recipient");
-
-            let recipient_is_ata_owner = arg_matches.is_present("recipient_is_ata_owner");
-            let use_unchecked_instruction = arg_matches.is_present("use_unchecked_instruction");
-            let memo = value_t!(arg_matches, "memo", String).ok();
+    }
 
-            command_transfer(
-                config,
-                token,
-                amount,
-                recipient,
-                sender,
-                owner,
-                allow_unfunded_recipient,
-                fund_recipient,
-                mint_decimals,
-                recipient_is_ata_owner,
-                use_unchecked_instruction,
-                memo,
+    #[tokio::test]
+    #[parallel(two)]
+    async fn sync_native_direct_transfer() {
+        let (test_validator, payer) = new_validator_for_test().await;
+        for program_id in [spl_token::id(), spl_token_2022::id()] {
+            let config = test_config(&test_validator, &payer, &program_id);
+            do_create_native_mint(&config, &program_id, &payer).await;
+            let (signer, account) = new_throwaway_signer();
+            let bulk_signers: Vec<Box<dyn Signer>> = vec![Box::new(clone_keypair(&payer)), signer];
+            command_wrap(
+                &config,
+                0.5,
+                payer.pubkey(),
+                payer.pubkey(),
+                Some(account),
                 bulk_signers,
-                arg_matches.is_present("no_wait"),
-                arg_matches.is_present("allow_non_system_account_recipient"),
             )
             .await
-        }
-        (CommandName::Burn, arg_matches) => {
-            let source = pubkey_of_signer(arg_matches, "source", &mut wallet_manager)
-                .unwrap()
-                .unwrap();
+            .unwrap();
 
-            let (owner_signer, owner) =
-                config.signer_or_default(arg_matches, "owner", &mut wallet_manager);
-            bulk_signers.push(owner_signer);
+            let blockhash = config.rpc_client.get_latest_blockhash().await.unwrap();
+            let transaction = Transaction::new_signed_with_payer(
+                &[system_instruction::transfer(
+                    &payer.pubkey(),
+                    &account,
+                    sol_to_lamports(0.25),
+                )],
+                Some(&payer.pubkey()),
+                &[&payer],
+                blockhash,
+            );
+            config
+                .rpc_client
+                .send_and_confirm_transaction(&transaction)
+                .await
+                .unwrap();
 
-            let amount = value_t_or_exit!(arg_matches, "amount", f64);
-            let mint_address =
-                pubkey_of_signer(arg_matches, MINT_ADDRESS_ARG.name, &mut wallet_manager).unwrap();
-            let mint_decimals = value_of::<u8>(arg_matches, MINT_DECIMALS_ARG.name);
-            let use_unchecked_instruction = arg_matches.is_present("use_unchecked_instruction");
-            let memo = value_t!(arg_matches, "memo", String).ok();
-            command_burn(
-                config,
-                source,
-                owner,
-                amount,
-                mint_address,
-                mint_decimals,
-                use_unchecked_instruction,
-                memo,
-                bulk_signers,
+            let result = process_test_command(
+                &config,
+                &payer,
+                &[
+                    "spl-token",
+                    CommandName::SyncNative.into(),
+                    &account.to_string(),
+                ],
             )
             .await
-        }
-        (CommandName::Mint, arg_matches) => {
-            let (mint_authority_signer, mint_authority) =
-                config.signer_or_default(arg_matches, "mint_authority", &mut wallet_manager);
-            bulk_signers.push(mint_authority_signer);
-
-            let token = pubkey_of_signer(arg_matches, "token", &mut wallet_manager)
+            .unwrap();
+            assert!(result.contains("0.75"));
+            let ui_account = config
+                .rpc_client
+                .get_token_account(&account)
+                .await
                 .unwrap()
                 .unwrap();
-            let amount = value_t_or_exit!(arg_matches, "amount", f64);
-            let mint_decimals = value_of::<u8>(arg_matches, MINT_DECIMALS_ARG.name);
-            let mint_info = config.get_mint_info(&token, mint_decimals).await?;
-            let recipient = if let Some(address) =
-                pubkey_of_signer(arg_matches, "recipient", &mut wallet_manager).unwrap()
-            {
-                address
-            } else {
-                config.associated_token_address_for_token_and_program(
-                    arg_matches,
-                    &mut wallet_manager,
-                    &mint_info.address,
-                    &mint_info.program_id,
-                )
-            };
-            config.check_account(&recipient, Some(token)).await?;
-            let use_unchecked_instruction = arg_matches.is_present("use_unchecked_instruction");
-            command_mint(
-                config,
-                token,
-                amount,
-                recipient,
-                mint_info,
-                mint_authority,
-                use_unchecked_instruction,
-                bulk_signers,
+            assert_eq!(
+                ui_account.token_amount.amount,
+                sol_to_lamports(0.75).to_string()
+            );
+
+            // a regular token account is taken as an owner, which has no wrapped SOL account
+            let token = create_token(&config, &payer).await;
+            let regular = create_associated_account(&config, &payer, token).await;
+            process_test_command(
+                &config,
+                &payer,
+                &[
+                    "spl-token",
+                    CommandName::SyncNative.into(),
+                    &regular.to_string(),
+                ],
             )
             .await
+            .unwrap_err();
         }
-        (CommandName::Freeze, arg_matches) => {
-            let (freeze_authority_signer, freeze_authority) =
-                config.signer_or_default(arg_matches, "freeze_authority", &mut wallet_manager);
-            bulk_signers.push(freeze_authority_signer);
+    }
 
-            let account = pubkey_of_signer(arg_matches, "account", &mut wallet_manager)
-                .unwrap()
-                .unwrap();
-            let mint_address =
-                pubkey_of_signer(arg_matches, MINT_ADDRESS_ARG.name, &mut wallet_manager).unwrap();
-            command_freeze(
-                config,
-                account,
-                mint_address,
-                freeze_authority,
+    #[tokio::test]
+    #[parallel(two)]
+    async fn unwrap_all() {
+        let (test_validator, payer) = new_validator_for_test().await;
+        for program_id in [spl_token::id(), spl_token_2022::id()] {
+            let native_mint = native_mint(&program_id).unwrap();
+            let config = test_config(&test_validator, &payer, &program_id);
+            do_create_native_mint(&config, &program_id, &payer).await;
+            let (signer, aux) = new_throwaway_signer();
+            let bulk_signers: Vec<Box<dyn Signer>> = vec![Box::new(clone_keypair(&payer)), signer];
+            command_wrap(
+                &config,
+                0.5,
+                payer.pubkey(),
+                payer.pubkey(),
+                Some(aux),
                 bulk_signers,
             )
             .await
-        }
-        (CommandName::Thaw, arg_matches) => {
-            let (freeze_authority_signer, freeze_authority) =
-                config.signer_or_default(arg_matches, "freeze_authority", &mut wallet_manager);
-            bulk_signers.push(freeze_authority_signer);
-
-            let account = pubkey_of_signer(arg_matches, "account", &mut wallet_manager)
-                .unwrap()
-                .unwrap();
-            let mint_address =
-                pubkey_of_signer(arg_matches, MINT_ADDRESS_ARG.name, &mut wallet_manager).unwrap();
-            command_thaw(
-                config,
-                account,
-                mint_address,
-                freeze_authority,
+            .unwrap();
+            let bulk_signers: Vec<Box<dyn Signer>> = vec![Box::new(clone_keypair(&payer))];
+            command_wrap(
+                &config,
+                0.25,
+                payer.pubkey(),
+                payer.pubkey(),
+                None,
                 bulk_signers,
             )
             .await
-        }
-        (CommandName::Wrap, arg_matches) => {
-            let amount = value_t_or_exit!(arg_matches, "amount", f64);
-            let account = if arg_matches.is_present("create_aux_account") {
-                let (signer, account) = new_throwaway_signer();
-                bulk_signers.push(signer);
-                Some(account)
-            } else {
-                // No need to add a signer when creating an associated token account
-                None
-            };
-
-            let (wallet_signer, wallet_address) =
-                config.signer_or_default(arg_matches, "wallet_keypair", &mut wallet_manager);
-            bulk_signers.push(wallet_signer);
+            .unwrap();
+            let associated = get_associated_token_address_with_program_id(
+                &payer.pubkey(),
+                &native_mint,
+                &program_id,
+            );
 
-            command_wrap(config, amount, wallet_address, account, bulk_signers).await
+            let recipient = Pubkey::new_unique();
+            process_test_command(
+                &config,
+                &payer,
+                &[
+                    "spl-token",
+                    CommandName::Unwrap.into(),
+                    "--all",
+                    "--recipient",
+                    &recipient.to_string(),
+                ],
+            )
+            .await
+            .unwrap();
+            config.rpc_client.get_account(&aux).await.unwrap_err();
+            config
+                .rpc_client
+                .get_account(&associated)
+                .await
+                .unwrap_err();
+            assert!(
+                config.rpc_client.get_balance(&recipient).await.unwrap() > sol_to_lamports(0.75)
+            );
         }
-        (CommandName::Unwrap, arg_matches) => {
-            let (wallet_signer, wallet_address) =
-                config.signer_or_default(arg_matches, "wallet_keypair", &mut wallet_manager);
-            bulk_signers.push(wallet_signer);
+    }
 
-            let address = pubkey_of_signer(arg_matches, "address", &mut wallet_manager).unwrap();
-            command_unwrap(config, wallet_address, address, bulk_signers).await
+    #[tokio::test]
+    #[parallel(one)]
+    async fn transfer() {
+        let (test_validator, payer) = new_validator_for_test().await;
+        for program_id in [spl_token::id(), spl_token_2022::id()] {
+            let config = test_config(&test_validator, &payer, &program_id);
+            let token = create_token(&config, &payer).await;
+            let source = create_associated_account(&config, &payer, token).await;
+            let destination = create_auxiliary_account(&config, &payer, token).await;
+            let ui_amount = 100.0;
+            mint_tokens(&config, &payer, token, ui_amount, source).await;
+            let result = process_test_command(
+                &config,
+                &payer,
+                &[
+                    "spl-token",
+                    CommandName::Transfer.into(),
+                    &token.to_string(),
+                    "10",
+                    &destination.to_string(),
+                ],
+            )
+            .await;
+            result.unwrap();
+
+            let account = config.rpc_client.get_account(&source).await.unwrap();
+            let token_account = StateWithExtensionsOwned::<Account>::unpack(account.data).unwrap();
+            assert_eq!(token_account.base.amount, 90);
+            let account = config.rpc_client.get_account(&destination).await.unwrap();
+            let token_account = StateWithExtensionsOwned::<Account>::unpack(account.data).unwrap();
+            assert_eq!(token_account.base.amount, 10);
         }
-        (CommandName::Approve, arg_matches) => {
-            let (owner_signer, owner_address) =
-                config.signer_or_default(arg_matches, "owner", &mut wallet_manager);
-            bulk_signers.push(owner_signer);
+    }
 
-            let account = pubkey_of_signer(arg_matches, "account", &mut wallet_manager)
-                .unwrap()
-                .unwrap();
-            let amount = value_t_or_exit!(arg_matches, "amount", f64);
-            let delegate = pubkey_of_signer(arg_matches, "delegate", &mut wallet_manager)
-                .unwrap()
-                .unwrap();
-            let mint_address =
-                pubkey_of_signer(arg_matches, MINT_ADDRESS_ARG.name, &mut wallet_manager).unwrap();
-            let mint_decimals = value_of::<u8>(arg_matches, MINT_DECIMALS_ARG.name);
-            let use_unchecked_instruction = arg_matches.is_present("use_unchecked_instruction");
-            command_approve(
-                config,
-                account,
-                owner_address,
-                amount,
-                delegate,
-                mint_address,
-                mint_decimals,
-                use_unchecked_instruction,
+    #[tokio::test]
+    #[parallel(two)]
+    async fn freeze_all_and_thaw_all() {
+        let (test_validator, payer) = new_validator_for_test().await;
+        for program_id in [spl_token::id(), spl_token_2022::id()] {
+            let config = test_config(&test_validator, &payer, &program_id);
+            let token = Keypair::new();
+            let token_pubkey = token.pubkey();
+            let bulk_signers: Vec<Box<dyn Signer>> =
+                vec![Box::new(clone_keypair(&payer)), Box::new(token)];
+            command_create_token(
+                &config,
+                TEST_DECIMALS,
+                token_pubkey,
+                payer.pubkey(),
+                true,
+                vec![],
                 bulk_signers,
             )
             .await
-        }
-        (CommandName::Revoke, arg_matches) => {
-            let (owner_signer, owner_address) =
-                config.signer_or_default(arg_matches, "owner", &mut wallet_manager);
-            bulk_signers.push(owner_signer);
+            .unwrap();
+            let associated = create_associated_account(&config, &payer, token_pubkey).await;
+            let auxiliary = create_auxiliary_account(&config, &payer, token_pubkey).await;
+            process_test_command(
+                &config,
+                &payer,
+                &[
+                    "spl-token",
+                    CommandName::Freeze.into(),
+                    &auxiliary.to_string(),
+                ],
+            )
+            .await
+            .unwrap();
+
+            // nothing happens without `--confirm`
+            process_test_command(
+                &config,
+                &payer,
+                &[
+                    "spl-token",
+                    CommandName::FreezeAll.into(),
+                    &token_pubkey.to_string(),
+                ],
+            )
+            .await
+            .unwrap_err();
+
+            let result = process_test_command(
+                &config,
+                &payer,
+                &[
+                    "spl-token",
+                    CommandName::FreezeAll.into(),
+                    &token_pubkey.to_string(),
+                    "--confirm",
+                ],
+            )
+            .await
+            .unwrap();
+            let value: serde_json::Value = serde_json::from_str(&result).unwrap();
+            assert_eq!(
+                value["accounts"],
+                serde_json::json!([associated.to_string()])
+            );
+            assert_eq!(value["unchanged"], 1);
+            for account in [associated, auxiliary] {
+                let account = config.rpc_client.get_account(&account).await.unwrap();
+                let token_account =
+                    StateWithExtensionsOwned::<Account>::unpack(account.data).unwrap();
+                assert_eq!(token_account.base.state, AccountState::Frozen);
+            }
 
-            let account = pubkey_of_signer(arg_matches, "account", &mut wallet_manager)
-                .unwrap()
-                .unwrap();
-            let delegate_address =
-                pubkey_of_signer(arg_matches, DELEGATE_ADDRESS_ARG.name, &mut wallet_manager)
-                    .unwrap();
-            command_revoke(
-                config,
-                account,
-                owner_address,
-                delegate_address,
-                bulk_signers,
+            process_test_command(
+                &config,
+                &payer,
+                &[
+                    "spl-token",
+                    CommandName::ThawAll.into(),
+                    &token_pubkey.to_string(),
+                    "--confirm",
+                ],
             )
             .await
+            .unwrap();
+            for account in [associated, auxiliary] {
+                let account = config.rpc_client.get_account(&account).await.unwrap();
+                let token_account =
+                    StateWithExtensionsOwned::<Account>::unpack(account.data).unwrap();
+                assert_eq!(token_account.base.state, AccountState::Initialized);
+            }
         }
-        (CommandName::Close, arg_matches) => {
-            let (close_authority_signer, close_authority) =
-                config.signer_or_default(arg_matches, "close_authority", &mut wallet_manager);
-            bulk_signers.push(close_authority_signer);
+    }
 
-            let address = config
-                .associated_token_address_or_override(arg_matches, "address", &mut wallet_manager)
-                .await;
-            let recipient = config.pubkey_or_default(arg_matches, "recipient", &mut wallet_manager);
-            command_close(config, address, close_authority, recipient, bulk_signers).await
-        }
-        (CommandName::Balance, arg_matches) => {
-            let address = config
-                .associated_token_address_or_override(arg_matches, "address", &mut wallet_manager)
-                .await;
-            command_balance(config, address).await
-        }
-        (CommandName::Supply, arg_matches) => {
-            let address = pubkey_of_signer(arg_matches, "address", &mut wallet_manager)
-                .unwrap()
-                .unwrap();
-            command_supply(config, address).await
-        }
-        (CommandName::Accounts, arg_matches) => {
-            let token = pubkey_of_signer(arg_matches, "token", &mut wallet_manager).unwrap();
-            let owner = config.pubkey_or_default(arg_matches, "owner", &mut wallet_manager);
-            command_accounts(config, token, owner).await
-        }
-        (CommandName::Address, arg_matches) => {
-            let token = pubkey_of_signer(arg_matches, "token", &mut wallet_manager).unwrap();
-            let owner = config.pubkey_or_default(arg_matches, "owner", &mut wallet_manager);
-            command_address(config, token, owner).await
-        }
-        (CommandName::AccountInfo, arg_matches) => {
-            let address = config
-                .associated_token_address_or_override(arg_matches, "address", &mut wallet_manager)
-                .await;
-            command_account_info(config, address).await
-        }
-        (CommandName::MultisigInfo, arg_matches) => {
-            let address = pubkey_of_signer(arg_matches, "address", &mut wallet_manager)
-                .unwrap()
-                .unwrap();
-            command_multisig(config, address).await
-        }
-        (CommandName::Gc, arg_matches) => {
-            match config.output_format {
-                OutputFormat::Json | OutputFormat::JsonCompact => {
-                    eprintln!(
-                        "`spl-token gc` does not support the `--ouput` parameter at this time"
-                    );
-                    exit(1);
-                }
-                _ => {}
-            }
+    #[tokio::test]
+    #[parallel(two)]
+    async fn authorize_close_mint() {
+        let (test_validator, payer) = new_validator_for_test().await;
+        let config = test_config(&test_validator, &payer, &spl_token_2022::id());
+        let token = Keypair::new();
+        let instruction = initialize_mint_close_authority(
+            &config.program_id,
+            &token.pubkey(),
+            Some(&payer.pubkey()),
+        )
+        .unwrap();
+        let token = create_token_with_extensions(
+            &config,
+            &payer,
+            token,
+            &[ExtensionType::MintCloseAuthority],
+            vec![instruction],
+        )
+        .await;
 
-            let close_empty_associated_accounts =
-                arg_matches.is_present("close_empty_associated_accounts");
+        let new_authority = Pubkey::new_unique();
+        process_test_command(
+            &config,
+            &payer,
+            &[
+                "spl-token",
+                CommandName::Authorize.into(),
+                &token.to_string(),
+                "close",
+                &new_authority.to_string(),
+            ],
+        )
+        .await
+        .unwrap();
+        let account = config.rpc_client.get_account(&token).await.unwrap();
+        let mint = StateWithExtensionsOwned::<Mint>::unpack(account.data).unwrap();
+        let extension = mint.get_extension::<MintCloseAuthority>().unwrap();
+        assert_eq!(
+            Option::<Pubkey>::from(extension.close_authority),
+            Some(new_authority)
+        );
 
-            let (owner_signer, owner_address) =
-                config.signer_or_default(arg_matches, "owner", &mut wallet_manager);
-            bulk_signers.push(owner_signer);
+        // the previous authority can no longer change it
+        process_test_command(
+            &config,
+            &payer,
+            &[
+                "spl-token",
+                CommandName::Authorize.into(),
+                &token.to_string(),
+                "close",
+                "--disable",
+            ],
+        )
+        .await
+        .unwrap_err();
 
-            command_gc(
-                config,
-                owner_address,
-                close_empty_associated_accounts,
-                bulk_signers,
+        // mints without the extension are rejected before sending anything
+        let plain_token = create_token(&config, &payer).await;
+        let result = process_test_command(
+            &config,
+            &payer,
+            &[
+                "spl-token",
+                CommandName::Authorize.into(),
+                &plain_token.to_string(),
+                "close",
+                "--disable",
+            ],
+        )
+        .await;
+        assert!(result
+            .unwrap_err()
+            .to_string()
+            .contains("does not have the close authority extension"));
+
+        // offline, `close` can't be resolved to the mint's close authority
+        let mut config = config;
+        config.sign_only = true;
+        let result = process_test_command(
+            &config,
+            &payer,
+            &[
+                "spl-token",
+                CommandName::Authorize.into(),
+                &token.to_string(),
+                "close",
+                "--disable",
+            ],
+        )
+        .await;
+        assert!(result.unwrap_err().to_string().contains("close-mint"));
+    }
+
+    #[tokio::test]
+    #[parallel(two)]
+    async fn authorize_extension_authorities() {
+        let (test_validator, payer) = new_validator_for_test().await;
+        let config = test_config(&test_validator, &payer, &spl_token_2022::id());
+        let token = create_token_with_transfer_fee(&config, &payer, 100, 1000).await;
+
+        let new_authority = Pubkey::new_unique();
+        for authority_type in ["transfer-fee-config", "withheld-withdraw"] {
+            process_test_command(
+                &config,
+                &payer,
+                &[
+                    "spl-token",
+                    CommandName::Authorize.into(),
+                    &token.to_string(),
+                    authority_type,
+                    &new_authority.to_string(),
+                ],
             )
             .await
+            .unwrap();
         }
-        (CommandName::SyncNative, arg_matches) => {
-            let program_id = config.program_id;
-            let native_mint = native_mint(&program_id)?;
-            let address = config
-                .associated_token_address_for_token_or_override(
-                    arg_matches,
-                    "address",
-                    &mut wallet_manager,
-                    Some(native_mint),
-                )
-                .await;
-            command_sync_native(address, bulk_signers, config).await
-        }
+        let account = config.rpc_client.get_account(&token).await.unwrap();
+        let mint = StateWithExtensionsOwned::<Mint>::unpack(account.data).unwrap();
+        let extension = mint.get_extension::<TransferFeeConfig>().unwrap();
+        assert_eq!(
+            Option::<Pubkey>::from(extension.transfer_fee_config_authority),
+            Some(new_authority)
+        );
+        assert_eq!(
+            Option::<Pubkey>::from(extension.withdraw_withheld_authority),
+            Some(new_authority)
+        );
+
+        let result = process_test_command(
+            &config,
+            &payer,
+            &[
+                "spl-token",
+                CommandName::Authorize.into(),
+                &token.to_string(),
+                "interest-rate",
+                "--disable",
+            ],
+        )
+        .await;
+        assert!(result
+            .unwrap_err()
+            .to_string()
+            .contains("does not have the interest-bearing extension"));
+
+        // The new confidential transfer mint authority signs as well
+        let token = create_confidential_token(&config, &payer).await;
+        let new_authority = Keypair::new();
+        let new_authority_file = NamedTempFile::new().unwrap();
+        write_keypair_file(&new_authority, &new_authority_file).unwrap();
+        process_test_command(
+            &config,
+            &payer,
+            &[
+                "spl-token",
+                CommandName::Authorize.into(),
+                &token.to_string(),
+                "confidential-transfer-mint",
+                new_authority_file.path().to_str().unwrap(),
+            ],
+        )
+        .await
+        .unwrap();
+        let account = config.rpc_client.get_account(&token).await.unwrap();
+        let mint = StateWithExtensionsOwned::<Mint>::unpack(account.data).unwrap();
+        let extension = mint.get_extension::<ConfidentialTransferMint>().unwrap();
+        assert_eq!(extension.authority, new_authority.pubkey());
+        assert!(bool::from(extension.auto_approve_new_accounts));
     }
-}
 
-fn format_output<T>(command_output: T, command_name: &CommandName, config: &Config) -> String
-where
-    T: Serialize + Display + QuietDisplay + VerboseDisplay,
-{
-    config.output_format.formatted_string(&CommandOutput {
-        command_name: command_name.to_string(),
-        command_output,
-    })
-}
-enum TransactionReturnData {
-    CliSignature(CliSignature),
-    CliSignOnlyData(CliSignOnlyData),
-}
-async fn handle_tx<'a>(
-    signer_info: &CliSignerInfo,
-    config: &Config<'a>,
-    no_wait: bool,
-    minimum_balance_for_rent_exemption: u64,
-    instructions: Vec<Instruction>,
-) -> Result<TransactionReturnData, Error> {
-    let fee_payer = Some(&config.fee_payer);
+    #[tokio::test]
+    #[parallel(two)]
+    async fn close_mint() {
+        let (test_validator, payer) = new_validator_for_test().await;
+        let config = test_config(&test_validator, &payer, &spl_token_2022::id());
+        let token = Keypair::new();
+        let instruction = initialize_mint_close_authority(
+            &config.program_id,
+            &token.pubkey(),
+            Some(&payer.pubkey()),
+        )
+        .unwrap();
+        let token = create_token_with_extensions(
+            &config,
+            &payer,
+            token,
+            &[ExtensionType::MintCloseAuthority],
+            vec![instruction],
+        )
+        .await;
+        let account = create_associated_account(&config, &payer, token).await;
+        mint_tokens(&config, &payer, token, 10.0, account).await;
 
-    let recent_blockhash = config.rpc_client.get_latest_blockhash().await?;
-    let message = if let Some(nonce_account) = config.nonce_account.as_ref() {
-        let mut message = Message::new_with_nonce(
-            instructions,
-            fee_payer,
-            nonce_account,
-            config.nonce_authority.as_ref().unwrap(),
-        );
-        message.recent_blockhash = recent_blockhash;
-        message
-    } else {
-        Message::new_with_blockhash(&instructions, fee_payer, &recent_blockhash)
-    };
-    let fee = config.rpc_client.get_fee_for_message(&message).await?;
+        let result = process_test_command(
+            &config,
+            &payer,
+            &[
+                "spl-token",
+                CommandName::CloseMint.into(),
+                &token.to_string(),
+            ],
+        )
+        .await;
+        assert!(result
+            .unwrap_err()
+            .to_string()
+            .contains("still has a supply"));
+
+        process_test_command(
+            &config,
+            &payer,
+            &[
+                "spl-token",
+                CommandName::Burn.into(),
+                &account.to_string(),
+                "10",
+            ],
+        )
+        .await
+        .unwrap();
+        let recipient = Pubkey::new_unique();
+        process_test_command(
+            &config,
+            &payer,
+            &[
+                "spl-token",
+                CommandName::CloseMint.into(),
+                &token.to_string(),
+                "--recipient",
+                &recipient.to_string(),
+            ],
+        )
+        .await
+        .unwrap();
+        config.rpc_client.get_account(&token).await.unwrap_err();
+        assert!(config.rpc_client.get_balance(&recipient).await.unwrap() > 0);
+    }
+
+    #[tokio::test]
+    #[parallel(two)]
+    async fn combine_signatures() {
+        let (test_validator, payer) = new_validator_for_test().await;
+        for program_id in [spl_token::id(), spl_token_2022::id()] {
+            let mut config = test_config(&test_validator, &payer, &program_id);
+            let members = [Keypair::new(), Keypair::new(), Keypair::new()];
+            let (multisig_signer, multisig) = new_throwaway_signer();
+            command_create_multisig(
+                &config,
+                multisig,
+                2,
+                members.iter().map(|member| member.pubkey()).collect(),
+                vec![Box::new(clone_keypair(&payer)), multisig_signer],
+            )
+            .await
+            .unwrap();
+            let token = create_token(&config, &payer).await;
+            let (source_signer, source) = new_throwaway_signer();
+            command_create_account(
+                &config,
+                token,
+                multisig,
+                Some(source),
+                false,
+                false,
+                None,
+                vec![Box::new(clone_keypair(&payer)), source_signer],
+            )
+            .await
+            .unwrap();
+            let destination = create_associated_account(&config, &payer, token).await;
+            mint_tokens(&config, &payer, token, 10.0, source).await;
 
-    if !config.sign_only {
-        check_fee_payer_balance(config, minimum_balance_for_rent_exemption + fee).await?;
-    }
+            let blockhash = config.rpc_client.get_latest_blockhash().await.unwrap();
+            let new_message = |signers: &[&Keypair]| {
+                let instruction = transfer_checked(
+                    &program_id,
+                    &source,
+                    &token,
+                    &destination,
+                    &multisig,
+                    &signers
+                        .iter()
+                        .map(|signer| signer.pubkey())
+                        .collect::<Vec<_>>()
+                        .iter()
+                        .collect::<Vec<_>>(),
+                    5,
+                    TEST_DECIMALS,
+                )
+                .unwrap();
+                Message::new_with_blockhash(&[instruction], Some(&payer.pubkey()), &blockhash)
+            };
+            let pair = |signer: &Keypair, message: &Message| {
+                format!(
+                    "{}={}",
+                    signer.pubkey(),
+                    signer.sign_message(&message.serialize())
+                )
+            };
 
-    let signers = signer_info.signers_for_message(&message);
-    let mut transaction = Transaction::new_unsigned(message);
+            // one member is not enough for a 2 of 3 multisig
+            let message = new_message(&[&members[0]]);
+            let result = process_test_command(
+                &config,
+                &payer,
+                &[
+                    "spl-token",
+                    CommandName::CombineSignatures.into(),
+                    &base64::encode(message.serialize()),
+                    &pair(&payer, &message),
+                    &pair(&members[0], &message),
+                ],
+            )
+            .await;
+            assert!(result
+                .unwrap_err()
+                .to_string()
+                .contains("requires 2 signatures"));
+
+            // a signature over some other message is rejected
+            let message = new_message(&[&members[0], &members[2]]);
+            let other_message = new_message(&[&members[0], &members[1]]);
+            let result = process_test_command(
+                &config,
+                &payer,
+                &[
+                    "spl-token",
+                    CommandName::CombineSignatures.into(),
+                    &base64::encode(message.serialize()),
+                    &pair(&payer, &message),
+                    &pair(&members[0], &other_message),
+                    &pair(&members[2], &message),
+                ],
+            )
+            .await;
+            assert!(result.unwrap_err().to_string().contains("does not verify"));
 
-    if config.sign_only {
-        transaction.try_partial_sign(&signers, recent_blockhash)?;
-        Ok(TransactionReturnData::CliSignOnlyData(return_signers_data(
-            &transaction,
-            &ReturnSignersConfig {
-                dump_transaction_message: config.dump_transaction_message,
-            },
-        )))
-    } else {
-        transaction.try_sign(&signers, recent_blockhash)?;
-        let signature = if no_wait {
-            config.rpc_client.send_transaction(&transaction).await?
-        } else {
+            let file = NamedTempFile::new().unwrap();
+            let path = file.path().to_str().unwrap().to_string();
+            config.output_file = Some(path.clone());
+            process_test_command(
+                &config,
+                &payer,
+                &[
+                    "spl-token",
+                    CommandName::CombineSignatures.into(),
+                    &base64::encode(message.serialize()),
+                    &pair(&members[2], &message),
+                    &pair(&payer, &message),
+                    &pair(&members[0], &message),
+                ],
+            )
+            .await
+            .unwrap();
+            config.output_file = None;
+            let transaction = read_transaction_file(&path).unwrap();
             config
                 .rpc_client
-                .send_and_confirm_transaction_with_spinner(&transaction)
-                .await?
-        };
-        Ok(TransactionReturnData::CliSignature(CliSignature {
-            signature: signature.to_string(),
-        }))
-    }
-}
-
-#[cfg(test)]
-mod tests {
-    use {
-        super::*,
-        serial_test::parallel,
-        solana_sdk::{
-            bpf_loader,
-            signature::{write_keypair_file, Keypair, Signer},
-        },
-        solana_test_validator::{ProgramInfo, TestValidator, TestValidatorGenesis},
-        std::path::PathBuf,
-        tempfile::NamedTempFile,
-    };
-
-    fn clone_keypair(keypair: &Keypair) -> Keypair {
-        Keypair::from_bytes(&keypair.to_bytes()).unwrap()
-    }
-
-    const TEST_DECIMALS: u8 = 0;
+                .send_and_confirm_transaction(&transaction)
+                .await
+                .unwrap();
+            let account = config.rpc_client.get_account(&destination).await.unwrap();
+            let token_account = StateWithExtensionsOwned::<Account>::unpack(account.data).unwrap();
+            assert_eq!(token_account.base.amount, 5);
+        }
 
-    async fn new_validator_for_test() -> (TestValidator, Keypair) {
-        solana_logger::setup();
-        let mut test_validator_genesis = TestValidatorGenesis::default();
-        test_validator_genesis.add_programs_with_path(&[
-            ProgramInfo {
-                program_id: spl_token::id(),
-                loader: bpf_loader::id(),
-                program_path: PathBuf::from("../../target/deploy/spl_token.so"),
-            },
-            ProgramInfo {
-                program_id: spl_associated_token_account::id(),
-                loader: bpf_loader::id(),
-                program_path: PathBuf::from("../../target/deploy/spl_associated_token_account.so"),
-            },
-            ProgramInfo {
-                program_id: spl_token_2022::id(),
-                loader: bpf_loader::id(),
-                program_path: PathBuf::from("../../target/deploy/spl_token_2022.so"),
-            },
-        ]);
-        test_validator_genesis.start_async().await
+        // a header claiming more signers than there are keys is rejected, not sliced
+        let config = test_config(&test_validator, &payer, &spl_token::id());
+        let mut message = Message::new(&[], Some(&payer.pubkey()));
+        message.header.num_required_signatures = 5;
+        let err = command_combine_signatures(&config, &base64::encode(message.serialize()), vec![])
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("malformed"));
     }
 
-    fn test_config<'a>(
-        test_validator: &TestValidator,
-        payer: &Keypair,
-        program_id: &Pubkey,
-    ) -> Config<'a> {
-        let websocket_url = test_validator.rpc_pubsub_url();
-        let rpc_client = Arc::new(test_validator.get_async_rpc_client());
-        Config {
-            rpc_client,
-            websocket_url,
-            output_format: OutputFormat::JsonCompact,
-            fee_payer: payer.pubkey(),
-            default_keypair: KeypairOrPath::Keypair(clone_keypair(payer)),
-            nonce_account: None,
-            nonce_authority: None,
-            sign_only: false,
-            dump_transaction_message: false,
-            multisigner_pubkeys: vec![],
-            program_id: *program_id,
-        }
-    }
+    #[tokio::test]
+    #[parallel(two)]
+    async fn create_multisig_signers_file() {
+        let (test_validator, payer) = new_validator_for_test().await;
+        let config = test_config(&test_validator, &payer, &spl_token::id());
+        let members = [
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        ];
+        let mut file = NamedTempFile::new().unwrap();
+        writeln!(file, "# council").unwrap();
+        writeln!(file, "{}", members[0]).unwrap();
+        writeln!(file).unwrap();
+        writeln!(file, "{} # treasurer", members[1]).unwrap();
+        let path = file.path().to_str().unwrap();
 
-    async fn do_create_native_mint(config: &Config<'_>, program_id: &Pubkey, payer: &Keypair) {
-        if program_id == &spl_token_2022::id() {
-            let native_mint = spl_token_2022::native_mint::id();
-            if config.rpc_client.get_account(&native_mint).await.is_err() {
-                let transaction = Transaction::new_signed_with_payer(
-                    &[create_native_mint(program_id, &payer.pubkey()).unwrap()],
-                    Some(&payer.pubkey()),
-                    &[payer],
-                    config.rpc_client.get_latest_blockhash().await.unwrap(),
-                );
-                config
-                    .rpc_client
-                    .send_and_confirm_transaction(&transaction)
-                    .await
-                    .unwrap();
-            }
-        }
+        let result = process_test_command(
+            &config,
+            &payer,
+            &[
+                "spl-token",
+                CommandName::CreateMultisig.into(),
+                "2",
+                &members[2].to_string(),
+                "--signers-file",
+                path,
+            ],
+        )
+        .await
+        .unwrap();
+        let value: serde_json::Value = serde_json::from_str(&result).unwrap();
+        assert_eq!(value["m"], 2);
+        assert_eq!(value["n"], 3);
+        let address = Pubkey::from_str(value["address"].as_str().unwrap()).unwrap();
+        let multisig = get_multisig(&config, &address).await.unwrap();
+        assert_eq!(multisig.m, 2);
+        assert_eq!(&multisig.signers[..3], &members[..]);
+
+        writeln!(file, "not-a-pubkey").unwrap();
+        let result = process_test_command(
+            &config,
+            &payer,
+            &[
+                "spl-token",
+                CommandName::CreateMultisig.into(),
+                "2",
+                "--signers-file",
+                path,
+            ],
+        )
+        .await;
+        assert!(result.unwrap_err().to_string().contains("line 5"));
     }
 
-    async fn create_token(config: &Config<'_>, payer: &Keypair) -> Pubkey {
-        let token = Keypair::new();
-        let token_pubkey = token.pubkey();
-        let bulk_signers: Vec<Box<dyn Signer>> =
-            vec![Box::new(clone_keypair(payer)), Box::new(token)];
+    #[tokio::test]
+    #[parallel(two)]
+    async fn multisig_info_expectations() {
+        let (test_validator, payer) = new_validator_for_test().await;
+        let config = test_config(&test_validator, &payer, &spl_token::id());
+        let members = [
+            Pubkey::new_unique().to_string(),
+            Pubkey::new_unique().to_string(),
+            Pubkey::new_unique().to_string(),
+        ];
+        let (multisig_signer, multisig) = new_throwaway_signer();
+        command_create_multisig(
+            &config,
+            multisig,
+            2,
+            members
+                .iter()
+                .map(|member| Pubkey::from_str(member).unwrap())
+                .collect(),
+            vec![Box::new(clone_keypair(&payer)), multisig_signer],
+        )
+        .await
+        .unwrap();
+        let multisig = multisig.to_string();
 
-        command_create_token(
-            config,
-            TEST_DECIMALS,
-            token_pubkey,
-            payer.pubkey(),
-            false,
-            None,
-            bulk_signers,
+        let result = process_test_command(
+            &config,
+            &payer,
+            &[
+                "spl-token",
+                CommandName::MultisigInfo.into(),
+                &multisig,
+                "--expect-threshold",
+                "2",
+                "--expect-signers",
+                &members[2],
+                &members[0],
+                &members[1],
+            ],
         )
         .await
         .unwrap();
-        token_pubkey
-    }
+        let value: serde_json::Value = serde_json::from_str(&result).unwrap();
+        assert_eq!(value["m"], 2);
+        assert_eq!(value["n"], 3);
 
-    async fn create_auxiliary_account(
-        config: &Config<'_>,
-        payer: &Keypair,
-        mint: Pubkey,
-    ) -> Pubkey {
-        let auxiliary = Keypair::new();
-        let address = auxiliary.pubkey();
-        let bulk_signers: Vec<Box<dyn Signer>> =
-            vec![Box::new(clone_keypair(payer)), Box::new(auxiliary)];
-        command_create_account(config, mint, payer.pubkey(), Some(address), bulk_signers)
-            .await
-            .unwrap();
-        address
-    }
+        let err = process_test_command(
+            &config,
+            &payer,
+            &[
+                "spl-token",
+                CommandName::MultisigInfo.into(),
+                &multisig,
+                "--expect-threshold",
+                "3",
+            ],
+        )
+        .await
+        .unwrap_err();
+        assert!(err.to_string().contains("expected 3"));
 
-    async fn create_associated_account(
-        config: &Config<'_>,
-        payer: &Keypair,
-        mint: Pubkey,
-    ) -> Pubkey {
-        let bulk_signers: Vec<Box<dyn Signer>> = vec![Box::new(clone_keypair(payer))];
-        command_create_account(config, mint, payer.pubkey(), None, bulk_signers)
-            .await
-            .unwrap();
-        get_associated_token_address_with_program_id(&payer.pubkey(), &mint, &config.program_id)
+        let err = process_test_command(
+            &config,
+            &payer,
+            &[
+                "spl-token",
+                CommandName::MultisigInfo.into(),
+                &multisig,
+                "--expect-signers",
+                &members[0],
+                &members[1],
+            ],
+        )
+        .await
+        .unwrap_err();
+        assert!(err.to_string().contains(&members[2]));
     }
 
-    async fn mint_tokens(
-        config: &Config<'_>,
-        payer: &Keypair,
-        mint: Pubkey,
-        ui_amount: f64,
-        recipient: Pubkey,
-    ) {
-        let bulk_signers: Vec<Box<dyn Signer>> = vec![Box::new(clone_keypair(payer))];
-        command_mint(
-            config,
-            mint,
-            ui_amount,
-            recipient,
-            MintInfo {
-                program_id: config.program_id,
-                address: mint,
-                decimals: TEST_DECIMALS,
-            },
-            payer.pubkey(),
-            false,
-            bulk_signers,
+    #[tokio::test]
+    #[parallel(two)]
+    async fn multisig_threshold_check() {
+        let (test_validator, payer) = new_validator_for_test().await;
+        let members = [Keypair::new(), Keypair::new(), Keypair::new()];
+        let member_pubkeys = members
+            .iter()
+            .map(|member| member.pubkey())
+            .collect::<Vec<_>>();
+        let mut config = test_config(&test_validator, &payer, &spl_token::id());
+        let (multisig_signer, multisig) = new_throwaway_signer();
+        command_create_multisig(
+            &config,
+            multisig,
+            2,
+            member_pubkeys.clone(),
+            vec![Box::new(clone_keypair(&payer)), multisig_signer],
         )
         .await
         .unwrap();
-    }
+        let token = create_token(&config, &payer).await;
 
-    async fn process_test_command(
-        config: &Config<'_>,
-        payer: &Keypair,
-        args: &[&str],
-    ) -> CommandResult {
-        let default_decimals = format!("{}", spl_token_2022::native_mint::DECIMALS);
-        let default_program_id = spl_token::id().to_string();
-        let minimum_signers_help = minimum_signers_help_string();
-        let multisig_member_help = multisig_member_help_string();
+        let message_for = |signers: &[&Pubkey]| {
+            let instruction = transfer_checked(
+                &spl_token::id(),
+                &Pubkey::new_unique(),
+                &token,
+                &Pubkey::new_unique(),
+                &multisig,
+                signers,
+                1,
+                TEST_DECIMALS,
+            )
+            .unwrap();
+            Message::new(&[instruction], Some(&payer.pubkey()))
+        };
 
-        let app_matches = app(
-            &default_decimals,
-            &default_program_id,
-            &minimum_signers_help,
-            &multisig_member_help,
+        // one member can't reach a threshold of two
+        config.multisigner_pubkeys = vec![&member_pubkeys[0]];
+        let message = message_for(&config.multisigner_pubkeys);
+        check_multisig_threshold(&config, &message).await.unwrap();
+        config.strict = true;
+        let err = check_multisig_threshold(&config, &message)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("requires 2 signers"));
+
+        config.multisigner_pubkeys = vec![&member_pubkeys[0], &member_pubkeys[2]];
+        let message = message_for(&config.multisigner_pubkeys);
+        check_multisig_threshold(&config, &message).await.unwrap();
+
+        // a member's own wallet following some other account is no multisig to check
+        config.multisigner_pubkeys = vec![&member_pubkeys[0]];
+        let account = create_associated_account(&config, &payer, token).await;
+        let instruction = close_account(
+            &spl_token::id(),
+            &account,
+            &member_pubkeys[0],
+            &payer.pubkey(),
+            &[],
         )
-        .get_matches_from(args);
-        let (sub_command, sub_matches) = app_matches.subcommand();
-        let sub_command = CommandName::from_str(sub_command).unwrap();
-        let matches = sub_matches.unwrap();
-
-        let wallet_manager = None;
-        let bulk_signers: Vec<Box<dyn Signer>> = vec![Box::new(clone_keypair(payer))];
-        process_command(&sub_command, matches, config, wallet_manager, bulk_signers).await
+        .unwrap();
+        let message = Message::new(&[instruction], Some(&payer.pubkey()));
+        check_multisig_threshold(&config, &message).await.unwrap();
     }
 
     #[tokio::test]
-    #[parallel(one)]
-    async fn create_token_default() {
+    #[parallel(two)]
+    async fn mint_with_multisig_authority() {
         let (test_validator, payer) = new_validator_for_test().await;
         for program_id in [spl_token::id(), spl_token_2022::id()] {
-            let config = test_config(&test_validator, &payer, &program_id);
-            let result = process_test_command(
+            let members = [Keypair::new(), Keypair::new(), Keypair::new()];
+            let member_pubkeys = members
+                .iter()
+                .map(|member| member.pubkey())
+                .collect::<Vec<_>>();
+            let mut config = test_config(&test_validator, &payer, &program_id);
+            let (multisig_signer, multisig) = new_throwaway_signer();
+            command_create_multisig(
                 &config,
-                &payer,
-                &["spl-token", CommandName::CreateToken.into()],
+                multisig,
+                2,
+                member_pubkeys.clone(),
+                vec![Box::new(clone_keypair(&payer)), multisig_signer],
             )
-            .await;
-            let value: serde_json::Value = serde_json::from_str(&result.unwrap()).unwrap();
-            let mint =
-                Pubkey::from_str(value["commandOutput"]["address"].as_str().unwrap()).unwrap();
-            let account = config.rpc_client.get_account(&mint).await.unwrap();
-            assert_eq!(account.owner, program_id);
-        }
-    }
+            .await
+            .unwrap();
+            let (token_signer, token) = new_throwaway_signer();
+            command_create_token(
+                &config,
+                TEST_DECIMALS,
+                token,
+                multisig,
+                false,
+                vec![],
+                vec![Box::new(clone_keypair(&payer)), token_signer],
+            )
+            .await
+            .unwrap();
+            let account = create_associated_account(&config, &payer, token).await;
+            let mint_info = config.get_mint_info(&token, None).await.unwrap();
+            let bulk_signers = || -> BulkSigners {
+                vec![
+                    Box::new(clone_keypair(&payer)),
+                    Box::new(clone_keypair(&members[0])),
+                    Box::new(clone_keypair(&members[2])),
+                ]
+            };
 
-    #[tokio::test]
-    #[parallel(two)]
-    async fn supply() {
-        let (test_validator, payer) = new_validator_for_test().await;
-        for program_id in [spl_token::id(), spl_token_2022::id()] {
-            let config = test_config(&test_validator, &payer, &program_id);
-            let token = create_token(&config, &payer).await;
-            let result = process_test_command(
+            // the same threshold check as any other multisig transaction
+            config.multisigner_pubkeys = vec![&member_pubkeys[0]];
+            config.strict = true;
+            let err = command_mint(
                 &config,
-                &payer,
-                &["spl-token", CommandName::Supply.into(), &token.to_string()],
+                token,
+                10.0,
+                account,
+                None,
+                false,
+                mint_info.clone(),
+                multisig,
+                false,
+                false,
+                bulk_signers(),
             )
-            .await;
-            let value: serde_json::Value = serde_json::from_str(&result.unwrap()).unwrap();
-            assert_eq!(value["amount"], "0");
-            assert_eq!(value["uiAmountString"], "0");
-        }
-    }
+            .await
+            .unwrap_err();
+            assert!(err.to_string().contains("requires 2 signers"));
 
-    #[tokio::test]
-    #[parallel(one)]
-    async fn create_account_default() {
-        let (test_validator, payer) = new_validator_for_test().await;
-        for program_id in [spl_token::id(), spl_token_2022::id()] {
-            let config = test_config(&test_validator, &payer, &program_id);
-            let token = create_token(&config, &payer).await;
-            let result = process_test_command(
+            config.multisigner_pubkeys = vec![&member_pubkeys[0], &member_pubkeys[2]];
+            let err = command_mint(
                 &config,
-                &payer,
-                &[
-                    "spl-token",
-                    CommandName::CreateAccount.into(),
-                    &token.to_string(),
-                ],
+                token,
+                10.0,
+                account,
+                None,
+                false,
+                mint_info.clone(),
+                payer.pubkey(),
+                false,
+                false,
+                bulk_signers(),
             )
-            .await;
-            result.unwrap();
+            .await
+            .unwrap_err();
+            assert!(err.to_string().contains("is not the mint authority"));
+
+            command_mint(
+                &config,
+                token,
+                10.0,
+                account,
+                None,
+                false,
+                mint_info,
+                multisig,
+                false,
+                false,
+                bulk_signers(),
+            )
+            .await
+            .unwrap();
+            let account = config.rpc_client.get_account(&account).await.unwrap();
+            let token_account = StateWithExtensionsOwned::<Account>::unpack(account.data).unwrap();
+            assert_eq!(token_account.base.amount, 10);
         }
     }
 
     #[tokio::test]
     #[parallel(two)]
-    async fn account_info() {
+    async fn transfer_percentage() {
         let (test_validator, payer) = new_validator_for_test().await;
         for program_id in [spl_token::id(), spl_token_2022::id()] {
             let config = test_config(&test_validator, &payer, &program_id);
             let token = create_token(&config, &payer).await;
-            let _account = create_associated_account(&config, &payer, token).await;
-            let result = process_test_command(
+            let source = create_associated_account(&config, &payer, token).await;
+            let destination = create_auxiliary_account(&config, &payer, token).await;
+            mint_tokens(&config, &payer, token, 90.0, source).await;
+            process_test_command(
                 &config,
                 &payer,
                 &[
                     "spl-token",
-                    CommandName::AccountInfo.into(),
+                    CommandName::Transfer.into(),
                     &token.to_string(),
+                    "25%",
+                    &destination.to_string(),
                 ],
             )
-            .await;
-            let value: serde_json::Value = serde_json::from_str(&result.unwrap()).unwrap();
-            let account = get_associated_token_address_with_program_id(
-                &payer.pubkey(),
-                &token,
-                &config.program_id,
-            );
-            assert_eq!(value["address"], account.to_string());
-            assert_eq!(value["mint"], token.to_string());
-            assert_eq!(value["isAssociated"], true);
-            assert_eq!(value["isNative"], false);
-            assert_eq!(value["owner"], payer.pubkey().to_string());
-            assert_eq!(value["state"], "initialized");
+            .await
+            .unwrap();
+
+            // 25% of 90 is 22.5, which rounds down to 22 with no decimals
+            let account = config.rpc_client.get_account(&source).await.unwrap();
+            let token_account = StateWithExtensionsOwned::<Account>::unpack(account.data).unwrap();
+            assert_eq!(token_account.base.amount, 68);
+            let account = config.rpc_client.get_account(&destination).await.unwrap();
+            let token_account = StateWithExtensionsOwned::<Account>::unpack(account.data).unwrap();
+            assert_eq!(token_account.base.amount, 22);
         }
     }
 
-    #[tokio::test]
-    #[parallel(one)]
-    async fn balance() {
-        let (test_validator, payer) = new_validator_for_test().await;
-        for program_id in [spl_token::id(), spl_token_2022::id()] {
-            let config = test_config(&test_validator, &payer, &program_id);
-            let token = create_token(&config, &payer).await;
-            let _account = create_associated_account(&config, &payer, token).await;
-            let result = process_test_command(
-                &config,
-                &payer,
-                &["spl-token", CommandName::Balance.into(), &token.to_string()],
-            )
-            .await;
-            let value: serde_json::Value = serde_json::from_str(&result.unwrap()).unwrap();
-            assert_eq!(value["amount"], "0");
-            assert_eq!(value["uiAmountString"], "0");
-        }
+    #[test]
+    fn amount_suffix() {
+        assert_eq!(expand_amount_suffix("1.5k").as_deref(), Some("1500"));
+        assert_eq!(expand_amount_suffix("2m").as_deref(), Some("2000000"));
+        assert_eq!(expand_amount_suffix("1.2345k").as_deref(), Some("1234.5"));
+        assert_eq!(
+            expand_amount_suffix("0.0000000015B").as_deref(),
+            Some("1.5")
+        );
+        assert_eq!(expand_amount_suffix("10"), None);
+        assert_eq!(expand_amount_suffix("ALL"), None);
+        assert_eq!(expand_amount_suffix("k"), None);
+        assert_eq!(expand_amount_suffix(".k"), None);
+        assert_eq!(expand_amount_suffix("1.2.3k"), None);
+        assert_eq!(expand_amount_suffix("1..5m"), None);
+        assert!(is_amount_with_suffix_or_all("1.2.3k".to_string()).is_err());
+        assert!(is_amount_with_suffix_or_all("1.5k".to_string()).is_ok());
+        assert!(is_amount_with_suffix_or_all("ALL".to_string()).is_ok());
+        assert!(is_amount_with_suffix_or_all("1.5x".to_string()).is_err());
+        assert_eq!(parse_balance_percentage("25%"), Some(2500));
+        assert_eq!(parse_balance_percentage("0.5%"), Some(50));
+        assert_eq!(parse_balance_percentage("14.35%"), Some(1435));
+        assert_eq!(parse_balance_percentage("33.339%"), Some(3333));
+        assert_eq!(parse_balance_percentage(".25%"), Some(25));
+        assert_eq!(parse_balance_percentage("100%"), Some(10_000));
+        assert_eq!(parse_balance_percentage("0%"), None);
+        assert_eq!(parse_balance_percentage("0.001%"), None);
+        assert_eq!(parse_balance_percentage("1.2.3%"), None);
+        assert_eq!(parse_balance_percentage(".%"), None);
+        assert_eq!(parse_balance_percentage("-5%"), None);
+        assert_eq!(parse_balance_percentage("101%"), None);
+        assert_eq!(parse_balance_percentage("25"), None);
+        assert!(is_amount_with_suffix_or_all("25%".to_string()).is_ok());
+        assert!(is_amount_with_suffix_or_all("150%".to_string()).is_err());
     }
 
     #[tokio::test]
     #[parallel(two)]
-    async fn mint() {
+    async fn transfer_fund_recipient() {
         let (test_validator, payer) = new_validator_for_test().await;
         for program_id in [spl_token::id(), spl_token_2022::id()] {
             let config = test_config(&test_validator, &payer, &program_id);
             let token = create_token(&config, &payer).await;
-            let account = create_associated_account(&config, &payer, token).await;
+            let source = create_associated_account(&config, &payer, token).await;
+            let recipient = Keypair::new().pubkey().to_string();
+            let ui_amount = 100.0;
+            mint_tokens(&config, &payer, token, ui_amount, source).await;
             let result = process_test_command(
                 &config,
                 &payer,
                 &[
                     "spl-token",
-                    CommandName::Mint.into(),
+                    CommandName::Transfer.into(),
+                    "--fund-recipient",
+                    "--allow-unfunded-recipient",
                     &token.to_string(),
-                    "100",
+                    "10",
+                    &recipient,
                 ],
             )
             .await;
             result.unwrap();
-            let account = config.rpc_client.get_account(&account).await.unwrap();
+
+            let account = config.rpc_client.get_account(&source).await.unwrap();
             let token_account = StateWithExtensionsOwned::<Account>::unpack(account.data).unwrap();
-            assert_eq!(token_account.base.amount, 100);
-            assert_eq!(token_account.base.mint, token);
-            assert_eq!(token_account.base.owner, payer.pubkey());
+            assert_eq!(token_account.base.amount, 90);
         }
     }
 
     #[tokio::test]
     #[parallel(one)]
-    async fn balance_after_mint() {
+    async fn batch_transfer() {
         let (test_validator, payer) = new_validator_for_test().await;
         for program_id in [spl_token::id(), spl_token_2022::id()] {
             let config = test_config(&test_validator, &payer, &program_id);
             let token = create_token(&config, &payer).await;
-            let account = create_associated_account(&config, &payer, token).await;
+            let source = create_associated_account(&config, &payer, token).await;
+            let destination = create_auxiliary_account(&config, &payer, token).await;
+            let recipient = Keypair::new().pubkey();
             let ui_amount = 100.0;
-            mint_tokens(&config, &payer, token, ui_amount, account).await;
+            mint_tokens(&config, &payer, token, ui_amount, source).await;
+
+            let mut file = NamedTempFile::new().unwrap();
+            writeln!(file, "recipient,amount").unwrap();
+            writeln!(file, "{},10", destination).unwrap();
+            writeln!(file, "{},20,{}", recipient, TEST_DECIMALS).unwrap();
+            writeln!(file, "{},5", destination).unwrap();
             let result = process_test_command(
                 &config,
                 &payer,
-                &["spl-token", CommandName::Balance.into(), &token.to_string()],
+                &[
+                    "spl-token",
+                    CommandName::BatchTransfer.into(),
+                    "--fund-recipient",
+                    "--allow-unfunded-recipient",
+                    &token.to_string(),
+                    file.path().to_str().unwrap(),
+                ],
             )
             .await;
             let value: serde_json::Value = serde_json::from_str(&result.unwrap()).unwrap();
-            assert_eq!(value["amount"], format!("{}", ui_amount));
-            assert_eq!(value["uiAmountString"], format!("{}", ui_amount));
+            let transactions = value["transactions"].as_array().unwrap();
+            assert_eq!(transactions.len(), 1);
+            assert_eq!(transactions[0]["rows"], serde_json::json!([2, 3, 4]));
+            // Token-2022 associated accounts always carry the immutable owner extension
+            let space = if program_id == spl_token_2022::id() {
+                ExtensionType::get_account_len::<Account>(&[ExtensionType::ImmutableOwner])
+            } else {
+                Account::LEN
+            };
+            let rent = config
+                .rpc_client
+                .get_minimum_balance_for_rent_exemption(space)
+                .await
+                .unwrap();
+            assert_eq!(value["recipientRent"]["accounts"], 1);
+            assert_eq!(value["recipientRent"]["lamports"], rent);
+
+            let account = config.rpc_client.get_account(&source).await.unwrap();
+            let token_account = StateWithExtensionsOwned::<Account>::unpack(account.data).unwrap();
+            assert_eq!(token_account.base.amount, 65);
+            let account = config.rpc_client.get_account(&destination).await.unwrap();
+            let token_account = StateWithExtensionsOwned::<Account>::unpack(account.data).unwrap();
+            assert_eq!(token_account.base.amount, 15);
+            let recipient_token_account =
+                get_associated_token_address_with_program_id(&recipient, &token, &program_id);
+            let account = config
+                .rpc_client
+                .get_account(&recipient_token_account)
+                .await
+                .unwrap();
+            let token_account = StateWithExtensionsOwned::<Account>::unpack(account.data).unwrap();
+            assert_eq!(token_account.base.amount, 20);
         }
     }
 
     #[tokio::test]
     #[parallel(two)]
-    async fn accounts() {
+    async fn batch_transfer_many_transactions() {
         let (test_validator, payer) = new_validator_for_test().await;
         for program_id in [spl_token::id(), spl_token_2022::id()] {
             let config = test_config(&test_validator, &payer, &program_id);
-            let token1 = create_token(&config, &payer).await;
-            let _account1 = create_associated_account(&config, &payer, token1).await;
-            let token2 = create_token(&config, &payer).await;
-            let _account2 = create_associated_account(&config, &payer, token2).await;
-            let token3 = create_token(&config, &payer).await;
-            let result = process_test_command(
-                &config,
-                &payer,
-                &["spl-token", CommandName::Accounts.into()],
-            )
-            .await
-            .unwrap();
-            assert!(result.contains(&token1.to_string()));
-            assert!(result.contains(&token2.to_string()));
-            assert!(!result.contains(&token3.to_string()));
-        }
-    }
+            let token = create_token(&config, &payer).await;
+            let source = create_associated_account(&config, &payer, token).await;
+            mint_tokens(&config, &payer, token, 100.0, source).await;
 
-    #[tokio::test]
-    #[parallel(one)]
-    async fn wrap() {
-        let (test_validator, payer) = new_validator_for_test().await;
-        for program_id in [spl_token::id(), spl_token_2022::id()] {
-            let native_mint = native_mint(&program_id).unwrap();
-            let config = test_config(&test_validator, &payer, &program_id);
-            do_create_native_mint(&config, &program_id, &payer).await;
-            let _result = process_test_command(
+            let recipients = (0..12).map(|_| Keypair::new().pubkey()).collect::<Vec<_>>();
+            let mut file = NamedTempFile::new().unwrap();
+            writeln!(file, "recipient,amount").unwrap();
+            for recipient in &recipients {
+                writeln!(file, "{},1", recipient).unwrap();
+            }
+            let result = process_test_command(
                 &config,
-                &payer,
-                &["spl-token", CommandName::Wrap.into(), "0.5"],
-            )
-            .await
-            .unwrap();
-            let account = get_associated_token_address_with_program_id(
-                &payer.pubkey(),
-                &native_mint,
-                &config.program_id,
-            );
-            let account = config.rpc_client.get_account(&account).await.unwrap();
-            let token_account = StateWithExtensionsOwned::<Account>::unpack(account.data).unwrap();
-            assert_eq!(token_account.base.mint, native_mint);
-            assert_eq!(token_account.base.owner, payer.pubkey());
-            assert!(token_account.base.is_native());
+                &payer,
+                &[
+                    "spl-token",
+                    CommandName::BatchTransfer.into(),
+                    "--fund-recipient",
+                    "--allow-unfunded-recipient",
+                    &token.to_string(),
+                    file.path().to_str().unwrap(),
+                ],
+            )
+            .await;
+            let value: serde_json::Value = serde_json::from_str(&result.unwrap()).unwrap();
+            let transactions = value["transactions"].as_array().unwrap();
+            assert!(transactions.len() > 1);
+            for transaction in transactions {
+                assert!(transaction["signature"].is_string());
+            }
+
+            for recipient in &recipients {
+                let recipient_token_account =
+                    get_associated_token_address_with_program_id(recipient, &token, &program_id);
+                let account = config
+                    .rpc_client
+                    .get_account(&recipient_token_account)
+                    .await
+                    .unwrap();
+                let token_account =
+                    StateWithExtensionsOwned::<Account>::unpack(account.data).unwrap();
+                assert_eq!(token_account.base.amount, 1);
+            }
         }
     }
 
     #[tokio::test]
     #[parallel(two)]
-    async fn unwrap() {
+    async fn batch_transfer_fee_payers() {
         let (test_validator, payer) = new_validator_for_test().await;
         for program_id in [spl_token::id(), spl_token_2022::id()] {
             let config = test_config(&test_validator, &payer, &program_id);
-            do_create_native_mint(&config, &program_id, &payer).await;
-            let (signer, account) = new_throwaway_signer();
-            let bulk_signers: Vec<Box<dyn Signer>> = vec![Box::new(clone_keypair(&payer)), signer];
-            command_wrap(&config, 0.5, payer.pubkey(), Some(account), bulk_signers)
+            let token = create_token(&config, &payer).await;
+            let source = create_associated_account(&config, &payer, token).await;
+            mint_tokens(&config, &payer, token, 100.0, source).await;
+
+            let other_fee_payer = Keypair::new();
+            let fee_payer_file = NamedTempFile::new().unwrap();
+            write_keypair_file(&other_fee_payer, &fee_payer_file).unwrap();
+            let blockhash = config.rpc_client.get_latest_blockhash().await.unwrap();
+            let transaction = Transaction::new_signed_with_payer(
+                &[system_instruction::transfer(
+                    &payer.pubkey(),
+                    &other_fee_payer.pubkey(),
+                    sol_to_lamports(1.0),
+                )],
+                Some(&payer.pubkey()),
+                &[&payer],
+                blockhash,
+            );
+            config
+                .rpc_client
+                .send_and_confirm_transaction(&transaction)
                 .await
                 .unwrap();
+
+            let recipients = (0..3).map(|_| Keypair::new().pubkey()).collect::<Vec<_>>();
+            let mut file = NamedTempFile::new().unwrap();
+            writeln!(file, "recipient,amount,decimals,fee_payer").unwrap();
+            writeln!(file, "{},1", recipients[0]).unwrap();
+            writeln!(
+                file,
+                "{},2,,{}",
+                recipients[1],
+                fee_payer_file.path().to_str().unwrap()
+            )
+            .unwrap();
+            writeln!(file, "{},3", recipients[2]).unwrap();
             let result = process_test_command(
                 &config,
                 &payer,
                 &[
                     "spl-token",
-                    CommandName::Unwrap.into(),
-                    &account.to_string(),
+                    CommandName::BatchTransfer.into(),
+                    "--fund-recipient",
+                    "--allow-unfunded-recipient",
+                    &token.to_string(),
+                    file.path().to_str().unwrap(),
                 ],
             )
             .await;
-            result.unwrap();
-            config.rpc_client.get_account(&account).await.unwrap_err();
+            let value: serde_json::Value = serde_json::from_str(&result.unwrap()).unwrap();
+            let transactions = value["transactions"].as_array().unwrap();
+            assert_eq!(transactions.len(), 2);
+            assert_eq!(transactions[0]["rows"], serde_json::json!([2, 4]));
+            assert_eq!(transactions[1]["rows"], serde_json::json!([3]));
+
+            // the other fee payer paid for its transaction and the associated account it created
+            let balance = config
+                .rpc_client
+                .get_balance(&other_fee_payer.pubkey())
+                .await
+                .unwrap();
+            assert!(balance < sol_to_lamports(1.0));
+            for (recipient, amount) in recipients.iter().zip([1, 2, 3]) {
+                let recipient_token_account =
+                    get_associated_token_address_with_program_id(recipient, &token, &program_id);
+                let account = config
+                    .rpc_client
+                    .get_account(&recipient_token_account)
+                    .await
+                    .unwrap();
+                let token_account =
+                    StateWithExtensionsOwned::<Account>::unpack(account.data).unwrap();
+                assert_eq!(token_account.base.amount, amount);
+            }
         }
     }
 
     #[tokio::test]
-    #[parallel(one)]
-    async fn transfer() {
+    #[parallel(two)]
+    async fn sign_only_with_blockhash() {
         let (test_validator, payer) = new_validator_for_test().await;
         for program_id in [spl_token::id(), spl_token_2022::id()] {
-            let config = test_config(&test_validator, &payer, &program_id);
+            let mut config = test_config(&test_validator, &payer, &program_id);
             let token = create_token(&config, &payer).await;
             let source = create_associated_account(&config, &payer, token).await;
             let destination = create_auxiliary_account(&config, &payer, token).await;
-            let ui_amount = 100.0;
-            mint_tokens(&config, &payer, token, ui_amount, source).await;
+            mint_tokens(&config, &payer, token, 100.0, source).await;
+
+            let blockhash = Hash::new_unique();
+            config.sign_only = true;
+            config.blockhash = Some(blockhash);
             let result = process_test_command(
                 &config,
                 &payer,
@@ -3724,50 +14813,76 @@ mod tests {
                     &token.to_string(),
                     "10",
                     &destination.to_string(),
+                    "--mint-decimals",
+                    &TEST_DECIMALS.to_string(),
+                    "--sign-only",
+                    "--blockhash",
+                    &blockhash.to_string(),
                 ],
             )
-            .await;
-            result.unwrap();
-
-            let account = config.rpc_client.get_account(&source).await.unwrap();
-            let token_account = StateWithExtensionsOwned::<Account>::unpack(account.data).unwrap();
-            assert_eq!(token_account.base.amount, 90);
-            let account = config.rpc_client.get_account(&destination).await.unwrap();
-            let token_account = StateWithExtensionsOwned::<Account>::unpack(account.data).unwrap();
-            assert_eq!(token_account.base.amount, 10);
+            .await
+            .unwrap();
+            let value: serde_json::Value = serde_json::from_str(&result).unwrap();
+            assert_eq!(value["blockhash"], blockhash.to_string());
         }
     }
 
     #[tokio::test]
     #[parallel(two)]
-    async fn transfer_fund_recipient() {
+    async fn transfer_with_transaction_file() {
         let (test_validator, payer) = new_validator_for_test().await;
         for program_id in [spl_token::id(), spl_token_2022::id()] {
-            let config = test_config(&test_validator, &payer, &program_id);
+            let mut config = test_config(&test_validator, &payer, &program_id);
             let token = create_token(&config, &payer).await;
             let source = create_associated_account(&config, &payer, token).await;
-            let recipient = Keypair::new().pubkey().to_string();
+            let destination = create_auxiliary_account(&config, &payer, token).await;
             let ui_amount = 100.0;
             mint_tokens(&config, &payer, token, ui_amount, source).await;
-            let result = process_test_command(
+
+            let file = NamedTempFile::new().unwrap();
+            let path = file.path().to_str().unwrap().to_string();
+            let blockhash = config.rpc_client.get_latest_blockhash().await.unwrap();
+            config.sign_only = true;
+            config.output_file = Some(path.clone());
+            process_test_command(
                 &config,
                 &payer,
                 &[
                     "spl-token",
                     CommandName::Transfer.into(),
-                    "--fund-recipient",
-                    "--allow-unfunded-recipient",
                     &token.to_string(),
                     "10",
-                    &recipient,
+                    &destination.to_string(),
+                    "--mint-decimals",
+                    &TEST_DECIMALS.to_string(),
+                    "--sign-only",
+                    "--blockhash",
+                    &blockhash.to_string(),
                 ],
             )
-            .await;
-            result.unwrap();
+            .await
+            .unwrap();
 
-            let account = config.rpc_client.get_account(&source).await.unwrap();
+            config.sign_only = false;
+            config.output_file = None;
+            config.input_file = Some(path);
+            process_test_command(
+                &config,
+                &payer,
+                &[
+                    "spl-token",
+                    CommandName::Transfer.into(),
+                    &token.to_string(),
+                    "10",
+                    &destination.to_string(),
+                ],
+            )
+            .await
+            .unwrap();
+
+            let account = config.rpc_client.get_account(&destination).await.unwrap();
             let token_account = StateWithExtensionsOwned::<Account>::unpack(account.data).unwrap();
-            assert_eq!(token_account.base.amount, 90);
+            assert_eq!(token_account.base.amount, 10);
         }
     }
 
@@ -3848,9 +14963,16 @@ mod tests {
             let source = create_associated_account(&config, &payer, token).await;
             do_create_native_mint(&config, &program_id, &payer).await;
             let ui_amount = 10.0;
-            command_wrap(&config, ui_amount, payer.pubkey(), None, bulk_signers)
-                .await
-                .unwrap();
+            command_wrap(
+                &config,
+                ui_amount,
+                payer.pubkey(),
+                payer.pubkey(),
+                None,
+                bulk_signers,
+            )
+            .await
+            .unwrap();
 
             let recipient = get_associated_token_address_with_program_id(
                 &payer.pubkey(),
@@ -3955,6 +15077,271 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    #[parallel(two)]
+    async fn export_accounts() {
+        let (test_validator, payer) = new_validator_for_test().await;
+        for program_id in [spl_token::id(), spl_token_2022::id()] {
+            let config = test_config(&test_validator, &payer, &program_id);
+            let token = create_token(&config, &payer).await;
+            let associated = create_associated_account(&config, &payer, token).await;
+            let auxiliary = create_auxiliary_account(&config, &payer, token).await;
+            mint_tokens(&config, &payer, token, 100.0, associated).await;
+            mint_tokens(&config, &payer, token, 5.0, auxiliary).await;
+
+            let file = NamedTempFile::new().unwrap();
+            process_test_command(
+                &config,
+                &payer,
+                &[
+                    "spl-token",
+                    CommandName::ExportAccounts.into(),
+                    "--mints",
+                    &token.to_string(),
+                    "--out",
+                    file.path().to_str().unwrap(),
+                ],
+            )
+            .await
+            .unwrap();
+            let contents = std::fs::read_to_string(file.path()).unwrap();
+            let value: serde_json::Value = serde_json::from_str(&contents).unwrap();
+            assert_eq!(value["version"], 1);
+            assert!(value["slot"].as_u64().unwrap() > 0);
+            let mints = value["mints"].as_array().unwrap();
+            assert_eq!(mints.len(), 1);
+            assert_eq!(mints[0]["programId"], program_id.to_string());
+            let mut amounts = mints[0]["accounts"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|account| account["amount"].as_str().unwrap().to_string())
+                .collect::<Vec<_>>();
+            amounts.sort();
+            assert_eq!(amounts, vec!["100".to_string(), "5".to_string()]);
+
+            let hash = Hash::from_str(value["hash"].as_str().unwrap()).unwrap();
+            let signature = Signature::from_str(value["signature"].as_str().unwrap()).unwrap();
+            assert!(signature.verify(payer.pubkey().as_ref(), hash.as_ref()));
+        }
+
+        // mints of both programs in one snapshot, whichever program the config was given
+        let config = test_config(&test_validator, &payer, &spl_token::id());
+        let token = create_token(&config, &payer).await;
+        let account = create_associated_account(&config, &payer, token).await;
+        mint_tokens(&config, &payer, token, 1.0, account).await;
+        let config_2022 = test_config(&test_validator, &payer, &spl_token_2022::id());
+        let token_2022 = create_token(&config_2022, &payer).await;
+        let account_2022 = create_associated_account(&config_2022, &payer, token_2022).await;
+        mint_tokens(&config_2022, &payer, token_2022, 2.0, account_2022).await;
+
+        let file = NamedTempFile::new().unwrap();
+        process_test_command(
+            &config,
+            &payer,
+            &[
+                "spl-token",
+                CommandName::ExportAccounts.into(),
+                "--mints",
+                &token.to_string(),
+                &token_2022.to_string(),
+                "--out",
+                file.path().to_str().unwrap(),
+            ],
+        )
+        .await
+        .unwrap();
+        let contents = std::fs::read_to_string(file.path()).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&contents).unwrap();
+        let mints = value["mints"].as_array().unwrap();
+        assert_eq!(mints.len(), 2);
+        for mint in mints {
+            let (program_id, amount) = if mint["mint"] == token.to_string() {
+                (spl_token::id(), "1")
+            } else {
+                (spl_token_2022::id(), "2")
+            };
+            assert_eq!(mint["programId"], program_id.to_string());
+            assert_eq!(mint["accounts"][0]["amount"], amount);
+        }
+    }
+
+    #[tokio::test]
+    #[parallel(two)]
+    async fn close_empty_accounts() {
+        let (test_validator, payer) = new_validator_for_test().await;
+        for program_id in [spl_token::id(), spl_token_2022::id()] {
+            let config = test_config(&test_validator, &payer, &program_id);
+            let token = create_token(&config, &payer).await;
+            let empty = create_associated_account(&config, &payer, token).await;
+            let funded = create_auxiliary_account(&config, &payer, token).await;
+            let _empty_aux = create_auxiliary_account(&config, &payer, token).await;
+            mint_tokens(&config, &payer, token, 10.0, funded).await;
+
+            let result = process_test_command(
+                &config,
+                &payer,
+                &["spl-token", CommandName::CloseEmptyAccounts.into()],
+            )
+            .await
+            .unwrap();
+            let value: serde_json::Value = serde_json::from_str(&result).unwrap();
+            assert_eq!(value["closed"].as_array().unwrap().len(), 2);
+            assert!(value["lamportsReclaimed"].as_u64().unwrap() > 0);
+            assert!(config.rpc_client.get_account(&empty).await.is_err());
+            config.rpc_client.get_account(&funded).await.unwrap();
+        }
+    }
+
+    #[tokio::test]
+    #[parallel(two)]
+    async fn accounts_mint_filter() {
+        let (test_validator, payer) = new_validator_for_test().await;
+        let config_2022 = test_config(&test_validator, &payer, &spl_token_2022::id());
+        let token_2022 = create_token(&config_2022, &payer).await;
+        let account_2022 = create_associated_account(&config_2022, &payer, token_2022).await;
+        let config = test_config(&test_validator, &payer, &spl_token::id());
+        let token = create_token(&config, &payer).await;
+        let account = create_associated_account(&config, &payer, token).await;
+
+        // the mint's own program is used, whatever `--program-id` says
+        for (mint, account) in [(token, account), (token_2022, account_2022)] {
+            let result = process_test_command(
+                &config,
+                &payer,
+                &[
+                    "spl-token",
+                    CommandName::Accounts.into(),
+                    "--mint",
+                    &mint.to_string(),
+                ],
+            )
+            .await
+            .unwrap();
+            let value: serde_json::Value = serde_json::from_str(&result).unwrap();
+            let accounts = value["accounts"].as_array().unwrap();
+            assert_eq!(accounts.len(), 1);
+            assert_eq!(accounts[0]["address"], account.to_string());
+        }
+
+        let result = process_test_command(
+            &config,
+            &payer,
+            &[
+                "spl-token",
+                CommandName::Accounts.into(),
+                "--mint",
+                &account.to_string(),
+            ],
+        )
+        .await;
+        assert!(result
+            .unwrap_err()
+            .to_string()
+            .contains("Invalid mint account"));
+    }
+
+    #[tokio::test]
+    #[parallel(two)]
+    async fn accounts_json_lines() {
+        let (test_validator, payer) = new_validator_for_test().await;
+        let mut config = test_config(&test_validator, &payer, &spl_token::id());
+        let token = create_token(&config, &payer).await;
+        let account = create_associated_account(&config, &payer, token).await;
+        let _aux = create_auxiliary_account(&config, &payer, token).await;
+        mint_tokens(&config, &payer, token, 100.0, account).await;
+
+        config.json_lines = true;
+        let result = process_test_command(
+            &config,
+            &payer,
+            &["spl-token", CommandName::Accounts.into()],
+        )
+        .await
+        .unwrap();
+        let lines = result
+            .lines()
+            .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(lines.len(), 2);
+        let line = lines
+            .iter()
+            .find(|line| line["address"] == account.to_string())
+            .unwrap();
+        assert_eq!(line["mint"], token.to_string());
+        assert_eq!(line["owner"], payer.pubkey().to_string());
+        assert_eq!(line["amount"], "100");
+        assert_eq!(line["uiAmount"], "100");
+        assert_eq!(line["delegate"], serde_json::Value::Null);
+    }
+
+    #[tokio::test]
+    #[parallel(two)]
+    async fn owner_manifest() {
+        let (test_validator, payer) = new_validator_for_test().await;
+        let config = test_config(&test_validator, &payer, &spl_token::id());
+        let token = create_token(&config, &payer).await;
+        let owners = [Keypair::new(), Keypair::new()];
+        let mut manifest = NamedTempFile::new().unwrap();
+        let mut keypair_files = vec![];
+        for owner in &owners {
+            let keypair_file = NamedTempFile::new().unwrap();
+            write_keypair_file(owner, &keypair_file).unwrap();
+            writeln!(manifest, "{}", keypair_file.path().to_str().unwrap()).unwrap();
+            keypair_files.push(keypair_file);
+            let bulk_signers: Vec<Box<dyn Signer>> = vec![Box::new(clone_keypair(&payer))];
+            command_create_account(
+                &config,
+                token,
+                owner.pubkey(),
+                None,
+                false,
+                false,
+                None,
+                bulk_signers,
+            )
+            .await
+            .unwrap();
+        }
+
+        let result = process_test_command(
+            &config,
+            &payer,
+            &[
+                "spl-token",
+                CommandName::Accounts.into(),
+                "--owner",
+                manifest.path().to_str().unwrap(),
+            ],
+        )
+        .await
+        .unwrap();
+        let value: serde_json::Value = serde_json::from_str(&result).unwrap();
+        let groups = value["owners"].as_array().unwrap();
+        assert_eq!(groups.len(), 2);
+        for (group, owner) in groups.iter().zip(&owners) {
+            assert_eq!(group["owner"], owner.pubkey().to_string());
+            assert_eq!(group["result"]["accounts"].as_array().unwrap().len(), 1);
+        }
+
+        let result = process_test_command(
+            &config,
+            &payer,
+            &[
+                "spl-token",
+                CommandName::CloseEmptyAccounts.into(),
+                "--owner",
+                manifest.path().to_str().unwrap(),
+            ],
+        )
+        .await
+        .unwrap();
+        let value: serde_json::Value = serde_json::from_str(&result).unwrap();
+        for group in value["owners"].as_array().unwrap() {
+            assert_eq!(group["result"]["closed"].as_array().unwrap().len(), 1);
+        }
+    }
+
     #[tokio::test]
     #[parallel(two)]
     async fn set_owner() {
@@ -3981,6 +15368,21 @@ mod tests {
             let token_account = StateWithExtensionsOwned::<Account>::unpack(account.data).unwrap();
             assert_eq!(token_account.base.mint, token);
             assert_eq!(token_account.base.owner, aux);
+
+            // The payer isn't the owner anymore
+            process_test_command(
+                &config,
+                &payer,
+                &[
+                    "spl-token",
+                    CommandName::Authorize.into(),
+                    &aux_string,
+                    "owner",
+                    &payer.pubkey().to_string(),
+                ],
+            )
+            .await
+            .unwrap_err();
         }
     }
 
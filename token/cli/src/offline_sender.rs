@@ -0,0 +1,34 @@
+use async_trait::async_trait;
+use solana_client::{
+    client_error::{ClientError, ClientErrorKind, Result as ClientResult},
+    rpc_request::RpcRequest,
+    rpc_sender::{RpcSender, RpcTransportStats},
+};
+
+/// Stands in for the RPC node with `--offline`, failing every request instead of sending it, so
+/// that nothing reaches the network from an airgapped machine. Commands only get here when an
+/// explicit argument was left out, which the error points to.
+pub(crate) struct OfflineSender;
+
+#[async_trait]
+impl RpcSender for OfflineSender {
+    async fn send(
+        &self,
+        request: RpcRequest,
+        _params: serde_json::Value,
+    ) -> ClientResult<serde_json::Value> {
+        Err(ClientError::from(ClientErrorKind::Custom(format!(
+            "`{}` needs the network, which --offline doesn't allow. \
+             Pass the information explicitly instead, like --blockhash or --mint-decimals",
+            request
+        ))))
+    }
+
+    fn get_transport_stats(&self) -> RpcTransportStats {
+        RpcTransportStats::default()
+    }
+
+    fn url(&self) -> String {
+        "offline".to_string()
+    }
+}
@@ -2,7 +2,12 @@ use crate::{config::Config, sort::UnsupportedAccount};
 use console::Emoji;
 use serde::{Deserialize, Serialize, Serializer};
 use solana_account_decoder::parse_token::{UiAccountState, UiTokenAccount, UiTokenAmount};
-use solana_cli_output::{display::writeln_name_value, OutputFormat, QuietDisplay, VerboseDisplay};
+use solana_cli_output::{
+    display::writeln_name_value, CliSignOnlyData, OutputFormat, QuietDisplay, VerboseDisplay,
+};
+use solana_client::rpc_response::RpcSimulateTransactionResult;
+use solana_sdk::{native_token::lamports_to_sol, transaction::TransactionError};
+use solana_transaction_status::TransactionConfirmationStatus;
 use std::fmt::{self, Display};
 
 pub(crate) trait Output: Serialize + fmt::Display + QuietDisplay + VerboseDisplay {}
@@ -101,17 +106,112 @@ where
     }
 }
 
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CliSyncNative<T>
+where
+    T: Serialize + Display + QuietDisplay + VerboseDisplay,
+{
+    pub(crate) address: String,
+    pub(crate) balance_before: Option<String>,
+    pub(crate) balance_after: Option<String>,
+    pub(crate) transaction_data: T,
+}
+
+impl<T> Display for CliSyncNative<T>
+where
+    T: Serialize + Display + QuietDisplay + VerboseDisplay,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f)?;
+        writeln_name_value(f, "Address:", &self.address)?;
+        if let Some(balance_before) = &self.balance_before {
+            writeln_name_value(f, "Balance before:", &format!("{} SOL", balance_before))?;
+        }
+        if let Some(balance_after) = &self.balance_after {
+            writeln_name_value(f, "Balance after:", &format!("{} SOL", balance_after))?;
+        }
+        Display::fmt(&self.transaction_data, f)
+    }
+}
+impl<T> QuietDisplay for CliSyncNative<T> where
+    T: Serialize + Display + QuietDisplay + VerboseDisplay
+{
+}
+impl<T> VerboseDisplay for CliSyncNative<T> where
+    T: Serialize + Display + QuietDisplay + VerboseDisplay
+{
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CliBalanceChange {
+    pub(crate) address: String,
+    // Missing for accounts that didn't exist
+    pub(crate) before: Option<String>,
+    pub(crate) after: Option<String>,
+    pub(crate) change: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CliBalanceChanges<T>
+where
+    T: Serialize + Display + QuietDisplay + VerboseDisplay,
+{
+    pub(crate) balances: Vec<CliBalanceChange>,
+    // Flattened so the output only gains the balances over the plain transaction output
+    #[serde(flatten)]
+    pub(crate) transaction_data: T,
+}
+
+impl<T> Display for CliBalanceChanges<T>
+where
+    T: Serialize + Display + QuietDisplay + VerboseDisplay,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f)?;
+        writeln!(f, "Balances:")?;
+        for balance in &self.balances {
+            writeln_name_value(
+                f,
+                &format!("  {}:", balance.address),
+                &format!(
+                    "{} -> {} ({})",
+                    balance.before.as_deref().unwrap_or("none"),
+                    balance.after.as_deref().unwrap_or("none"),
+                    balance.change
+                ),
+            )?;
+        }
+        Display::fmt(&self.transaction_data, f)
+    }
+}
+impl<T> QuietDisplay for CliBalanceChanges<T> where
+    T: Serialize + Display + QuietDisplay + VerboseDisplay
+{
+}
+impl<T> VerboseDisplay for CliBalanceChanges<T> where
+    T: Serialize + Display + QuietDisplay + VerboseDisplay
+{
+}
+
 #[derive(Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub(crate) struct CliTokenAmount {
     #[serde(flatten)]
     pub(crate) amount: UiTokenAmount,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub(crate) ui_amount_with_interest: Option<String>,
 }
 
 impl QuietDisplay for CliTokenAmount {}
 impl VerboseDisplay for CliTokenAmount {
     fn write_str(&self, w: &mut dyn fmt::Write) -> fmt::Result {
         writeln!(w, "ui amount: {}", self.amount.real_number_string_trimmed())?;
+        if let Some(ui_amount_with_interest) = &self.ui_amount_with_interest {
+            writeln!(w, "ui amount with interest: {}", ui_amount_with_interest)?;
+        }
         writeln!(w, "decimals: {}", self.amount.decimals)?;
         writeln!(w, "amount: {}", self.amount.amount)
     }
@@ -119,7 +219,154 @@ impl VerboseDisplay for CliTokenAmount {
 
 impl fmt::Display for CliTokenAmount {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        writeln!(f, "{}", self.amount.real_number_string_trimmed())
+        match &self.ui_amount_with_interest {
+            Some(ui_amount_with_interest) => writeln!(
+                f,
+                "{} (with interest, raw amount: {})",
+                ui_amount_with_interest, self.amount.amount
+            ),
+            None => writeln!(f, "{}", self.amount.real_number_string_trimmed()),
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CliMintSize {
+    pub(crate) extensions: Vec<String>,
+    pub(crate) space: usize,
+    pub(crate) rent_exempt_lamports: u64,
+}
+
+impl QuietDisplay for CliMintSize {}
+impl VerboseDisplay for CliMintSize {}
+
+impl fmt::Display for CliMintSize {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f)?;
+        if self.extensions.is_empty() {
+            writeln_name_value(f, "Extensions:", "None")?;
+        } else {
+            writeln_name_value(f, "Extensions:", &self.extensions.join(", "))?;
+        }
+        writeln_name_value(f, "Size:", &format!("{} bytes", self.space))?;
+        writeln_name_value(
+            f,
+            "Rent-exempt balance:",
+            &format!("{} SOL", lamports_to_sol(self.rent_exempt_lamports)),
+        )
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CliSupply {
+    #[serde(flatten)]
+    pub(crate) amount: UiTokenAmount,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) withheld: Option<CliWithheldSupply>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CliWithheldSupply {
+    pub(crate) in_mint: UiTokenAmount,
+    pub(crate) in_accounts: UiTokenAmount,
+}
+
+impl QuietDisplay for CliSupply {}
+impl VerboseDisplay for CliSupply {}
+
+impl fmt::Display for CliSupply {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{}", self.amount.real_number_string_trimmed())?;
+        if let Some(withheld) = &self.withheld {
+            writeln_name_value(
+                f,
+                "Withheld in mint:",
+                &withheld.in_mint.real_number_string_trimmed(),
+            )?;
+            writeln_name_value(
+                f,
+                "Withheld in accounts:",
+                &withheld.in_accounts.real_number_string_trimmed(),
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CliUiAmount {
+    pub(crate) mint: String,
+    pub(crate) amount: String,
+    pub(crate) ui_amount: String,
+}
+
+impl QuietDisplay for CliUiAmount {}
+impl VerboseDisplay for CliUiAmount {}
+
+impl fmt::Display for CliUiAmount {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{}", self.ui_amount)
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CliRawAmount {
+    pub(crate) mint: String,
+    pub(crate) amount: String,
+    pub(crate) ui_amount: String,
+}
+
+impl QuietDisplay for CliRawAmount {}
+impl VerboseDisplay for CliRawAmount {}
+
+impl fmt::Display for CliRawAmount {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{}", self.amount)
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CliAccountBalance {
+    pub(crate) address: String,
+    #[serde(flatten)]
+    pub(crate) amount: UiTokenAmount,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CliOwnerBalance {
+    pub(crate) owner: String,
+    pub(crate) mint: String,
+    #[serde(flatten)]
+    pub(crate) total: UiTokenAmount,
+    pub(crate) accounts: Vec<CliAccountBalance>,
+}
+
+impl QuietDisplay for CliOwnerBalance {
+    fn write_str(&self, w: &mut dyn fmt::Write) -> fmt::Result {
+        writeln!(w, "{}", self.total.real_number_string_trimmed())
+    }
+}
+impl VerboseDisplay for CliOwnerBalance {}
+
+impl fmt::Display for CliOwnerBalance {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{}", self.total.real_number_string_trimmed())?;
+        for account in &self.accounts {
+            writeln!(
+                f,
+                "  {}: {}",
+                account.address,
+                account.amount.real_number_string_trimmed()
+            )?;
+        }
+        Ok(())
     }
 }
 
@@ -128,6 +375,10 @@ impl fmt::Display for CliTokenAmount {
 pub(crate) struct CliWalletAddress {
     pub(crate) wallet_address: String,
     #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) mint: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) program_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub(crate) associated_token_address: Option<String>,
 }
 
@@ -137,6 +388,12 @@ impl VerboseDisplay for CliWalletAddress {}
 impl fmt::Display for CliWalletAddress {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         writeln!(f, "Wallet address: {}", self.wallet_address)?;
+        if let Some(mint) = &self.mint {
+            writeln!(f, "Mint: {}", mint)?;
+        }
+        if let Some(program_id) = &self.program_id {
+            writeln!(f, "Program: {}", program_id)?;
+        }
         if let Some(associated_token_address) = &self.associated_token_address {
             writeln!(f, "Associated token address: {}", associated_token_address)?;
         }
@@ -172,6 +429,45 @@ impl fmt::Display for CliMultisig {
     }
 }
 
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CliCreatedMultisig<T>
+where
+    T: Serialize + Display + QuietDisplay + VerboseDisplay,
+{
+    #[serde(flatten)]
+    pub(crate) multisig: CliMultisig,
+    pub(crate) transaction_data: T,
+}
+
+impl<T> Display for CliCreatedMultisig<T>
+where
+    T: Serialize + Display + QuietDisplay + VerboseDisplay,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Display::fmt(&self.multisig, f)?;
+        Display::fmt(&self.transaction_data, f)
+    }
+}
+impl<T> QuietDisplay for CliCreatedMultisig<T>
+where
+    T: Serialize + Display + QuietDisplay + VerboseDisplay,
+{
+    fn write_str(&self, w: &mut dyn std::fmt::Write) -> std::fmt::Result {
+        write!(w, "{}", self.multisig)?;
+        QuietDisplay::write_str(&self.transaction_data, w)
+    }
+}
+impl<T> VerboseDisplay for CliCreatedMultisig<T>
+where
+    T: Serialize + Display + QuietDisplay + VerboseDisplay,
+{
+    fn write_str(&self, w: &mut dyn std::fmt::Write) -> std::fmt::Result {
+        write!(w, "{}", self.multisig)?;
+        VerboseDisplay::write_str(&self.transaction_data, w)
+    }
+}
+
 #[derive(Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub(crate) struct CliTokenAccount {
@@ -179,6 +475,38 @@ pub(crate) struct CliTokenAccount {
     pub(crate) is_associated: bool,
     #[serde(flatten)]
     pub(crate) account: UiTokenAccount,
+    // Only known for Token-2022 accounts
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) required_transfer_memos: Option<bool>,
+}
+
+// A single entry of `--output jsonl` listings, self-contained so that each line can be consumed
+// on its own
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CliTokenAccountLine {
+    pub(crate) mint: String,
+    pub(crate) address: String,
+    pub(crate) amount: String,
+    pub(crate) ui_amount: String,
+    pub(crate) decimals: u8,
+    pub(crate) owner: String,
+    pub(crate) delegate: Option<String>,
+}
+
+impl From<&CliTokenAccount> for CliTokenAccountLine {
+    fn from(token_account: &CliTokenAccount) -> Self {
+        let account = &token_account.account;
+        Self {
+            mint: account.mint.clone(),
+            address: token_account.address.clone(),
+            amount: account.token_amount.amount.clone(),
+            ui_amount: account.token_amount.ui_amount_string.clone(),
+            decimals: account.token_amount.decimals,
+            owner: account.owner.clone(),
+            delegate: account.delegate.clone(),
+        }
+    }
 }
 
 impl QuietDisplay for CliTokenAccount {}
@@ -225,6 +553,17 @@ impl fmt::Display for CliTokenAccount {
                 .as_ref()
                 .unwrap_or(&String::new()),
         )?;
+        if let Some(required_transfer_memos) = self.required_transfer_memos {
+            writeln_name_value(
+                f,
+                "Required transfer memos:",
+                if required_transfer_memos {
+                    "Enabled"
+                } else {
+                    "Disabled"
+                },
+            )?;
+        }
         if !self.is_associated {
             writeln!(f)?;
             writeln!(f, "* Please run `spl-token gc` to clean up Aux accounts")?;
@@ -399,6 +738,1261 @@ impl fmt::Display for CliTokenAccounts {
     }
 }
 
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CliSimulation {
+    #[serde(flatten)]
+    pub(crate) result: RpcSimulateTransactionResult,
+}
+
+impl QuietDisplay for CliSimulation {}
+impl VerboseDisplay for CliSimulation {}
+
+impl fmt::Display for CliSimulation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f)?;
+        match &self.result.err {
+            Some(err) => writeln_name_value(f, "Simulation failed:", &err.to_string())?,
+            None => writeln_name_value(f, "Simulation succeeded", "")?,
+        }
+        if let Some(units_consumed) = self.result.units_consumed {
+            writeln_name_value(f, "Compute units consumed:", &units_consumed.to_string())?;
+        }
+        match &self.result.logs {
+            Some(logs) if !logs.is_empty() => {
+                writeln!(f, "Logs:")?;
+                for log in logs {
+                    writeln!(f, "  {}", log)?;
+                }
+            }
+            _ => writeln_name_value(f, "Logs:", "None")?,
+        }
+        Ok(())
+    }
+}
+
+// The signers data of a sign-only transaction, plus what is known about each account and each
+// instruction of the message when the message itself is dumped
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CliSignOnlyTransaction {
+    #[serde(flatten)]
+    pub(crate) sign_only_data: CliSignOnlyData,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub(crate) accounts: Vec<CliLabeledAccount>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub(crate) instructions: Vec<CliDecodedInstruction>,
+    // Only with `--relayer-sign-only`, base64 like the files of `--output-file`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) relayer_transaction: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CliLabeledAccount {
+    pub(crate) address: String,
+    pub(crate) label: Option<String>,
+}
+
+impl QuietDisplay for CliSignOnlyTransaction {}
+impl VerboseDisplay for CliSignOnlyTransaction {}
+
+impl fmt::Display for CliSignOnlyTransaction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.sign_only_data)?;
+        if !self.accounts.is_empty() {
+            writeln!(f)?;
+            writeln!(f, "Message Accounts:")?;
+            for (i, account) in self.accounts.iter().enumerate() {
+                match &account.label {
+                    Some(label) => writeln!(f, "  {}. {} ({})", i, account.address, label)?,
+                    None => writeln!(f, "  {}. {}", i, account.address)?,
+                }
+            }
+        }
+        write_instructions(f, &self.instructions)?;
+        if let Some(relayer_transaction) = &self.relayer_transaction {
+            writeln!(f)?;
+            writeln_name_value(f, "Transaction for the relayer:", relayer_transaction)?;
+        }
+        Ok(())
+    }
+}
+
+// The result of `create-account --idempotent`, which sends nothing for an account that already
+// exists. `existed` is always false in sign-only mode, where there is no way to check.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CliCreatedAccount<T>
+where
+    T: Serialize + Display,
+{
+    pub(crate) address: String,
+    pub(crate) existed: bool,
+    #[serde(flatten, skip_serializing_if = "Option::is_none")]
+    pub(crate) transaction_data: Option<T>,
+}
+
+impl<T> QuietDisplay for CliCreatedAccount<T> where T: Serialize + Display {}
+impl<T> VerboseDisplay for CliCreatedAccount<T> where T: Serialize + Display {}
+
+impl<T> Display for CliCreatedAccount<T>
+where
+    T: Serialize + Display,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.transaction_data {
+            Some(transaction_data) => Display::fmt(transaction_data, f),
+            None => {
+                writeln!(f)?;
+                writeln_name_value(f, "Account already exists:", &self.address)
+            }
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CliBatchTransferTransaction {
+    pub(crate) rows: Vec<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) signature: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) sign_only_data: Option<CliSignOnlyTransaction>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) simulation: Option<CliSimulation>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) error: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CliBatchTransfer {
+    pub(crate) mint: String,
+    pub(crate) sender: String,
+    // Only with `--fund-recipient`, when online
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) recipient_rent: Option<CliRecipientRent>,
+    pub(crate) transactions: Vec<CliBatchTransferTransaction>,
+}
+
+// The rent the fee payers spend on the recipient accounts the batch creates
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CliRecipientRent {
+    pub(crate) accounts: usize,
+    pub(crate) lamports: u64,
+    pub(crate) sol: f64,
+}
+
+impl QuietDisplay for CliBatchTransfer {}
+impl VerboseDisplay for CliBatchTransfer {}
+
+impl fmt::Display for CliBatchTransfer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, transaction) in self.transactions.iter().enumerate() {
+            writeln!(f)?;
+            writeln!(f, "Transaction {}:", i + 1)?;
+            let rows = transaction
+                .rows
+                .iter()
+                .map(|row| row.to_string())
+                .collect::<Vec<_>>()
+                .join(", ");
+            writeln_name_value(f, "  Rows:", &rows)?;
+            if let Some(signature) = &transaction.signature {
+                writeln_name_value(f, "  Signature:", signature)?;
+            }
+            if let Some(error) = &transaction.error {
+                writeln_name_value(f, "  Error:", error)?;
+            }
+            if let Some(sign_only_data) = &transaction.sign_only_data {
+                write!(f, "{}", sign_only_data)?;
+            }
+            if let Some(simulation) = &transaction.simulation {
+                write!(f, "{}", simulation)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CliOwnerResult {
+    pub(crate) owner: String,
+    pub(crate) result: serde_json::Value,
+    #[serde(skip_serializing)]
+    pub(crate) display: String,
+}
+
+impl CliOwnerResult {
+    // Keep the already formatted output of a command, embedding it as JSON when it is JSON
+    pub(crate) fn new(owner: String, output: String) -> Self {
+        Self {
+            owner,
+            result: serde_json::from_str(&output)
+                .unwrap_or_else(|_| serde_json::Value::String(output.clone())),
+            display: output,
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CliOwnerResults {
+    pub(crate) owners: Vec<CliOwnerResult>,
+}
+
+impl QuietDisplay for CliOwnerResults {}
+impl VerboseDisplay for CliOwnerResults {}
+
+impl fmt::Display for CliOwnerResults {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for owner_result in &self.owners {
+            writeln!(f)?;
+            writeln_name_value(f, "Owner:", &owner_result.owner)?;
+            writeln!(f, "{}", owner_result.display)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CliSkippedAccount {
+    pub(crate) address: String,
+    pub(crate) reason: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CliCloseEmptyAccounts {
+    pub(crate) owner: String,
+    pub(crate) closed: Vec<String>,
+    pub(crate) skipped: Vec<CliSkippedAccount>,
+    pub(crate) lamports_reclaimed: u64,
+    pub(crate) signatures: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub(crate) simulations: Vec<CliSimulation>,
+}
+
+impl QuietDisplay for CliCloseEmptyAccounts {}
+impl VerboseDisplay for CliCloseEmptyAccounts {}
+
+impl fmt::Display for CliCloseEmptyAccounts {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f)?;
+        writeln_name_value(f, "Owner:", &self.owner)?;
+        writeln_name_value(f, "Accounts closed:", &self.closed.len().to_string())?;
+        for address in &self.closed {
+            writeln!(f, "  {}", address)?;
+        }
+        if !self.skipped.is_empty() {
+            writeln_name_value(f, "Accounts skipped:", &self.skipped.len().to_string())?;
+            for skipped in &self.skipped {
+                writeln!(f, "  {}: {}", skipped.address, skipped.reason)?;
+            }
+        }
+        writeln_name_value(
+            f,
+            "Reclaimed:",
+            &format!("{} SOL", lamports_to_sol(self.lamports_reclaimed)),
+        )?;
+        for signature in &self.signatures {
+            writeln_name_value(f, "Signature:", signature)?;
+        }
+        for simulation in &self.simulations {
+            write!(f, "{}", simulation)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CliMovedBalance {
+    pub(crate) address: String,
+    pub(crate) amount: u64,
+    pub(crate) ui_amount: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CliRecreateAta {
+    pub(crate) address: String,
+    pub(crate) program_id: String,
+    pub(crate) created: bool,
+    pub(crate) moved: Vec<CliMovedBalance>,
+    pub(crate) signatures: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub(crate) simulations: Vec<CliSimulation>,
+}
+
+impl QuietDisplay for CliRecreateAta {}
+impl VerboseDisplay for CliRecreateAta {}
+
+impl fmt::Display for CliRecreateAta {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f)?;
+        writeln_name_value(f, "Associated token account:", &self.address)?;
+        writeln_name_value(f, "  Program:", &self.program_id)?;
+        if self.created {
+            writeln!(f, "  Created")?;
+        }
+        for moved in &self.moved {
+            writeln!(
+                f,
+                "  Moved {} tokens from {}",
+                moved.ui_amount, moved.address
+            )?;
+        }
+        if !self.created && self.moved.is_empty() {
+            writeln!(
+                f,
+                "Nothing to do, the associated token account already holds all tokens"
+            )?;
+        }
+        for signature in &self.signatures {
+            writeln_name_value(f, "Signature:", signature)?;
+        }
+        for simulation in &self.simulations {
+            write!(f, "{}", simulation)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CliDelegation {
+    pub(crate) address: String,
+    pub(crate) mint: String,
+    pub(crate) amount: u64,
+    pub(crate) ui_amount: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CliApproveMany {
+    pub(crate) delegate: String,
+    pub(crate) accounts: Vec<CliDelegation>,
+    pub(crate) signatures: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub(crate) simulations: Vec<CliSimulation>,
+}
+
+impl QuietDisplay for CliApproveMany {}
+impl VerboseDisplay for CliApproveMany {}
+
+impl fmt::Display for CliApproveMany {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f)?;
+        writeln_name_value(f, "Delegate:", &self.delegate)?;
+        writeln_name_value(f, "Accounts approved:", &self.accounts.len().to_string())?;
+        for delegation in &self.accounts {
+            writeln!(
+                f,
+                "  {}: {} of token {}",
+                delegation.address, delegation.ui_amount, delegation.mint
+            )?;
+        }
+        for signature in &self.signatures {
+            writeln_name_value(f, "Signature:", signature)?;
+        }
+        for simulation in &self.simulations {
+            write!(f, "{}", simulation)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CliRevokeAll {
+    pub(crate) owner: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) mint: Option<String>,
+    pub(crate) revoked: Vec<String>,
+    pub(crate) signatures: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub(crate) simulations: Vec<CliSimulation>,
+}
+
+impl QuietDisplay for CliRevokeAll {}
+impl VerboseDisplay for CliRevokeAll {}
+
+impl fmt::Display for CliRevokeAll {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f)?;
+        writeln_name_value(f, "Owner:", &self.owner)?;
+        if let Some(mint) = &self.mint {
+            writeln_name_value(f, "Mint:", mint)?;
+        }
+        writeln_name_value(f, "Delegates cleared:", &self.revoked.len().to_string())?;
+        for address in &self.revoked {
+            writeln!(f, "  {}", address)?;
+        }
+        for signature in &self.signatures {
+            writeln_name_value(f, "Signature:", signature)?;
+        }
+        for simulation in &self.simulations {
+            write!(f, "{}", simulation)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CliDecodedTransaction {
+    pub(crate) blockhash: String,
+    pub(crate) signers: Vec<CliTransactionSigner>,
+    pub(crate) instructions: Vec<CliDecodedInstruction>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CliTransactionSigner {
+    pub(crate) address: String,
+    // Missing until the signer has signed
+    pub(crate) signature: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CliDecodedInstruction {
+    pub(crate) program_id: String,
+    pub(crate) program: Option<String>,
+    pub(crate) accounts: Vec<CliInstructionAccount>,
+    // Only for the programs the CLI knows about
+    pub(crate) instruction: Option<String>,
+    pub(crate) data: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CliInstructionAccount {
+    pub(crate) address: String,
+    // Only for the accounts the CLI can tell apart, like the fee payer or a mint
+    pub(crate) label: Option<String>,
+    pub(crate) signer: bool,
+    pub(crate) writable: bool,
+}
+
+// Each instruction with its program, accounts and, when the CLI can decode it, its arguments
+fn write_instructions(
+    f: &mut fmt::Formatter,
+    instructions: &[CliDecodedInstruction],
+) -> fmt::Result {
+    for (i, instruction) in instructions.iter().enumerate() {
+        writeln!(f)?;
+        writeln!(
+            f,
+            "Instruction {}: {}",
+            i,
+            instruction.program.as_deref().unwrap_or("Unknown program")
+        )?;
+        writeln_name_value(f, "  Program:", &instruction.program_id)?;
+        writeln!(f, "  Accounts:")?;
+        for (j, account) in instruction.accounts.iter().enumerate() {
+            let mut flags = vec![];
+            if account.writable {
+                flags.push("writable");
+            }
+            if account.signer {
+                flags.push("signer");
+            }
+            let address = match &account.label {
+                Some(label) => format!("{} [{}]", account.address, label),
+                None => account.address.clone(),
+            };
+            if flags.is_empty() {
+                writeln!(f, "    {}. {}", j, address)?;
+            } else {
+                writeln!(f, "    {}. {} ({})", j, address, flags.join(", "))?;
+            }
+        }
+        match &instruction.instruction {
+            Some(decoded) => writeln_name_value(f, "  Instruction:", decoded)?,
+            None => writeln_name_value(f, "  Data:", &instruction.data)?,
+        }
+    }
+    Ok(())
+}
+
+impl QuietDisplay for CliDecodedTransaction {}
+impl VerboseDisplay for CliDecodedTransaction {}
+
+impl fmt::Display for CliDecodedTransaction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f)?;
+        writeln_name_value(f, "Blockhash:", &self.blockhash)?;
+        let signed = self
+            .signers
+            .iter()
+            .filter(|signer| signer.signature.is_some())
+            .count();
+        writeln_name_value(
+            f,
+            "Signatures:",
+            &format!("{} of {} present", signed, self.signers.len()),
+        )?;
+        for signer in &self.signers {
+            writeln!(
+                f,
+                "  {}: {}",
+                signer.address,
+                signer.signature.as_deref().unwrap_or("missing")
+            )?;
+        }
+        write_instructions(f, &self.instructions)
+    }
+}
+
+// The fields of the RPC signature status, plus the signature itself
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CliSignatureStatus {
+    pub(crate) signature: String,
+    pub(crate) slot: u64,
+    pub(crate) confirmations: Option<usize>,
+    pub(crate) err: Option<TransactionError>,
+    pub(crate) confirmation_status: Option<TransactionConfirmationStatus>,
+}
+
+impl QuietDisplay for CliSignatureStatus {}
+impl VerboseDisplay for CliSignatureStatus {}
+
+impl fmt::Display for CliSignatureStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f)?;
+        writeln_name_value(f, "Signature:", &self.signature)?;
+        writeln_name_value(f, "Slot:", &self.slot.to_string())?;
+        let status = match &self.confirmation_status {
+            Some(TransactionConfirmationStatus::Processed) => "processed",
+            Some(TransactionConfirmationStatus::Confirmed) => "confirmed",
+            Some(TransactionConfirmationStatus::Finalized) => "finalized",
+            None => "unknown",
+        };
+        writeln_name_value(f, "Confirmation status:", status)?;
+        if let Some(confirmations) = self.confirmations {
+            writeln_name_value(f, "Confirmations:", &confirmations.to_string())?;
+        }
+        if let Some(err) = &self.err {
+            writeln_name_value(f, "Error:", &err.to_string())?;
+        }
+        Ok(())
+    }
+}
+
+/// The holdings of a set of mints, as written by `export-accounts`. Fields are never reordered or
+/// renamed, so that snapshots taken by different versions can be diffed.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CliAccountsSnapshot {
+    #[serde(flatten)]
+    pub(crate) body: CliAccountsSnapshotBody,
+    /// Hash of the compact JSON of `body`
+    pub(crate) hash: String,
+    pub(crate) signer: String,
+    /// Signature of `hash` by `signer`
+    pub(crate) signature: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CliAccountsSnapshotBody {
+    pub(crate) version: u8,
+    /// The latest slot any of the mints was read at
+    pub(crate) slot: u64,
+    pub(crate) mints: Vec<CliSnapshotMint>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CliSnapshotMint {
+    pub(crate) mint: String,
+    pub(crate) program_id: String,
+    pub(crate) decimals: u8,
+    pub(crate) slot: u64,
+    pub(crate) accounts: Vec<CliSnapshotAccount>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CliSnapshotAccount {
+    pub(crate) address: String,
+    pub(crate) owner: String,
+    pub(crate) amount: String,
+    pub(crate) ui_amount: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CliExportAccounts {
+    pub(crate) file: String,
+    pub(crate) slot: u64,
+    pub(crate) accounts: usize,
+    pub(crate) hash: String,
+    pub(crate) signature: String,
+}
+
+impl QuietDisplay for CliExportAccounts {}
+impl VerboseDisplay for CliExportAccounts {}
+
+impl fmt::Display for CliExportAccounts {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f)?;
+        writeln_name_value(f, "Snapshot:", &self.file)?;
+        writeln_name_value(f, "Slot:", &self.slot.to_string())?;
+        writeln_name_value(f, "Accounts:", &self.accounts.to_string())?;
+        writeln_name_value(f, "Hash:", &self.hash)?;
+        writeln_name_value(f, "Signature:", &self.signature)?;
+        Ok(())
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CliFoundMints {
+    pub(crate) authority: String,
+    pub(crate) mints: Vec<CliFoundMint>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CliFoundMint {
+    pub(crate) address: String,
+    pub(crate) program_id: String,
+    pub(crate) decimals: u8,
+    pub(crate) supply: String,
+    pub(crate) ui_supply: String,
+    pub(crate) mint_authority: bool,
+    pub(crate) freeze_authority: bool,
+}
+
+impl QuietDisplay for CliFoundMints {}
+impl VerboseDisplay for CliFoundMints {}
+
+impl fmt::Display for CliFoundMints {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f)?;
+        if self.mints.is_empty() {
+            writeln!(f, "No mints found for authority {}", self.authority)?;
+            return Ok(());
+        }
+        writeln!(
+            f,
+            "{:<44}  {:<8}  {:<20}  Authority",
+            "Mint", "Decimals", "Supply"
+        )?;
+        writeln!(f, "{}", "-".repeat(95))?;
+        for mint in &self.mints {
+            let roles = match (mint.mint_authority, mint.freeze_authority) {
+                (true, true) => "mint, freeze",
+                (true, false) => "mint",
+                (false, true) => "freeze",
+                (false, false) => "",
+            };
+            writeln!(
+                f,
+                "{:<44}  {:<8}  {:<20}  {}",
+                mint.address, mint.decimals, mint.ui_supply, roles
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CliFreezeAll {
+    pub(crate) mint: String,
+    pub(crate) state: String,
+    pub(crate) accounts: Vec<String>,
+    pub(crate) unchanged: usize,
+    pub(crate) signatures: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub(crate) simulations: Vec<CliSimulation>,
+}
+
+impl QuietDisplay for CliFreezeAll {}
+impl VerboseDisplay for CliFreezeAll {}
+
+impl fmt::Display for CliFreezeAll {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f)?;
+        writeln_name_value(f, "Mint:", &self.mint)?;
+        writeln_name_value(
+            f,
+            &format!("Accounts {}:", self.state),
+            &self.accounts.len().to_string(),
+        )?;
+        for address in &self.accounts {
+            writeln!(f, "  {}", address)?;
+        }
+        if self.unchanged > 0 {
+            writeln_name_value(
+                f,
+                &format!("Already {}:", self.state),
+                &self.unchanged.to_string(),
+            )?;
+        }
+        for signature in &self.signatures {
+            writeln_name_value(f, "Signature:", signature)?;
+        }
+        for simulation in &self.simulations {
+            write!(f, "{}", simulation)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CliHarvestedWithheldTokens {
+    pub(crate) mint: String,
+    #[serde(flatten)]
+    pub(crate) total: UiTokenAmount,
+    pub(crate) accounts: Vec<CliAccountBalance>,
+    pub(crate) signatures: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub(crate) simulations: Vec<CliSimulation>,
+}
+
+impl QuietDisplay for CliHarvestedWithheldTokens {}
+impl VerboseDisplay for CliHarvestedWithheldTokens {}
+
+impl fmt::Display for CliHarvestedWithheldTokens {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f)?;
+        writeln_name_value(f, "Mint:", &self.mint)?;
+        writeln_name_value(f, "Harvested:", &self.total.real_number_string_trimmed())?;
+        for account in &self.accounts {
+            writeln!(
+                f,
+                "  {}: {}",
+                account.address,
+                account.amount.real_number_string_trimmed()
+            )?;
+        }
+        for signature in &self.signatures {
+            writeln_name_value(f, "Signature:", signature)?;
+        }
+        for simulation in &self.simulations {
+            write!(f, "{}", simulation)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CliWithdrawnWithheldTokens<T>
+where
+    T: Serialize + Display + QuietDisplay + VerboseDisplay,
+{
+    pub(crate) mint: String,
+    pub(crate) destination: String,
+    // Unknown when signing offline
+    pub(crate) amount: Option<String>,
+    pub(crate) transaction_data: T,
+}
+
+impl<T> Display for CliWithdrawnWithheldTokens<T>
+where
+    T: Serialize + Display + QuietDisplay + VerboseDisplay,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f)?;
+        writeln_name_value(f, "Mint:", &self.mint)?;
+        writeln_name_value(f, "Destination:", &self.destination)?;
+        if let Some(amount) = &self.amount {
+            writeln_name_value(f, "Withdrawn:", amount)?;
+        }
+        Display::fmt(&self.transaction_data, f)
+    }
+}
+impl<T> QuietDisplay for CliWithdrawnWithheldTokens<T> where
+    T: Serialize + Display + QuietDisplay + VerboseDisplay
+{
+}
+impl<T> VerboseDisplay for CliWithdrawnWithheldTokens<T> where
+    T: Serialize + Display + QuietDisplay + VerboseDisplay
+{
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CliCombinedTransaction {
+    pub(crate) signers: Vec<String>,
+    pub(crate) transaction: String,
+}
+
+impl QuietDisplay for CliCombinedTransaction {}
+impl VerboseDisplay for CliCombinedTransaction {}
+
+impl fmt::Display for CliCombinedTransaction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f)?;
+        writeln_name_value(f, "Signers:", &self.signers.len().to_string())?;
+        for signer in &self.signers {
+            writeln!(f, "  {}", signer)?;
+        }
+        writeln_name_value(f, "Transaction:", &self.transaction)
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CliMintInfo {
+    pub(crate) address: String,
+    pub(crate) program_id: String,
+    pub(crate) supply: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) supply_with_interest: Option<String>,
+    pub(crate) decimals: u8,
+    pub(crate) mint_authority: Option<String>,
+    pub(crate) freeze_authority: Option<String>,
+    pub(crate) extensions: Vec<CliMintExtension>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum CliMintExtension {
+    TransferFeeConfig(CliTransferFeeConfig),
+    MintCloseAuthority(CliMintCloseAuthority),
+    ConfidentialTransferMint(CliConfidentialTransferMint),
+    DefaultAccountState(CliDefaultAccountState),
+    NonTransferable {},
+    InterestBearingConfig(CliInterestBearingConfig),
+    Unparseable(CliUnparseableExtension),
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CliTransferFee {
+    pub(crate) epoch: u64,
+    pub(crate) maximum_fee: u64,
+    pub(crate) transfer_fee_basis_points: u16,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CliTransferFeeConfig {
+    pub(crate) transfer_fee_config_authority: Option<String>,
+    pub(crate) withdraw_withheld_authority: Option<String>,
+    pub(crate) withheld_amount: u64,
+    pub(crate) older_transfer_fee: CliTransferFee,
+    pub(crate) newer_transfer_fee: CliTransferFee,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CliMintCloseAuthority {
+    pub(crate) close_authority: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CliConfidentialTransferMint {
+    pub(crate) authority: String,
+    pub(crate) auto_approve_new_accounts: bool,
+    pub(crate) auditor_encryption_pubkey: Option<String>,
+    pub(crate) withdraw_withheld_authority_encryption_pubkey: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CliDefaultAccountState {
+    pub(crate) account_state: UiAccountState,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CliInterestBearingConfig {
+    pub(crate) rate_authority: Option<String>,
+    pub(crate) initialization_timestamp: i64,
+    pub(crate) pre_update_average_rate: i16,
+    pub(crate) last_update_timestamp: i64,
+    pub(crate) current_rate: i16,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CliUnparseableExtension {
+    pub(crate) extension_type: String,
+    pub(crate) error: String,
+}
+
+fn display_optional(value: &Option<String>) -> &str {
+    value.as_deref().unwrap_or("None")
+}
+
+fn writeln_transfer_fee(f: &mut dyn fmt::Write, name: &str, fee: &CliTransferFee) -> fmt::Result {
+    writeln!(f, "    {}", name)?;
+    writeln_name_value(f, "      Epoch:", &fee.epoch.to_string())?;
+    writeln_name_value(f, "      Maximum fee:", &fee.maximum_fee.to_string())?;
+    writeln_name_value(
+        f,
+        "      Basis points:",
+        &fee.transfer_fee_basis_points.to_string(),
+    )
+}
+
+impl fmt::Display for CliMintExtension {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::TransferFeeConfig(config) => {
+                writeln!(f, "  Transfer fee config:")?;
+                writeln_name_value(
+                    f,
+                    "    Config authority:",
+                    display_optional(&config.transfer_fee_config_authority),
+                )?;
+                writeln_name_value(
+                    f,
+                    "    Withdraw withheld authority:",
+                    display_optional(&config.withdraw_withheld_authority),
+                )?;
+                writeln_name_value(
+                    f,
+                    "    Withheld amount:",
+                    &config.withheld_amount.to_string(),
+                )?;
+                writeln_transfer_fee(f, "Older transfer fee:", &config.older_transfer_fee)?;
+                writeln_transfer_fee(f, "Newer transfer fee:", &config.newer_transfer_fee)
+            }
+            Self::MintCloseAuthority(close_authority) => {
+                writeln!(f, "  Mint close authority:")?;
+                writeln_name_value(
+                    f,
+                    "    Close authority:",
+                    display_optional(&close_authority.close_authority),
+                )
+            }
+            Self::ConfidentialTransferMint(mint) => {
+                writeln!(f, "  Confidential transfer mint:")?;
+                writeln_name_value(f, "    Authority:", &mint.authority)?;
+                writeln_name_value(
+                    f,
+                    "    Auto-approve new accounts:",
+                    &mint.auto_approve_new_accounts.to_string(),
+                )?;
+                writeln_name_value(
+                    f,
+                    "    Auditor encryption pubkey:",
+                    display_optional(&mint.auditor_encryption_pubkey),
+                )?;
+                writeln_name_value(
+                    f,
+                    "    Withdraw withheld authority encryption pubkey:",
+                    display_optional(&mint.withdraw_withheld_authority_encryption_pubkey),
+                )
+            }
+            Self::DefaultAccountState(default_account_state) => {
+                writeln!(f, "  Default account state:")?;
+                writeln_name_value(
+                    f,
+                    "    State:",
+                    &format!("{:?}", default_account_state.account_state),
+                )
+            }
+            Self::NonTransferable {} => writeln!(f, "  Non-transferable"),
+            Self::InterestBearingConfig(config) => {
+                writeln!(f, "  Interest-bearing config:")?;
+                writeln_name_value(
+                    f,
+                    "    Rate authority:",
+                    display_optional(&config.rate_authority),
+                )?;
+                writeln_name_value(
+                    f,
+                    "    Current rate (bps):",
+                    &config.current_rate.to_string(),
+                )?;
+                writeln_name_value(
+                    f,
+                    "    Average rate before last update (bps):",
+                    &config.pre_update_average_rate.to_string(),
+                )?;
+                writeln_name_value(
+                    f,
+                    "    Initialization timestamp:",
+                    &config.initialization_timestamp.to_string(),
+                )?;
+                writeln_name_value(
+                    f,
+                    "    Last update timestamp:",
+                    &config.last_update_timestamp.to_string(),
+                )
+            }
+            Self::Unparseable(unparseable) => writeln_name_value(
+                f,
+                &format!("  {}:", unparseable.extension_type),
+                &format!("could not be parsed: {}", unparseable.error),
+            ),
+        }
+    }
+}
+
+impl QuietDisplay for CliMintInfo {}
+impl VerboseDisplay for CliMintInfo {}
+
+impl fmt::Display for CliMintInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f)?;
+        writeln_name_value(f, "Address:", &self.address)?;
+        writeln_name_value(f, "Program:", &self.program_id)?;
+        writeln_name_value(f, "Supply:", &self.supply)?;
+        if let Some(supply_with_interest) = &self.supply_with_interest {
+            writeln_name_value(f, "Supply with interest:", supply_with_interest)?;
+        }
+        writeln_name_value(f, "Decimals:", &self.decimals.to_string())?;
+        writeln_name_value(f, "Mint authority:", display_optional(&self.mint_authority))?;
+        writeln_name_value(
+            f,
+            "Freeze authority:",
+            display_optional(&self.freeze_authority),
+        )?;
+        if self.extensions.is_empty() {
+            writeln_name_value(f, "Extensions:", "None")
+        } else {
+            writeln!(f, "Extensions:")?;
+            for extension in &self.extensions {
+                write!(f, "{}", extension)?;
+            }
+            Ok(())
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CliTokenAccountInfo {
+    pub(crate) address: String,
+    pub(crate) program_id: String,
+    pub(crate) mint: String,
+    pub(crate) owner: String,
+    pub(crate) is_associated: bool,
+    pub(crate) amount: String,
+    pub(crate) decimals: u8,
+    pub(crate) delegate: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) delegated_amount: Option<String>,
+    pub(crate) state: UiAccountState,
+    pub(crate) is_native: bool,
+    pub(crate) close_authority: Option<String>,
+    pub(crate) extensions: Vec<CliAccountExtension>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum CliAccountExtension {
+    ImmutableOwner {},
+    MemoTransfer(CliMemoTransfer),
+    ConfidentialTransferAccount(CliConfidentialTransferAccount),
+    TransferFeeAmount(CliTransferFeeAmount),
+    Unparseable(CliUnparseableExtension),
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CliMemoTransfer {
+    pub(crate) require_incoming_transfer_memos: bool,
+}
+
+// The encrypted balances are left out, only the owner's key can make sense of them
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CliConfidentialTransferAccount {
+    pub(crate) approved: bool,
+    pub(crate) encryption_pubkey: Option<String>,
+    pub(crate) allow_balance_credits: bool,
+    pub(crate) pending_balance_credit_counter: u64,
+    pub(crate) maximum_pending_balance_credit_counter: u64,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CliTransferFeeAmount {
+    pub(crate) withheld_amount: u64,
+}
+
+impl fmt::Display for CliAccountExtension {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::ImmutableOwner {} => writeln!(f, "  Immutable owner"),
+            Self::MemoTransfer(memo_transfer) => {
+                writeln!(f, "  Memo transfer:")?;
+                writeln_name_value(
+                    f,
+                    "    Require incoming transfer memos:",
+                    &memo_transfer.require_incoming_transfer_memos.to_string(),
+                )
+            }
+            Self::ConfidentialTransferAccount(account) => {
+                writeln!(f, "  Confidential transfer account:")?;
+                writeln_name_value(f, "    Approved:", &account.approved.to_string())?;
+                writeln_name_value(
+                    f,
+                    "    Encryption pubkey:",
+                    display_optional(&account.encryption_pubkey),
+                )?;
+                writeln_name_value(
+                    f,
+                    "    Allow balance credits:",
+                    &account.allow_balance_credits.to_string(),
+                )?;
+                writeln_name_value(
+                    f,
+                    "    Pending balance credits:",
+                    &format!(
+                        "{} of {}",
+                        account.pending_balance_credit_counter,
+                        account.maximum_pending_balance_credit_counter
+                    ),
+                )
+            }
+            Self::TransferFeeAmount(transfer_fee_amount) => {
+                writeln!(f, "  Transfer fee amount:")?;
+                writeln_name_value(
+                    f,
+                    "    Withheld amount:",
+                    &transfer_fee_amount.withheld_amount.to_string(),
+                )
+            }
+            Self::Unparseable(unparseable) => writeln_name_value(
+                f,
+                &format!("  {}:", unparseable.extension_type),
+                &format!("could not be parsed: {}", unparseable.error),
+            ),
+        }
+    }
+}
+
+impl QuietDisplay for CliTokenAccountInfo {}
+impl VerboseDisplay for CliTokenAccountInfo {}
+
+impl fmt::Display for CliTokenAccountInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f)?;
+        writeln_name_value(f, "Address:", &self.address)?;
+        writeln_name_value(f, "Program:", &self.program_id)?;
+        writeln_name_value(f, "Mint:", &self.mint)?;
+        writeln_name_value(f, "Owner:", &self.owner)?;
+        writeln_name_value(f, "Associated:", &self.is_associated.to_string())?;
+        writeln_name_value(f, "Balance:", &self.amount)?;
+        writeln_name_value(f, "Decimals:", &self.decimals.to_string())?;
+        writeln_name_value(f, "Delegate:", display_optional(&self.delegate))?;
+        if let Some(delegated_amount) = &self.delegated_amount {
+            writeln_name_value(f, "Delegated amount:", delegated_amount)?;
+        }
+        writeln_name_value(f, "State:", &format!("{:?}", self.state))?;
+        writeln_name_value(f, "Native:", &self.is_native.to_string())?;
+        writeln_name_value(
+            f,
+            "Close authority:",
+            display_optional(&self.close_authority),
+        )?;
+        if self.extensions.is_empty() {
+            writeln_name_value(f, "Extensions:", "None")
+        } else {
+            writeln!(f, "Extensions:")?;
+            for extension in &self.extensions {
+                write!(f, "{}", extension)?;
+            }
+            Ok(())
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CliRpcBench {
+    pub(crate) url: String,
+    pub(crate) iterations: usize,
+    pub(crate) get_account: CliLatency,
+    pub(crate) get_latest_blockhash: CliLatency,
+    pub(crate) simulate_transaction: CliLatency,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CliLatency {
+    pub(crate) p50_ms: f64,
+    pub(crate) p95_ms: f64,
+    // In the order they were taken
+    pub(crate) samples_ms: Vec<f64>,
+}
+
+impl QuietDisplay for CliRpcBench {}
+impl VerboseDisplay for CliRpcBench {}
+
+impl fmt::Display for CliRpcBench {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f)?;
+        writeln_name_value(f, "RPC URL:", &self.url)?;
+        writeln_name_value(f, "Iterations:", &self.iterations.to_string())?;
+        writeln!(f)?;
+        writeln!(
+            f,
+            "{:<24}  {:>10}  {:>10}",
+            "Request", "p50 (ms)", "p95 (ms)"
+        )?;
+        writeln!(f, "{}", "-".repeat(48))?;
+        for (name, latency) in [
+            ("getAccountInfo", &self.get_account),
+            ("getLatestBlockhash", &self.get_latest_blockhash),
+            ("simulateTransaction", &self.simulate_transaction),
+        ] {
+            writeln!(
+                f,
+                "{:<24}  {:>10.1}  {:>10.1}",
+                name, latency.p50_ms, latency.p95_ms
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum CliErrorKind {
+    // The cluster could not be reached or refused the request
+    Rpc,
+    // A token program rejected the transaction
+    Program,
+    // Anything the command itself rejected
+    Validation,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CliErrorDetails {
+    pub(crate) message: String,
+    pub(crate) kind: CliErrorKind,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CliError {
+    pub(crate) error: CliErrorDetails,
+    // What the command did get done before it failed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) output: Option<serde_json::Value>,
+}
+
+impl QuietDisplay for CliError {}
+impl VerboseDisplay for CliError {}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{}", self.error.message)
+    }
+}
+
 fn flattened<S: Serializer>(
     vec: &[Vec<CliTokenAccount>],
     serializer: S,
@@ -0,0 +1,96 @@
+use crate::config::Config;
+use indicatif::{ProgressBar, ProgressStyle};
+use solana_cli_output::OutputFormat;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+const TICK_MILLIS: u64 = 100;
+
+/// Progress of a command that scans many accounts or sends many transactions, drawn on stderr.
+/// Nothing is drawn for `--quiet`, JSON output or sign-only, so that the output of those can
+/// still be consumed by other programs.
+pub(crate) struct Progress {
+    bar: ProgressBar,
+    sent: AtomicU64,
+}
+
+impl Progress {
+    fn enabled(config: &Config<'_>) -> bool {
+        !config.quiet
+            && !config.sign_only
+            && matches!(
+                config.output_format,
+                OutputFormat::Display | OutputFormat::DisplayVerbose
+            )
+    }
+
+    fn new(bar: ProgressBar) -> Self {
+        Self {
+            bar,
+            sent: AtomicU64::new(0),
+        }
+    }
+
+    pub(crate) fn hidden() -> Self {
+        Self::new(ProgressBar::hidden())
+    }
+
+    pub(crate) fn spinner(config: &Config<'_>, message: String) -> Self {
+        if !Self::enabled(config) {
+            return Self::hidden();
+        }
+        let bar = ProgressBar::new_spinner();
+        bar.set_style(ProgressStyle::default_spinner().template("{spinner:.green} {msg}"));
+        bar.set_message(message);
+        bar.enable_steady_tick(TICK_MILLIS);
+        Self::new(bar)
+    }
+
+    pub(crate) fn transactions(config: &Config<'_>, count: usize) -> Self {
+        if !Self::enabled(config) || count < 2 {
+            return Self::hidden();
+        }
+        let bar = ProgressBar::new(count as u64);
+        bar.set_style(
+            ProgressStyle::default_bar()
+                .template("{spinner:.green} [{bar:40}] {pos}/{len} transactions confirmed, {msg}"),
+        );
+        bar.set_message("0 sent");
+        bar.enable_steady_tick(TICK_MILLIS);
+        Self::new(bar)
+    }
+
+    pub(crate) fn is_hidden(&self) -> bool {
+        self.bar.is_hidden()
+    }
+
+    pub(crate) fn sent(&self) {
+        let sent = self.sent.fetch_add(1, Ordering::Relaxed) + 1;
+        self.bar.set_message(format!("{} sent", sent));
+    }
+
+    pub(crate) fn confirmed(&self) {
+        self.bar.inc(1);
+    }
+
+    // Printed above the bar, which would otherwise draw over it
+    pub(crate) fn eprintln(&self, message: String) {
+        if self.is_hidden() {
+            eprintln!("{}", message);
+        } else {
+            self.bar.println(message);
+        }
+    }
+
+    pub(crate) fn finish_with_message(&self, message: String) {
+        self.bar.finish_with_message(message);
+    }
+}
+
+// Also clears the bar when the command stops early on an error
+impl Drop for Progress {
+    fn drop(&mut self) {
+        if !self.bar.is_finished() {
+            self.bar.finish_and_clear();
+        }
+    }
+}
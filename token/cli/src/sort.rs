@@ -57,6 +57,7 @@ pub(crate) fn sort_and_parse_token_accounts(
                             address,
                             account: ui_token_account,
                             is_associated,
+                            required_transfer_memos: None,
                         };
                         let entry = mint_accounts.entry(mint);
                         match entry {